@@ -0,0 +1,92 @@
+//! Compares filling and stroking many small paths one by one with the batched calls.
+//!
+//! Run with `cargo bench --bench paths`.
+
+use std::time::{Duration, Instant};
+
+use femtovg::{renderer::Void, Canvas, Color, Paint, Path};
+
+const PATHS: usize = 10_000;
+const ITERATIONS: u32 = 20;
+
+fn main() {
+    let paths: Vec<Path> = (0..PATHS)
+        .map(|i| {
+            let mut path = Path::new();
+            let (x, y) = ((i % 100) as f32 * 10.0, (i / 100) as f32 * 10.0);
+            if i % 2 == 0 {
+                path.rect(x, y, 8.0, 8.0);
+            } else {
+                path.circle(x + 4.0, y + 4.0, 4.0);
+            }
+            path
+        })
+        .collect();
+    let path_refs: Vec<&Path> = paths.iter().collect();
+
+    let paints = [
+        ("color", Paint::color(Color::rgb(200, 40, 40))),
+        (
+            "gradient",
+            Paint::linear_gradient_stops(
+                0.0,
+                0.0,
+                1000.0,
+                1000.0,
+                [
+                    (0.0, Color::rgb(200, 40, 40)),
+                    (0.5, Color::rgb(40, 200, 40)),
+                    (1.0, Color::rgb(40, 40, 200)),
+                ],
+            ),
+        ),
+    ];
+
+    for (name, paint) in &paints {
+        let items: Vec<(&Path, &Paint)> = paths.iter().map(|path| (path, paint)).collect();
+
+        report(&format!("fill_path {name}"), |canvas| {
+            for path in &paths {
+                canvas.fill_path(path, paint);
+            }
+        });
+        report(&format!("fill_paths {name}"), |canvas| {
+            canvas.fill_paths(items.iter().copied())
+        });
+        report(&format!("fill_paths_with_paint {name}"), |canvas| {
+            canvas.fill_paths_with_paint(&path_refs, paint);
+        });
+
+        report(&format!("stroke_path {name}"), |canvas| {
+            for path in &paths {
+                canvas.stroke_path(path, paint);
+            }
+        });
+        report(&format!("stroke_paths {name}"), |canvas| {
+            canvas.stroke_paths(items.iter().copied())
+        });
+        report(&format!("stroke_paths_with_paint {name}"), |canvas| {
+            canvas.stroke_paths_with_paint(&path_refs, paint);
+        });
+    }
+}
+
+// Prints the average time of drawing and flushing a frame with `draw`.
+fn report(name: &str, mut draw: impl FnMut(&mut Canvas<Void>)) {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(1000, 1000, 1.0);
+
+    // The first frame tessellates the paths, which later frames reuse from the path caches
+    draw(&mut canvas);
+    canvas.flush_to_surface(&());
+
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        draw(&mut canvas);
+        canvas.flush_to_surface(&());
+        total += start.elapsed();
+    }
+
+    println!("{name:<32} {:>10.3?} per frame", total / ITERATIONS);
+}
@@ -1,7 +1,7 @@
 use std::num::NonZeroU32;
 
 use glutin_winit::DisplayBuilder;
-use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle_05::HasRawWindowHandle;
 use winit::dpi::PhysicalSize;
 use winit::event_loop::EventLoop;
 use winit::window::WindowBuilder;
@@ -5,7 +5,7 @@ use femtovg::{Canvas, Color};
 use glutin::surface::Surface;
 use glutin::{context::PossiblyCurrentContext, display::Display};
 use glutin_winit::DisplayBuilder;
-use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle_05::HasRawWindowHandle;
 use winit::dpi::PhysicalPosition;
 use winit::event::{Event, WindowEvent};
 use winit::event_loop::EventLoop;
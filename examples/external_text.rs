@@ -163,6 +163,7 @@ impl RenderCache {
 
                 let cmd = cmd_map.entry(rendered.texture_index).or_insert_with(|| DrawCommand {
                     image_id: self.glyph_textures[rendered.texture_index].image_id,
+                    layer: 0,
                     quads: Vec::new(),
                 });
 
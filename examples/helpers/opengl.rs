@@ -16,7 +16,7 @@ use glutin::{
 #[cfg(not(target_arch = "wasm32"))]
 use glutin_winit::DisplayBuilder;
 #[cfg(not(target_arch = "wasm32"))]
-use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle_05::HasRawWindowHandle;
 use winit::{event_loop::EventLoop, window::WindowBuilder};
 
 pub struct DemoSurface {
@@ -10,7 +10,7 @@ use glutin::{
     surface::{SurfaceAttributesBuilder, WindowSurface},
 };
 use glutin_winit::DisplayBuilder;
-use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle_05::HasRawWindowHandle;
 use winit::{event::Event, event::WindowEvent, event_loop::EventLoop, window::WindowBuilder};
 
 const WINDOW_WIDTH: f32 = 640.0;
@@ -1,9 +1,8 @@
 use std::sync::Arc;
 
-use femtovg::{Canvas, Color, FillRule, ImageFlags, Paint, Path};
+use femtovg::{svg::Svg, Canvas, Color, ImageFlags};
 use instant::Instant;
 use resource::resource;
-use usvg::TreeParsing;
 use winit::{
     event::{ElementState, Event, MouseButton, WindowEvent},
     event_loop::EventLoop,
@@ -44,18 +43,7 @@ fn run<W: WindowSurface>(mut canvas: Canvas<W::Renderer>, el: EventLoop<()>, mut
     let mut perf = PerfGraph::new();
 
     let svg_data = include_bytes!("assets/Ghostscript_Tiger.svg");
-    let tree = usvg::Tree::from_data(svg_data, &usvg::Options::default()).unwrap();
-
-    let paths = render_svg(tree);
-
-    // print memory usage
-    let mut total_sisze_bytes = 0;
-
-    for path in &paths {
-        total_sisze_bytes += path.0.size();
-    }
-
-    log::info!("Path mem usage: {}kb", total_sisze_bytes / 1024);
+    let svg = Svg::from_data(&mut canvas, svg_data).unwrap();
 
     el.run(move |event, event_loop_window_target| {
         event_loop_window_target.set_control_flow(winit::event_loop::ControlFlow::Poll);
@@ -135,20 +123,7 @@ fn run<W: WindowSurface>(mut canvas: Canvas<W::Renderer>, el: EventLoop<()>, mut
                     canvas.save();
                     canvas.translate(200.0, 200.0);
 
-                    for (path, fill, stroke) in &paths {
-                        if let Some(fill) = fill {
-                            canvas.fill_path(path, fill);
-                        }
-
-                        if let Some(stroke) = stroke {
-                            canvas.stroke_path(path, stroke);
-                        }
-
-                        if canvas.contains_point(path, mousex, mousey, FillRule::NonZero) {
-                            let paint = Paint::color(Color::rgb(32, 240, 32)).with_line_width(1.0);
-                            canvas.stroke_path(path, &paint);
-                        }
-                    }
+                    svg.draw(&mut canvas);
 
                     canvas.restore();
 
@@ -168,50 +143,3 @@ fn run<W: WindowSurface>(mut canvas: Canvas<W::Renderer>, el: EventLoop<()>, mut
     })
     .unwrap();
 }
-
-fn render_svg(svg: usvg::Tree) -> Vec<(Path, Option<Paint>, Option<Paint>)> {
-    use usvg::NodeKind;
-    use usvg::PathSegment;
-
-    let mut paths = Vec::new();
-
-    for node in svg.root.descendants() {
-        if let NodeKind::Path(svg_path) = &*node.borrow() {
-            let mut path = Path::new();
-
-            for command in svg_path.data.segments() {
-                match command {
-                    PathSegment::MoveTo { x, y } => path.move_to(x as f32, y as f32),
-                    PathSegment::LineTo { x, y } => path.line_to(x as f32, y as f32),
-                    PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
-                        path.bezier_to(x1 as f32, y1 as f32, x2 as f32, y2 as f32, x as f32, y as f32)
-                    }
-                    PathSegment::ClosePath => path.close(),
-                }
-            }
-
-            let to_femto_color = |usvg_paint: &usvg::Paint| match usvg_paint {
-                usvg::Paint::Color(usvg::Color { red, green, blue }) => Some(Color::rgb(*red, *green, *blue)),
-                _ => None,
-            };
-
-            let fill = svg_path
-                .fill
-                .as_ref()
-                .and_then(|fill| to_femto_color(&fill.paint))
-                .map(|col| Paint::color(col).with_anti_alias(true));
-
-            let stroke = svg_path.stroke.as_ref().and_then(|stroke| {
-                to_femto_color(&stroke.paint).map(|paint| {
-                    Paint::color(paint)
-                        .with_line_width(stroke.width.get() as f32)
-                        .with_anti_alias(true)
-                })
-            });
-
-            paths.push((path, fill, stroke))
-        }
-    }
-
-    paths
-}
@@ -82,6 +82,105 @@ impl Color {
         }
     }
 
+    /// Parses a color from a 3 (`RGB`), 4 (`RGBA`), 6 (`RRGGBB`) or 8-digit (`RRGGBBAA`) HTML
+    /// hexadecimal string. The “#” is optional.
+    ///
+    /// Unlike [`Self::hex()`], this returns `None` for anything that isn't a valid color.
+    pub fn from_hex(raw_hex: &str) -> Option<Self> {
+        let hex = raw_hex.strip_prefix('#').unwrap_or(raw_hex);
+
+        if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        // Single digits are repeated, so "f" is the same as "ff"
+        let digit = |i: usize| hex_to_u8(&hex[i..i + 1]) * 17;
+        let pair = |i: usize| hex_to_u8(&hex[i..i + 2]);
+
+        match hex.len() {
+            3 => Some(Self::rgb(digit(0), digit(1), digit(2))),
+            4 => Some(Self::rgba(digit(0), digit(1), digit(2), digit(3))),
+            6 => Some(Self::rgb(pair(0), pair(2), pair(4))),
+            8 => Some(Self::rgba(pair(0), pair(2), pair(4), pair(6))),
+            _ => None,
+        }
+    }
+
+    /// Creates a color from hue, saturation, and value f32 values. Alpha is set to 1.0.
+    /// All values are all in range [0..1].
+    pub fn hsv(h: f32, s: f32, v: f32) -> Self {
+        Self::hsva(h, s, v, 1.0)
+    }
+
+    /// Creates a color from hue, saturation, value, and alpha f32 values.
+    /// All values are all in range [0..1].
+    pub fn hsva(h: f32, s: f32, v: f32, a: f32) -> Self {
+        let h = h.rem_euclid(1.0) * 6.0;
+        let s = s.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+
+        let c = v * s;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let m = v - c;
+
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        Self::rgbaf(r + m, g + m, b + m, a)
+    }
+
+    /// Returns the hue, saturation, lightness, and alpha of the color, all in range [0..1].
+    pub fn to_hsla(self) -> [f32; 4] {
+        let (h, max, min) = self.hue_max_min();
+
+        let l = (max + min) * 0.5;
+        let s = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        };
+
+        [h, s, l, self.a]
+    }
+
+    /// Returns the hue, saturation, value, and alpha of the color, all in range [0..1].
+    pub fn to_hsva(self) -> [f32; 4] {
+        let (h, max, min) = self.hue_max_min();
+
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+
+        [h, s, max, self.a]
+    }
+
+    /// Returns the color linearly interpolated towards `other`, where a `t` of 0.0 is this color and
+    /// 1.0 is `other`.
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        Self {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+
+    /// Returns the color with its HSL lightness increased by `amount`.
+    pub fn lighten(self, amount: f32) -> Self {
+        let [h, s, l, a] = self.to_hsla();
+
+        Self::hsla(h, s, l + amount, a)
+    }
+
+    /// Returns the color with its HSL lightness decreased by `amount`.
+    pub fn darken(self, amount: f32) -> Self {
+        self.lighten(-amount)
+    }
+
     /// Returns a white color (1.0, 1.0, 1.0, 1.0)
     pub const fn white() -> Self {
         Self::rgbaf(1.0, 1.0, 1.0, 1.0)
@@ -121,6 +220,25 @@ impl Color {
     pub fn is_black(&self) -> bool {
         self.r == 0.0 && self.g == 0.0 && self.b == 0.0 && self.a == 0.0
     }
+
+    // Returns the hue in range [0..1] and the largest and smallest color component.
+    fn hue_max_min(self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let delta = max - min;
+
+        let h = if delta == 0.0 {
+            0.0
+        } else if max == self.r {
+            ((self.g - self.b) / delta).rem_euclid(6.0)
+        } else if max == self.g {
+            (self.b - self.r) / delta + 2.0
+        } else {
+            (self.r - self.g) / delta + 4.0
+        };
+
+        (h / 6.0, max, min)
+    }
 }
 
 impl Default for Color {
@@ -159,3 +277,52 @@ fn hue(mut h: f32, m1: f32, m2: f32) -> f32 {
 fn hex_to_u8(hex_string: &str) -> u8 {
     u8::from_str_radix(hex_string, 16).unwrap_or(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Color, b: Color) {
+        let close = a.to_array().iter().zip(b.to_array()).all(|(a, b)| (a - b).abs() < 1e-5);
+        assert!(close, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn parse_hex() {
+        assert_eq!(Color::from_hex("#ff8000"), Some(Color::rgb(255, 128, 0)));
+        assert_eq!(Color::from_hex("ff800080"), Some(Color::rgba(255, 128, 0, 128)));
+        assert_eq!(Color::from_hex("#f80"), Some(Color::rgb(255, 136, 0)));
+        assert_eq!(Color::from_hex("#f808"), Some(Color::rgba(255, 136, 0, 136)));
+        assert_eq!(Color::from_hex("#ff80"), Some(Color::rgba(255, 255, 136, 0)));
+        assert_eq!(Color::from_hex("#ff800"), None);
+        assert_eq!(Color::from_hex("#gg8000"), None);
+        assert_eq!(Color::from_hex("#ffé000"), None);
+    }
+
+    #[test]
+    fn hsl_hsv_round_trip() {
+        for color in [
+            Color::rgba(255, 128, 0, 64),
+            Color::rgb(12, 200, 180),
+            Color::rgb(90, 20, 240),
+            Color::rgb(128, 128, 128),
+            Color::black(),
+            Color::white(),
+        ] {
+            let [h, s, l, a] = color.to_hsla();
+            assert_close(Color::hsla(h, s, l, a), color);
+
+            let [h, s, v, a] = color.to_hsva();
+            assert_close(Color::hsva(h, s, v, a), color);
+        }
+
+        assert_close(Color::hsv(1.0 / 3.0, 1.0, 1.0), Color::rgb(0, 255, 0));
+    }
+
+    #[test]
+    fn lerp_lighten_darken() {
+        assert_close(Color::black().lerp(Color::white(), 0.5), Color::rgbf(0.5, 0.5, 0.5));
+        assert_close(Color::rgbf(0.5, 0.5, 0.5).lighten(0.25), Color::rgbf(0.75, 0.75, 0.75));
+        assert_close(Color::rgbf(0.5, 0.5, 0.5).darken(1.0), Color::black());
+    }
+}
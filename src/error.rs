@@ -32,16 +32,30 @@ pub enum ErrorKind {
     ShaderLinkError(String),
     /// An error related to a render target.
     RenderTargetError(String),
-    /// The specified image ID was not found.
+    /// The specified image ID was not found, for example because the image was deleted.
     ImageIdNotFound,
+    /// The specified paint ID was not found.
+    PaintIdNotFound,
+    /// The specified shader ID was not found.
+    ShaderIdNotFound,
     /// An error occurred while updating an image, as it is out of bounds.
     ImageUpdateOutOfBounds,
     /// An error occurred while updating an image with a different format.
     ImageUpdateWithDifferentFormat,
     /// The specified image format is not supported.
     UnsupportedImageFormat,
+    /// The image is empty or larger than the maximum texture size of the renderer.
+    UnsupportedImageSize,
+    /// Points of a path were left out because the transform made their coordinates infinite or NaN.
+    PathOutOfRange,
     /// The requested operation is not supported (for example screenshot by wgpu renderer).
     UnsupportedOperation,
+    /// The area to read back is outside of the render target.
+    ReadbackOutOfBounds,
+    /// The buffer is too small to hold the requested pixels.
+    BufferTooSmall,
+    /// The commands of a recorded frame refer to vertices the frame doesn't contain.
+    FrameVertsOutOfRange,
 }
 
 impl Display for ErrorKind {
@@ -57,6 +71,13 @@ impl From<::image::ImageError> for ErrorKind {
     }
 }
 
+#[cfg(feature = "svg")]
+impl From<usvg::Error> for ErrorKind {
+    fn from(error: usvg::Error) -> Self {
+        Self::GeneralError(error.to_string())
+    }
+}
+
 impl From<io::Error> for ErrorKind {
     fn from(error: io::Error) -> Self {
         Self::IoError(error)
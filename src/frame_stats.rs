@@ -0,0 +1,102 @@
+use crate::renderer::{Command, CommandType};
+
+/// Statistics about the most recent flush of a [`Canvas`](crate::Canvas), returned by
+/// [`Canvas::frame_stats`](crate::Canvas::frame_stats).
+///
+/// Draw calls, triangles and state changes are derived from the commands handed to the renderer and
+/// match what the OpenGL backend issues with antialiasing enabled. Other backends may differ slightly.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct FrameStats {
+    /// Number of commands passed to the renderer.
+    pub commands: usize,
+    /// Number of draw calls, including the ones only writing to the stencil buffer.
+    pub draw_calls: usize,
+    /// Number of triangles drawn.
+    pub triangles: usize,
+    /// Number of times the kind of command, image, glyph texture, blend mode or render target changed
+    /// between consecutive commands.
+    pub state_changes: usize,
+    /// Number of image updates since the previous flush, including glyph atlas and gradient uploads.
+    pub texture_uploads: usize,
+    /// Number of bytes uploaded with those image updates.
+    pub texture_upload_bytes: usize,
+    /// Number of textures of the glyph atlas.
+    pub glyph_atlas_textures: usize,
+    /// Fraction of the glyph atlas texture area taken by rendered glyphs, between 0 and 1.
+    pub glyph_atlas_usage: f32,
+    /// Number of textures of multi-stop gradients.
+    pub gradient_textures: usize,
+    /// Estimate of the video memory taken by all images, in bytes.
+    pub image_memory: usize,
+    /// Number of errors of drawing operations reported since the previous flush, such as glyphs too large for the
+    /// glyph atlas or paths left out because of out of range coordinates. See [`Canvas::set_error_callback`].
+    ///
+    /// [`Canvas::set_error_callback`]: crate::Canvas::set_error_callback
+    pub errors: usize,
+}
+
+impl FrameStats {
+    // Counts the draw calls, triangles and state changes of the commands.
+    pub(crate) fn add_commands(&mut self, commands: &[Command]) {
+        let mut prev: Option<&Command> = None;
+
+        for cmd in commands {
+            self.commands += 1;
+
+            if prev.is_some_and(|prev| !prev.shares_state(cmd))
+                || matches!(cmd.cmd_type, CommandType::SetRenderTarget(_))
+            {
+                self.state_changes += 1;
+            }
+            prev = Some(cmd);
+
+            let fills = || cmd.drawables.iter().filter_map(|drawable| drawable.fill_verts);
+            let strokes = || cmd.drawables.iter().filter_map(|drawable| drawable.stroke_verts);
+
+            match cmd.cmd_type {
+                CommandType::SetRenderTarget(_) => (),
+                CommandType::ClearRect { .. } | CommandType::Triangles { .. } => {
+                    self.add_triangles(cmd.triangles_verts);
+                }
+                CommandType::ConvexFill { .. } => {
+                    fills().for_each(|verts| self.add_triangles(Some(verts)));
+                    strokes().for_each(|verts| self.add_strip(Some(verts)));
+                }
+                CommandType::ConcaveFill { .. } => {
+                    fills().for_each(|verts| self.add_triangles(Some(verts)));
+                    strokes().for_each(|verts| self.add_strip(Some(verts)));
+                    self.add_strip(cmd.triangles_verts);
+                }
+                CommandType::Stroke { .. } => {
+                    strokes().for_each(|verts| self.add_strip(Some(verts)));
+                }
+                CommandType::StencilStroke { .. } => {
+                    // Base, antialiasing and stencil clear passes
+                    for _ in 0..3 {
+                        strokes().for_each(|verts| self.add_strip(Some(verts)));
+                    }
+                }
+                CommandType::RenderFilteredImage { .. } => {
+                    // Horizontal and vertical blur passes, each drawing a quad
+                    self.draw_calls += 2;
+                    self.triangles += 4;
+                }
+            }
+        }
+    }
+
+    fn add_triangles(&mut self, verts: Option<(usize, usize)>) {
+        if let Some((_, count)) = verts {
+            self.draw_calls += 1;
+            self.triangles += count / 3;
+        }
+    }
+
+    fn add_strip(&mut self, verts: Option<(usize, usize)>) {
+        if let Some((_, count)) = verts {
+            self.draw_calls += 1;
+            self.triangles += count.saturating_sub(2);
+        }
+    }
+}
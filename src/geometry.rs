@@ -1,4 +1,5 @@
 use std::{
+    f32::consts::{PI, TAU},
     hash::{Hash, Hasher},
     ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign},
 };
@@ -49,6 +50,10 @@ impl Sub for Position {
 }
 
 impl Position {
+    pub(crate) fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+
     pub(crate) fn equals(p1: Self, p2: Self, tol: f32) -> bool {
         (p2 - p1).mag2() < tol * tol
     }
@@ -189,10 +194,44 @@ impl MulAssign<f32> for Vector {
     }
 }
 
+#[cfg(feature = "text")]
 pub fn quantize(a: f32, d: f32) -> f32 {
     (a / d + 0.5).trunc() * d
 }
 
+/// The components a [`Transform2D`] is made of, see [`Transform2D::decompose()`].
+///
+/// A transform is recomposed by scaling, then skewing horizontally, then rotating and finally translating.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransformComponents {
+    /// Horizontal translation.
+    pub translate_x: f32,
+    /// Vertical translation.
+    pub translate_y: f32,
+    /// Rotation angle in radians.
+    pub rotation: f32,
+    /// Horizontal scale factor, never negative.
+    pub scale_x: f32,
+    /// Vertical scale factor, negative if the transform mirrors.
+    pub scale_y: f32,
+    /// Horizontal skew angle in radians.
+    pub skew: f32,
+}
+
+impl Default for TransformComponents {
+    fn default() -> Self {
+        Self {
+            translate_x: 0.0,
+            translate_y: 0.0,
+            rotation: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            skew: 0.0,
+        }
+    }
+}
+
 /// 2×3 matrix (2 rows, 3 columns) used for 2D linear transformations. It can represent transformations such as translation, rotation, or scaling.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -338,6 +377,77 @@ impl Transform2D {
         (sx + sy) * 0.5
     }
 
+    /// Splits the transformation matrix into translation, rotation, scale and skew.
+    ///
+    /// Interpolating the components instead of the matrix elements keeps the shape of rotated content intact,
+    /// see [`Transform2D::lerp()`]. [`Transform2D::recompose()`] builds the matrix back from the components.
+    pub fn decompose(&self) -> TransformComponents {
+        let &Self([a, b, c, d, x, y]) = self;
+
+        let scale_x = a.hypot(b);
+        let (sin, cos) = if scale_x == 0.0 {
+            (0.0, 1.0)
+        } else {
+            (b / scale_x, a / scale_x)
+        };
+
+        // the second column is the skewed part along the first one plus the scaled part orthogonal to it
+        let skewed = c * cos + d * sin;
+        let scale_y = d * cos - c * sin;
+        let skew = if scale_y == 0.0 { 0.0 } else { (skewed / scale_y).atan() };
+
+        TransformComponents {
+            translate_x: x,
+            translate_y: y,
+            rotation: sin.atan2(cos),
+            scale_x,
+            scale_y,
+            skew,
+        }
+    }
+
+    /// Creates a transformation matrix from the components returned by [`Transform2D::decompose()`].
+    pub fn recompose(components: TransformComponents) -> Self {
+        let (sin, cos) = components.rotation.sin_cos();
+        let skewed = components.skew.tan() * components.scale_y;
+
+        Self([
+            cos * components.scale_x,
+            sin * components.scale_x,
+            cos * skewed - sin * components.scale_y,
+            sin * skewed + cos * components.scale_y,
+            components.translate_x,
+            components.translate_y,
+        ])
+    }
+
+    /// Interpolates between this transform at `t` = 0 and `other` at `t` = 1.
+    ///
+    /// The decomposed components are interpolated, with the rotation taking the shorter way around, so that
+    /// content tweened between two states rotates and scales without shearing in between.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let from = self.decompose();
+        let to = other.decompose();
+
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+
+        let mut rotation_delta = (to.rotation - from.rotation) % TAU;
+        if rotation_delta > PI {
+            rotation_delta -= TAU;
+        } else if rotation_delta < -PI {
+            rotation_delta += TAU;
+        }
+
+        Self::recompose(TransformComponents {
+            translate_x: lerp(from.translate_x, to.translate_x),
+            translate_y: lerp(from.translate_y, to.translate_y),
+            rotation: from.rotation + rotation_delta * t,
+            scale_x: lerp(from.scale_x, to.scale_x),
+            scale_y: lerp(from.scale_y, to.scale_y),
+            skew: lerp(from.skew, to.skew),
+        })
+    }
+
     /// Converts the current transformation matrix to a 3×4 matrix format.
     pub fn to_mat3x4(self) -> [f32; 12] {
         let Self([a, b, c, d, x, y]) = self;
@@ -448,19 +558,27 @@ impl DivAssign for Transform2D {
     }
 }
 
+/// An axis-aligned rectangle.
 #[derive(Copy, Clone, Default, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Rect {
+    /// X coordinate of the left edge.
     pub x: f32,
+    /// Y coordinate of the top edge.
     pub y: f32,
+    /// Width of the rectangle.
     pub w: f32,
+    /// Height of the rectangle.
     pub h: f32,
 }
 
 impl Rect {
+    /// Creates a new rectangle from its top-left corner and size.
     pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
         Self { x, y, w, h }
     }
 
+    /// Returns the overlap of both rectangles, which is empty if they don't overlap.
     pub fn intersect(&self, other: Self) -> Self {
         let minx = self.x.max(other.x);
         let miny = self.y.max(other.y);
@@ -470,6 +588,7 @@ impl Rect {
         Self::new(minx, miny, 0.0f32.max(maxx - minx), 0.0f32.max(maxy - miny))
     }
 
+    /// Returns true if `other` lies completely inside this rectangle.
     pub fn contains_rect(&self, other: &Self) -> bool {
         other.is_empty()
             || (self.x <= other.x
@@ -478,6 +597,7 @@ impl Rect {
                 && other.y + other.h <= self.y + self.h)
     }
 
+    /// Returns the overlap of both rectangles, or `None` if they don't overlap.
     pub fn intersection(&self, other: &Self) -> Option<Self> {
         let x = self.x.max(other.x);
         let y = self.y.max(other.y);
@@ -492,6 +612,7 @@ impl Rect {
         }
     }
 
+    /// Returns true if the rectangle has no area.
     pub fn is_empty(&self) -> bool {
         self.w <= 0. || self.h <= 0.
     }
@@ -520,4 +641,29 @@ impl Bounds {
     pub(crate) fn contains(&self, x: f32, y: f32) -> bool {
         (self.minx..=self.maxx).contains(&x) && (self.miny..=self.maxy).contains(&y)
     }
+
+    // Grows the bounds to include the point.
+    pub(crate) fn add_point(&mut self, pos: Position) {
+        self.minx = self.minx.min(pos.x);
+        self.miny = self.miny.min(pos.y);
+        self.maxx = self.maxx.max(pos.x);
+        self.maxy = self.maxy.max(pos.y);
+    }
+
+    // Grows the bounds to include the other bounds.
+    pub(crate) fn add_bounds(&mut self, other: &Self) {
+        self.minx = self.minx.min(other.minx);
+        self.miny = self.miny.min(other.miny);
+        self.maxx = self.maxx.max(other.maxx);
+        self.maxy = self.maxy.max(other.maxy);
+    }
+
+    // The bounds as a rectangle, empty at the origin if no point was added.
+    pub(crate) fn to_rect(self) -> Rect {
+        if self.minx > self.maxx || self.miny > self.maxy {
+            return Rect::default();
+        }
+
+        Rect::new(self.minx, self.miny, self.maxx - self.minx, self.maxy - self.miny)
+    }
 }
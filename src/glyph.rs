@@ -0,0 +1,53 @@
+use crate::{Color, ImageId};
+
+mod atlas;
+pub use atlas::Atlas;
+
+/// Represents a command to draw an image with a set of quads.
+#[derive(Clone, Debug)]
+pub struct DrawCommand {
+    /// The ID of the image to draw.
+    pub image_id: ImageId,
+    /// The layer of the image the quads sample, 0 unless the image has several layers.
+    pub layer: usize,
+    /// The quads defining the positions and texture coordinates for drawing the image.
+    pub quads: Vec<Quad>,
+}
+
+/// Represents a quad with position and texture coordinates.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Quad {
+    /// X-coordinate of the top-left corner of the quad.
+    pub x0: f32,
+    /// Y-coordinate of the top-left corner of the quad.
+    pub y0: f32,
+    /// U-coordinate (horizontal texture coordinate) of the top-left corner of the quad.
+    pub s0: f32,
+    /// V-coordinate (vertical texture coordinate) of the top-left corner of the quad.
+    pub t0: f32,
+    /// X-coordinate of the bottom-right corner of the quad.
+    pub x1: f32,
+    /// Y-coordinate of the bottom-right corner of the quad.
+    pub y1: f32,
+    /// U-coordinate (horizontal texture coordinate) of the bottom-right corner of the quad.
+    pub s1: f32,
+    /// V-coordinate (vertical texture coordinate) of the bottom-right corner of the quad.
+    pub t1: f32,
+}
+
+/// A quad drawn by [`Canvas::draw_quads()`](crate::Canvas::draw_quads), with an optional color.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct TexturedQuad {
+    /// The position and texture coordinates of the quad.
+    pub quad: Quad,
+    /// The color the image is multiplied with for this quad, instead of the paint.
+    pub color: Option<Color>,
+}
+
+/// Represents the drawing commands for glyphs, separated into alpha and color glyphs.
+pub struct GlyphDrawCommands {
+    /// Drawing commands for alpha (opacity) glyphs.
+    pub alpha_glyphs: Vec<DrawCommand>,
+    /// Drawing commands for color glyphs.
+    pub color_glyphs: Vec<DrawCommand>,
+}
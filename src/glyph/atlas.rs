@@ -11,6 +11,7 @@ pub struct Atlas {
     width: usize,
     height: usize,
     nodes: Vec<Node>,
+    used_area: usize,
 }
 
 impl Atlas {
@@ -20,6 +21,7 @@ impl Atlas {
             width,
             height,
             nodes: vec![Node { x: 0, y: 0, width }],
+            used_area: 0,
         }
     }
 
@@ -28,6 +30,11 @@ impl Atlas {
         (self.width, self.height)
     }
 
+    /// Returns the total area of the rectangles added to the atlas.
+    pub fn used_area(&self) -> usize {
+        self.used_area
+    }
+
     /// Expands the atlas to the specified width and height.
     pub fn expand(&mut self, width: usize, height: usize) {
         // Insert node for empty space
@@ -71,6 +78,7 @@ impl Atlas {
         if let Some(besti) = besti {
             // Perform the actual packing.
             self.add_skyline_level(besti, bestx, besty, rect_width, rect_height);
+            self.used_area += rect_width * rect_height;
             return Some((bestx, besty));
         }
 
@@ -6,21 +6,40 @@ use crate::{
     Color, ErrorKind, ImageFlags, ImageId, ImageInfo, ImageSource, Renderer,
 };
 
+// Smallest and largest width of a gradient texture.
+const MIN_GRADIENT_WIDTH: usize = 256;
+const MAX_GRADIENT_WIDTH: usize = 4096;
+
+// Texels used for the span between the two closest stops of a gradient.
+const TEXELS_PER_SPAN: f32 = 8.0;
+
 /// `GradientStore` holds image ids for multi-stop gradients. The actual image/textures
 /// are contained by the Canvas's `ImageStore`.
+///
+/// Every flush starts a new generation. Textures of gradients that weren't used for `lifetime`
+/// generations are released, so animating the stops of a gradient doesn't accumulate textures.
 //
 // If many gradients are used in a frame, we could combine them into a single texture
 // and update the texture immediately prior to giving the renderer the command list.
 pub struct GradientStore {
-    this_frame: BTreeMap<MultiStopGradient, ImageId>,
-    prev_frame: BTreeMap<MultiStopGradient, ImageId>,
+    gradients: BTreeMap<MultiStopGradient, GradientTexture>,
+    generation: u64,
+    lifetime: usize,
 }
+
+// A gradient texture and the generation it was last used in.
+struct GradientTexture {
+    image_id: ImageId,
+    last_used: u64,
+}
+
 impl GradientStore {
     /// Create a new empty gradient store
     pub fn new() -> Self {
         Self {
-            this_frame: BTreeMap::new(),
-            prev_frame: BTreeMap::new(),
+            gradients: BTreeMap::new(),
+            generation: 0,
+            lifetime: 1,
         }
     }
 
@@ -31,44 +50,90 @@ impl GradientStore {
         images: &mut ImageStore<R::Image>,
         renderer: &mut R,
     ) -> Result<ImageId, ErrorKind> {
-        if let Some(gradient_image_id) = self.prev_frame.remove(colors) {
-            // See if we already have this texture from the previous frame. If we find
-            // it then we migrate it to the current frame so we don't release it and
-            // return the texture id to the caller.
-            self.this_frame.insert(colors.clone(), gradient_image_id);
-            Ok(gradient_image_id)
-        } else if let Some(gradient_image_id) = self.this_frame.get(colors) {
-            // See if we already used this gradient in this frame, and return the texture
-            // id if we do.
-            Ok(*gradient_image_id)
-        } else {
-            // We need to allocate a texture and synthesize the gradient image.
-            let info = ImageInfo::new(ImageFlags::REPEAT_Y, 256, 1, crate::PixelFormat::Rgba8);
-            let gradient_image_id = images.alloc(renderer, info)?;
-            let image = linear_gradient_stops(colors);
-            images.update(renderer, gradient_image_id, ImageSource::Rgba(image.as_ref()), 0, 0)?;
-
-            self.this_frame.insert(colors.clone(), gradient_image_id);
-            Ok(gradient_image_id)
+        if let Some(texture) = self.gradients.get_mut(colors) {
+            // Reuse the texture and keep it alive for this generation
+            texture.last_used = self.generation;
+            return Ok(texture.image_id);
         }
+
+        // We need to allocate a texture and synthesize the gradient image.
+        let width = gradient_width(colors);
+        let info = ImageInfo::new(ImageFlags::REPEAT_Y, width, 1, crate::PixelFormat::Rgba8);
+        let image_id = images.alloc(renderer, info)?;
+        let image = linear_gradient_stops(colors, width);
+        images.update(renderer, image_id, ImageSource::Rgba(image.as_ref()), 0, 0)?;
+
+        self.gradients.insert(
+            colors.clone(),
+            GradientTexture {
+                image_id,
+                last_used: self.generation,
+            },
+        );
+        Ok(image_id)
     }
 
-    /// Release the textures that were not used in the most recently rendered frame. This
-    /// method should be called when all the commands have been submitted.
+    /// Returns the number of flushes a gradient texture is kept for after its last use.
+    pub fn lifetime(&self) -> usize {
+        self.lifetime
+    }
+
+    /// Sets the number of flushes a gradient texture is kept for after its last use. At least 1.
+    pub fn set_lifetime(&mut self, flushes: usize) {
+        self.lifetime = flushes.max(1);
+    }
+
+    /// Returns the number of gradient textures currently allocated.
+    pub fn texture_count(&self) -> usize {
+        self.gradients.len()
+    }
+
+    /// Release the textures that weren't used for the lifetime of gradients and start a new
+    /// generation. This method should be called when all the commands have been submitted.
     pub fn release_old_gradients<R: Renderer>(&mut self, images: &mut ImageStore<R::Image>, renderer: &mut R) {
-        let mut prev_textures = BTreeMap::new();
-        std::mem::swap(&mut prev_textures, &mut self.prev_frame);
-        for (_, gradient_image_id) in prev_textures {
-            images.remove(renderer, gradient_image_id);
-        }
-        // Move the "this_frame" textures to "prev_frame". "prev_frame" is already empty.
-        std::mem::swap(&mut self.this_frame, &mut self.prev_frame);
+        self.generation += 1;
+        self.release_unused_since(self.generation.saturating_sub(self.lifetime as u64), images, renderer);
+    }
+
+    /// Release the textures of all gradients that weren't used since the last call to
+    /// `release_old_gradients()`. Gradients used by commands that weren't submitted yet are kept.
+    pub fn release_unused<R: Renderer>(&mut self, images: &mut ImageStore<R::Image>, renderer: &mut R) {
+        self.release_unused_since(self.generation, images, renderer);
+    }
+
+    fn release_unused_since<R: Renderer>(
+        &mut self,
+        generation: u64,
+        images: &mut ImageStore<R::Image>,
+        renderer: &mut R,
+    ) {
+        self.gradients.retain(|_, texture| {
+            let keep = texture.last_used >= generation;
+            if !keep {
+                images.remove(renderer, texture.image_id);
+            }
+            keep
+        });
     }
 }
 
+// Returns the texture width needed to give the closest stops of the gradient enough texels that
+// they don't get quantized. Widths are powers of two for the benefit of older GPUs.
+fn gradient_width(gradient: &MultiStopGradient) -> usize {
+    let min_span = gradient
+        .pairs()
+        .filter(|[GradientStop(s0, _), GradientStop(s1, _)]| *s0 >= 0.0 && *s1 <= 1.0 && s1 > s0)
+        .map(|[GradientStop(s0, _), GradientStop(s1, _)]| s1 - s0)
+        .fold(1.0, f32::min);
+
+    ((TEXELS_PER_SPAN / min_span).ceil() as usize)
+        .next_power_of_two()
+        .clamp(MIN_GRADIENT_WIDTH, MAX_GRADIENT_WIDTH)
+}
+
 #[allow(clippy::many_single_char_names)]
 // Gradient filling, adapted from https://github.com/lieff/lvg/blob/master/render/common.c#L147
-fn gradient_span(dest: &mut [rgb::RGBA8; 256], color0: Color, color1: Color, offset0: f32, offset1: f32) {
+fn gradient_span(dest: &mut [rgb::RGBA8], color0: Color, color1: Color, offset0: f32, offset1: f32) {
     let s0o = offset0.clamp(0.0, 1.0);
     let s1o = offset1.clamp(0.0, 1.0);
 
@@ -76,8 +141,9 @@ fn gradient_span(dest: &mut [rgb::RGBA8; 256], color0: Color, color1: Color, off
         return;
     }
 
-    let s = (s0o * 256.0) as usize;
-    let e = (s1o * 256.0) as usize;
+    let width = dest.len() as f32;
+    let s = (s0o * width) as usize;
+    let e = (s1o * width) as usize;
 
     let mut r = color0.r;
     let mut g = color0.g;
@@ -111,8 +177,8 @@ fn gradient_span(dest: &mut [rgb::RGBA8; 256], color0: Color, color1: Color, off
         a += da;
     }
 }
-fn linear_gradient_stops(gradient: &MultiStopGradient) -> imgref::Img<Vec<rgb::RGBA8>> {
-    let mut dest = [rgb::RGBA8::new(0, 0, 0, 0); 256];
+fn linear_gradient_stops(gradient: &MultiStopGradient, width: usize) -> imgref::Img<Vec<rgb::RGBA8>> {
+    let mut dest = vec![rgb::RGBA8::new(0, 0, 0, 0); width];
 
     // Fill the gradient up to the first stop.
     let first_stop = gradient.get(0);
@@ -140,5 +206,38 @@ fn linear_gradient_stops(gradient: &MultiStopGradient) -> imgref::Img<Vec<rgb::R
             break;
         };
     }
-    imgref::Img::new(dest.to_vec(), 256, 1)
+    imgref::Img::new(dest, width, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{paint::GradientColors, Paint};
+
+    #[test]
+    fn many_stops_get_wider_texture() {
+        let colors = [Color::rgb(255, 0, 0), Color::rgb(0, 0, 255)];
+        let paint = Paint::linear_gradient_stops(
+            0.0,
+            0.0,
+            100.0,
+            0.0,
+            (0..=100).map(|i| (i as f32 / 100.0, colors[i % 2])),
+        );
+
+        let Some(GradientColors::MultiStop { stops }) = paint.flavor.gradient_colors() else {
+            panic!("expected a multi-stop gradient");
+        };
+
+        let width = gradient_width(stops);
+        assert_eq!(width, 1024);
+
+        // Every stop still starts with its own color
+        let image = linear_gradient_stops(stops, width);
+        for i in 0..100 {
+            let texel = image.buf()[(i as f32 / 100.0 * width as f32) as usize];
+            let expected = if i % 2 == 0 { (255, 0) } else { (0, 255) };
+            assert_eq!((texel.r, texel.b), expected, "stop {i}");
+        }
+    }
 }
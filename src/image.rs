@@ -8,16 +8,21 @@ use slotmap::{DefaultKey, SlotMap};
 use ::image::DynamicImage;
 
 #[cfg(feature = "image-loading")]
-use std::convert::TryFrom;
+use std::borrow::Cow;
 
 use crate::{ErrorKind, Renderer};
 
 /// An image handle.
+///
+/// Handles are generational: once an image is deleted its handle never refers to another image, even if
+/// the new image reuses the storage of the deleted one.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ImageId(DefaultKey);
 
 /// Specifies the format of an image's pixels.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PixelFormat {
     /// 24-bit RGB image format (8 bits per channel)
     Rgb8,
@@ -27,9 +32,52 @@ pub enum PixelFormat {
     Gray8,
 }
 
+impl PixelFormat {
+    pub(crate) fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb8 => 3,
+            Self::Rgba8 => 4,
+            Self::Gray8 => 1,
+        }
+    }
+}
+
+/// Specifies the format of pixels read back with [`Canvas::screenshot_area()`](crate::Canvas::screenshot_area).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ReadbackFormat {
+    /// 32-bit RGBA format (8 bits per channel, including alpha)
+    Rgba8,
+    /// 32-bit BGRA format (8 bits per channel, including alpha), as used by many windowing systems
+    Bgra8,
+    /// 24-bit RGB format (8 bits per channel), dropping alpha
+    Rgb8,
+}
+
+impl ReadbackFormat {
+    /// Returns the number of bytes of a pixel in this format.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgba8 | Self::Bgra8 => 4,
+            Self::Rgb8 => 3,
+        }
+    }
+
+    // Writes the pixels in this format to the start of `buffer`.
+    pub(crate) fn write_pixels(self, pixels: impl IntoIterator<Item = RGBA8>, buffer: &mut [u8]) {
+        for (pixel, dst) in pixels.into_iter().zip(buffer.chunks_exact_mut(self.bytes_per_pixel())) {
+            match self {
+                Self::Rgba8 => dst.copy_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]),
+                Self::Bgra8 => dst.copy_from_slice(&[pixel.b, pixel.g, pixel.r, pixel.a]),
+                Self::Rgb8 => dst.copy_from_slice(&[pixel.r, pixel.g, pixel.b]),
+            }
+        }
+    }
+}
+
 bitflags! {
     /// Represents a set of flags that modify the behavior of an image.
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct ImageFlags: u32 {
         /// Generates mipmaps during the creation of the image.
         const GENERATE_MIPMAPS = 1;
@@ -56,9 +104,17 @@ pub enum ImageSource<'a> {
     Rgba(ImgRef<'a, RGBA8>),
     /// Image source with 8-bit grayscale image format
     Gray(ImgRef<'a, Gray<u8>>),
+    /// Image source referencing an image of the `image` crate that doesn't have 8 bits per channel, which is
+    /// converted to the closest 8-bit format when it's uploaded
+    #[cfg(feature = "image-loading")]
+    Dynamic(&'a DynamicImage),
     /// Image source referencing a HTML image element (only available on `wasm32` target)
     #[cfg(target_arch = "wasm32")]
     HtmlImageElement(&'a web_sys::HtmlImageElement),
+    /// Image source referencing a decoded image bitmap, which unlike image elements is available in web workers
+    /// (only available on `wasm32` target)
+    #[cfg(target_arch = "wasm32")]
+    ImageBitmap(&'a web_sys::ImageBitmap),
 }
 
 impl ImageSource<'_> {
@@ -68,8 +124,10 @@ impl ImageSource<'_> {
             Self::Rgb(_) => PixelFormat::Rgb8,
             Self::Rgba(_) => PixelFormat::Rgba8,
             Self::Gray(_) => PixelFormat::Gray8,
+            #[cfg(feature = "image-loading")]
+            Self::Dynamic(image) => dynamic_image_format(image),
             #[cfg(target_arch = "wasm32")]
-            Self::HtmlImageElement(_) => PixelFormat::Rgba8,
+            Self::HtmlImageElement(_) | Self::ImageBitmap(_) => PixelFormat::Rgba8,
         }
     }
 
@@ -79,8 +137,12 @@ impl ImageSource<'_> {
             Self::Rgb(imgref) => Size::new(imgref.width(), imgref.height()),
             Self::Rgba(imgref) => Size::new(imgref.width(), imgref.height()),
             Self::Gray(imgref) => Size::new(imgref.width(), imgref.height()),
+            #[cfg(feature = "image-loading")]
+            Self::Dynamic(image) => Size::new(image.width() as usize, image.height() as usize),
             #[cfg(target_arch = "wasm32")]
             Self::HtmlImageElement(element) => Size::new(element.width() as usize, element.height() as usize),
+            #[cfg(target_arch = "wasm32")]
+            Self::ImageBitmap(bitmap) => Size::new(bitmap.width() as usize, bitmap.height() as usize),
         }
     }
 }
@@ -110,12 +172,32 @@ impl<'a> From<&'a web_sys::HtmlImageElement> for ImageSource<'a> {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
+impl<'a> From<&'a web_sys::ImageBitmap> for ImageSource<'a> {
+    fn from(src: &'a web_sys::ImageBitmap) -> Self {
+        Self::ImageBitmap(src)
+    }
+}
+
 #[cfg(feature = "image-loading")]
 impl<'a> TryFrom<&'a DynamicImage> for ImageSource<'a> {
     type Error = ErrorKind;
 
+    /// Never fails since images of every format are converted, see [`ImageSource::from_dynamic_image()`].
     fn try_from(src: &'a DynamicImage) -> Result<Self, ErrorKind> {
-        Ok(match src {
+        Ok(Self::from_dynamic_image(src))
+    }
+}
+
+#[cfg(feature = "image-loading")]
+impl<'a> ImageSource<'a> {
+    /// Creates an image source referencing an image of the `image` crate.
+    ///
+    /// 8-bit gray, RGB and RGBA images are referenced as they are. Other formats are converted to the closest of
+    /// them when the source is uploaded: images with an alpha channel to RGBA, color images to RGB and the rest to
+    /// gray.
+    pub fn from_dynamic_image(src: &'a DynamicImage) -> Self {
+        match src {
             ::image::DynamicImage::ImageLuma8(img) => {
                 let src: Img<&[Gray<u8>]> = Img::new(img.as_pixels(), img.width() as usize, img.height() as usize);
                 ImageSource::from(src)
@@ -128,14 +210,194 @@ impl<'a> TryFrom<&'a DynamicImage> for ImageSource<'a> {
                 let src = Img::new(img.as_rgba(), img.width() as usize, img.height() as usize);
                 ImageSource::from(src)
             }
-            // TODO: if format is not supported maybe we should convert it here,
-            // But that is an expensive operation on the render thread that will remain hidden from the user
-            _ => return Err(ErrorKind::UnsupportedImageFormat),
+            _ => ImageSource::Dynamic(src),
+        }
+    }
+
+    // Returns the part of the source within the rectangle, which must lie within its dimensions.
+    pub(crate) fn sub_image(self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        match self {
+            Self::Rgb(img) => Self::Rgb(img.sub_image(x, y, width, height)),
+            Self::Rgba(img) => Self::Rgba(img.sub_image(x, y, width, height)),
+            Self::Gray(img) => Self::Gray(img.sub_image(x, y, width, height)),
+            Self::Dynamic(_) => unreachable!("images of the image crate are converted before taking a part of them"),
+            #[cfg(target_arch = "wasm32")]
+            source => source,
+        }
+    }
+}
+
+// Returns whether an image source can borrow the pixels of `image` as `format`.
+#[cfg(feature = "image-loading")]
+pub(crate) fn is_dynamic_image_format(image: &DynamicImage, format: PixelFormat) -> bool {
+    matches!(
+        (image, format),
+        (DynamicImage::ImageLuma8(_), PixelFormat::Gray8)
+            | (DynamicImage::ImageRgb8(_), PixelFormat::Rgb8)
+            | (DynamicImage::ImageRgba8(_), PixelFormat::Rgba8)
+    )
+}
+
+// Returns the format `image` is uploaded as, the closest one with 8 bits per channel if it has another format.
+#[cfg(feature = "image-loading")]
+pub(crate) fn dynamic_image_format(image: &DynamicImage) -> PixelFormat {
+    let color = image.color();
+
+    if color.has_alpha() {
+        PixelFormat::Rgba8
+    } else if color.has_color() {
+        PixelFormat::Rgb8
+    } else {
+        PixelFormat::Gray8
+    }
+}
+
+// Converts `image` to `format`, copying it only if an image source can't borrow its pixels as they are.
+#[cfg(feature = "image-loading")]
+pub(crate) fn convert_dynamic_image(image: &DynamicImage, format: PixelFormat) -> Cow<'_, DynamicImage> {
+    if is_dynamic_image_format(image, format) {
+        return Cow::Borrowed(image);
+    }
+
+    Cow::Owned(match format {
+        PixelFormat::Gray8 => image.to_luma8().into(),
+        PixelFormat::Rgb8 => image.to_rgb8().into(),
+        PixelFormat::Rgba8 => image.to_rgba8().into(),
+    })
+}
+
+// Converts premultiplied pixels, as read back from a render target, to an image with straight alpha.
+#[cfg(feature = "image-loading")]
+pub(crate) fn unpremultiplied_dynamic_image(img: ImgVec<RGBA8>) -> DynamicImage {
+    let (width, height) = (img.width() as u32, img.height() as u32);
+
+    let bytes = img
+        .pixels()
+        .flat_map(|pixel| {
+            let unpremultiply = |c: u8| match pixel.a {
+                0 => 0,
+                a => ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8,
+            };
+            [
+                unpremultiply(pixel.r),
+                unpremultiply(pixel.g),
+                unpremultiply(pixel.b),
+                pixel.a,
+            ]
         })
+        .collect();
+
+    ::image::RgbaImage::from_raw(width, height, bytes)
+        .expect("buffer matches the image size")
+        .into()
+}
+
+// Owned copy of the pixels of an image source, for uploads that happen after the source was borrowed.
+pub(crate) enum ImageData {
+    Rgb(ImgVec<RGB8>),
+    Rgba(ImgVec<RGBA8>),
+    Gray(ImgVec<Gray<u8>>),
+}
+
+impl ImageData {
+    pub(crate) fn new(src: ImageSource) -> Result<Self, ErrorKind> {
+        Ok(match src {
+            ImageSource::Rgb(img) => Self::Rgb(ImgVec::new(img.pixels().collect(), img.width(), img.height())),
+            ImageSource::Rgba(img) => Self::Rgba(ImgVec::new(img.pixels().collect(), img.width(), img.height())),
+            ImageSource::Gray(img) => Self::Gray(ImgVec::new(img.pixels().collect(), img.width(), img.height())),
+            #[cfg(feature = "image-loading")]
+            ImageSource::Dynamic(image) => {
+                let image = convert_dynamic_image(image, dynamic_image_format(image));
+                return Self::new(ImageSource::from_dynamic_image(&image));
+            }
+            #[cfg(target_arch = "wasm32")]
+            ImageSource::HtmlImageElement(..) | ImageSource::ImageBitmap(..) => {
+                return Err(ErrorKind::UnsupportedImageFormat)
+            }
+        })
+    }
+
+    pub(crate) fn as_source(&self) -> ImageSource<'_> {
+        match self {
+            Self::Rgb(img) => ImageSource::Rgb(img.as_ref()),
+            Self::Rgba(img) => ImageSource::Rgba(img.as_ref()),
+            Self::Gray(img) => ImageSource::Gray(img.as_ref()),
+        }
     }
+
+    // Returns `height` rows starting at row `y`.
+    pub(crate) fn rows(&self, y: usize, height: usize) -> ImageSource<'_> {
+        match self {
+            Self::Rgb(img) => ImageSource::Rgb(img.sub_image(0, y, img.width(), height)),
+            Self::Rgba(img) => ImageSource::Rgba(img.sub_image(0, y, img.width(), height)),
+            Self::Gray(img) => ImageSource::Gray(img.sub_image(0, y, img.width(), height)),
+        }
+    }
+}
+
+// The pixels of image data as tightly packed bytes, the serialized form of `ImageData`.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct RawImageData<'a> {
+    format: PixelFormat,
+    width: usize,
+    height: usize,
+    pixels: std::borrow::Cow<'a, [u8]>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ImageData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Image data is always created with a stride equal to its width
+        let (format, width, height, pixels) = match self {
+            Self::Rgb(img) => (PixelFormat::Rgb8, img.width(), img.height(), img.buf().as_bytes()),
+            Self::Rgba(img) => (PixelFormat::Rgba8, img.width(), img.height(), img.buf().as_bytes()),
+            Self::Gray(img) => (PixelFormat::Gray8, img.width(), img.height(), img.buf().as_bytes()),
+        };
+
+        RawImageData {
+            format,
+            width,
+            height,
+            pixels: pixels.into(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ImageData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawImageData::deserialize(deserializer)?;
+
+        let size = raw.width.checked_mul(raw.height);
+        if size.and_then(|size| size.checked_mul(raw.format.bytes_per_pixel())) != Some(raw.pixels.len()) {
+            return Err(serde::de::Error::custom("image data size doesn't match its dimensions"));
+        }
+
+        let pixels = &raw.pixels[..];
+        Ok(match raw.format {
+            PixelFormat::Rgb8 => Self::Rgb(ImgVec::new(pixels.as_rgb().to_vec(), raw.width, raw.height)),
+            PixelFormat::Rgba8 => Self::Rgba(ImgVec::new(pixels.as_rgba().to_vec(), raw.width, raw.height)),
+            PixelFormat::Gray8 => Self::Gray(ImgVec::new(
+                pixels.iter().map(|&value| Gray::new(value)).collect(),
+                raw.width,
+                raw.height,
+            )),
+        })
+    }
+}
+
+// An image created with `Canvas::create_image_streamed`, uploaded a few rows per flush.
+pub(crate) struct ImageUpload {
+    pub(crate) id: ImageId,
+    pub(crate) data: ImageData,
+    pub(crate) next_row: usize,
+    pub(crate) on_complete: Box<dyn FnOnce(ImageId)>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Size {
     pub width: usize,
     pub height: usize,
@@ -149,10 +411,12 @@ impl Size {
 
 /// Information about an image.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ImageInfo {
     flags: ImageFlags,
     size: Size,
     format: PixelFormat,
+    layers: usize,
 }
 
 impl ImageInfo {
@@ -162,9 +426,17 @@ impl ImageInfo {
             flags,
             size: Size { width, height },
             format,
+            layers: 1,
         }
     }
 
+    // Makes the image a texture array of `layers` layers of this size, used by glyph atlases.
+    #[cfg(feature = "text")]
+    pub(crate) fn with_layers(mut self, layers: usize) -> Self {
+        self.layers = layers;
+        self
+    }
+
     /// Returns the image flags.
     pub fn flags(&self) -> ImageFlags {
         self.flags
@@ -190,13 +462,24 @@ impl ImageInfo {
         self.format
     }
 
+    /// Returns the number of layers of the image. Glyph atlases are texture arrays with more than one layer
+    /// when the renderer supports them, all other images have a single layer.
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
     /// Sets the image format.
     pub fn set_format(&mut self, format: PixelFormat) {
         self.format = format;
     }
 }
 
-pub struct ImageStore<T>(SlotMap<DefaultKey, (ImageInfo, T)>);
+pub struct ImageStore<T> {
+    images: SlotMap<DefaultKey, (ImageInfo, T)>,
+    // Number of updates and uploaded bytes since the last call to `take_uploads`
+    uploads: usize,
+    upload_bytes: usize,
+}
 
 impl<T> Default for ImageStore<T> {
     fn default() -> Self {
@@ -206,12 +489,20 @@ impl<T> Default for ImageStore<T> {
 
 impl<T> ImageStore<T> {
     pub fn new() -> Self {
-        Self(SlotMap::new())
+        Self {
+            images: SlotMap::new(),
+            uploads: 0,
+            upload_bytes: 0,
+        }
     }
 
     pub fn alloc<R: Renderer<Image = T>>(&mut self, renderer: &mut R, info: ImageInfo) -> Result<ImageId, ErrorKind> {
+        if info.width() == 0 || info.height() == 0 {
+            return Err(ErrorKind::UnsupportedImageSize);
+        }
+
         let image = renderer.alloc_image(info)?;
-        Ok(ImageId(self.0.insert((info, image))))
+        Ok(ImageId(self.images.insert((info, image))))
     }
 
     pub fn register_native_texture<R: Renderer<Image = T>>(
@@ -221,7 +512,7 @@ impl<T> ImageStore<T> {
         info: ImageInfo,
     ) -> Result<ImageId, ErrorKind> {
         let image = renderer.create_image_from_native_texture(texture, info)?;
-        Ok(ImageId(self.0.insert((info, image))))
+        Ok(ImageId(self.images.insert((info, image))))
     }
 
     // Reallocates the image without changing the id.
@@ -231,7 +522,11 @@ impl<T> ImageStore<T> {
         id: ImageId,
         info: ImageInfo,
     ) -> Result<(), ErrorKind> {
-        if let Some(old) = self.0.get_mut(id.0) {
+        if info.width() == 0 || info.height() == 0 {
+            return Err(ErrorKind::UnsupportedImageSize);
+        }
+
+        if let Some(old) = self.images.get_mut(id.0) {
             let new = renderer.alloc_image(info)?;
             old.0 = info;
             old.1 = new;
@@ -242,11 +537,11 @@ impl<T> ImageStore<T> {
     }
 
     pub fn get(&self, id: ImageId) -> Option<&T> {
-        self.0.get(id.0).map(|inner| &inner.1)
+        self.images.get(id.0).map(|inner| &inner.1)
     }
 
     pub fn get_mut(&mut self, id: ImageId) -> Option<&mut T> {
-        self.0.get_mut(id.0).map(|inner| &mut inner.1)
+        self.images.get_mut(id.0).map(|inner| &mut inner.1)
     }
 
     pub fn update<R: Renderer<Image = T>>(
@@ -257,8 +552,35 @@ impl<T> ImageStore<T> {
         x: usize,
         y: usize,
     ) -> Result<(), ErrorKind> {
-        if let Some(image) = self.0.get_mut(id.0) {
-            renderer.update_image(&mut image.1, data, x, y)?;
+        self.update_layer(renderer, id, data, x, y, 0)
+    }
+
+    pub fn update_layer<R: Renderer<Image = T>>(
+        &mut self,
+        renderer: &mut R,
+        id: ImageId,
+        data: ImageSource,
+        x: usize,
+        y: usize,
+        layer: usize,
+    ) -> Result<(), ErrorKind> {
+        // Renderers only upload pixels with 8 bits per channel
+        #[cfg(feature = "image-loading")]
+        if let ImageSource::Dynamic(image) = data {
+            let image = convert_dynamic_image(image, dynamic_image_format(image));
+            return self.update_layer(renderer, id, ImageSource::from_dynamic_image(&image), x, y, layer);
+        }
+
+        if let Some(image) = self.images.get_mut(id.0) {
+            let size = data.dimensions();
+            let bytes = size.width * size.height * data.format().bytes_per_pixel();
+            if layer == 0 {
+                renderer.update_image(&mut image.1, data, x, y)?;
+            } else {
+                renderer.update_image_layer(&mut image.1, data, x, y, layer)?;
+            }
+            self.uploads += 1;
+            self.upload_bytes += bytes;
             Ok(())
         } else {
             Err(ErrorKind::ImageIdNotFound)
@@ -266,29 +588,53 @@ impl<T> ImageStore<T> {
     }
 
     pub fn info(&self, id: ImageId) -> Option<ImageInfo> {
-        self.0.get(id.0).map(|inner| inner.0)
+        self.images.get(id.0).map(|inner| inner.0)
     }
 
     pub fn remove<R: Renderer<Image = T>>(&mut self, renderer: &mut R, id: ImageId) {
-        if let Some(image) = self.0.remove(id.0) {
+        if let Some(image) = self.images.remove(id.0) {
             renderer.delete_image(image.1, id);
         }
     }
 
     pub fn clear<R: Renderer<Image = T>>(&mut self, renderer: &mut R) {
-        for (idx, image) in self.0.drain() {
+        for (idx, image) in self.images.drain() {
             renderer.delete_image(image.1, ImageId(idx));
         }
     }
+
+    // Returns the number of updates and uploaded bytes since the last call and resets them.
+    pub fn take_uploads(&mut self) -> (usize, usize) {
+        (
+            std::mem::take(&mut self.uploads),
+            std::mem::take(&mut self.upload_bytes),
+        )
+    }
+
+    // Estimates the memory used by all images, not counting mipmaps.
+    pub fn memory_usage(&self) -> usize {
+        self.images
+            .values()
+            .map(|(info, _)| info.width() * info.height() * info.layers() * info.format().bytes_per_pixel())
+            .sum()
+    }
 }
 
 /// Specifies the type of filter to apply to images with `crate::Canvas::filter_image`.
 #[derive(Clone, Copy, Debug)]
 #[non_exhaustive]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ImageFilter {
     /// Applies a Gaussian blur filter with the specified standard deviation.
     GaussianBlur {
         /// The standard deviation of the Gaussian blur filter.
         sigma: f32,
     },
+    /// Approximates a blur of the specified radius with a dual Kawase blur, which repeatedly halves the image
+    /// and scales it back up. Its cost barely grows with the radius, which makes it suitable for large blurs of
+    /// shadows or frosted glass at interactive frame rates.
+    FastBlur {
+        /// The radius of the blur in pixels.
+        radius: f32,
+    },
 }
@@ -19,50 +19,83 @@ TODO:
 #[macro_use]
 extern crate serde;
 
-use std::{cell::RefCell, ops::Range, path::Path as FilePath, rc::Rc};
+#[cfg(feature = "text")]
+use std::ops::Range;
+#[cfg(any(feature = "text", feature = "image-loading"))]
+use std::path::Path as FilePath;
+#[cfg(any(feature = "text", test))]
+use std::{cell::RefCell, rc::Rc};
+use std::{
+    collections::VecDeque,
+    ops::{Deref, DerefMut},
+};
 
+use fnv::FnvHashMap;
 use imgref::ImgVec;
 use rgb::RGBA8;
+use slotmap::{DefaultKey, SlotMap};
 
+#[cfg(feature = "text")]
 mod text;
 
 mod error;
 pub use error::ErrorKind;
 
+#[cfg(feature = "fontdb")]
+pub use text::FontStyle;
+#[cfg(feature = "text")]
 pub use text::{
-    Align, Atlas, Baseline, DrawCommand, FontId, FontMetrics, GlyphDrawCommands, Quad, RenderMode, TextContext,
-    TextMetrics,
+    script, Align, Baseline, FontId, FontMetrics, FontSet, ParagraphAlign, RenderMode, RustybuzzShaper, Script,
+    ShapedGlyphInfo, ShapingRun, TextContext, TextDecoration, TextDirection, TextHinting, TextLayout, TextLine,
+    TextMetrics, TextRun, TextShaper,
 };
 
+#[cfg(feature = "text")]
 use text::{GlyphAtlas, TextContextImpl};
 
+mod glyph;
+pub use glyph::{Atlas, DrawCommand, GlyphDrawCommands, Quad, TexturedQuad};
+
 mod image;
-use crate::image::ImageStore;
-pub use crate::image::{ImageFilter, ImageFlags, ImageId, ImageInfo, ImageSource, PixelFormat};
+#[cfg(feature = "image-loading")]
+use crate::image::{convert_dynamic_image, is_dynamic_image_format, unpremultiplied_dynamic_image};
+use crate::image::{ImageData, ImageStore, ImageUpload};
+pub use crate::image::{ImageFilter, ImageFlags, ImageId, ImageInfo, ImageSource, PixelFormat, ReadbackFormat};
+#[cfg(feature = "image-loading")]
+use ::image::{DynamicImage, GenericImageView, SubImage};
 
 mod color;
 pub use color::Color;
 
 pub mod renderer;
-pub use renderer::{RenderTarget, Renderer};
+pub use renderer::{CompressedFormats, RenderTarget, Renderer, RendererCapabilities};
 
-use renderer::{Command, CommandType, Drawable, Params, ShaderType, SurfacelessRenderer, Vertex};
+use renderer::{
+    Command, CommandType, Drawable, Frame, FrameImage, ImageOp, Outline, PaintBinding, Params, Recorder, ShaderType,
+    SurfacelessRenderer, SvgRenderer, Vertex,
+};
 
 pub(crate) mod geometry;
-pub use geometry::Transform2D;
 use geometry::*;
+pub use geometry::{Rect, Transform2D, TransformComponents};
 
 mod paint;
-pub use paint::Paint;
+pub use paint::{FringeWidth, Paint, PaintId, ShaderId};
 use paint::{GlyphTexture, PaintFlavor, StrokeSettings};
 
 mod path;
-use path::Convexity;
+use path::{Convexity, PathCache};
 pub use path::{Path, PathIter, Solidity, Verb};
 
 mod gradient_store;
 use gradient_store::GradientStore;
 
+mod frame_stats;
+pub use frame_stats::FrameStats;
+
+#[cfg(feature = "svg")]
+pub mod svg;
+
 /// Determines the fill rule used when filling paths.
 ///
 /// The fill rule defines how the interior of a shape is determined.
@@ -81,6 +114,7 @@ pub enum FillRule {
 
 /// Blend factors.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum BlendFactor {
     /// Not all
     Zero,
@@ -135,6 +169,7 @@ pub enum CompositeOperation {
 
 /// Determines how a new ("source") data is displayed against an existing ("destination") data.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompositeOperationState {
     src_rgb: BlendFactor,
     src_alpha: BlendFactor,
@@ -257,6 +292,47 @@ pub enum LineJoin {
     Bevel,
 }
 
+/// Determines how a gradient continues beyond its start and end.
+///
+/// The default value is `Pad`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Spread {
+    /// The colors at the start and the end extend infinitely.
+    #[default]
+    Pad,
+    /// The gradient repeats, starting over at the start color after the end.
+    Repeat,
+    /// The gradient repeats, going back and forth between the start and the end.
+    Reflect,
+}
+
+/// Controls how finely paths are flattened into line segments and how wide their antialiasing fringe is.
+///
+/// All values are in device pixels at a device pixel ratio of 1 and are divided by the ratio passed to
+/// [`Canvas::set_size()`]. Curves are flattened after the canvas transform is applied, so zooming in
+/// doesn't make them look polygonal, but smaller tolerances are still useful for precise output and larger
+/// ones save CPU time on complex scenes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TessellationQuality {
+    /// Maximum distance between a curve and the line segments approximating it. Defaults to 0.25.
+    pub tolerance: f32,
+    /// Distance below which consecutive points are merged. Defaults to 0.01.
+    pub distance_tolerance: f32,
+    /// Width of the antialiasing fringe around filled and stroked shapes. Defaults to 1.0.
+    pub fringe_width: f32,
+}
+
+impl Default for TessellationQuality {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.25,
+            distance_tolerance: 0.01,
+            fringe_width: 1.0,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 struct State {
     composite_operation: CompositeOperationState,
@@ -276,17 +352,138 @@ impl Default for State {
     }
 }
 
+/// A snapshot of the full render state of a [`Canvas`], see [`Canvas::state_snapshot()`].
+#[derive(Clone, Debug)]
+pub struct StateSnapshot {
+    state_stack: Vec<State>,
+    render_target: RenderTarget,
+}
+
+/// Restores the render state of a [`Canvas`] when dropped, see [`Canvas::save_guard()`] and
+/// [`Canvas::checkpoint()`].
+///
+/// The guard dereferences to the canvas, so drawing continues through it. Because the state is restored in
+/// `Drop`, early returns with `?` can't leave the canvas in a modified state.
+pub struct SaveGuard<'a, T: Renderer> {
+    canvas: &'a mut Canvas<T>,
+    // Restored on drop if the guard is a checkpoint, otherwise the saved state is popped with `restore()`
+    snapshot: Option<StateSnapshot>,
+}
+
+impl<T: Renderer> Deref for SaveGuard<'_, T> {
+    type Target = Canvas<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.canvas
+    }
+}
+
+impl<T: Renderer> DerefMut for SaveGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.canvas
+    }
+}
+
+impl<T: Renderer> Drop for SaveGuard<'_, T> {
+    fn drop(&mut self) {
+        match &self.snapshot {
+            Some(snapshot) => self.canvas.restore_snapshot(snapshot),
+            None => self.canvas.restore(),
+        }
+    }
+}
+
+// A path flattened by `Canvas::fill_path_cached`
+struct KeyedPathCache {
+    // Content cache key of the path the cache was built from
+    key: u64,
+    path_cache: PathCache,
+    // Whether the cache was drawn since the last flush
+    used: bool,
+}
+
+// The GPU state of a fill, shared by all paths filled with the same paint
+struct FillStyle {
+    // Paint with the global alpha applied
+    paint_flavor: PaintFlavor,
+    paint_transform: Transform2D,
+    // Width of the fringe the paths are expanded by, zero for shapes that aren't anti-aliased
+    fringe_width: f32,
+    fill_rule: FillRule,
+    params: Params,
+    image: Option<ImageId>,
+    composite_operation: CompositeOperationState,
+    paint_binding: Option<PaintBinding>,
+}
+
+// The GPU state of a stroke, shared by all paths stroked with the same paint
+struct StrokeStyle {
+    stroke: StrokeSettings,
+    // Line width scaled by the transform and widened to the fringe for hairlines
+    line_width: f32,
+    fringe_width: f32,
+    path_gradient: bool,
+    params: Params,
+    // Params of the second pass of stencil strokes
+    stencil_params: Option<Params>,
+    image: Option<ImageId>,
+    composite_operation: CompositeOperationState,
+    paint_binding: Option<PaintBinding>,
+}
+
+// The stroke states of a paint, paths that can be pixel snapped are stroked with a width rounded to whole pixels
+struct StrokeStyles {
+    style: StrokeStyle,
+    // Style of pixel snapped paths along with the offsets of the stroke and its ends from the pixel grid
+    snapped: Option<(StrokeStyle, f32, [f32; 2])>,
+}
+
+impl StrokeStyles {
+    fn get(&self, snapped: bool) -> &StrokeStyle {
+        match &self.snapped {
+            Some((style, ..)) if snapped => style,
+            _ => &self.style,
+        }
+    }
+
+    // Returns the path snapped to the pixel grid if pixel snapping is enabled and the path is axis-aligned.
+    fn snap(&self, path_cache: &PathCache) -> Option<PathCache> {
+        let (_, offset, end_offsets) = self.snapped.as_ref()?;
+        path_cache.snap_to_pixel_grid(*offset, Some(*end_offsets))
+    }
+}
+
+// Geometry of paths drawn as a single shape by one command
+#[derive(Default)]
+struct PathBatch {
+    drawables: Vec<Drawable>,
+    outlines: Vec<Outline>,
+    bounds: Bounds,
+    // Whether any path isn't a single convex contour, these are filled through the stencil buffer
+    concave: bool,
+}
+
 /// Main 2D drawing context.
 pub struct Canvas<T: Renderer> {
     width: u32,
     height: u32,
     renderer: T,
+    #[cfg(feature = "text")]
     text_context: Rc<RefCell<TextContextImpl>>,
+    #[cfg(feature = "text")]
     glyph_atlas: Rc<GlyphAtlas>,
     // Glyph atlas used for direct rendering of color glyphs, dropped after flush()
+    #[cfg(feature = "text")]
     ephemeral_glyph_atlas: Option<Rc<GlyphAtlas>>,
     current_render_target: RenderTarget,
+    // Layer of the current render target image, only glyph atlases backed by a texture array render to other
+    // layers than 0
+    current_render_target_layer: usize,
+    // Render target the commands since the last flush start out drawing to
+    frame_render_target: RenderTarget,
     state_stack: Vec<State>,
+    // Render states to return to when popping render targets
+    render_target_stack: Vec<StateSnapshot>,
     commands: Vec<Command>,
     verts: Vec<Vertex>,
     images: ImageStore<T::Image>,
@@ -294,7 +491,26 @@ pub struct Canvas<T: Renderer> {
     device_px_ratio: f32,
     tess_tol: f32,
     dist_tol: f32,
+    tessellation_quality: TessellationQuality,
     gradients: GradientStore,
+    paints: SlotMap<DefaultKey, Paint>,
+    shaders: SlotMap<DefaultKey, ()>,
+    fixed_point_bits: Option<u32>,
+    pixel_snapping: bool,
+    // Images allocated for frames of recorder canvases, by recorder and image serial
+    frame_images: FnvHashMap<(u64, u64), ImageId>,
+    // Images deleted by a recorded frame, removed once the frame was rendered
+    released_frame_images: Vec<ImageId>,
+    frame_stats: FrameStats,
+    // Errors passed to the error callback since the last flush
+    reported_errors: usize,
+    error_callback: Option<Box<dyn FnMut(ErrorKind)>>,
+    // Images created with `create_image_streamed` that still have rows to upload, in creation order
+    image_uploads: VecDeque<ImageUpload>,
+    // Bytes of streamed images uploaded per flush
+    image_upload_budget: usize,
+    // Flattened paths stored under the keys of `fill_path_cached`
+    path_caches: FnvHashMap<u64, KeyedPathCache>,
 }
 
 impl<T> Canvas<T>
@@ -307,10 +523,16 @@ where
             width: 0,
             height: 0,
             renderer,
+            #[cfg(feature = "text")]
             text_context: Default::default(),
+            #[cfg(feature = "text")]
             glyph_atlas: Default::default(),
+            #[cfg(feature = "text")]
             ephemeral_glyph_atlas: None,
             current_render_target: RenderTarget::Screen,
+            current_render_target_layer: 0,
+            frame_render_target: RenderTarget::Screen,
+            render_target_stack: Vec::new(),
             state_stack: Vec::new(),
             commands: Vec::new(),
             verts: Vec::new(),
@@ -319,7 +541,20 @@ where
             device_px_ratio: 1.0,
             tess_tol: 0.25,
             dist_tol: 0.01,
+            tessellation_quality: TessellationQuality::default(),
             gradients: GradientStore::new(),
+            paints: SlotMap::new(),
+            shaders: SlotMap::new(),
+            fixed_point_bits: None,
+            pixel_snapping: false,
+            frame_images: FnvHashMap::default(),
+            released_frame_images: Vec::new(),
+            frame_stats: FrameStats::default(),
+            reported_errors: 0,
+            error_callback: None,
+            image_uploads: VecDeque::new(),
+            image_upload_budget: 4 * 1024 * 1024,
+            path_caches: FnvHashMap::default(),
         };
 
         canvas.save();
@@ -330,6 +565,7 @@ where
     /// Creates a new canvas with the specified renderer and using the fonts registered with the
     /// provided [`TextContext`]. Note that the context is explicitly shared, so that any fonts
     /// registered with a clone of this context will also be visible to this canvas.
+    #[cfg(feature = "text")]
     pub fn new_with_text_context(renderer: T, text_context: TextContext) -> Result<Self, ErrorKind> {
         let mut canvas = Self {
             width: 0,
@@ -339,6 +575,9 @@ where
             glyph_atlas: Default::default(),
             ephemeral_glyph_atlas: None,
             current_render_target: RenderTarget::Screen,
+            current_render_target_layer: 0,
+            frame_render_target: RenderTarget::Screen,
+            render_target_stack: Vec::new(),
             state_stack: Vec::new(),
             commands: Vec::new(),
             verts: Vec::new(),
@@ -347,7 +586,20 @@ where
             device_px_ratio: 1.0,
             tess_tol: 0.25,
             dist_tol: 0.01,
+            tessellation_quality: TessellationQuality::default(),
             gradients: GradientStore::new(),
+            paints: SlotMap::new(),
+            shaders: SlotMap::new(),
+            fixed_point_bits: None,
+            pixel_snapping: false,
+            frame_images: FnvHashMap::default(),
+            released_frame_images: Vec::new(),
+            frame_stats: FrameStats::default(),
+            reported_errors: 0,
+            error_callback: None,
+            image_uploads: VecDeque::new(),
+            image_upload_budget: 4 * 1024 * 1024,
+            path_caches: FnvHashMap::default(),
         };
 
         canvas.save();
@@ -359,16 +611,260 @@ where
     pub fn set_size(&mut self, width: u32, height: u32, dpi: f32) {
         self.width = width;
         self.height = height;
-        self.fringe_width = 1.0 / dpi;
-        self.tess_tol = 0.25 / dpi;
-        self.dist_tol = 0.01 / dpi;
         self.device_px_ratio = dpi;
+        self.update_tolerances();
 
         self.renderer.set_size(width, height, dpi);
 
         self.append_cmd(Command::new(CommandType::SetRenderTarget(RenderTarget::Screen)));
     }
 
+    /// Returns the tolerances used to flatten and antialias paths.
+    pub fn tessellation_quality(&self) -> TessellationQuality {
+        self.tessellation_quality
+    }
+
+    /// Sets the tolerances used to flatten and antialias paths drawn from now on.
+    pub fn set_tessellation_quality(&mut self, quality: TessellationQuality) {
+        self.tessellation_quality = quality;
+        self.update_tolerances();
+    }
+
+    fn update_tolerances(&mut self) {
+        let quality = self.tessellation_quality;
+        let dpi = self.device_px_ratio;
+
+        self.fringe_width = quality.fringe_width / dpi;
+        self.tess_tol = quality.tolerance / dpi;
+        self.dist_tol = quality.distance_tolerance / dpi;
+    }
+
+    /// Returns the number of fractional bits vertex positions are snapped to, if any.
+    pub fn fixed_point_precision(&self) -> Option<u32> {
+        self.fixed_point_bits
+    }
+
+    /// Makes all geometry sent to the GPU use fixed-point device coordinates.
+    ///
+    /// With `Some(bits)`, vertex positions are rounded to multiples of `1 / 2^bits` device pixels
+    /// before they are handed to the renderer, so that the same drawing rasterizes to the same
+    /// pixels regardless of the floating point precision of the GPU or driver. `bits` is clamped to
+    /// 8, the subpixel precision of common GPU rasterizers. `None`, the default, disables snapping.
+    pub fn set_fixed_point_precision(&mut self, bits: Option<u32>) {
+        self.fixed_point_bits = bits.map(|bits| bits.min(8));
+    }
+
+    /// Returns true if axis-aligned paths are snapped to the device pixel grid.
+    pub fn pixel_snapping(&self) -> bool {
+        self.pixel_snapping
+    }
+
+    /// Snaps paths made of horizontal and vertical lines only, such as rectangles, borders and separators, to the
+    /// device pixel grid before they are tessellated, so that they are drawn crisp instead of blurred across two
+    /// rows or columns of pixels. Disabled by default.
+    ///
+    /// Fills are snapped to whole device pixels. The width of strokes is rounded to whole device pixels, strokes of
+    /// odd widths and hairlines thinner than a pixel are centered on pixels and those of even widths on pixel
+    /// boundaries. Snapping happens after the transform is applied, so it doesn't depend on the device pixel ratio.
+    /// Curves, diagonal lines and text are never snapped, and neither are strokes drawn with a transform that
+    /// scales the horizontal and vertical axes differently.
+    pub fn set_pixel_snapping(&mut self, enabled: bool) {
+        self.pixel_snapping = enabled;
+    }
+
+    // Returns the path snapped to whole device pixels if pixel snapping is enabled and the path is axis-aligned.
+    fn pixel_snapped_fill(&self, path_cache: &PathCache) -> Option<PathCache> {
+        if !self.pixel_snapping {
+            return None;
+        }
+
+        path_cache.snap_to_pixel_grid(0.0, None)
+    }
+
+    // Returns the path snapped so that the edges of its stroke fall on device pixel boundaries along with the
+    // stroke settings with the width rounded to whole device pixels, if pixel snapping is enabled, the path is
+    // axis-aligned and the transform scales both axes the same.
+    fn pixel_snapped_stroke(
+        &self,
+        path_cache: &PathCache,
+        stroke: &StrokeSettings,
+    ) -> Option<(PathCache, StrokeSettings)> {
+        let (stroke, offset, end_offsets) = self.pixel_snapped_stroke_settings(stroke)?;
+        let snapped = path_cache.snap_to_pixel_grid(offset, Some(end_offsets))?;
+
+        Some((snapped, stroke))
+    }
+
+    // Returns the stroke settings with the width rounded to whole device pixels along with the offsets of the
+    // stroke and its ends from the pixel grid, if pixel snapping is enabled and the transform scales both axes
+    // the same.
+    fn pixel_snapped_stroke_settings(&self, stroke: &StrokeSettings) -> Option<(StrokeSettings, f32, [f32; 2])> {
+        if !self.pixel_snapping {
+            return None;
+        }
+
+        // The width of a stroke can't be whole device pixels both horizontally and vertically if the axes are
+        // scaled differently, which is the case unless the rows of the transform are orthogonal and equally long
+        let Transform2D([a, b, c, d, ..]) = self.state().transform;
+        let (scale_sq, other_scale_sq) = (a * a + b * b, c * c + d * d);
+        let tolerance = scale_sq * 1e-4;
+        if scale_sq <= 0.0 || (scale_sq - other_scale_sq).abs() > tolerance || (a * c + b * d).abs() > tolerance {
+            return None;
+        }
+        let scale = scale_sq.sqrt();
+
+        // Hairlines keep their width, which is emulated with alpha
+        let line_width = stroke.line_width * scale;
+        let line_width = if line_width < 1.0 {
+            line_width
+        } else {
+            line_width.round()
+        };
+        let offset = if line_width < 1.0 || line_width % 2.0 == 1.0 {
+            0.5
+        } else {
+            0.0
+        };
+
+        // Butt caps end where their line does, other caps extend it by half the width
+        let end_offset = |cap: LineCap| if cap == LineCap::Butt { 0.0 } else { offset };
+        let end_offsets = [end_offset(stroke.line_cap_start), end_offset(stroke.line_cap_end)];
+
+        let stroke = StrokeSettings {
+            line_width: line_width / scale,
+            ..stroke.clone()
+        };
+
+        Some((stroke, offset, end_offsets))
+    }
+
+    // Rounds vertex positions to the fixed-point grid, if enabled.
+    fn snap_vertices(&mut self) {
+        let Some(bits) = self.fixed_point_bits else {
+            return;
+        };
+
+        let scale = (1u32 << bits) as f32;
+
+        for vertex in &mut self.verts {
+            vertex.x = (vertex.x * scale).round() / scale;
+            vertex.y = (vertex.y * scale).round() / scale;
+        }
+    }
+
+    /// Returns statistics about the most recent flush, such as the number of draw calls and texture
+    /// uploads.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    /// Returns the bounding box of everything drawn to the current render target since the last flush, in whole
+    /// device pixels, or `None` if nothing visible was drawn.
+    ///
+    /// The bounds are clipped to the size of the render target but not to the scissor, so they may be larger than
+    /// the area that actually changes. Use them to crop exported images or to size offscreen caches to their content.
+    pub fn content_bounds(&self) -> Option<Rect> {
+        let mut target = self.frame_render_target;
+        let mut bounds = Bounds::default();
+
+        for cmd in &self.commands {
+            match cmd.cmd_type {
+                CommandType::SetRenderTarget(new_target) => {
+                    target = new_target;
+                    continue;
+                }
+                // Filters draw into their own target image
+                CommandType::RenderFilteredImage { .. } => continue,
+                _ if target != self.current_render_target => continue,
+                _ => {}
+            }
+
+            self.add_command_bounds(cmd, &mut bounds);
+        }
+
+        let bounds = bounds.to_rect();
+        let (x, y) = (bounds.x.floor(), bounds.y.floor());
+        let pixels = Rect::new(x, y, (bounds.x + bounds.w).ceil() - x, (bounds.y + bounds.h).ceil() - y);
+
+        pixels.intersection(&Rect::new(0.0, 0.0, self.width() as f32, self.height() as f32))
+    }
+
+    // Extends `bounds` by the vertices drawn by the command.
+    fn add_command_bounds(&self, cmd: &Command, bounds: &mut Bounds) {
+        for (start, count) in cmd.vert_ranges() {
+            for vert in &self.verts[start..start + count] {
+                bounds.add_point(Position { x: vert.x, y: vert.y });
+            }
+        }
+    }
+
+    /// Returns the limits and optional features of the renderer, such as the largest supported image size.
+    pub fn capabilities(&self) -> RendererCapabilities {
+        self.renderer.capabilities()
+    }
+
+    /// Sets a callback that receives the errors of drawing operations that don't return a `Result`, such as
+    /// [`Canvas::fill_path`] failing to upload a gradient texture or referencing a deleted image, paths whose
+    /// coordinates are out of range after transformation and glyphs too large for the glyph atlas.
+    ///
+    /// Geometry referencing a missing texture is drawn without it, out of range points and oversized glyphs are left
+    /// out. Without a callback the errors are logged. Either way they are counted in [`FrameStats::errors`].
+    pub fn set_error_callback(&mut self, callback: impl FnMut(ErrorKind) + 'static) {
+        self.error_callback = Some(Box::new(callback));
+    }
+
+    // Passes an error that can't be returned to the error callback.
+    fn report_error(&mut self, error: ErrorKind) {
+        self.reported_errors += 1;
+
+        match &mut self.error_callback {
+            Some(callback) => callback(error),
+            None => log::warn!("{error:?}"),
+        }
+    }
+
+    // Whether the path overlaps the canvas, paths outside of it aren't drawn.
+    fn is_path_visible(&self, path_cache: &PathCache) -> bool {
+        path_cache.bounds.maxx >= 0.0
+            && path_cache.bounds.minx <= self.width() as f32
+            && path_cache.bounds.maxy >= 0.0
+            && path_cache.bounds.miny <= self.height() as f32
+    }
+
+    // Reports a path with points the transform moved out of range, once for each time the path is drawn.
+    fn check_path_range(&mut self, path_cache: &PathCache) {
+        if path_cache.out_of_range {
+            self.report_error(ErrorKind::PathOutOfRange);
+        }
+    }
+
+    // Collects the statistics of the commands about to be flushed.
+    fn update_frame_stats(&mut self) {
+        let mut stats = FrameStats::default();
+        stats.add_commands(&self.commands);
+
+        (stats.texture_uploads, stats.texture_upload_bytes) = self.images.take_uploads();
+
+        #[cfg(feature = "text")]
+        {
+            let glyph_textures = self.glyph_atlas.glyph_textures.borrow();
+            let (used_area, total_area) = glyph_textures.iter().fold((0, 0), |(used, total), texture| {
+                let (width, height) = texture.atlas.size();
+                (used + texture.atlas.used_area(), total + width * height)
+            });
+            stats.glyph_atlas_textures = glyph_textures.len();
+            if total_area > 0 {
+                stats.glyph_atlas_usage = used_area as f32 / total_area as f32;
+            }
+        }
+
+        stats.gradient_textures = self.gradients.texture_count();
+        stats.image_memory = self.images.memory_usage();
+        stats.errors = std::mem::take(&mut self.reported_errors);
+
+        self.frame_stats = stats;
+    }
+
     /// Clears the rectangle area defined by left upper corner (x,y), width and height with the provided color.
     pub fn clear_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Color) {
         let mut cmd = Command::new(CommandType::ClearRect { color });
@@ -419,6 +915,10 @@ where
     ///
     /// Call this at the end of each frame.
     pub fn flush_to_surface(&mut self, surface: &T::Surface) {
+        self.upload_image_chunks();
+        self.resolve_paint_bindings();
+        self.snap_vertices();
+        self.update_frame_stats();
         self.renderer.render(
             surface,
             &mut self.images,
@@ -426,18 +926,158 @@ where
             std::mem::take(&mut self.commands),
         );
         self.verts.clear();
+        for id in self.released_frame_images.drain(..) {
+            self.images.remove(&mut self.renderer, id);
+        }
         self.gradients
             .release_old_gradients(&mut self.images, &mut self.renderer);
+        self.release_unused_path_caches();
+        self.frame_render_target = self.current_render_target;
+        #[cfg(feature = "text")]
         if let Some(atlas) = self.ephemeral_glyph_atlas.take() {
             atlas.clear(self);
         }
     }
 
+    /// Appends the commands of a frame recorded by a [`Recorder`] canvas, for example on another thread, to this
+    /// canvas. The image uploads of the frame are performed right away, the drawing happens with the next flush.
+    ///
+    /// Fails without drawing anything if the commands of the frame refer to vertices the frame doesn't contain,
+    /// to custom shaders that weren't created on this canvas or to images that don't exist.
+    pub fn append_frame(&mut self, frame: Frame) -> Result<(), ErrorKind> {
+        let recorder = frame.recorder;
+
+        // Frames may have been deserialized, so their references are checked before drawing them
+        for cmd in &frame.commands {
+            let out_of_range =
+                |(start, count): (usize, usize)| start.checked_add(count).is_none_or(|end| end > frame.verts.len());
+            if cmd.vert_ranges().any(out_of_range) {
+                return Err(ErrorKind::FrameVertsOutOfRange);
+            }
+
+            if cmd.custom_shaders().any(|shader| !self.shaders.contains_key(shader.0)) {
+                return Err(ErrorKind::ShaderIdNotFound);
+            }
+        }
+
+        let mut released = Vec::new();
+
+        for op in frame.image_ops {
+            match op {
+                ImageOp::Alloc { serial, info } => {
+                    let id = self.images.alloc(&mut self.renderer, info)?;
+                    self.frame_images.insert((recorder, serial), id);
+                }
+                ImageOp::Update { serial, data, x, y } => {
+                    if let Some(&id) = self.frame_images.get(&(recorder, serial)) {
+                        self.images.update(&mut self.renderer, id, data.as_source(), x, y)?;
+                    }
+                }
+                ImageOp::Delete { serial } => released.push(serial),
+            }
+        }
+
+        // Images of the recorder are replaced by the ones allocated for them or the shared images of this canvas
+        let (images, frame_images) = (&self.images, &self.frame_images);
+        let mut missing_image = false;
+        let mut map_image = |id: ImageId| {
+            let mapped = match frame.images.get(&id) {
+                Some(FrameImage::Recorded(serial)) => frame_images.get(&(recorder, *serial)).copied(),
+                Some(FrameImage::Shared(shared)) => images.info(*shared).map(|_| *shared),
+                None => None,
+            };
+            missing_image |= mapped.is_none();
+            mapped.unwrap_or(id)
+        };
+
+        let offset = self.verts.len();
+
+        let mut commands = frame.commands;
+        for cmd in &mut commands {
+            cmd.offset_verts(offset);
+            cmd.map_images(&mut map_image);
+        }
+
+        let frame_render_target = match frame.render_target {
+            RenderTarget::Image(id) => RenderTarget::Image(map_image(id)),
+            RenderTarget::Screen => RenderTarget::Screen,
+        };
+
+        if missing_image {
+            return Err(ErrorKind::ImageIdNotFound);
+        }
+
+        // Recorded frames start out drawing to the screen
+        let render_target = self.current_render_target;
+        self.set_render_target(RenderTarget::Screen);
+
+        self.commands.extend(commands);
+        self.verts.extend(frame.verts);
+
+        self.current_render_target = frame_render_target;
+        self.current_render_target_layer = 0;
+        self.set_render_target(render_target);
+
+        for serial in released {
+            if let Some(id) = self.frame_images.remove(&(recorder, serial)) {
+                self.released_frame_images.push(id);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Returns a screenshot of the current canvas.
     pub fn screenshot(&mut self) -> Result<ImgVec<RGBA8>, ErrorKind> {
         self.renderer.screenshot()
     }
 
+    /// Returns a screenshot of the current canvas as an RGBA image of the `image` crate.
+    ///
+    /// The canvas renders with premultiplied alpha, the pixels of the returned image are converted to straight
+    /// alpha like the `image` crate expects, so they can be saved as is.
+    #[cfg(feature = "image-loading")]
+    pub fn screenshot_dynamic_image(&mut self) -> Result<DynamicImage, ErrorKind> {
+        self.screenshot().map(unpremultiplied_dynamic_image)
+    }
+
+    /// Reads back the pixels of an area of the current render target into `buffer`, without reading back
+    /// the whole target.
+    ///
+    /// `x` and `y` are the top left corner of the area in device pixels. The rows of the area are written
+    /// top to bottom without padding, so `buffer` must hold at least `width * height *
+    /// format.bytes_per_pixel()` bytes. Like [`Self::screenshot()`], commands that weren't flushed yet
+    /// aren't included. The area must lie within the current render target, which is the image for
+    /// [`RenderTarget::Image`].
+    pub fn screenshot_area(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        format: ReadbackFormat,
+        buffer: &mut [u8],
+    ) -> Result<(), ErrorKind> {
+        let (target_width, target_height) = match self.current_render_target {
+            RenderTarget::Image(id) => {
+                let info = self.images.info(id).ok_or(ErrorKind::ImageIdNotFound)?;
+                (info.width() as u32, info.height() as u32)
+            }
+            RenderTarget::Screen => (self.width, self.height),
+        };
+
+        if x.saturating_add(width) > target_width || y.saturating_add(height) > target_height {
+            return Err(ErrorKind::ReadbackOutOfBounds);
+        }
+
+        if buffer.len() < width as usize * height as usize * format.bytes_per_pixel() {
+            return Err(ErrorKind::BufferTooSmall);
+        }
+
+        self.renderer
+            .screenshot_area(x as usize, y as usize, width as usize, height as usize, format, buffer)
+    }
+
     // State Handling
 
     /// Pushes and saves the current render state into a state stack.
@@ -476,6 +1116,55 @@ where
         self.restore();
     }
 
+    /// Saves the current state and returns a guard that restores it when dropped.
+    ///
+    /// Like a matching `restore()` call, this pops a single state, so saves made through the guard without a
+    /// matching restore stay on the stack. Use [`Self::checkpoint()`] to discard those too.
+    pub fn save_guard(&mut self) -> SaveGuard<'_, T> {
+        self.save();
+
+        SaveGuard {
+            canvas: self,
+            snapshot: None,
+        }
+    }
+
+    /// Captures the full render state and returns a guard that restores it with [`Self::restore_snapshot()`]
+    /// when dropped.
+    ///
+    /// Unlike [`Self::save_guard()`], this also undoes unbalanced `save()` and `restore()` calls and changes of the
+    /// render target made through the guard.
+    pub fn checkpoint(&mut self) -> SaveGuard<'_, T> {
+        let snapshot = self.state_snapshot();
+
+        SaveGuard {
+            canvas: self,
+            snapshot: Some(snapshot),
+        }
+    }
+
+    /// Captures the full render state: the saved states, the current transform, scissor, composite
+    /// operation, global alpha and render target.
+    ///
+    /// Together with [`Self::restore_snapshot()`] this allows code that calls back into foreign draw
+    /// code to isolate itself without relying on balanced `save()` -> `restore()` calls.
+    pub fn state_snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            state_stack: self.state_stack.clone(),
+            render_target: self.current_render_target,
+        }
+    }
+
+    /// Restores the render state captured by [`Self::state_snapshot()`].
+    ///
+    /// States saved after the snapshot was taken are discarded. If states were restored past the
+    /// snapshot, the states that were saved when it was taken are brought back as well.
+    pub fn restore_snapshot(&mut self, snapshot: &StateSnapshot) {
+        self.state_stack.clone_from(&snapshot.state_stack);
+
+        self.set_render_target(snapshot.render_target);
+    }
+
     // Render styles
 
     /// Sets the transparency applied to all rendered shapes.
@@ -513,14 +1202,50 @@ where
 
     /// Sets a new render target. All drawing operations after this call will happen on the provided render target
     pub fn set_render_target(&mut self, target: RenderTarget) {
-        if self.current_render_target != target {
-            self.append_cmd(Command::new(CommandType::SetRenderTarget(target)));
+        self.set_render_target_layer(target, 0);
+    }
+
+    // Sets a layer of an image with several layers as render target, used to render into glyph atlases.
+    pub(crate) fn set_render_target_layer(&mut self, target: RenderTarget, layer: usize) {
+        if self.current_render_target != target || self.current_render_target_layer != layer {
+            let mut cmd = Command::new(CommandType::SetRenderTarget(target));
+            cmd.target_layer = layer;
+            self.append_cmd(cmd);
             self.current_render_target = target;
+            self.current_render_target_layer = layer;
+        }
+    }
+
+    /// Switches to a new render target, remembering the current one along with the render state.
+    ///
+    /// Drawing on the new target starts with the default transform, scissor, composite operation and global
+    /// alpha, and [`Self::width()`] and [`Self::height()`] return the size of the target. Calls can be nested,
+    /// for example to render a cached layer into an offscreen layer that is then blurred.
+    pub fn push_render_target(&mut self, target: RenderTarget) {
+        self.render_target_stack.push(self.state_snapshot());
+        self.save();
+        self.reset();
+        self.set_render_target(target);
+    }
+
+    /// Returns to the render target and render state of the matching [`Self::push_render_target()`] call.
+    ///
+    /// States saved and not restored since are discarded. Does nothing if no render target was pushed.
+    pub fn pop_render_target(&mut self) {
+        if let Some(snapshot) = self.render_target_stack.pop() {
+            self.restore_snapshot(&snapshot);
         }
     }
 
+    // Appends the command, merging it into the previous one if they only differ in their drawables.
     fn append_cmd(&mut self, cmd: Command) {
-        self.commands.push(cmd);
+        match self.commands.last_mut() {
+            Some(last) if last.can_merge(&cmd) => {
+                last.drawables.extend(cmd.drawables);
+                last.outlines.extend(cmd.outlines);
+            }
+            _ => self.commands.push(cmd),
+        }
     }
 
     // Images
@@ -566,26 +1291,117 @@ where
         Ok(id)
     }
 
-    /// Returns the native texture of an image given its ID.
-    pub fn get_native_texture(&self, id: ImageId) -> Result<T::NativeTexture, ErrorKind> {
-        self.get_image(id)
-            .ok_or(ErrorKind::ImageIdNotFound)
-            .and_then(|image| self.renderer.get_native_texture(image))
+    /// Creates an image from the specified image data, uploading it over several flushes instead of all at once.
+    ///
+    /// Use this for large images that would otherwise stall the frame they're created in. The data is copied and
+    /// at most [`Self::image_upload_budget()`] bytes of all streamed images are uploaded per flush, in the order
+    /// the images were created. The contents of the image are undefined until `on_complete` is called with its
+    /// id. Deleting the image before that cancels the upload.
+    pub fn create_image_streamed<'a, S: Into<ImageSource<'a>>>(
+        &mut self,
+        src: S,
+        flags: ImageFlags,
+        on_complete: impl FnOnce(ImageId) + 'static,
+    ) -> Result<ImageId, ErrorKind> {
+        let src = src.into();
+        let data = ImageData::new(src)?;
+        let size = src.dimensions();
+        let id = self.create_image_empty(size.width, size.height, src.format(), flags)?;
+
+        self.image_uploads.push_back(ImageUpload {
+            id,
+            data,
+            next_row: 0,
+            on_complete: Box::new(on_complete),
+        });
+
+        Ok(id)
     }
 
-    /// Retrieves a reference to the image with the specified ID.
-    pub fn get_image(&self, id: ImageId) -> Option<&T::Image> {
-        self.images.get(id)
+    /// Returns the number of bytes of streamed images uploaded per flush.
+    pub fn image_upload_budget(&self) -> usize {
+        self.image_upload_budget
     }
 
-    /// Retrieves a mutable reference to the image with the specified ID.
-    pub fn get_image_mut(&mut self, id: ImageId) -> Option<&mut T::Image> {
-        self.images.get_mut(id)
+    /// Sets the number of bytes of images created with [`Self::create_image_streamed()`] that are uploaded per
+    /// flush. At least one row of pixels is uploaded per flush regardless of the budget. The default is 4 MiB.
+    pub fn set_image_upload_budget(&mut self, bytes: usize) {
+        self.image_upload_budget = bytes;
     }
 
-    /// Resizes an image to the new provided dimensions.
-    pub fn realloc_image(
-        &mut self,
+    /// Returns the number of flushes the texture of a multi-stop gradient is kept for after it was last drawn.
+    pub fn gradient_texture_lifetime(&self) -> usize {
+        self.gradients.lifetime()
+    }
+
+    /// Sets the number of flushes the texture of a multi-stop gradient is kept for after it was last drawn, at
+    /// least 1. Longer lifetimes avoid recreating textures of gradients that are drawn intermittently, at the
+    /// cost of texture memory. The default is 1, releasing textures not drawn in the previous flush.
+    pub fn set_gradient_texture_lifetime(&mut self, flushes: usize) {
+        self.gradients.set_lifetime(flushes);
+    }
+
+    /// Releases the textures of multi-stop gradients that weren't drawn since the last flush, regardless of
+    /// their lifetime.
+    pub fn release_unused_gradients(&mut self) {
+        self.gradients.release_unused(&mut self.images, &mut self.renderer);
+    }
+
+    // Uploads the next rows of the streamed images, within the upload budget.
+    fn upload_image_chunks(&mut self) {
+        let mut budget = self.image_upload_budget.max(1);
+
+        while budget > 0 {
+            let Some(upload) = self.image_uploads.front_mut() else {
+                break;
+            };
+
+            let size = upload.data.as_source().dimensions();
+            let row_bytes = size.width * upload.data.as_source().format().bytes_per_pixel();
+            let rows = (budget / row_bytes.max(1)).clamp(1, size.height - upload.next_row);
+            budget = budget.saturating_sub(rows * row_bytes);
+
+            let result = self.images.update(
+                &mut self.renderer,
+                upload.id,
+                upload.data.rows(upload.next_row, rows),
+                0,
+                upload.next_row,
+            );
+            upload.next_row += rows;
+            let done = upload.next_row >= size.height;
+
+            if let Err(error) = result {
+                self.image_uploads.pop_front();
+                self.report_error(error);
+            } else if done {
+                if let Some(upload) = self.image_uploads.pop_front() {
+                    (upload.on_complete)(upload.id);
+                }
+            }
+        }
+    }
+
+    /// Returns the native texture of an image given its ID.
+    pub fn get_native_texture(&self, id: ImageId) -> Result<T::NativeTexture, ErrorKind> {
+        self.get_image(id)
+            .ok_or(ErrorKind::ImageIdNotFound)
+            .and_then(|image| self.renderer.get_native_texture(image))
+    }
+
+    /// Retrieves a reference to the image with the specified ID.
+    pub fn get_image(&self, id: ImageId) -> Option<&T::Image> {
+        self.images.get(id)
+    }
+
+    /// Retrieves a mutable reference to the image with the specified ID.
+    pub fn get_image_mut(&mut self, id: ImageId) -> Option<&mut T::Image> {
+        self.images.get_mut(id)
+    }
+
+    /// Resizes an image to the new provided dimensions.
+    pub fn realloc_image(
+        &mut self,
         id: ImageId,
         width: usize,
         height: usize,
@@ -605,9 +1421,7 @@ where
     ) -> Result<ImageId, ErrorKind> {
         let image = ::image::open(filename)?;
 
-        let src = ImageSource::try_from(&image)?;
-
-        self.create_image(src, flags)
+        self.create_image_from_dynamic_image(&image, flags)
     }
 
     /// Decode an image from memory
@@ -615,9 +1429,50 @@ where
     pub fn load_image_mem(&mut self, data: &[u8], flags: ImageFlags) -> Result<ImageId, ErrorKind> {
         let image = ::image::load_from_memory(data)?;
 
-        let src = ImageSource::try_from(&image)?;
+        self.create_image_from_dynamic_image(&image, flags)
+    }
+
+    /// Creates an image from an image of the `image` crate.
+    ///
+    /// 8-bit gray, RGB and RGBA images are uploaded as they are. Other formats are converted to the closest of
+    /// them first: images with an alpha channel to RGBA, color images to RGB and the rest to gray.
+    #[cfg(feature = "image-loading")]
+    pub fn create_image_from_dynamic_image(
+        &mut self,
+        image: &DynamicImage,
+        flags: ImageFlags,
+    ) -> Result<ImageId, ErrorKind> {
+        self.create_image(ImageSource::from_dynamic_image(image), flags)
+    }
+
+    /// Updates the image specified by image handle with a view into an image of the `image` crate, as returned
+    /// by [`GenericImageView::view()`].
+    ///
+    /// The pixels of the view are converted to the format of the updated image if they have another one.
+    #[cfg(feature = "image-loading")]
+    pub fn update_image_from_dynamic_image(
+        &mut self,
+        id: ImageId,
+        view: &SubImage<&DynamicImage>,
+        x: usize,
+        y: usize,
+    ) -> Result<(), ErrorKind> {
+        let format = self.image_info(id)?.format();
+        let (view_x, view_y) = view.offsets();
+        let (width, height) = view.dimensions();
+
+        let converted;
+        let (image, view_x, view_y) = if is_dynamic_image_format(view.inner(), format) {
+            (view.inner(), view_x, view_y)
+        } else {
+            // only the pixels within the view are converted
+            converted = convert_dynamic_image(&DynamicImage::from(view.to_image()), format).into_owned();
+            (&converted, 0, 0)
+        };
+
+        let src = ImageSource::from_dynamic_image(image).sub_image(view_x as _, view_y as _, width as _, height as _);
 
-        self.create_image(src, flags)
+        self.update_image(id, src, x, y)
     }
 
     /// Updates image data specified by image handle.
@@ -632,7 +1487,11 @@ where
     }
 
     /// Deletes created image.
+    ///
+    /// The id stays invalid even when a new image reuses the storage of the deleted one. Drawing with it is
+    /// skipped and reports [`ErrorKind::ImageIdNotFound`] to the error callback.
     pub fn delete_image(&mut self, id: ImageId) {
+        self.image_uploads.retain(|upload| upload.id != id);
         self.images.remove(&mut self.renderer, id);
     }
 
@@ -695,6 +1554,15 @@ where
         self.append_cmd(cmd)
     }
 
+    /// Renders a blurred copy of `source_image` into `target_image`, using [`ImageFilter::FastBlur`].
+    ///
+    /// The blur halves the source image a few times and scales it back up, so its cost stays nearly constant for
+    /// large radii. The target image must have the same size as the source image. Like
+    /// [`Self::filter_image()`], the blur runs when [`Self::flush()`] is called.
+    pub fn blur_image(&mut self, source_image: ImageId, target_image: ImageId, radius: f32) {
+        self.filter_image(target_image, ImageFilter::FastBlur { radius }, source_image);
+    }
+
     // Transforms
 
     /// Resets current transform to a identity matrix.
@@ -835,113 +1703,320 @@ where
     }
 
     /// Fills the provided Path with the specified Paint.
+    ///
+    /// Convex paths filled right after each other with the same paint, composite operation and scissor
+    /// are batched into a single draw call.
     pub fn fill_path(&mut self, path: &Path, paint: &Paint) {
-        self.fill_path_internal(path, &paint.flavor, paint.shape_anti_alias, paint.fill_rule);
+        self.draw_path(path, Some(paint), None);
     }
 
-    fn fill_path_internal(&mut self, path: &Path, paint_flavor: &PaintFlavor, anti_alias: bool, fill_rule: FillRule) {
-        let mut paint_flavor = paint_flavor.clone();
+    /// Fills and then strokes the provided Path with the specified Paints.
+    ///
+    /// This is equivalent to calling [`Self::fill_path()`] followed by [`Self::stroke_path()`], but
+    /// the path is flattened only once for both operations. Passing `None` skips the respective operation.
+    pub fn draw_path(&mut self, path: &Path, fill: Option<&Paint>, stroke: Option<&Paint>) {
+        self.draw_path_with_binding(path, fill, stroke, None);
+    }
+
+    // Fills and strokes the path, the paint is taken from the paint resource at flush time if `paint_id` is given.
+    fn draw_path_with_binding(
+        &mut self,
+        path: &Path,
+        fill: Option<&Paint>,
+        stroke: Option<&Paint>,
+        paint_id: Option<PaintId>,
+    ) {
         let transform = self.state().transform;
 
         // The path cache saves a flattened and transformed version of the path.
         let mut path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
 
-        let canvas_width = self.width();
-        let canvas_height = self.height();
+        // The fill and stroke share the flattened path, which is only reported once if it's out of range
+        self.check_path_range(&path_cache);
 
-        // Early out if path is outside the canvas bounds
-        if path_cache.bounds.maxx < 0.0
-            || path_cache.bounds.minx > canvas_width as f32
-            || path_cache.bounds.maxy < 0.0
-            || path_cache.bounds.miny > canvas_height as f32
-        {
+        if let Some(paint) = fill {
+            let mut snapped = self.pixel_snapped_fill(&path_cache);
+
+            self.fill_flattened_path(
+                snapped.as_mut().unwrap_or(&mut path_cache),
+                &paint.flavor,
+                &paint.transform,
+                self.shape_fringe_width(paint),
+                paint.fill_rule,
+                paint_id,
+                self.outline(path),
+            );
+        }
+
+        if let Some(paint) = stroke {
+            let mut snapped = self.pixel_snapped_stroke(&path_cache, &paint.stroke);
+            let (path_cache, stroke) = match &mut snapped {
+                Some((snapped, stroke)) => (snapped, &*stroke),
+                None => (&mut *path_cache, &paint.stroke),
+            };
+
+            self.stroke_flattened_path(
+                path_cache,
+                &paint.flavor,
+                &paint.transform,
+                self.shape_fringe_width(paint),
+                stroke,
+                paint_id,
+                None,
+                self.outline(path),
+            );
+        }
+    }
+
+    /// Draws the image, or the `src_rect` part of it, stretched to fill `dst_rect`.
+    ///
+    /// `src_rect` is in image pixels and defaults to the whole image, `dst_rect` is in the current
+    /// coordinate system. Nothing is drawn if the image doesn't exist or either rectangle is empty.
+    pub fn draw_image(&mut self, id: ImageId, src_rect: Option<Rect>, dst_rect: Rect, opacity: f32) {
+        if self.image_size(id).is_err() || src_rect.is_some_and(|rect| rect.is_empty()) || dst_rect.is_empty() {
             return;
         }
 
-        // Apply global alpha
-        paint_flavor.mul_alpha(self.state().alpha);
+        let transform = self.state().transform;
+        let Transform2D([_, b, c, ..]) = transform;
+        let (x0, y0) = transform.transform_point(dst_rect.x, dst_rect.y);
+        let (x1, y1) = transform.transform_point(dst_rect.x + dst_rect.w, dst_rect.y + dst_rect.h);
+        let pixel_aligned = b == 0.0 && c == 0.0 && [x0, y0, x1, y1].iter().all(|coord| coord.fract() == 0.0);
 
-        let scissor = self.state().scissor;
+        // Images with edges on whole pixels don't need anti-aliasing, which also allows drawing them as a plain blit
+        let paint = Paint::image(id, dst_rect.x, dst_rect.y, dst_rect.w, dst_rect.h, 0.0, opacity)
+            .with_source_rect(src_rect)
+            .with_anti_alias(!pixel_aligned);
+
+        let mut path = Path::new();
+        path.rect(dst_rect.x, dst_rect.y, dst_rect.w, dst_rect.h);
+
+        self.fill_path(&path, &paint);
+    }
+
+    /// Fills each path with its paint.
+    ///
+    /// This draws the same as calling [`Self::fill_path()`] for every item, but the GPU state of a paint is
+    /// resolved once for consecutive items sharing it, and consecutive convex paths with the same paint are
+    /// added to a single draw command as they are flattened. Image fills are always drawn through the regular
+    /// shader rather than as a plain blit.
+    pub fn fill_paths<'a>(&mut self, items: impl IntoIterator<Item = (&'a Path, &'a Paint)>) {
+        let transform = self.state().transform;
+
+        // The paint of the previous item and its fill state, which is only resolved again once the paint changes
+        let mut current: Option<(&Paint, Option<FillStyle>)> = None;
+        let mut batch = PathBatch::default();
+
+        for (path, paint) in items {
+            // The path cache saves a flattened and transformed version of the path.
+            let mut path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
+            self.check_path_range(&path_cache);
+
+            if !current
+                .as_ref()
+                .is_some_and(|(current, _)| std::ptr::eq(*current, paint))
+            {
+                if let Some((_, Some(style))) = &current {
+                    self.append_fill_command(style, std::mem::take(&mut batch));
+                }
+
+                let style = self.fill_style(
+                    &paint.flavor,
+                    &paint.transform,
+                    self.shape_fringe_width(paint),
+                    paint.fill_rule,
+                    None,
+                );
+                current = Some((paint, style));
+            }
+
+            let Some((_, Some(style))) = &current else {
+                continue;
+            };
+
+            let mut snapped = self.pixel_snapped_fill(&path_cache);
+            let path_cache = snapped.as_mut().unwrap_or(&mut path_cache);
+
+            if path_cache.contours.len() == 1 && path_cache.contours[0].convexity == Convexity::Convex {
+                self.add_fill_geometry(&mut batch, path_cache, style, self.outline(path));
+            } else {
+                // Paths filled through the stencil buffer get a command of their own, so that they don't
+                // affect each other
+                self.append_fill_command(style, std::mem::take(&mut batch));
+                self.add_fill_geometry(&mut batch, path_cache, style, self.outline(path));
+                self.append_fill_command(style, std::mem::take(&mut batch));
+            }
+        }
+
+        if let Some((_, Some(style))) = &current {
+            self.append_fill_command(style, batch);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    fn fill_path_internal(
+        &mut self,
+        path: &Path,
+        paint_flavor: &PaintFlavor,
+        fringe_width: Option<f32>,
+        fill_rule: FillRule,
+    ) {
+        let transform = self.state().transform;
+
+        // The path cache saves a flattened and transformed version of the path.
+        let mut path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
+        self.check_path_range(&path_cache);
+
+        self.fill_flattened_path(
+            &mut path_cache,
+            paint_flavor,
+            &Transform2D::identity(),
+            fringe_width,
+            fill_rule,
+            None,
+            self.outline(path),
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn fill_flattened_path(
+        &mut self,
+        path_cache: &mut PathCache,
+        paint_flavor: &PaintFlavor,
+        paint_transform: &Transform2D,
+        fringe_width: Option<f32>,
+        fill_rule: FillRule,
+        paint_id: Option<PaintId>,
+        outline: Option<Outline>,
+    ) {
+        // Early out if path is outside the canvas bounds
+        if !self.is_path_visible(path_cache) {
+            return;
+        }
+
+        let Some(style) = self.fill_style(paint_flavor, paint_transform, fringe_width, fill_rule, paint_id) else {
+            return;
+        };
 
         // Calculate fill vertices.
         // expand_fill will fill path_cache.contours[].{stroke, fill} with vertex data for the GPU
         // fringe_with is the size of the strip of triangles generated at the path border used for AA
-        let fringe_width = if anti_alias { self.fringe_width } else { 0.0 };
-        path_cache.expand_fill(fringe_width, LineJoin::Miter, 2.4);
+        path_cache.expand_fill(style.fringe_width, LineJoin::Miter, 2.4);
 
-        // Detect if this path fill is in fact just an unclipped image copy
+        // Detect if this path fill is in fact just an unclipped image copy.
+        // Paint resources may change before the flush, so they always take the regular path,
+        // as do images that are rotated or skewed by the paint transform and paths drawn as outlines.
+        let Transform2D([_, b, c, ..]) = style.paint_transform;
+        let scissor = self.state().scissor;
 
         if let (Some(path_rect), Some(scissor_rect), true) = (
             path_cache.path_fill_is_rect(),
-            scissor.as_rect(canvas_width as f32, canvas_height as f32),
-            paint_flavor.is_straight_tinted_image(anti_alias),
+            scissor.as_rect(self.width() as f32, self.height() as f32),
+            paint_id.is_none()
+                && outline.is_none()
+                && b == 0.0
+                && c == 0.0
+                && style.paint_flavor.is_straight_tinted_image(style.fringe_width > 0.0),
         ) {
             if scissor_rect.contains_rect(&path_rect) {
-                self.render_unclipped_image_blit(&path_rect, &transform, &paint_flavor);
+                self.render_unclipped_image_blit(&path_rect, &style.paint_transform, &style.paint_flavor);
             } else if let Some(intersection) = path_rect.intersection(&scissor_rect) {
-                self.render_unclipped_image_blit(&intersection, &transform, &paint_flavor);
+                self.render_unclipped_image_blit(&intersection, &style.paint_transform, &style.paint_flavor);
             }
 
             return;
         }
 
-        // GPU uniforms
-        let flavor = if path_cache.contours.len() == 1 && path_cache.contours[0].convexity == Convexity::Convex {
-            let params = Params::new(
-                &self.images,
-                &transform,
-                &paint_flavor,
-                &GlyphTexture::default(),
-                &scissor,
-                self.fringe_width,
-                self.fringe_width,
-                -1.0,
-            );
+        let mut batch = PathBatch::default();
+        self.add_fill_geometry(&mut batch, path_cache, &style, outline);
+        self.append_fill_command(&style, batch);
+    }
 
-            CommandType::ConvexFill { params }
-        } else {
-            let stencil_params = Params {
-                stroke_thr: -1.0,
-                shader_type: ShaderType::Stencil,
-                ..Params::default()
-            };
+    // Resolves the GPU state of filling with the paint, reporting an error and returning `None` if its image
+    // doesn't exist.
+    fn fill_style(
+        &mut self,
+        paint_flavor: &PaintFlavor,
+        paint_transform: &Transform2D,
+        fringe_width: Option<f32>,
+        fill_rule: FillRule,
+        paint_id: Option<PaintId>,
+    ) -> Option<FillStyle> {
+        let mut paint_flavor = paint_flavor.clone();
+        let transform = self.state().transform;
+        // The gradient or image pattern is transformed by the paint transform first
+        let paint_transform = *paint_transform * transform;
 
-            let fill_params = Params::new(
-                &self.images,
-                &transform,
-                &paint_flavor,
-                &GlyphTexture::default(),
-                &scissor,
-                self.fringe_width,
-                self.fringe_width,
-                -1.0,
-            );
+        // Apply global alpha
+        paint_flavor.mul_alpha(self.state().alpha);
 
-            CommandType::ConcaveFill {
-                stencil_params,
-                fill_params,
+        let scissor = self.state().scissor;
+
+        // Shapes that aren't anti-aliased are expanded by a zero width fringe, their shader params still use the
+        // regular one.
+        let (fringe_width, params_fringe_width) = match fringe_width {
+            Some(width) if width > 0.0 => (width, width),
+            _ => (0.0, self.fringe_width),
+        };
+
+        // GPU uniforms
+        let params = Params::new(
+            &self.images,
+            &paint_transform,
+            &paint_flavor,
+            &GlyphTexture::default(),
+            &scissor,
+            params_fringe_width,
+            params_fringe_width,
+            -1.0,
+        );
+
+        let image = match self.paint_image(&paint_flavor) {
+            Ok(image) => image,
+            Err(error) => {
+                self.report_error(error);
+                return None;
             }
         };
 
-        // GPU command
-        let mut cmd = Command::new(flavor);
-        cmd.fill_rule = fill_rule;
-        cmd.composite_operation = self.state().composite_operation;
+        Some(FillStyle {
+            paint_flavor,
+            paint_transform,
+            fringe_width,
+            fill_rule,
+            params,
+            image,
+            composite_operation: self.state().composite_operation,
+            paint_binding: paint_id.map(|id| PaintBinding {
+                id,
+                transform,
+                scissor,
+                stroke_width: params_fringe_width,
+                fringe_width: params_fringe_width,
+                alpha: self.state().alpha,
+            }),
+        })
+    }
 
-        if let PaintFlavor::Image { id, .. } = paint_flavor {
-            cmd.image = Some(id);
-        } else if let Some(paint::GradientColors::MultiStop { stops }) = paint_flavor.gradient_colors() {
-            cmd.image = self
-                .gradients
-                .lookup_or_add(stops, &mut self.images, &mut self.renderer)
-                .ok();
+    // Expands the path for filling and adds its vertices to the batch, unless it's outside of the canvas.
+    fn add_fill_geometry(
+        &mut self,
+        batch: &mut PathBatch,
+        path_cache: &mut PathCache,
+        style: &FillStyle,
+        outline: Option<Outline>,
+    ) {
+        if !self.is_path_visible(path_cache) {
+            return;
         }
 
+        path_cache.expand_fill(style.fringe_width, LineJoin::Miter, 2.4);
+
         // All verts from all shapes are kept in a single buffer here in the canvas.
         // Drawable struct is used to describe the range of vertices each draw call will operate on
         let mut offset = self.verts.len();
 
-        cmd.drawables.reserve_exact(path_cache.contours.len());
+        batch.drawables.reserve(path_cache.contours.len());
         for contour in &path_cache.contours {
             let mut drawable = Drawable::default();
 
@@ -960,155 +2035,457 @@ where
                 offset += contour.stroke.len();
             }
 
-            cmd.drawables.push(drawable);
+            batch.drawables.push(drawable);
+        }
+
+        batch.concave |= !(path_cache.contours.len() == 1 && path_cache.contours[0].convexity == Convexity::Convex);
+        batch.bounds.add_bounds(&path_cache.bounds);
+        batch.outlines.extend(outline);
+    }
+
+    // Appends the command filling the paths of the batch as a single shape.
+    fn append_fill_command(&mut self, style: &FillStyle, batch: PathBatch) {
+        if batch.drawables.is_empty() {
+            return;
         }
 
-        if let CommandType::ConcaveFill { .. } = cmd.cmd_type {
+        let flavor = if batch.concave {
+            let stencil_params = Params {
+                stroke_thr: -1.0,
+                shader_type: ShaderType::Stencil,
+                ..Params::default()
+            };
+
+            CommandType::ConcaveFill {
+                stencil_params,
+                fill_params: style.params,
+            }
+        } else {
+            CommandType::ConvexFill { params: style.params }
+        };
+
+        // GPU command
+        let mut cmd = Command::new(flavor);
+        cmd.fill_rule = style.fill_rule;
+        cmd.composite_operation = style.composite_operation;
+        cmd.outlines = batch.outlines;
+        cmd.paint_binding = style.paint_binding;
+        cmd.image = style.image;
+        cmd.drawables = batch.drawables;
+
+        if batch.concave {
             // Concave shapes are first filled by writing to a stencil buffer and then drawing a quad
             // over the shape area with stencil test enabled to produce the final fill. These are
             // the verts needed for the covering quad
+            let bounds = batch.bounds;
+            let fringe_width = style.fringe_width;
+            cmd.triangles_verts = Some((self.verts.len(), 4));
+
             self.verts.push(Vertex::new(
-                path_cache.bounds.maxx + fringe_width,
-                path_cache.bounds.maxy + fringe_width,
-                0.5,
-                1.0,
-            ));
-            self.verts.push(Vertex::new(
-                path_cache.bounds.maxx + fringe_width,
-                path_cache.bounds.miny - fringe_width,
+                bounds.maxx + fringe_width,
+                bounds.maxy + fringe_width,
                 0.5,
                 1.0,
             ));
             self.verts.push(Vertex::new(
-                path_cache.bounds.minx - fringe_width,
-                path_cache.bounds.maxy + fringe_width,
+                bounds.maxx + fringe_width,
+                bounds.miny - fringe_width,
                 0.5,
                 1.0,
             ));
             self.verts.push(Vertex::new(
-                path_cache.bounds.minx - fringe_width,
-                path_cache.bounds.miny,
+                bounds.minx - fringe_width,
+                bounds.maxy + fringe_width,
                 0.5,
                 1.0,
             ));
-
-            cmd.triangles_verts = Some((offset, 4));
+            self.verts
+                .push(Vertex::new(bounds.minx - fringe_width, bounds.miny, 0.5, 1.0));
         }
 
         self.append_cmd(cmd);
     }
 
+    /// Fills the path with the paint, keeping its tessellation under `key`.
+    ///
+    /// [`Path`] already caches its tessellation, but paths that are rebuilt every frame start out without one. While
+    /// the same `key` is used with a path of unchanged verbs and points under the same transform, the path is not
+    /// flattened again. Tessellations whose key wasn't drawn with since the previous flush are released on flush.
+    pub fn fill_path_cached(&mut self, key: u64, path: &Path, paint: &Paint) {
+        let transform = self.state().transform;
+        let content_key = path.content_cache_key(&transform, self.tess_tol, self.dist_tol);
+
+        let mut entry = match self.path_caches.remove(&key) {
+            Some(entry) if entry.key == content_key => entry,
+            _ => KeyedPathCache {
+                key: content_key,
+                path_cache: PathCache::new(path.verbs(), &transform, self.tess_tol, self.dist_tol),
+                used: true,
+            },
+        };
+
+        self.check_path_range(&entry.path_cache);
+        let mut snapped = self.pixel_snapped_fill(&entry.path_cache);
+
+        self.fill_flattened_path(
+            snapped.as_mut().unwrap_or(&mut entry.path_cache),
+            &paint.flavor,
+            &paint.transform,
+            self.shape_fringe_width(paint),
+            paint.fill_rule,
+            None,
+            self.outline(path),
+        );
+
+        entry.used = true;
+        self.path_caches.insert(key, entry);
+    }
+
     /// Strokes the provided Path with the specified Paint.
     pub fn stroke_path(&mut self, path: &Path, paint: &Paint) {
-        self.stroke_path_internal(path, &paint.flavor, paint.shape_anti_alias, &paint.stroke);
+        self.draw_path(path, None, Some(paint));
     }
 
-    fn stroke_path_internal(
-        &mut self,
-        path: &Path,
-        paint_flavor: &PaintFlavor,
-        anti_alias: bool,
-        stroke: &StrokeSettings,
-    ) {
-        let mut paint_flavor = paint_flavor.clone();
+    /// Strokes the provided Path with a width that varies along it, as drawn by pressure-sensitive ink or
+    /// calligraphic brushes.
+    ///
+    /// `width_profile` is called with a position along the path by arc length, from 0.0 at its start to 1.0
+    /// at its end, and returns the factor the line width of the paint is multiplied with at that position.
+    /// The width is interpolated between the points of the flattened path, joins and caps use the width at
+    /// their point.
+    pub fn stroke_path_with_width_profile(&mut self, path: &Path, paint: &Paint, width_profile: impl Fn(f32) -> f32) {
         let transform = self.state().transform;
-
-        // The path cache saves a flattened and transformed version of the path.
         let mut path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
+        self.check_path_range(&path_cache);
+
+        self.stroke_flattened_path(
+            &mut path_cache,
+            &paint.flavor,
+            &paint.transform,
+            self.shape_fringe_width(paint),
+            &paint.stroke,
+            None,
+            Some(&width_profile),
+            self.outline(path),
+        );
+    }
 
-        // Early out if path is outside the canvas bounds
-        if path_cache.bounds.maxx < 0.0
-            || path_cache.bounds.minx > self.width() as f32
-            || path_cache.bounds.maxy < 0.0
-            || path_cache.bounds.miny > self.height() as f32
-        {
-            return;
-        }
+    /// Strokes each path with its paint.
+    ///
+    /// Like [`Self::fill_paths()`], this draws the same as calling [`Self::stroke_path()`] for every item, with
+    /// the GPU state of a paint resolved once for consecutive items sharing it. Stencil strokes (see
+    /// [`Paint::set_stencil_strokes()`]) of different paths must not affect each other, so only consecutive paths
+    /// with stencil strokes disabled are added to a single draw command.
+    pub fn stroke_paths<'a>(&mut self, items: impl IntoIterator<Item = (&'a Path, &'a Paint)>) {
+        let transform = self.state().transform;
 
-        let scissor = self.state().scissor;
+        // The paint of the previous item and its stroke state, which is only resolved again once the paint changes
+        let mut current: Option<(&Paint, Option<StrokeStyles>)> = None;
+        let mut batch = PathBatch::default();
+        // Whether the paths in the batch were pixel snapped, which strokes them with a rounded width
+        let mut batch_snapped = false;
+
+        for (path, paint) in items {
+            // The path cache saves a flattened and transformed version of the path.
+            let mut path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
+            self.check_path_range(&path_cache);
+
+            if !current
+                .as_ref()
+                .is_some_and(|(current, _)| std::ptr::eq(*current, paint))
+            {
+                if let Some((_, Some(styles))) = &current {
+                    self.append_stroke_command(styles.get(batch_snapped), std::mem::take(&mut batch));
+                }
 
-        // Scale stroke width by current transform scale.
-        // Note: I don't know why the original author clamped the max stroke width to 200, but it didn't
-        // look correct when zooming in. There was probably a good reson for doing so and I may have
-        // introduced a bug by removing the upper bound.
-        //paint.set_stroke_width((paint.stroke_width() * transform.average_scale()).max(0.0).min(200.0));
-        let mut line_width = (stroke.line_width * transform.average_scale()).max(0.0);
+                current = Some((paint, self.stroke_styles(paint)));
+            }
 
-        if line_width < self.fringe_width {
-            // If the stroke width is less than pixel size, use alpha to emulate coverage.
-            // Since coverage is area, scale by alpha*alpha.
-            let alpha = (line_width / self.fringe_width).clamp(0.0, 1.0);
+            let Some((_, Some(styles))) = &current else {
+                continue;
+            };
 
-            paint_flavor.mul_alpha(alpha * alpha);
-            line_width = self.fringe_width;
-        }
+            let mut snapped = styles.snap(&path_cache);
+            let is_snapped = snapped.is_some();
+            let path_cache = snapped.as_mut().unwrap_or(&mut path_cache);
 
-        // Apply global alpha
-        paint_flavor.mul_alpha(self.state().alpha);
+            if is_snapped != batch_snapped {
+                self.append_stroke_command(styles.get(batch_snapped), std::mem::take(&mut batch));
+                batch_snapped = is_snapped;
+            }
 
-        // Calculate stroke vertices.
-        // expand_stroke will fill path_cache.contours[].stroke with vertex data for the GPU
-        let fringe_with = if anti_alias { self.fringe_width } else { 0.0 };
-        path_cache.expand_stroke(
-            line_width * 0.5,
-            fringe_with,
-            stroke.line_cap_start,
-            stroke.line_cap_end,
-            stroke.line_join,
-            stroke.miter_limit,
-            self.tess_tol,
+            let style = styles.get(is_snapped);
+            self.add_stroke_geometry(&mut batch, path_cache, style, None, self.outline(path));
+
+            if style.stencil_params.is_some() {
+                self.append_stroke_command(style, std::mem::take(&mut batch));
+            }
+        }
+
+        if let Some((_, Some(styles))) = &current {
+            self.append_stroke_command(styles.get(batch_snapped), batch);
+        }
+    }
+
+    // Resolves the GPU state of stroking with the paint, along with that of pixel snapped paths if pixel
+    // snapping is enabled.
+    fn stroke_styles(&mut self, paint: &Paint) -> Option<StrokeStyles> {
+        let fringe_width = self.shape_fringe_width(paint);
+        let style = self.stroke_style(&paint.flavor, &paint.transform, fringe_width, &paint.stroke, None)?;
+
+        let snapped = match self.pixel_snapped_stroke_settings(&paint.stroke) {
+            Some((stroke, offset, end_offsets)) => {
+                let snapped_style = self.stroke_style(&paint.flavor, &paint.transform, fringe_width, &stroke, None)?;
+                Some((snapped_style, offset, end_offsets))
+            }
+            None => None,
+        };
+
+        Some(StrokeStyles { style, snapped })
+    }
+
+    /// Fills all paths with the same paint as a single shape, which suits charts made of thousands of small
+    /// shapes sharing a style.
+    ///
+    /// The paths are drawn by a single command sharing one range of vertices, concave paths with one stencil
+    /// pass and one cover pass over all of them. Unlike [`Self::fill_paths()`], overlapping paths are therefore
+    /// filled as their union with the fill rule of the paint applied across paths, so translucent paints don't
+    /// add up where paths overlap.
+    pub fn fill_paths_with_paint(&mut self, paths: &[&Path], paint: &Paint) {
+        let Some(style) = self.fill_style(
+            &paint.flavor,
+            &paint.transform,
+            self.shape_fringe_width(paint),
+            paint.fill_rule,
+            None,
+        ) else {
+            return;
+        };
+
+        let transform = self.state().transform;
+        let mut batch = PathBatch::default();
+
+        for path in paths {
+            // The path cache saves a flattened and transformed version of the path.
+            let mut path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
+            self.check_path_range(&path_cache);
+
+            let mut snapped = self.pixel_snapped_fill(&path_cache);
+            self.add_fill_geometry(
+                &mut batch,
+                snapped.as_mut().unwrap_or(&mut path_cache),
+                &style,
+                self.outline(path),
+            );
+        }
+
+        self.append_fill_command(&style, batch);
+    }
+
+    /// Strokes all paths with the same paint as a single shape.
+    ///
+    /// Like [`Self::fill_paths_with_paint()`], the paths are drawn by a single command. With stencil strokes (see
+    /// [`Paint::set_stencil_strokes()`]) each pixel is covered once across all paths. With pixel snapping
+    /// enabled, all paths are stroked with the width rounded to whole pixels.
+    pub fn stroke_paths_with_paint(&mut self, paths: &[&Path], paint: &Paint) {
+        let Some(styles) = self.stroke_styles(paint) else {
+            return;
+        };
+
+        let transform = self.state().transform;
+        let style = styles.get(true);
+        let mut batch = PathBatch::default();
+
+        for path in paths {
+            // The path cache saves a flattened and transformed version of the path.
+            let mut path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
+            self.check_path_range(&path_cache);
+
+            let mut snapped = styles.snap(&path_cache);
+            self.add_stroke_geometry(
+                &mut batch,
+                snapped.as_mut().unwrap_or(&mut path_cache),
+                style,
+                None,
+                self.outline(path),
+            );
+        }
+
+        self.append_stroke_command(style, batch);
+    }
+
+    #[cfg(feature = "text")]
+    fn stroke_path_internal(
+        &mut self,
+        path: &Path,
+        paint_flavor: &PaintFlavor,
+        fringe_width: Option<f32>,
+        stroke: &StrokeSettings,
+    ) {
+        let transform = self.state().transform;
+
+        // The path cache saves a flattened and transformed version of the path.
+        let mut path_cache = path.cache(&transform, self.tess_tol, self.dist_tol);
+        self.check_path_range(&path_cache);
+
+        self.stroke_flattened_path(
+            &mut path_cache,
+            paint_flavor,
+            &Transform2D::identity(),
+            fringe_width,
+            stroke,
+            None,
+            None,
+            self.outline(path),
         );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn stroke_flattened_path(
+        &mut self,
+        path_cache: &mut PathCache,
+        paint_flavor: &PaintFlavor,
+        paint_transform: &Transform2D,
+        fringe_width: Option<f32>,
+        stroke: &StrokeSettings,
+        paint_id: Option<PaintId>,
+        width_profile: Option<&dyn Fn(f32) -> f32>,
+        outline: Option<Outline>,
+    ) {
+        // Early out if path is outside the canvas bounds
+        if !self.is_path_visible(path_cache) {
+            return;
+        }
+
+        let Some(style) = self.stroke_style(paint_flavor, paint_transform, fringe_width, stroke, paint_id) else {
+            return;
+        };
+
+        let mut batch = PathBatch::default();
+        self.add_stroke_geometry(&mut batch, path_cache, &style, width_profile, outline);
+        self.append_stroke_command(&style, batch);
+    }
+
+    // Resolves the GPU state of stroking with the paint, reporting an error and returning `None` if its image
+    // doesn't exist.
+    fn stroke_style(
+        &mut self,
+        paint_flavor: &PaintFlavor,
+        paint_transform: &Transform2D,
+        fringe_width: Option<f32>,
+        stroke: &StrokeSettings,
+        paint_id: Option<PaintId>,
+    ) -> Option<StrokeStyle> {
+        let mut paint_flavor = paint_flavor.clone();
+        let transform = self.state().transform;
+        // The gradient or image pattern is transformed by the paint transform first
+        let paint_transform = *paint_transform * transform;
+
+        let scissor = self.state().scissor;
+
+        // Scale stroke width by current transform scale.
+        // Note: I don't know why the original author clamped the max stroke width to 200, but it didn't
+        // look correct when zooming in. There was probably a good reson for doing so and I may have
+        // introduced a bug by removing the upper bound.
+        //paint.set_stroke_width((paint.stroke_width() * transform.average_scale()).max(0.0).min(200.0));
+        let mut line_width = (stroke.line_width * transform.average_scale()).max(0.0);
+        let mut alpha = self.state().alpha;
+
+        let (fringe_width, params_fringe_width) = match fringe_width {
+            Some(width) if width > 0.0 => (width, width),
+            _ => (0.0, self.fringe_width),
+        };
+
+        // Outlines keep their width, which is drawn as it is
+        if line_width < params_fringe_width && !self.renderer.draws_path_outlines() {
+            // If the stroke width is less than pixel size, use alpha to emulate coverage.
+            // Since coverage is area, scale by alpha*alpha.
+            let coverage = (line_width / params_fringe_width).clamp(0.0, 1.0);
+
+            alpha *= coverage * coverage;
+            line_width = params_fringe_width;
+        }
+
+        // Apply global alpha
+        paint_flavor.mul_alpha(alpha);
 
         // GPU uniforms
         let params = Params::new(
             &self.images,
-            &transform,
+            &paint_transform,
             &paint_flavor,
             &GlyphTexture::default(),
             &scissor,
             line_width,
-            self.fringe_width,
+            params_fringe_width,
             -1.0,
         );
 
-        let flavor = if stroke.stencil_strokes {
-            let params2 = Params::new(
-                &self.images,
-                &transform,
-                &paint_flavor,
-                &GlyphTexture::default(),
-                &scissor,
-                line_width,
-                self.fringe_width,
-                1.0 - 0.5 / 255.0,
-            );
+        let stencil_params = stroke.stencil_strokes.then(|| Params {
+            stroke_thr: 1.0 - 0.5 / 255.0,
+            ..params
+        });
 
-            CommandType::StencilStroke {
-                params1: params,
-                params2,
+        let image = match self.paint_image(&paint_flavor) {
+            Ok(image) => image,
+            Err(error) => {
+                self.report_error(error);
+                return None;
             }
-        } else {
-            CommandType::Stroke { params }
         };
 
-        // GPU command
-        let mut cmd = Command::new(flavor);
-        cmd.composite_operation = self.state().composite_operation;
+        Some(StrokeStyle {
+            stroke: stroke.clone(),
+            line_width,
+            fringe_width,
+            path_gradient: matches!(paint_flavor, PaintFlavor::PathGradient { .. }),
+            params,
+            stencil_params,
+            image,
+            composite_operation: self.state().composite_operation,
+            paint_binding: paint_id.map(|id| PaintBinding {
+                id,
+                transform,
+                scissor,
+                stroke_width: line_width,
+                fringe_width: params_fringe_width,
+                alpha,
+            }),
+        })
+    }
 
-        if let PaintFlavor::Image { id, .. } = paint_flavor {
-            cmd.image = Some(id);
-        } else if let Some(paint::GradientColors::MultiStop { stops }) = paint_flavor.gradient_colors() {
-            cmd.image = self
-                .gradients
-                .lookup_or_add(stops, &mut self.images, &mut self.renderer)
-                .ok();
+    // Expands the path for stroking and adds its vertices to the batch, unless it's outside of the canvas.
+    fn add_stroke_geometry(
+        &mut self,
+        batch: &mut PathBatch,
+        path_cache: &mut PathCache,
+        style: &StrokeStyle,
+        width_profile: Option<&dyn Fn(f32) -> f32>,
+        outline: Option<Outline>,
+    ) {
+        if !self.is_path_visible(path_cache) {
+            return;
         }
 
+        // Calculate stroke vertices.
+        // expand_stroke will fill path_cache.contours[].stroke with vertex data for the GPU
+        path_cache.expand_stroke(
+            style.line_width * 0.5,
+            style.fringe_width,
+            style.stroke.line_cap_start,
+            style.stroke.line_cap_end,
+            style.stroke.line_join,
+            style.stroke.miter_limit,
+            self.tess_tol,
+            style.path_gradient,
+            width_profile,
+        );
+
         // All verts from all shapes are kept in a single buffer here in the canvas.
         // Drawable struct is used to describe the range of vertices each draw call will operate on
         let mut offset = self.verts.len();
 
-        cmd.drawables.reserve_exact(path_cache.contours.len());
+        batch.drawables.reserve(path_cache.contours.len());
         for contour in &path_cache.contours {
             let mut drawable = Drawable::default();
 
@@ -1118,13 +2495,47 @@ where
                 offset += contour.stroke.len();
             }
 
-            cmd.drawables.push(drawable);
+            batch.drawables.push(drawable);
         }
 
+        batch.bounds.add_bounds(&path_cache.bounds);
+        batch.outlines.extend(outline.map(|outline| Outline {
+            stroke: Some(style.stroke.clone()),
+            ..outline
+        }));
+    }
+
+    // Appends the command stroking the paths of the batch, stencil strokes cover each pixel of the batch once.
+    fn append_stroke_command(&mut self, style: &StrokeStyle, batch: PathBatch) {
+        if batch.drawables.is_empty() {
+            return;
+        }
+
+        let flavor = match style.stencil_params {
+            Some(params2) => CommandType::StencilStroke {
+                params1: style.params,
+                params2,
+            },
+            None => CommandType::Stroke { params: style.params },
+        };
+
+        // GPU command
+        let mut cmd = Command::new(flavor);
+        cmd.composite_operation = style.composite_operation;
+        cmd.outlines = batch.outlines;
+        cmd.paint_binding = style.paint_binding;
+        cmd.image = style.image;
+        cmd.drawables = batch.drawables;
+
         self.append_cmd(cmd);
     }
 
     fn render_unclipped_image_blit(&mut self, target_rect: &Rect, transform: &Transform2D, paint_flavor: &PaintFlavor) {
+        let image = match self.paint_image(paint_flavor) {
+            Ok(image) => image,
+            Err(error) => return self.report_error(error),
+        };
+
         let scissor = self.state().scissor;
 
         let mut params = Params::new(
@@ -1177,251 +2588,304 @@ where
             Vertex::new(p4, p5, s1, t1),
         ];
 
-        if let &PaintFlavor::Image { id, .. } = paint_flavor {
-            cmd.image = Some(id);
-        }
-
+        cmd.image = image;
         cmd.triangles_verts = Some((self.verts.len(), verts.len()));
         self.append_cmd(cmd);
 
         self.verts.extend_from_slice(&verts);
     }
 
-    // Text
+    // Paint resources
 
-    /// Adds a font file to the canvas
-    pub fn add_font<P: AsRef<FilePath>>(&mut self, file_path: P) -> Result<FontId, ErrorKind> {
-        self.text_context.borrow_mut().add_font_file(file_path)
+    /// Adds a paint to the canvas and returns a handle to it.
+    ///
+    /// Paths filled or stroked with the handle use whatever the paint is at the time of the next
+    /// flush, so changes made with [`Self::update_paint()`] apply to everything drawn with it since.
+    /// This includes gradients and image patterns: replacing a gradient paint with one of other colors
+    /// recolors everything drawn with the handle.
+    pub fn create_paint(&mut self, paint: Paint) -> PaintId {
+        PaintId(self.paints.insert(paint))
     }
 
-    /// Adds a font to the canvas by reading it from the specified chunk of memory.
-    pub fn add_font_mem(&mut self, data: &[u8]) -> Result<FontId, ErrorKind> {
-        self.text_context.borrow_mut().add_font_mem(data)
+    /// Returns the paint for the specified handle.
+    pub fn paint(&self, id: PaintId) -> Option<&Paint> {
+        self.paints.get(id.0)
     }
 
-    /// Adds all .ttf files from a directory
-    pub fn add_font_dir<P: AsRef<FilePath>>(&mut self, dir_path: P) -> Result<Vec<FontId>, ErrorKind> {
-        self.text_context.borrow_mut().add_font_dir(dir_path)
+    /// Replaces the paint for the specified handle.
+    ///
+    /// Only the color, gradient or image of the paint is picked up by commands that were already
+    /// drawn, their geometry (line width, caps, anti-aliasing) stays as it was when they were drawn.
+    pub fn update_paint(&mut self, id: PaintId, paint: Paint) -> Result<(), ErrorKind> {
+        let slot = self.paints.get_mut(id.0).ok_or(ErrorKind::PaintIdNotFound)?;
+        *slot = paint;
+        Ok(())
     }
 
-    /// Returns information on how the provided text will be drawn with the specified paint.
-    pub fn measure_text<S: AsRef<str>>(
-        &self,
-        x: f32,
-        y: f32,
-        text: S,
-        paint: &Paint,
-    ) -> Result<TextMetrics, ErrorKind> {
-        let scale = self.font_scale() * self.device_px_ratio;
-
-        let mut text_settings = paint.text.clone();
-        text_settings.font_size *= scale;
-        text_settings.letter_spacing *= scale;
-
-        let scale = self.font_scale() * self.device_px_ratio;
-        let invscale = 1.0 / scale;
-
-        self.text_context
-            .borrow_mut()
-            .measure_text(x * scale, y * scale, text, &text_settings)
-            .map(|mut metrics| {
-                metrics.scale(invscale);
-                metrics
-            })
+    /// Deletes the paint for the specified handle.
+    ///
+    /// Commands that were already drawn with it keep the paint as it was when they were drawn.
+    pub fn delete_paint(&mut self, id: PaintId) {
+        self.paints.remove(id.0);
     }
 
-    /// Returns font metrics for a particular Paint.
-    pub fn measure_font(&self, paint: &Paint) -> Result<FontMetrics, ErrorKind> {
-        let scale = self.font_scale() * self.device_px_ratio;
+    /// Fills the provided Path with the paint for the specified handle.
+    pub fn fill_path_by_id(&mut self, path: &Path, id: PaintId) {
+        if let Some(paint) = self.paints.get(id.0).cloned() {
+            self.draw_path_with_binding(path, Some(&paint), None, Some(id));
+        }
+    }
 
-        self.text_context
-            .borrow_mut()
-            .measure_font(paint.text.font_size * scale, &paint.text.font_ids)
+    /// Strokes the provided Path with the paint for the specified handle.
+    pub fn stroke_path_by_id(&mut self, path: &Path, id: PaintId) {
+        if let Some(paint) = self.paints.get(id.0).cloned() {
+            self.draw_path_with_binding(path, None, Some(&paint), Some(id));
+        }
     }
 
-    /// Returns the maximum index-th byte of text that will fit inside `max_width`.
-    ///
-    /// The retuned index will always lie at the start and/or end of a UTF-8 code point sequence or at the start or end of the text
-    pub fn break_text<S: AsRef<str>>(&self, max_width: f32, text: S, paint: &Paint) -> Result<usize, ErrorKind> {
-        let scale = self.font_scale() * self.device_px_ratio;
+    // Recomputes the params of commands drawn with a paint handle from the current paint.
+    fn resolve_paint_bindings(&mut self) {
+        for index in 0..self.commands.len() {
+            let cmd = &mut self.commands[index];
+            let Some(binding) = cmd.paint_binding else {
+                continue;
+            };
 
-        let mut text_settings = paint.text.clone();
-        text_settings.font_size *= scale;
-        text_settings.letter_spacing *= scale;
+            let Some(paint) = self.paints.get(binding.id.0) else {
+                continue;
+            };
 
-        let max_width = max_width * scale;
+            let mut paint_flavor = paint.flavor.clone();
+            paint_flavor.mul_alpha(binding.alpha);
+
+            let resolve = |params: &mut Params| {
+                *params = Params::new(
+                    &self.images,
+                    &(paint.transform * binding.transform),
+                    &paint_flavor,
+                    &GlyphTexture::default(),
+                    &binding.scissor,
+                    binding.stroke_width,
+                    binding.fringe_width,
+                    params.stroke_thr,
+                );
+            };
 
-        self.text_context
-            .borrow_mut()
-            .break_text(max_width, text, &text_settings)
+            match &mut cmd.cmd_type {
+                CommandType::ConvexFill { params } | CommandType::Stroke { params } => resolve(params),
+                CommandType::ConcaveFill { fill_params, .. } => resolve(fill_params),
+                CommandType::StencilStroke { params1, params2 } => {
+                    resolve(params1);
+                    resolve(params2);
+                }
+                _ => {}
+            }
+
+            // Commands of paints updated with a deleted image are skipped
+            match self.paint_image(&paint_flavor) {
+                Ok(image) => self.commands[index].image = image,
+                Err(error) => {
+                    self.commands[index].drawables.clear();
+                    self.commands[index].triangles_verts = None;
+                    self.report_error(error);
+                }
+            }
+        }
     }
 
-    /// Returnes a list of ranges representing each line of text that will fit inside `max_width`
-    pub fn break_text_vec<S: AsRef<str>>(
-        &self,
-        max_width: f32,
-        text: S,
-        paint: &Paint,
-    ) -> Result<Vec<Range<usize>>, ErrorKind> {
-        let scale = self.font_scale() * self.device_px_ratio;
+    // Returns the image sampled by the paint, uploading the gradient texture of multi-stop gradients. Fails if
+    // the image was deleted, its id may refer to a newer image reusing the slot of the deleted one otherwise.
+    fn paint_image(&mut self, paint_flavor: &PaintFlavor) -> Result<Option<ImageId>, ErrorKind> {
+        if let &PaintFlavor::Image { id, .. } = paint_flavor {
+            self.images.info(id).ok_or(ErrorKind::ImageIdNotFound)?;
+            Ok(Some(id))
+        } else if let Some(paint::GradientColors::MultiStop { stops }) = paint_flavor.gradient_colors() {
+            self.gradients
+                .lookup_or_add(stops, &mut self.images, &mut self.renderer)
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
 
-        let mut text_settings = paint.text.clone();
-        text_settings.font_size *= scale;
-        text_settings.letter_spacing *= scale;
+    // Returns the path as it's drawn with the current transform, if the renderer draws outlines.
+    fn outline(&self, path: &Path) -> Option<Outline> {
+        self.renderer.draws_path_outlines().then(|| Outline {
+            verbs: path.verbs().collect(),
+            transform: self.state().transform,
+            stroke: None,
+        })
+    }
 
-        let max_width = max_width * scale;
+    // Returns the width of the anti-aliasing fringe drawn around shapes with the paint, `None` if they aren't
+    // anti-aliased. Multisampled screens are already anti-aliased, so no fringe is drawn on them.
+    fn shape_fringe_width(&self, paint: &Paint) -> Option<f32> {
+        if !paint.shape_anti_alias
+            || (self.current_render_target == RenderTarget::Screen && self.renderer.sample_count() > 1)
+        {
+            return None;
+        }
 
-        self.text_context
-            .borrow_mut()
-            .break_text_vec(max_width, text, &text_settings)
+        match paint.fringe_width {
+            FringeWidth::Canvas => Some(self.fringe_width),
+            // Like the canvas fringe width, overrides in pixels are converted to canvas units
+            FringeWidth::Pixels(width) => Some(width.max(0.0) / self.device_px_ratio),
+        }
     }
 
-    /// Fills the provided string with the specified Paint.
-    pub fn fill_text<S: AsRef<str>>(
-        &mut self,
-        x: f32,
-        y: f32,
-        text: S,
-        paint: &Paint,
-    ) -> Result<TextMetrics, ErrorKind> {
-        self.draw_text(x, y, text.as_ref(), paint, RenderMode::Fill)
+    // Custom shaders
+
+    /// Compiles a custom fill shader and returns a handle to use with [`Paint::custom()`].
+    ///
+    /// With the OpenGL renderer `source` is GLSL ES 1.00 code that defines
+    /// `vec4 renderCustom(vec2 pt)`, which returns the premultiplied color at the point `pt` in the
+    /// coordinate system the path was drawn in. The eight uniforms of the paint are available as the
+    /// `vec4`s `customUniforms0` and `customUniforms1`. Anti-aliasing, scissoring and global alpha are
+    /// applied to the result, and the paint is batched like any other.
+    ///
+    /// ```glsl
+    /// vec4 renderCustom(vec2 pt) {
+    ///     vec2 cell = floor(pt / customUniforms1.x);
+    ///     return mod(cell.x + cell.y, 2.0) < 1.0 ? customUniforms0 : vec4(0.0);
+    /// }
+    /// ```
+    ///
+    /// With the Metal renderer `source` is Metal Shading Language code that defines
+    /// `float4 renderCustom(float2 pt, constant Uniforms& u)`, with the same `customUniforms0` and
+    /// `customUniforms1`, which read from `u`.
+    ///
+    /// Returns [`ErrorKind::UnsupportedOperation`] if the renderer doesn't support custom shaders.
+    pub fn create_shader(&mut self, source: &str) -> Result<ShaderId, ErrorKind> {
+        let id = ShaderId(self.shaders.insert(()));
+
+        if let Err(error) = self.renderer.create_shader(id, source) {
+            self.shaders.remove(id.0);
+            return Err(error);
+        }
+
+        Ok(id)
     }
 
-    /// Strokes the provided string with the specified Paint.
-    pub fn stroke_text<S: AsRef<str>>(
-        &mut self,
-        x: f32,
-        y: f32,
-        text: S,
-        paint: &Paint,
-    ) -> Result<TextMetrics, ErrorKind> {
-        self.draw_text(x, y, text.as_ref(), paint, RenderMode::Stroke)
+    /// Deletes the custom shader for the specified handle.
+    pub fn delete_shader(&mut self, id: ShaderId) {
+        if self.shaders.remove(id.0).is_some() {
+            self.renderer.delete_shader(id);
+        }
     }
 
     /// Dispatch an explicit set of `GlyphDrawCommands` to the renderer. Use this only if you are
     /// using a custom font rasterizer/layout.
     pub fn draw_glyph_commands(&mut self, draw_commands: GlyphDrawCommands, paint: &Paint, scale: f32) {
         let transform = self.state().transform;
-        let invscale = 1.0 / scale;
-        let create_vertices = |quads: &Vec<text::Quad>| {
-            let mut verts = Vec::with_capacity(quads.len() * 6);
 
-            for quad in quads {
-                let left = quad.x0 * invscale;
-                let right = quad.x1 * invscale;
-                let top = quad.y0 * invscale;
-                let bottom = quad.y1 * invscale;
-
-                let (p0, p1) = transform.transform_point(left, top);
-                let (p2, p3) = transform.transform_point(right, top);
-                let (p4, p5) = transform.transform_point(right, bottom);
-                let (p6, p7) = transform.transform_point(left, bottom);
-
-                verts.push(Vertex::new(p0, p1, quad.s0, quad.t0));
-                verts.push(Vertex::new(p4, p5, quad.s1, quad.t1));
-                verts.push(Vertex::new(p2, p3, quad.s1, quad.t0));
-                verts.push(Vertex::new(p0, p1, quad.s0, quad.t0));
-                verts.push(Vertex::new(p6, p7, quad.s0, quad.t1));
-                verts.push(Vertex::new(p4, p5, quad.s1, quad.t1));
-            }
-            verts
-        };
+        self.draw_glyph_quads(draw_commands, paint, &transform, 1.0 / scale);
+    }
 
-        // Apply global alpha
-        let mut paint_flavor = paint.flavor.clone();
-        paint_flavor.mul_alpha(self.state().alpha);
+    /// Draws quads with parts of the image mapped onto them, such as glyphs of a custom text stack, particles or
+    /// map tiles.
+    ///
+    /// Quads are positioned in the current coordinate system, their texture coordinates go from 0 to 1 across the
+    /// image. The image is multiplied with the paint, or with the color of quads that have one. Consecutive quads
+    /// with the same color are submitted with a single draw command.
+    pub fn draw_quads(&mut self, image_id: ImageId, quads: &[TexturedQuad], paint: &Paint) {
+        if self.images.info(image_id).is_none() {
+            return self.report_error(ErrorKind::ImageIdNotFound);
+        }
 
-        for cmd in draw_commands.alpha_glyphs {
-            let verts = create_vertices(&cmd.quads);
+        let transform = self.state().transform;
+        let alpha = self.state().alpha;
 
-            self.render_triangles(&verts, &transform, &paint_flavor, GlyphTexture::AlphaMask(cmd.image_id));
-        }
+        let mut start = 0;
 
-        for cmd in draw_commands.color_glyphs {
-            let verts = create_vertices(&cmd.quads);
+        while start < quads.len() {
+            let color = quads[start].color;
+            let end = quads[start..]
+                .iter()
+                .position(|quad| quad.color != color)
+                .map_or(quads.len(), |count| start + count);
 
-            self.render_triangles(
-                &verts,
-                &transform,
-                &paint_flavor,
-                GlyphTexture::ColorTexture(cmd.image_id),
-            );
+            let mut verts = Vec::with_capacity((end - start) * 6);
+
+            for textured_quad in &quads[start..end] {
+                push_quad_vertices(&mut verts, &textured_quad.quad, &transform, 1.0);
+            }
+
+            let mut paint_flavor = color.map_or_else(|| paint.flavor.clone(), PaintFlavor::Color);
+            paint_flavor.mul_alpha(alpha);
+
+            self.render_triangles(&verts, &transform, &paint_flavor, GlyphTexture::Image(image_id), None);
+
+            start = end;
         }
     }
 
     // Private
 
-    fn draw_text(
+    // Draws glyph quads, mapping their corners with `invscale` and then `quad_transform`
+    fn draw_glyph_quads(
         &mut self,
-        x: f32,
-        y: f32,
-        text: &str,
+        draw_commands: GlyphDrawCommands,
         paint: &Paint,
-        render_mode: RenderMode,
-    ) -> Result<TextMetrics, ErrorKind> {
-        let scale = self.font_scale() * self.device_px_ratio;
-        let invscale = 1.0 / scale;
-
-        let mut stroke = paint.stroke.clone();
-        stroke.line_width *= scale;
-
-        let mut text_settings = paint.text.clone();
-        text_settings.font_size *= scale;
-        text_settings.letter_spacing *= scale;
+        quad_transform: &Transform2D,
+        invscale: f32,
+    ) {
+        let transform = self.state().transform;
+        let create_vertices = |quads: &Vec<Quad>| {
+            let mut verts = Vec::with_capacity(quads.len() * 6);
 
-        let mut layout = text::shape(
-            x * scale,
-            y * scale,
-            &mut self.text_context.borrow_mut(),
-            &text_settings,
-            text,
-            None,
-        )?;
-        //let layout = self.layout_text(x, y, text, &paint)?;
+            for quad in quads {
+                push_quad_vertices(&mut verts, quad, quad_transform, invscale);
+            }
+            verts
+        };
 
-        // TODO: Early out if text is outside the canvas bounds, or maybe even check for each character in layout.
+        // Apply global alpha
+        let mut paint_flavor = paint.flavor.clone();
+        paint_flavor.mul_alpha(self.state().alpha);
 
-        let bitmap_glyphs = layout.has_bitmap_glyphs();
-        let need_direct_rendering = text_settings.font_size > 92.0;
+        #[cfg(feature = "text")]
+        let mask_coverage = Some((paint.text.gamma, paint.text.contrast));
+        #[cfg(not(feature = "text"))]
+        let mask_coverage = None;
 
-        if need_direct_rendering && !bitmap_glyphs {
-            text::render_direct(
-                self,
-                &layout,
-                &paint.flavor,
-                paint.shape_anti_alias,
-                &stroke,
-                text_settings.font_size,
-                render_mode,
-                invscale,
-            )?;
-        } else {
-            let atlas = if bitmap_glyphs && need_direct_rendering {
-                self.ephemeral_glyph_atlas.get_or_insert_with(Default::default).clone()
-            } else {
-                self.glyph_atlas.clone()
-            };
+        for cmd in draw_commands.alpha_glyphs {
+            let verts = create_vertices(&cmd.quads);
 
-            let draw_commands =
-                atlas.render_atlas(self, &layout, text_settings.font_size, stroke.line_width, render_mode)?;
-            self.draw_glyph_commands(draw_commands, paint, scale);
+            self.render_triangles(
+                &verts,
+                &transform,
+                &paint_flavor,
+                GlyphTexture::AlphaMask(cmd.image_id, cmd.layer),
+                mask_coverage,
+            );
         }
 
-        layout.scale(invscale);
+        for cmd in draw_commands.color_glyphs {
+            let verts = create_vertices(&cmd.quads);
 
-        Ok(layout)
+            self.render_triangles(
+                &verts,
+                &transform,
+                &paint_flavor,
+                GlyphTexture::ColorTexture(cmd.image_id, cmd.layer),
+                None,
+            );
+        }
     }
 
+    // Draws triangles with a glyph texture, adjusting the coverage of alpha masks with `mask_coverage`, the gamma
+    // and contrast of the text.
     fn render_triangles(
         &mut self,
         verts: &[Vertex],
         transform: &Transform2D,
         paint_flavor: &PaintFlavor,
         glyph_texture: GlyphTexture,
+        mask_coverage: Option<(f32, f32)>,
     ) {
         let scissor = self.state().scissor;
 
-        let params = Params::new(
+        let mut params = Params::new(
             &self.images,
             transform,
             paint_flavor,
@@ -1432,18 +2896,19 @@ where
             -1.0,
         );
 
+        if let Some((gamma, contrast)) = mask_coverage {
+            params.mask_gamma = gamma;
+            params.mask_contrast = contrast;
+        }
+
         let mut cmd = Command::new(CommandType::Triangles { params });
         cmd.composite_operation = self.state().composite_operation;
         cmd.glyph_texture = glyph_texture;
 
-        if let &PaintFlavor::Image { id, .. } = paint_flavor {
-            cmd.image = Some(id);
-        } else if let Some(paint::GradientColors::MultiStop { stops }) = paint_flavor.gradient_colors() {
-            cmd.image = self
-                .gradients
-                .lookup_or_add(stops, &mut self.images, &mut self.renderer)
-                .ok();
-        }
+        cmd.image = match self.paint_image(paint_flavor) {
+            Ok(image) => image,
+            Err(error) => return self.report_error(error),
+        };
 
         cmd.triangles_verts = Some((self.verts.len(), verts.len()));
         self.append_cmd(cmd);
@@ -1451,10 +2916,10 @@ where
         self.verts.extend_from_slice(verts);
     }
 
-    fn font_scale(&self) -> f32 {
-        let avg_scale = self.state().transform.average_scale();
-
-        geometry::quantize(avg_scale, 0.1).min(7.0)
+    // Releases the keyed path caches that weren't drawn with since the last flush.
+    fn release_unused_path_caches(&mut self) {
+        self.path_caches
+            .retain(|_, entry| std::mem::replace(&mut entry.used, false));
     }
 
     //
@@ -1470,12 +2935,18 @@ where
     /// Get a list of all font textures.
     #[cfg(feature = "debug_inspector")]
     pub fn debug_inspector_get_font_textures(&self) -> Vec<ImageId> {
-        self.glyph_atlas
+        #[cfg(feature = "text")]
+        return self
+            .glyph_atlas
             .glyph_textures
             .borrow()
             .iter()
+            .filter(|t| t.layer == 0)
             .map(|t| t.image_id)
-            .collect()
+            .collect();
+
+        #[cfg(not(feature = "text"))]
+        Vec::new()
     }
 
     /// Draws an image with the specified `id` on the whole canvas.
@@ -1489,147 +2960,2354 @@ where
             self.fill_path(&path, &Paint::image(id, 0f32, 0f32, width, height, 0f32, 1f32));
         }
     }
-}
 
-impl<T> Canvas<T>
-where
-    T: SurfacelessRenderer,
-{
-    /// Tells the renderer to execute all drawing commands and clears the current internal state
+    /// Draws a debug overlay on top of everything drawn to the screen since the last flush.
     ///
-    /// Call this at the end of each frame.
-    pub fn flush(&mut self) {
-        self.renderer
-            .render_surfaceless(&mut self.images, &self.verts, std::mem::take(&mut self.commands));
-        self.verts.clear();
-        self.gradients
-            .release_old_gradients(&mut self.images, &mut self.renderer);
-        if let Some(atlas) = self.ephemeral_glyph_atlas.take() {
-            atlas.clear(self);
+    /// The overlay shows the bounding box of every pending command, colored by batch (consecutive commands
+    /// sharing shader, textures and blend state), a red heat map of overdraw, where each command adds a
+    /// translucent layer over its bounding box, and the glyph atlas textures in the bottom left corner.
+    /// Call it right before flushing.
+    #[cfg(feature = "debug_inspector")]
+    pub fn draw_debug_overlay(&mut self) {
+        // Bounding boxes and batch index of the commands drawn to the screen
+        let mut boxes = Vec::new();
+        let mut batch = 0;
+        let mut target = RenderTarget::Screen;
+        let mut prev: Option<&Command> = None;
+
+        for cmd in &self.commands {
+            if let CommandType::SetRenderTarget(new_target) = cmd.cmd_type {
+                target = new_target;
+                continue;
+            }
+
+            if prev.is_some_and(|prev| !prev.shares_state(cmd)) {
+                batch += 1;
+            }
+            prev = Some(cmd);
+
+            let mut bounds = Bounds::default();
+            self.add_command_bounds(cmd, &mut bounds);
+
+            if target == RenderTarget::Screen && bounds.minx <= bounds.maxx {
+                boxes.push((bounds, batch));
+            }
         }
-    }
-}
 
-impl<T: Renderer> Drop for Canvas<T> {
-    fn drop(&mut self) {
-        self.images.clear(&mut self.renderer);
-    }
-}
+        let render_target = self.current_render_target;
+        self.set_render_target(RenderTarget::Screen);
 
-// re-exports
-#[cfg(feature = "image-loading")]
-pub use ::image as img;
+        self.save();
+        self.reset();
 
-pub use imgref;
-pub use rgb;
+        let heat = Paint::color(Color::rgbaf(1.0, 0.0, 0.0, 0.1));
+        for (bounds, _) in &boxes {
+            let mut path = Path::new();
+            path.rect(
+                bounds.minx,
+                bounds.miny,
+                bounds.maxx - bounds.minx,
+                bounds.maxy - bounds.miny,
+            );
+            self.fill_path(&path, &heat);
+        }
 
-/// Internal structure that implements the Renderer trait for unit testing.
-#[cfg(test)]
-#[derive(Default)]
-pub struct RecordingRenderer {
-    /// Vector of the last commands submitted to the renderer.
-    pub last_commands: Rc<RefCell<Vec<renderer::Command>>>,
+        for (bounds, batch) in &boxes {
+            let mut path = Path::new();
+            path.rect(
+                bounds.minx + 0.5,
+                bounds.miny + 0.5,
+                bounds.maxx - bounds.minx - 1.0,
+                bounds.maxy - bounds.miny - 1.0,
+            );
+            // Golden ratio steps give distinct hues for neighboring batches
+            let color = Color::hsl(*batch as f32 * 0.618_034, 1.0, 0.5);
+            self.stroke_path(&path, &Paint::color(color).with_line_width(1.0));
+        }
+
+        let mut x = 0.0;
+        for id in self.debug_inspector_get_font_textures() {
+            let Ok((width, height)) = self.image_size(id) else {
+                continue;
+            };
+            let scale = (256.0 / width.max(height) as f32).min(1.0);
+            let (width, height) = (width as f32 * scale, height as f32 * scale);
+            let y = self.height as f32 - height;
+
+            let mut path = Path::new();
+            path.rect(x, y, width, height);
+            self.fill_path(&path, &Paint::color(Color::black()));
+            self.fill_path(&path, &Paint::image(id, x, y, width, height, 0.0, 1.0));
+            self.stroke_path(&path, &Paint::color(Color::white()).with_line_width(1.0));
+
+            x += width;
+        }
+
+        self.restore();
+        self.set_render_target(render_target);
+    }
 }
 
-#[cfg(test)]
-impl Renderer for RecordingRenderer {
-    type Image = DummyImage;
-    type NativeTexture = ();
-    type Surface = ();
+#[cfg(feature = "text")]
+impl<T> Canvas<T>
+where
+    T: Renderer,
+{
+    /// Adds a font file to the canvas
+    pub fn add_font<P: AsRef<FilePath>>(&mut self, file_path: P) -> Result<FontId, ErrorKind> {
+        self.text_context.borrow_mut().add_font_file(file_path)
+    }
 
-    fn set_size(&mut self, _width: u32, _height: u32, _dpi: f32) {}
+    /// Adds a font to the canvas by reading it from the specified chunk of memory.
+    pub fn add_font_mem(&mut self, data: &[u8]) -> Result<FontId, ErrorKind> {
+        self.text_context.borrow_mut().add_font_mem(data)
+    }
 
-    fn render(
-        &mut self,
-        _surface: &Self::Surface,
-        _images: &mut ImageStore<Self::Image>,
-        _verts: &[renderer::Vertex],
-        commands: Vec<renderer::Command>,
-    ) {
-        *self.last_commands.borrow_mut() = commands;
+    /// Adds all .ttf files from a directory
+    pub fn add_font_dir<P: AsRef<FilePath>>(&mut self, dir_path: P) -> Result<Vec<FontId>, ErrorKind> {
+        self.text_context.borrow_mut().add_font_dir(dir_path)
     }
 
-    fn alloc_image(&mut self, info: crate::ImageInfo) -> Result<Self::Image, ErrorKind> {
-        Ok(Self::Image { info })
+    /// Makes the fonts installed on the system available to [`Self::find_font()`], and as fallback for emoji and
+    /// symbols missing from the fonts of the canvas.
+    #[cfg(feature = "fontdb")]
+    pub fn add_system_fonts(&mut self) {
+        self.text_context.borrow_mut().add_system_fonts()
     }
 
-    fn create_image_from_native_texture(
-        &mut self,
-        _native_texture: Self::NativeTexture,
-        _info: crate::ImageInfo,
-    ) -> Result<Self::Image, ErrorKind> {
-        Err(ErrorKind::UnsupportedImageFormat)
+    /// Returns the system font of `family` that matches `weight` and `style` best, adding it to the canvas the first
+    /// time it's found. See [`TextContext::find_font()`] for the accepted families and weights.
+    #[cfg(feature = "fontdb")]
+    pub fn find_font(&mut self, family: &str, weight: u16, style: FontStyle) -> Result<FontId, ErrorKind> {
+        self.text_context.borrow_mut().find_system_font(family, weight, style)
     }
 
-    fn update_image(
-        &mut self,
-        image: &mut Self::Image,
-        data: crate::ImageSource,
-        x: usize,
-        y: usize,
-    ) -> Result<(), ErrorKind> {
-        let size = data.dimensions();
+    /// Returns information on how the provided text will be drawn with the specified paint.
+    pub fn measure_text<S: AsRef<str>>(
+        &self,
+        x: f32,
+        y: f32,
+        text: S,
+        paint: &Paint,
+    ) -> Result<TextMetrics, ErrorKind> {
+        let scale = self.font_scale() * self.device_px_ratio;
 
-        if x + size.width > image.info.width() {
-            return Err(ErrorKind::ImageUpdateOutOfBounds);
-        }
+        let mut text_settings = paint.text.clone();
+        text_settings.font_size *= scale;
+        text_settings.letter_spacing *= scale;
 
-        if y + size.height > image.info.height() {
-            return Err(ErrorKind::ImageUpdateOutOfBounds);
-        }
+        let scale = self.font_scale() * self.device_px_ratio;
+        let invscale = 1.0 / scale;
 
-        Ok(())
+        self.text_context
+            .borrow_mut()
+            .measure_text(x * scale, y * scale, text, &text_settings)
+            .map(|mut metrics| {
+                metrics.scale(invscale);
+                metrics
+            })
     }
 
-    fn delete_image(&mut self, _image: Self::Image, _image_id: crate::ImageId) {}
+    /// Returns font metrics for a particular Paint.
+    pub fn measure_font(&self, paint: &Paint) -> Result<FontMetrics, ErrorKind> {
+        let scale = self.font_scale() * self.device_px_ratio;
 
-    fn screenshot(&mut self) -> Result<imgref::ImgVec<rgb::RGBA8>, ErrorKind> {
-        Ok(imgref::ImgVec::new(Vec::new(), 0, 0))
+        self.text_context
+            .borrow_mut()
+            .measure_font(paint.text.font_size * scale, &paint.text.font_ids)
     }
-}
-
-/// Dummy image type used for tests.
-#[cfg(test)]
-pub struct DummyImage {
-    info: ImageInfo,
-}
 
-#[test]
-fn test_image_blit_fast_path() {
-    use renderer::{Command, CommandType};
+    /// Returns the maximum index-th byte of text that will fit inside `max_width`.
+    ///
+    /// The retuned index will always lie at the start and/or end of a UTF-8 code point sequence or at the start or end of the text
+    pub fn break_text<S: AsRef<str>>(&self, max_width: f32, text: S, paint: &Paint) -> Result<usize, ErrorKind> {
+        let scale = self.font_scale() * self.device_px_ratio;
 
-    let renderer = RecordingRenderer::default();
+        let mut text_settings = paint.text.clone();
+        text_settings.font_size *= scale;
+        text_settings.letter_spacing *= scale;
+
+        let max_width = max_width * scale;
+
+        self.text_context
+            .borrow_mut()
+            .break_text(max_width, text, &text_settings)
+    }
+
+    /// Returnes a list of ranges representing each line of text that will fit inside `max_width`
+    pub fn break_text_vec<S: AsRef<str>>(
+        &self,
+        max_width: f32,
+        text: S,
+        paint: &Paint,
+    ) -> Result<Vec<Range<usize>>, ErrorKind> {
+        let scale = self.font_scale() * self.device_px_ratio;
+
+        let mut text_settings = paint.text.clone();
+        text_settings.font_size *= scale;
+        text_settings.letter_spacing *= scale;
+
+        let max_width = max_width * scale;
+
+        self.text_context
+            .borrow_mut()
+            .break_text_vec(max_width, text, &text_settings)
+    }
+
+    /// Breaks `text` into lines no wider than `max_width` and arranges them for display, see [`TextLayout`].
+    ///
+    /// Unlike [`Self::break_text_vec()`], lines of paragraphs that mix left-to-right and right-to-left text are
+    /// reordered and aligned correctly.
+    pub fn layout_text<S: AsRef<str>>(
+        &self,
+        text: S,
+        max_width: f32,
+        align: ParagraphAlign,
+        paint: &Paint,
+    ) -> Result<TextLayout, ErrorKind> {
+        let scale = self.font_scale() * self.device_px_ratio;
+
+        let mut text_settings = paint.text.clone();
+        text_settings.font_size *= scale;
+        text_settings.letter_spacing *= scale;
+
+        self.text_context
+            .borrow_mut()
+            .layout_text(text, max_width * scale, align, &text_settings)
+            .map(|mut layout| {
+                layout.scale(1.0 / scale);
+                layout
+            })
+    }
+
+    /// Fills the provided string with the specified Paint.
+    pub fn fill_text<S: AsRef<str>>(
+        &mut self,
+        x: f32,
+        y: f32,
+        text: S,
+        paint: &Paint,
+    ) -> Result<TextMetrics, ErrorKind> {
+        self.draw_text(x, y, text.as_ref(), paint, RenderMode::Fill)
+    }
+
+    /// Strokes the provided string with the specified Paint.
+    pub fn stroke_text<S: AsRef<str>>(
+        &mut self,
+        x: f32,
+        y: f32,
+        text: S,
+        paint: &Paint,
+    ) -> Result<TextMetrics, ErrorKind> {
+        self.draw_text(x, y, text.as_ref(), paint, RenderMode::Stroke)
+    }
+
+    /// Fills the lines of a [`TextLayout`] with the specified Paint, with the top-left corner of the layout at `x`
+    /// and `y`.
+    pub fn fill_text_layout(&mut self, x: f32, y: f32, layout: &TextLayout, paint: &Paint) -> Result<(), ErrorKind> {
+        self.draw_text_layout(x, y, layout, paint, RenderMode::Fill)
+    }
+
+    /// Strokes the lines of a [`TextLayout`] with the specified Paint, with the top-left corner of the layout at
+    /// `x` and `y`.
+    pub fn stroke_text_layout(&mut self, x: f32, y: f32, layout: &TextLayout, paint: &Paint) -> Result<(), ErrorKind> {
+        self.draw_text_layout(x, y, layout, paint, RenderMode::Stroke)
+    }
+
+    // Private
+
+    fn draw_text(
+        &mut self,
+        x: f32,
+        y: f32,
+        text: &str,
+        paint: &Paint,
+        render_mode: RenderMode,
+    ) -> Result<TextMetrics, ErrorKind> {
+        let scale = self.font_scale() * self.device_px_ratio;
+
+        let mut text_settings = paint.text.clone();
+        text_settings.font_size *= scale;
+        text_settings.letter_spacing *= scale;
+
+        let mut layout = text::shape(
+            x * scale,
+            y * scale,
+            &mut self.text_context.borrow_mut(),
+            &text_settings,
+            text,
+            None,
+        )?;
+
+        self.draw_shaped_text(&mut layout, paint, render_mode)?;
+
+        Ok(layout)
+    }
+
+    fn draw_text_layout(
+        &mut self,
+        x: f32,
+        y: f32,
+        layout: &TextLayout,
+        paint: &Paint,
+        render_mode: RenderMode,
+    ) -> Result<(), ErrorKind> {
+        let scale = self.font_scale() * self.device_px_ratio;
+
+        for line in layout.lines() {
+            let mut metrics = line.metrics().clone();
+            metrics.translate(x, y);
+            metrics.scale(scale);
+
+            self.draw_shaped_text(&mut metrics, paint, render_mode)?;
+        }
+
+        Ok(())
+    }
+
+    // Draws glyphs positioned in device pixels and scales `layout` back to canvas units.
+    fn draw_shaped_text(
+        &mut self,
+        layout: &mut TextMetrics,
+        paint: &Paint,
+        render_mode: RenderMode,
+    ) -> Result<(), ErrorKind> {
+        let scale = self.font_scale() * self.device_px_ratio;
+        let invscale = 1.0 / scale;
+
+        let mut stroke = paint.stroke.clone();
+        stroke.line_width *= scale;
+
+        let mut text_settings = paint.text.clone();
+        text_settings.font_size *= scale;
+        text_settings.letter_spacing *= scale;
+
+        // TODO: Early out if text is outside the canvas bounds, or maybe even check for each character in layout.
+
+        let bitmap_glyphs = layout.has_bitmap_glyphs();
+        let need_direct_rendering = text_settings.font_size > 92.0 || self.renderer.draws_text_as_paths();
+
+        if need_direct_rendering && !bitmap_glyphs {
+            let params = text::DirectRenderParams {
+                paint_flavor: &paint.flavor,
+                fringe_width: self.shape_fringe_width(paint),
+                stroke: &stroke,
+                font_size: text_settings.font_size,
+                mode: render_mode,
+                invscale,
+            };
+            text::render_direct(self, layout, &params)?;
+        } else if let Some(glyph_transform) = self.transformed_glyph_transform(paint, invscale, bitmap_glyphs) {
+            let draw_commands = self.glyph_atlas.clone().render_atlas_transformed(
+                self,
+                layout,
+                text_settings.font_size,
+                stroke.line_width,
+                render_mode,
+                &glyph_transform,
+            )?;
+            self.draw_glyph_quads(draw_commands, paint, &Transform2D::identity(), 1.0);
+        } else {
+            let atlas = if bitmap_glyphs && need_direct_rendering {
+                self.ephemeral_glyph_atlas.get_or_insert_with(Default::default).clone()
+            } else {
+                self.glyph_atlas.clone()
+            };
+
+            let draw_commands = atlas.render_atlas(self, layout, &text_settings, stroke.line_width, render_mode)?;
+            self.draw_glyph_commands(draw_commands, paint, scale);
+        }
+
+        layout.scale(invscale);
+
+        if !paint.text.decoration.is_empty() && layout.width() > 0.0 {
+            self.draw_text_decoration(layout, paint, render_mode)?;
+        }
+
+        Ok(())
+    }
+
+    // Draws the underline, strikethrough and overline of laid out text as one path, in the same units and with the
+    // same transform as the glyphs.
+    fn draw_text_decoration(
+        &mut self,
+        layout: &TextMetrics,
+        paint: &Paint,
+        render_mode: RenderMode,
+    ) -> Result<(), ErrorKind> {
+        let metrics = self
+            .text_context
+            .borrow_mut()
+            .measure_font(paint.text.font_size, &paint.text.font_ids)?;
+
+        let lines = [
+            (
+                TextDecoration::UNDERLINE,
+                metrics.underline_position(),
+                metrics.underline_thickness(),
+            ),
+            (
+                TextDecoration::STRIKETHROUGH,
+                metrics.strikeout_position(),
+                metrics.strikeout_thickness(),
+            ),
+            (
+                TextDecoration::OVERLINE,
+                metrics.ascender(),
+                metrics.underline_thickness(),
+            ),
+        ];
+
+        let mut path = Path::new();
+        for (decoration, position, thickness) in lines {
+            if paint.text.decoration.contains(decoration) {
+                path.rect(layout.x, layout.baseline - position, layout.width(), thickness);
+            }
+        }
+
+        match render_mode {
+            RenderMode::Fill => {
+                let mut paint = paint.clone();
+                paint.set_fill_rule(FillRule::NonZero);
+                self.fill_path(&path, &paint);
+            }
+            RenderMode::Stroke => self.stroke_path(&path, paint),
+        }
+
+        Ok(())
+    }
+
+    // Returns the transform from text layout to device coordinates if glyphs should be rasterized
+    // with it baked in.
+    fn transformed_glyph_transform(&self, paint: &Paint, invscale: f32, bitmap_glyphs: bool) -> Option<Transform2D> {
+        if !paint.text.rasterize_transformed || bitmap_glyphs {
+            return None;
+        }
+
+        let [a, b, c, d, x, y] = self.state().transform.0;
+        let transform = Transform2D::new(a * invscale, b * invscale, c * invscale, d * invscale, x, y);
+
+        text::glyph_transform_key(&transform).map(|_| transform)
+    }
+
+    fn font_scale(&self) -> f32 {
+        let avg_scale = self.state().transform.average_scale();
+
+        geometry::quantize(avg_scale, 0.1).min(7.0)
+    }
+}
+
+impl<T> Canvas<T>
+where
+    T: SurfacelessRenderer,
+{
+    /// Tells the renderer to execute all drawing commands and clears the current internal state
+    ///
+    /// Call this at the end of each frame.
+    pub fn flush(&mut self) {
+        self.upload_image_chunks();
+        self.resolve_paint_bindings();
+        self.snap_vertices();
+        self.update_frame_stats();
+        self.renderer
+            .render_surfaceless(&mut self.images, &self.verts, std::mem::take(&mut self.commands));
+        self.verts.clear();
+        for id in self.released_frame_images.drain(..) {
+            self.images.remove(&mut self.renderer, id);
+        }
+        self.gradients
+            .release_old_gradients(&mut self.images, &mut self.renderer);
+        self.release_unused_path_caches();
+        self.frame_render_target = self.current_render_target;
+        #[cfg(feature = "text")]
+        if let Some(atlas) = self.ephemeral_glyph_atlas.take() {
+            atlas.clear(self);
+        }
+    }
+}
+
+impl Canvas<SvgRenderer> {
+    /// Flushes the canvas and returns an SVG document of everything drawn to the screen since the last export.
+    pub fn export_svg(&mut self) -> String {
+        self.flush();
+        self.renderer.take_document()
+    }
+}
+
+impl Canvas<Recorder> {
+    /// Flushes the canvas and returns everything that was drawn since the last recorded frame.
+    ///
+    /// The returned frame can be sent to the thread owning the graphics context and drawn there with
+    /// [`Canvas::append_frame`].
+    pub fn record_frame(&mut self) -> Frame {
+        self.flush();
+        self.renderer.take_frame()
+    }
+
+    /// Makes an image of the canvas the recorded frames are appended to available to this canvas. The
+    /// returned id can be used for drawing, but the image can't be updated.
+    pub fn import_image(&mut self, id: ImageId, info: ImageInfo) -> Result<ImageId, ErrorKind> {
+        self.images.register_native_texture(&mut self.renderer, id, info)
+    }
+}
+
+impl<T: Renderer> Drop for Canvas<T> {
+    fn drop(&mut self) {
+        self.images.clear(&mut self.renderer);
+    }
+}
+
+// Pushes the two triangles of the quad, mapping its corners with `invscale` and then `transform`
+fn push_quad_vertices(verts: &mut Vec<Vertex>, quad: &Quad, transform: &Transform2D, invscale: f32) {
+    let left = quad.x0 * invscale;
+    let right = quad.x1 * invscale;
+    let top = quad.y0 * invscale;
+    let bottom = quad.y1 * invscale;
+
+    let (p0, p1) = transform.transform_point(left, top);
+    let (p2, p3) = transform.transform_point(right, top);
+    let (p4, p5) = transform.transform_point(right, bottom);
+    let (p6, p7) = transform.transform_point(left, bottom);
+
+    verts.push(Vertex::new(p0, p1, quad.s0, quad.t0));
+    verts.push(Vertex::new(p4, p5, quad.s1, quad.t1));
+    verts.push(Vertex::new(p2, p3, quad.s1, quad.t0));
+    verts.push(Vertex::new(p0, p1, quad.s0, quad.t0));
+    verts.push(Vertex::new(p6, p7, quad.s0, quad.t1));
+    verts.push(Vertex::new(p4, p5, quad.s1, quad.t1));
+}
+
+// re-exports
+#[cfg(feature = "image-loading")]
+pub use ::image as img;
+
+pub use imgref;
+#[cfg(feature = "kurbo")]
+pub use kurbo;
+#[cfg(feature = "lyon")]
+pub use lyon_path;
+pub use rgb;
+#[cfg(feature = "svg")]
+pub use usvg;
+
+/// Internal structure that implements the Renderer trait for unit testing.
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingRenderer {
+    /// Vector of the last commands submitted to the renderer.
+    pub last_commands: Rc<RefCell<Vec<renderer::Command>>>,
+    /// Vector of the last vertices submitted to the renderer.
+    pub last_verts: Rc<RefCell<Vec<renderer::Vertex>>>,
+    /// Samples per pixel reported by the renderer.
+    pub sample_count: u32,
+    /// Capabilities reported by the renderer.
+    pub capabilities: RendererCapabilities,
+}
+
+#[cfg(test)]
+impl Renderer for RecordingRenderer {
+    type Image = DummyImage;
+    type NativeTexture = ();
+    type Surface = ();
+
+    fn set_size(&mut self, _width: u32, _height: u32, _dpi: f32) {}
+
+    fn sample_count(&self) -> u32 {
+        self.sample_count.max(1)
+    }
+
+    fn capabilities(&self) -> RendererCapabilities {
+        self.capabilities
+    }
+
+    fn render(
+        &mut self,
+        _surface: &Self::Surface,
+        _images: &mut ImageStore<Self::Image>,
+        verts: &[renderer::Vertex],
+        commands: Vec<renderer::Command>,
+    ) {
+        *self.last_commands.borrow_mut() = commands;
+        *self.last_verts.borrow_mut() = verts.to_vec();
+    }
+
+    fn alloc_image(&mut self, info: crate::ImageInfo) -> Result<Self::Image, ErrorKind> {
+        Ok(Self::Image { info })
+    }
+
+    fn create_image_from_native_texture(
+        &mut self,
+        _native_texture: Self::NativeTexture,
+        _info: crate::ImageInfo,
+    ) -> Result<Self::Image, ErrorKind> {
+        Err(ErrorKind::UnsupportedImageFormat)
+    }
+
+    fn update_image(
+        &mut self,
+        image: &mut Self::Image,
+        data: crate::ImageSource,
+        x: usize,
+        y: usize,
+    ) -> Result<(), ErrorKind> {
+        let size = data.dimensions();
+
+        if x + size.width > image.info.width() {
+            return Err(ErrorKind::ImageUpdateOutOfBounds);
+        }
+
+        if y + size.height > image.info.height() {
+            return Err(ErrorKind::ImageUpdateOutOfBounds);
+        }
+
+        Ok(())
+    }
+
+    fn delete_image(&mut self, _image: Self::Image, _image_id: crate::ImageId) {}
+
+    fn create_shader(&mut self, _id: ShaderId, _source: &str) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+
+    fn screenshot(&mut self) -> Result<imgref::ImgVec<rgb::RGBA8>, ErrorKind> {
+        Ok(imgref::ImgVec::new(Vec::new(), 0, 0))
+    }
+}
+
+/// Dummy image type used for tests.
+#[cfg(test)]
+pub struct DummyImage {
+    info: ImageInfo,
+}
+
+#[test]
+fn test_image_blit_fast_path() {
+    use renderer::{Command, CommandType};
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+    let mut path = Path::new();
+    path.rect(10., 10., 50., 50.);
+    let image = canvas
+        .create_image_empty(30, 30, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let paint = Paint::image(image, 0., 0., 30., 30., 0., 0.).with_anti_alias(false);
+    canvas.fill_path(&path, &paint);
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let mut commands = commands.iter();
+    assert!(matches!(
+        commands.next(),
+        Some(Command {
+            cmd_type: CommandType::SetRenderTarget(..),
+            ..
+        })
+    ));
+    assert!(matches!(
+        commands.next(),
+        Some(Command {
+            cmd_type: CommandType::Triangles {
+                params: Params {
+                    shader_type: renderer::ShaderType::TextureCopyUnclipped,
+                    ..
+                }
+            },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_draw_image_anti_aliasing() {
+    use renderer::{CommandType, ShaderType};
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let image = canvas
+        .create_image_empty(16, 16, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+
+    // Returns whether the image was drawn as a blit without anti-aliasing
+    let draw_is_blit = |canvas: &mut Canvas<RecordingRenderer>, translation: f32, scale: f32| {
+        canvas.save();
+        canvas.translate(translation, translation);
+        canvas.scale(scale, scale);
+        canvas.draw_image(image, None, Rect::new(10., 10., 16., 16.), 1.);
+        canvas.restore();
+        canvas.flush_to_surface(&());
+
+        recorded_commands.borrow().iter().any(|cmd| {
+            matches!(&cmd.cmd_type, CommandType::Triangles { params } if params.shader_type == ShaderType::TextureCopyUnclipped)
+        })
+    };
+
+    assert!(draw_is_blit(&mut canvas, 0., 1.));
+    assert!(draw_is_blit(&mut canvas, 5., 2.));
+    assert!(!draw_is_blit(&mut canvas, 0.5, 1.));
+    assert!(!draw_is_blit(&mut canvas, 0., 1.1));
+}
+
+#[test]
+fn test_paint_resource_update() {
+    use renderer::{Command, CommandType};
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+    let mut path = Path::new();
+    path.rect(10., 10., 50., 50.);
+
+    let paint = canvas.create_paint(Paint::color(Color::rgb(255, 0, 0)));
+    canvas.fill_path_by_id(&path, paint);
+    canvas.set_global_alpha(0.5);
+    canvas.stroke_path_by_id(&path, paint);
+
+    canvas.update_paint(paint, Paint::color(Color::rgb(0, 0, 255))).unwrap();
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let colors: Vec<_> = commands
+        .iter()
+        .filter_map(|command| match command {
+            Command {
+                cmd_type:
+                    CommandType::ConvexFill { params }
+                    | CommandType::Stroke { params }
+                    | CommandType::StencilStroke { params1: params, .. },
+                ..
+            } => Some(params.inner_col),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(colors, [[0.0, 0.0, 1.0, 1.0], [0.0, 0.0, 0.5, 0.5]]);
+    drop(commands);
+
+    // Gradients are shared through paint handles as well
+    canvas.set_global_alpha(1.0);
+    let gradient = canvas.create_paint(Paint::linear_gradient(0., 0., 50., 0., Color::black(), Color::white()));
+    canvas.fill_path_by_id(&path, gradient);
+    let recolored = Paint::linear_gradient(0., 0., 50., 0., Color::rgb(255, 0, 0), Color::rgb(0, 0, 255));
+    canvas.update_paint(gradient, recolored).unwrap();
+    canvas.flush_to_surface(&());
+
+    match recorded_commands.borrow().last() {
+        Some(Command {
+            cmd_type: CommandType::ConvexFill { params },
+            ..
+        }) => assert_eq!(
+            (params.inner_col, params.outer_col),
+            ([1., 0., 0., 1.], [0., 0., 1., 1.])
+        ),
+        command => panic!("unexpected command {command:?}"),
+    }
+
+    canvas.delete_paint(paint);
+    assert!(canvas.paint(paint).is_none());
+    assert!(canvas.update_paint(paint, Paint::default()).is_err());
+}
+
+#[test]
+fn test_fill_paths_merges_commands() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let paths: Vec<_> = (0..4)
+        .map(|i| {
+            let mut path = Path::new();
+            path.rect(i as f32 * 20., 10., 10., 10.);
+            path
+        })
+        .collect();
+
+    let red = Paint::color(Color::rgb(255, 0, 0));
+    let blue = Paint::color(Color::rgb(0, 0, 255));
+
+    canvas.fill_paths([
+        (&paths[0], &red),
+        (&paths[1], &red),
+        (&paths[2], &blue),
+        (&paths[3], &blue),
+    ]);
+    // stencil strokes are kept separate
+    canvas.stroke_paths(paths.iter().map(|path| (path, &red)));
+
+    let plain_red = red.clone().with_stencil_strokes(false);
+    canvas.stroke_paths(paths.iter().map(|path| (path, &plain_red)));
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let drawables: Vec<_> = commands
+        .iter()
+        .filter(|command| !matches!(command.cmd_type, CommandType::SetRenderTarget(..)))
+        .map(|command| command.drawables.len())
+        .collect();
+
+    assert_eq!(drawables, [2, 2, 1, 1, 1, 1, 4]);
+}
+
+#[test]
+fn test_paths_with_shared_paint() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    // Concave L shapes, filled through the stencil buffer
+    let paths: Vec<_> = (0..4)
+        .map(|i| {
+            let x = i as f32 * 20.;
+            let mut path = Path::new();
+            path.move_to(x, 10.);
+            path.line_to(x + 10., 10.);
+            path.line_to(x + 10., 15.);
+            path.line_to(x + 5., 15.);
+            path.line_to(x + 5., 20.);
+            path.line_to(x, 20.);
+            path.close();
+            path
+        })
+        .collect();
+    let paths: Vec<_> = paths.iter().collect();
+
+    let paint = Paint::color(Color::rgb(255, 0, 0));
+    let draw_calls = [
+        |canvas: &mut Canvas<RecordingRenderer>, paths: &[&Path], paint: &Paint| {
+            canvas.fill_paths_with_paint(paths, paint);
+        },
+        |canvas: &mut Canvas<RecordingRenderer>, paths: &[&Path], paint: &Paint| {
+            canvas.stroke_paths_with_paint(paths, &paint.clone().with_stencil_strokes(false));
+        },
+        |canvas: &mut Canvas<RecordingRenderer>, paths: &[&Path], paint: &Paint| {
+            canvas.stroke_paths_with_paint(paths, paint);
+        },
+    ];
+
+    for (i, draw) in draw_calls.iter().enumerate() {
+        draw(&mut canvas, &paths, &paint);
+        canvas.flush_to_surface(&());
+
+        let commands = recorded_commands.borrow();
+        let commands: Vec<_> = commands
+            .iter()
+            .filter(|command| !matches!(command.cmd_type, CommandType::SetRenderTarget(..)))
+            .collect();
+
+        // All paths share a single command
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].drawables.len(), 4);
+
+        match (i, &commands[0].cmd_type) {
+            (0, CommandType::ConcaveFill { .. }) => {
+                // The cover quad spans all paths
+                let (offset, count) = commands[0].triangles_verts.unwrap();
+                let verts = &recorded_verts.borrow()[offset..offset + count];
+                let min_x = verts.iter().map(|vert| vert.x).fold(f32::MAX, f32::min);
+                let max_x = verts.iter().map(|vert| vert.x).fold(f32::MIN, f32::max);
+                assert!(min_x <= 0. && max_x >= 70.);
+            }
+            (1, CommandType::Stroke { .. }) | (2, CommandType::StencilStroke { .. }) => {}
+            (_, cmd_type) => panic!("unexpected command {cmd_type:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_draw_path_matches_fill_and_stroke() {
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let mut path = Path::new();
+    path.rounded_rect(10., 10., 50., 50., 5.);
+    let fill = Paint::color(Color::rgb(100, 100, 100));
+    let stroke = Paint::color(Color::black()).with_line_width(2.);
+
+    let record = |canvas: &mut Canvas<RecordingRenderer>| {
+        canvas.flush_to_surface(&());
+        let commands = recorded_commands
+            .borrow()
+            .iter()
+            .map(|cmd| format!("{cmd:?}"))
+            .collect::<Vec<_>>();
+        (commands, recorded_verts.borrow().clone())
+    };
+
+    // The first flush also sets up the render target
+    record(&mut canvas);
+    let nothing = record(&mut canvas);
+
+    canvas.fill_path(&path, &fill);
+    canvas.stroke_path(&path, &stroke);
+    let separate = record(&mut canvas);
+    assert!(separate.0.len() > nothing.0.len());
+
+    canvas.draw_path(&path, Some(&fill), Some(&stroke));
+    assert_eq!(record(&mut canvas), separate);
+
+    canvas.stroke_path(&path, &stroke);
+    let stroke_only = record(&mut canvas);
+    canvas.draw_path(&path, None, Some(&stroke));
+    assert_eq!(record(&mut canvas), stroke_only);
+
+    canvas.draw_path(&path, None, None);
+    assert_eq!(record(&mut canvas), nothing);
+}
+
+#[test]
+fn test_gradient_texture_lifetime() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(100, 100, 1.);
+    let mut path = Path::new();
+    path.rect(10., 10., 50., 50.);
+
+    let stops = |offset: f32| {
+        Paint::linear_gradient_stops(
+            0.,
+            0.,
+            100.,
+            0.,
+            [
+                (0.0, Color::black()),
+                (offset, Color::rgb(255, 0, 0)),
+                (1.0, Color::white()),
+            ],
+        )
+    };
+
+    // Animated stops don't accumulate textures
+    for i in 1..10 {
+        canvas.fill_path(&path, &stops(i as f32 / 10.));
+        canvas.flush_to_surface(&());
+    }
+    assert_eq!(canvas.gradients.texture_count(), 1);
+
+    canvas.set_gradient_texture_lifetime(3);
+    canvas.fill_path(&path, &stops(0.5));
+    canvas.flush_to_surface(&());
+    canvas.flush_to_surface(&());
+    canvas.flush_to_surface(&());
+    assert_eq!(canvas.gradients.texture_count(), 1);
+    canvas.flush_to_surface(&());
+    assert_eq!(canvas.gradients.texture_count(), 0);
+
+    // Gradients of pending commands are kept
+    canvas.fill_path(&path, &stops(0.5));
+    canvas.flush_to_surface(&());
+    canvas.fill_path(&path, &stops(0.25));
+    canvas.release_unused_gradients();
+    assert_eq!(canvas.gradients.texture_count(), 1);
+}
+
+#[test]
+fn test_consecutive_fills_are_batched() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let red = Paint::color(Color::rgb(255, 0, 0));
+
+    for i in 0..3 {
+        let mut path = Path::new();
+        path.rect(i as f32 * 20., 10., 10., 10.);
+        canvas.fill_path(&path, &red);
+    }
+
+    // a different scissor or composite operation starts a new batch
+    let mut path = Path::new();
+    path.rect(70., 10., 10., 10.);
+    canvas.scissor(0., 0., 50., 50.);
+    canvas.fill_path(&path, &red);
+    canvas.global_composite_operation(CompositeOperation::Lighter);
+    canvas.fill_path(&path, &red);
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let drawables: Vec<_> = commands
+        .iter()
+        .filter(|command| !matches!(command.cmd_type, CommandType::SetRenderTarget(..)))
+        .map(|command| command.drawables.len())
+        .collect();
+
+    assert_eq!(drawables, [3, 1, 1]);
+}
+
+#[test]
+fn test_fixed_point_vertices() {
+    let renderer = RecordingRenderer::default();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+    canvas.set_fixed_point_precision(Some(4));
+
+    let mut path = Path::new();
+    path.circle(50.3, 50.7, 20.1);
+    canvas.rotate(0.1);
+    canvas.fill_path(&path, &Paint::color(Color::black()));
+    canvas.flush_to_surface(&());
+
+    let verts = recorded_verts.borrow();
+    assert!(!verts.is_empty());
+
+    for vertex in verts.iter() {
+        assert_eq!((vertex.x * 16.).fract(), 0.);
+        assert_eq!((vertex.y * 16.).fract(), 0.);
+    }
+}
+
+#[test]
+fn test_paint_transform() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+    canvas.scale(2., 2.);
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+
+    let (black, white) = (Color::black(), Color::white());
+
+    // Moving the gradient with the paint transform is the same as moving its end points
+    let transformed =
+        Paint::linear_gradient(0., 0., 10., 0., black, white).with_paint_transform(Transform2D::translation(20., 5.));
+    let moved = Paint::linear_gradient(20., 5., 30., 5., black, white);
+
+    canvas.fill_path(&path, &transformed);
+    canvas.fill_path(&path, &moved);
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let fills: Vec<_> = commands
+        .iter()
+        .filter_map(|command| match &command.cmd_type {
+            CommandType::ConvexFill { params } => Some((params.paint_mat, command.drawables.len())),
+            _ => None,
+        })
+        .collect();
+
+    // Fills with identical params are batched into a single command
+    assert_eq!(fills.iter().map(|(_, drawables)| drawables).sum::<usize>(), 2);
+
+    for (matrix, _) in &fills {
+        for (a, b) in matrix.iter().zip(&fills[0].0) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn test_custom_shader_paint() {
+    use renderer::{CommandType, ShaderType};
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+    let mut path = Path::new();
+    path.rect(10., 10., 50., 50.);
+
+    let shader = canvas
+        .create_shader("vec4 renderCustom(vec2 pt) { return customUniforms0; }")
+        .unwrap();
+    let uniforms = [1., 0., 0., 1., 8., 0., 0., 0.];
+
+    canvas.set_global_alpha(0.5);
+    canvas.fill_path(&path, &Paint::custom(shader, uniforms));
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let CommandType::ConvexFill { params } = &commands[1].cmd_type else {
+        panic!("expected a convex fill");
+    };
+
+    assert_eq!(params.shader_type, ShaderType::FillCustom);
+    assert_eq!(params.custom_shader, Some(shader));
+    assert_eq!(params.inner_col, [1., 0., 0., 1.]);
+    assert_eq!(params.outer_col, [8., 0., 0., 0.]);
+    assert_eq!(params.custom_alpha, 0.5);
+
+    canvas.delete_shader(shader);
+}
+
+#[test]
+fn test_append_recorded_frame() {
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+
+    let recorder_path = path.clone();
+    let frame = std::thread::spawn(move || {
+        let mut recorder = Canvas::new(Recorder::new()).unwrap();
+        recorder.set_size(100, 100, 1.);
+        let image = recorder
+            .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+            .unwrap();
+        recorder.fill_path(&recorder_path, &Paint::image(image, 0., 0., 4., 4., 0., 1.));
+        recorder.record_frame()
+    })
+    .join()
+    .unwrap();
+
+    assert!(!frame.is_empty());
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+    canvas.fill_path(&path, &Paint::color(Color::black()));
+    let own_verts = canvas.verts.len();
+
+    canvas.append_frame(frame).unwrap();
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let recorded = commands.iter().find(|cmd| cmd.image.is_some()).unwrap();
+
+    // The recorded image was allocated in this canvas
+    assert_eq!(canvas.image_size(recorded.image.unwrap()).unwrap(), (4, 4));
+
+    // The recorded vertices follow the ones of this canvas
+    for (start, count) in recorded.vert_ranges() {
+        assert!(start >= own_verts);
+        assert!(start + count <= recorded_verts.borrow().len());
+    }
+}
+
+#[test]
+fn test_append_frames_with_shared_image() {
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+    let image = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let info = canvas.images.info(image).unwrap();
+
+    let frames: Vec<Frame> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..2)
+            .map(|i| {
+                scope.spawn(move || {
+                    let mut recorder = Canvas::new(Recorder::new()).unwrap();
+                    recorder.set_size(100, 100, 1.);
+                    let shared = recorder.import_image(image, info).unwrap();
+
+                    // Shared images are read-only
+                    let pixels = [RGBA8::default(); 16];
+                    let src = ImageSource::Rgba(imgref::Img::new(&pixels[..], 4, 4));
+                    assert!(recorder.update_image(shared, src, 0, 0).is_err());
+
+                    let mut path = Path::new();
+                    path.rect(i as f32 * 50., 0., 50., 50.);
+                    recorder.fill_path(&path, &Paint::image(shared, 0., 0., 4., 4., 0., 1.));
+                    recorder.record_frame()
+                })
+            })
+            .collect();
+
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+    });
+
+    for frame in frames {
+        canvas.append_frame(frame).unwrap();
+    }
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let images: Vec<_> = commands.iter().filter_map(|cmd| cmd.image).collect();
+    assert_eq!(images, [image, image]);
+}
+
+#[test]
+fn test_append_invalid_frame() {
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+
+    let record = |paint: &dyn Fn(&mut Canvas<Recorder>) -> Paint| {
+        let mut recorder = Canvas::new(Recorder::new()).unwrap();
+        recorder.set_size(100, 100, 1.);
+        let paint = paint(&mut recorder);
+        recorder.fill_path(&path, &paint);
+        recorder.record_frame()
+    };
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let mut frame = record(&|_| Paint::color(Color::black()));
+    frame.verts.pop();
+    assert!(matches!(
+        canvas.append_frame(frame),
+        Err(ErrorKind::FrameVertsOutOfRange)
+    ));
+
+    let frame = record(&|recorder| Paint::custom(recorder.create_shader("").unwrap(), [0.; 8]));
+    assert!(matches!(canvas.append_frame(frame), Err(ErrorKind::ShaderIdNotFound)));
+
+    let image = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let info = canvas.images.info(image).unwrap();
+    let frame = record(&|recorder| {
+        let shared = recorder.import_image(image, info).unwrap();
+        Paint::image(shared, 0., 0., 4., 4., 0., 1.)
+    });
+    canvas.delete_image(image);
+    assert!(matches!(canvas.append_frame(frame), Err(ErrorKind::ImageIdNotFound)));
+
+    // Nothing of the rejected frames is drawn
+    canvas.flush_to_surface(&());
+    let commands = recorded_commands.borrow();
+    assert!(commands
+        .iter()
+        .all(|command| matches!(command.cmd_type, CommandType::SetRenderTarget(..))));
+}
+
+#[cfg(all(feature = "serde", feature = "text"))]
+#[test]
+fn test_serialized_frame_round_trip() {
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+
+    let mut recorder = Canvas::new(Recorder::new()).unwrap();
+    recorder.set_size(100, 100, 1.);
+    let image = recorder
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let pixels = [RGBA8::new(255, 0, 0, 255); 16];
+    recorder
+        .update_image(image, ImageSource::Rgba(imgref::Img::new(&pixels[..], 4, 4)), 0, 0)
+        .unwrap();
+    let shader = recorder.create_shader("").unwrap();
+    let font = recorder.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+
+    recorder.fill_path(&path, &Paint::image(image, 0., 0., 4., 4., 0., 1.));
+    recorder.fill_path(&path, &Paint::custom(shader, [0.; 8]));
+    recorder
+        .fill_text(10., 50., "Hello", &Paint::color(Color::black()).with_font(&[font]))
+        .unwrap();
+
+    let json = serde_json::to_string(&recorder.record_frame()).unwrap();
+    let frame: Frame = serde_json::from_str(&json).unwrap();
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+    assert_eq!(canvas.create_shader("").unwrap(), shader);
+
+    canvas.append_frame(frame).unwrap();
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+
+    // The image was allocated and uploaded in this canvas
+    let image = commands.iter().find_map(|cmd| cmd.image).unwrap();
+    assert_eq!(canvas.image_size(image).unwrap(), (4, 4));
+
+    assert!(commands.iter().any(|cmd| cmd.custom_shaders().eq([shader])));
+
+    // So was the glyph atlas
+    let atlas = commands
+        .iter()
+        .find_map(|cmd| match cmd.glyph_texture {
+            GlyphTexture::AlphaMask(id, _) | GlyphTexture::ColorTexture(id, _) => Some(id),
+            _ => None,
+        })
+        .unwrap();
+    assert!(canvas.images.info(atlas).is_some());
+}
+
+#[test]
+fn test_append_frame_drawn_with_paint_id() {
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+
+    let mut recorder = Canvas::new(Recorder::new()).unwrap();
+    recorder.set_size(100, 100, 1.);
+    let red = recorder.create_paint(Paint::color(Color::rgb(255, 0, 0)));
+    recorder.fill_path_by_id(&path, red);
+    let frame = recorder.record_frame();
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    // A paint of this canvas with the same handle doesn't affect the appended commands
+    let blue = canvas.create_paint(Paint::color(Color::rgb(0, 0, 255)));
+    assert_eq!(blue, red);
+
+    canvas.append_frame(frame).unwrap();
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let params = commands
+        .iter()
+        .find_map(|cmd| match cmd.cmd_type {
+            CommandType::ConvexFill { params } => Some(params),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(params.inner_col, [1., 0., 0., 1.]);
+}
+
+#[test]
+fn test_pixel_snapping() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(100, 100, 1.);
+    canvas.scale(1.5, 1.5);
+    canvas.set_pixel_snapping(true);
+
+    let mut path = Path::new();
+    path.move_to(10., 10.1);
+    path.line_to(30., 10.1);
+    let paint = Paint::color(Color::black()).with_line_width(1.);
+
+    let vertical_extent = |verts: &[Vertex]| {
+        verts.iter().fold((f32::MAX, f32::MIN), |(min, max), vertex| {
+            (min.min(vertex.y), max.max(vertex.y))
+        })
+    };
+
+    // The line, 1.5 device pixels wide, is widened to cover the two rows of pixels between 14 and 16, plus the
+    // antialiasing fringe
+    canvas.stroke_path(&path, &paint);
+    assert_eq!(vertical_extent(&canvas.verts), (13.5, 16.5));
+
+    // The cached flattened path isn't affected by snapping
+    canvas.set_pixel_snapping(false);
+    let offset = canvas.verts.len();
+    canvas.stroke_path(&path, &paint);
+    let (min, max) = vertical_extent(&canvas.verts[offset..]);
+    assert!(((min + max) / 2. - 15.15).abs() < 1e-4);
+
+    // Strokes aren't snapped if the axes are scaled differently, even when rotated
+    canvas.set_pixel_snapping(true);
+    for rotation in [0., std::f32::consts::FRAC_PI_2] {
+        canvas.reset_transform();
+        canvas.rotate(rotation);
+        canvas.scale(1., 1.5);
+        canvas.rotate(-rotation);
+
+        let offset = canvas.verts.len();
+        canvas.stroke_path(&path, &paint);
+        let (min, max) = vertical_extent(&canvas.verts[offset..]);
+        let expected = if rotation == 0. { 15.15 } else { 10.1 };
+        assert!(((min + max) / 2. - expected).abs() < 1e-4);
+    }
+}
+
+#[cfg(feature = "debug_inspector")]
+#[test]
+fn test_debug_overlay() {
+    use renderer::{CommandType, Drawable};
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    // A fill and a stroke are drawn with different pipelines and end up in different batches
+    let mut path = Path::new();
+    path.rect(10., 10., 20., 20.);
+    canvas.fill_path(&path, &Paint::color(Color::black()).with_anti_alias(false));
+    let mut path = Path::new();
+    path.rect(50., 40., 30., 20.);
+    canvas.stroke_path(&path, &Paint::color(Color::black()).with_anti_alias(false));
+
+    // Commands drawn to images aren't part of the overlay
+    let image = canvas
+        .create_image_empty(16, 16, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    canvas.set_render_target(RenderTarget::Image(image));
+    canvas.fill_path(&path, &Paint::color(Color::black()));
+    canvas.set_render_target(RenderTarget::Screen);
+
+    let drawn = canvas.commands.len();
+    canvas.draw_debug_overlay();
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let verts = recorded_verts.borrow();
+    let bounds = |drawable: &Drawable| {
+        let mut bounds = Bounds::default();
+        for (start, count) in [drawable.fill_verts, drawable.stroke_verts].into_iter().flatten() {
+            for vert in &verts[start..start + count] {
+                bounds.add_point(Position { x: vert.x, y: vert.y });
+            }
+        }
+        bounds
+    };
+
+    let overlay = &commands[drawn..];
+    assert!(overlay
+        .iter()
+        .all(|cmd| !matches!(cmd.cmd_type, CommandType::SetRenderTarget(RenderTarget::Image(_)))));
+
+    // One heat map fill and one batch colored box per command drawn to the screen
+    let heat: Vec<_> = overlay
+        .iter()
+        .filter(|cmd| matches!(cmd.cmd_type, CommandType::ConvexFill { .. }))
+        .flat_map(|cmd| &cmd.drawables)
+        .map(bounds)
+        .collect();
+    assert_eq!(heat.len(), 2);
+    for (bounds, (x, y, w, h)) in heat.iter().zip([(10., 10., 20., 20.), (49.5, 39.5, 31., 21.)]) {
+        assert!((bounds.minx - x).abs() <= 1. && (bounds.miny - y).abs() <= 1.);
+        assert!((bounds.maxx - (x + w)).abs() <= 1. && (bounds.maxy - (y + h)).abs() <= 1.);
+    }
+
+    let colors: Vec<_> = overlay
+        .iter()
+        .filter_map(|cmd| match &cmd.cmd_type {
+            CommandType::Stroke { params } | CommandType::StencilStroke { params1: params, .. } => {
+                Some(params.inner_col)
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(colors.len(), 2);
+    assert_ne!(colors[0], colors[1]);
+    drop((commands, verts));
+
+    // Glyph atlas textures are drawn once text has been rendered
+    let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+    canvas
+        .fill_text(10., 50., "Overlay", &Paint::color(Color::black()).with_font(&[font]))
+        .unwrap();
+    let textures = canvas.debug_inspector_get_font_textures();
+    assert!(!textures.is_empty());
+
+    canvas.draw_debug_overlay();
+    canvas.flush_to_surface(&());
+    assert!(recorded_commands
+        .borrow()
+        .iter()
+        .any(|cmd| cmd.image.is_some_and(|image| textures.contains(&image))));
+}
+
+#[cfg(all(feature = "text", feature = "debug_inspector"))]
+#[test]
+fn test_renderer_capabilities() {
+    // Returns the layer count of the glyph atlas texture after drawing text
+    let atlas_layers = |capabilities: RendererCapabilities| {
+        let mut canvas = Canvas::new(RecordingRenderer {
+            capabilities,
+            ..Default::default()
+        })
+        .unwrap();
+        canvas.set_size(100, 100, 1.);
+        assert_eq!(canvas.capabilities(), capabilities);
+
+        let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+        canvas
+            .fill_text(10., 50., "Layers", &Paint::color(Color::black()).with_font(&[font]))
+            .unwrap();
+        let textures = canvas.debug_inspector_get_font_textures();
+        assert_eq!(textures.len(), 1);
+        canvas.image_info(textures[0]).unwrap().layers()
+    };
+
+    // Without texture array support the glyph atlas is a plain texture
+    assert_eq!(atlas_layers(RendererCapabilities::default()), 1);
+
+    let capabilities = RendererCapabilities {
+        max_texture_array_layers: 256,
+        ..Default::default()
+    };
+    assert!(atlas_layers(capabilities) > 1);
+}
+
+#[test]
+fn test_frame_stats() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let image = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let pixels = [RGBA8::default(); 16];
+    canvas
+        .update_image(image, ImageSource::Rgba(imgref::Img::new(&pixels[..], 4, 4)), 0, 0)
+        .unwrap();
+
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+    canvas.fill_path(&path, &Paint::color(Color::black()));
+    canvas.flush_to_surface(&());
+
+    // Setting the render target and a fill with its two triangles and an anti-aliasing fringe strip of eight
+    let stats = canvas.frame_stats();
+    assert_eq!(stats.commands, 2);
+    assert_eq!(stats.state_changes, 2);
+    assert_eq!(stats.draw_calls, 2);
+    assert_eq!(stats.triangles, 2 + 8);
+    assert_eq!(stats.texture_uploads, 1);
+    assert_eq!(stats.texture_upload_bytes, 64);
+    assert_eq!(stats.image_memory, 64);
+
+    canvas.flush_to_surface(&());
+
+    let stats = canvas.frame_stats();
+    assert_eq!(stats.draw_calls, 0);
+    assert_eq!(stats.texture_uploads, 0);
+    assert_eq!(stats.image_memory, 64);
+
+    let mut other = Path::new();
+    other.rect(50., 50., 30., 30.);
+    canvas.fill_path(&path, &Paint::color(Color::black()));
+    canvas.fill_path(&other, &Paint::color(Color::black()));
+    canvas.stroke_path(&other, &Paint::color(Color::white()));
+    canvas.flush_to_surface(&());
+
+    // Both fills are batched into one command, the stroke is drawn in three passes over its strip
+    let stats = canvas.frame_stats();
+    assert_eq!(stats.commands, 2);
+    assert_eq!(stats.state_changes, 1);
+    assert_eq!(stats.draw_calls, 2 * 2 + 3);
+    assert_eq!(stats.triangles, 2 * (2 + 8) + 3 * 8);
+}
+
+#[test]
+fn test_create_image_streamed() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(100, 100, 1.);
+    canvas.set_image_upload_budget(10 * 4 * 30);
+
+    let pixels = [RGBA8::default(); 10 * 100];
+    let completed = Rc::new(RefCell::new(Vec::new()));
+
+    let on_complete = |completed: &Rc<RefCell<Vec<ImageId>>>| {
+        let completed = completed.clone();
+        move |id| completed.borrow_mut().push(id)
+    };
+    let image = canvas
+        .create_image_streamed(
+            ImageSource::Rgba(imgref::Img::new(&pixels[..], 10, 100)),
+            ImageFlags::empty(),
+            on_complete(&completed),
+        )
+        .unwrap();
+    let cancelled = canvas
+        .create_image_streamed(
+            ImageSource::Rgba(imgref::Img::new(&pixels[..], 10, 100)),
+            ImageFlags::empty(),
+            on_complete(&completed),
+        )
+        .unwrap();
+    canvas.delete_image(cancelled);
+
+    for _ in 0..3 {
+        canvas.flush_to_surface(&());
+        assert_eq!(canvas.frame_stats().texture_upload_bytes, 10 * 4 * 30);
+        assert!(completed.borrow().is_empty());
+    }
+
+    canvas.flush_to_surface(&());
+    assert_eq!(canvas.frame_stats().texture_upload_bytes, 10 * 4 * 10);
+    assert_eq!(*completed.borrow(), [image]);
+
+    canvas.flush_to_surface(&());
+    assert_eq!(canvas.frame_stats().texture_uploads, 0);
+}
+
+#[test]
+fn test_error_callback() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let errors = Rc::new(RefCell::new(Vec::new()));
+    let reported = errors.clone();
+    canvas.set_error_callback(move |error| reported.borrow_mut().push(error));
+
+    assert!(matches!(
+        canvas.create_image_empty(0, 4, PixelFormat::Rgba8, ImageFlags::empty()),
+        Err(ErrorKind::UnsupportedImageSize)
+    ));
+
+    let image = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    canvas.delete_image(image);
+
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+    canvas.fill_path(&path, &Paint::image(image, 0., 0., 4., 4., 0., 1.));
+
+    assert!(matches!(errors.borrow()[..], [ErrorKind::ImageIdNotFound]));
+
+    // Points moved out of the f32 range by the transform are left out
+    canvas.save();
+    canvas.scale(f32::MAX, f32::MAX);
+    canvas.fill_path(&path, &Paint::color(Color::black()));
+    assert!(matches!(errors.borrow()[1..], [ErrorKind::PathOutOfRange]));
+
+    // The fill and stroke of the same path report it once
+    let paint = Paint::color(Color::black());
+    canvas.draw_path(&path, Some(&paint), Some(&paint));
+    canvas.restore();
+    assert!(matches!(
+        errors.borrow()[1..],
+        [ErrorKind::PathOutOfRange, ErrorKind::PathOutOfRange]
+    ));
+
+    // Glyphs too large for the glyph atlas are left out of the text
+    #[cfg(feature = "text")]
+    {
+        let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+        let paint = Paint::color(Color::black()).with_font(&[font]).with_line_width(600.);
+        assert!(canvas.stroke_text(10., 50., "A", &paint).is_ok());
+        assert!(matches!(errors.borrow()[3..], [ErrorKind::FontSizeTooLargeForAtlas]));
+    }
+
+    canvas.flush_to_surface(&());
+    assert_eq!(canvas.frame_stats().errors, errors.borrow().len());
+}
+
+#[test]
+fn test_stale_image_id() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
     let recorded_commands = renderer.last_commands.clone();
     let mut canvas = Canvas::new(renderer).unwrap();
     canvas.set_size(100, 100, 1.);
+
+    let errors = Rc::new(RefCell::new(Vec::new()));
+    let reported = errors.clone();
+    canvas.set_error_callback(move |error| reported.borrow_mut().push(error));
+
+    let stale = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    canvas.delete_image(stale);
+    let image = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    assert_ne!(stale, image);
+    assert!(canvas.image_info(stale).is_err());
+
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+    canvas.fill_path(&path, &Paint::image(stale, 0., 0., 4., 4., 0., 1.));
+    canvas.stroke_path(&path, &Paint::image(stale, 0., 0., 4., 4., 0., 1.));
+    // An axis aligned rect without anti-aliasing takes the image blit fast path
+    canvas.fill_path(
+        &path,
+        &Paint::image(stale, 10., 10., 30., 30., 0., 1.).with_anti_alias(false),
+    );
+    canvas.flush_to_surface(&());
+
+    assert!(matches!(
+        errors.borrow()[..],
+        [
+            ErrorKind::ImageIdNotFound,
+            ErrorKind::ImageIdNotFound,
+            ErrorKind::ImageIdNotFound
+        ]
+    ));
+    assert!(recorded_commands
+        .borrow()
+        .iter()
+        .all(|command| matches!(command.cmd_type, CommandType::SetRenderTarget(..))));
+}
+
+#[test]
+fn test_content_bounds() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(100, 100, 1.);
+    assert_eq!(canvas.content_bounds(), None);
+
     let mut path = Path::new();
-    path.rect(10., 10., 50., 50.);
+    path.rect(10., 10., 20., 20.);
+    let paint = Paint::color(Color::black()).with_anti_alias(false);
+    canvas.fill_path(&path, &paint);
+    assert_eq!(canvas.content_bounds(), Some(Rect::new(10., 10., 20., 20.)));
+
+    // drawing outside of the target is clipped, bounds only cover the current target
     let image = canvas
-        .create_image_empty(30, 30, PixelFormat::Rgba8, ImageFlags::empty())
+        .create_image_empty(200, 200, PixelFormat::Rgba8, ImageFlags::empty())
         .unwrap();
-    let paint = Paint::image(image, 0., 0., 30., 30., 0., 0.).with_anti_alias(false);
+    canvas.translate(80., 50.);
+    canvas.fill_path(&path, &paint);
+    canvas.push_render_target(RenderTarget::Image(image));
     canvas.fill_path(&path, &paint);
+    assert_eq!(canvas.content_bounds(), Some(Rect::new(10., 10., 20., 20.)));
+
+    canvas.pop_render_target();
+    assert_eq!(canvas.content_bounds(), Some(Rect::new(10., 10., 90., 70.)));
+
     canvas.flush_to_surface(&());
+    assert_eq!(canvas.content_bounds(), None);
+}
 
-    let commands = recorded_commands.borrow();
-    let mut commands = commands.iter();
+#[test]
+fn test_render_target_stack() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let layer = canvas
+        .create_image_empty(40, 30, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let cached = canvas
+        .create_image_empty(20, 10, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+
+    canvas.translate(10., 10.);
+    canvas.scissor(0., 0., 50., 50.);
+
+    canvas.push_render_target(RenderTarget::Image(layer));
+    assert_eq!((canvas.width(), canvas.height()), (40, 30));
+    assert_eq!(canvas.transform(), Transform2D::identity());
+    assert!(canvas.state().scissor.extent.is_none());
+    canvas.scale(2., 2.);
+
+    canvas.push_render_target(RenderTarget::Image(cached));
+    assert_eq!((canvas.width(), canvas.height()), (20, 10));
+    assert_eq!(canvas.transform(), Transform2D::identity());
+    // unbalanced saves are discarded
+    canvas.save();
+    canvas.pop_render_target();
+
+    assert_eq!((canvas.width(), canvas.height()), (40, 30));
+    assert_eq!(canvas.transform(), Transform2D::scaling(2., 2.));
+    canvas.pop_render_target();
+
+    assert_eq!((canvas.width(), canvas.height()), (100, 100));
+    assert_eq!(canvas.transform(), Transform2D::translation(10., 10.));
+    assert!(canvas.state().scissor.extent.is_some());
+
+    // popping an empty stack does nothing
+    canvas.pop_render_target();
+    assert_eq!(canvas.transform(), Transform2D::translation(10., 10.));
+}
+
+#[test]
+fn test_screenshot_area_bounds() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(100, 50, 1.);
+
+    let mut buffer = vec![0; 10 * 10 * 3];
     assert!(matches!(
-        commands.next(),
-        Some(Command {
-            cmd_type: CommandType::SetRenderTarget(..),
-            ..
-        })
+        canvas.screenshot_area(95, 0, 10, 10, ReadbackFormat::Rgb8, &mut buffer),
+        Err(ErrorKind::ReadbackOutOfBounds)
     ));
     assert!(matches!(
-        commands.next(),
-        Some(Command {
-            cmd_type: CommandType::Triangles {
-                params: Params {
-                    shader_type: renderer::ShaderType::TextureCopyUnclipped,
-                    ..
-                }
-            },
-            ..
+        canvas.screenshot_area(0, 0, 10, 10, ReadbackFormat::Rgba8, &mut buffer),
+        Err(ErrorKind::BufferTooSmall)
+    ));
+
+    // Image targets are read back within the bounds of the image
+    let image = canvas
+        .create_image_empty(20, 20, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    canvas.set_render_target(RenderTarget::Image(image));
+    assert!(matches!(
+        canvas.screenshot_area(15, 0, 10, 10, ReadbackFormat::Rgb8, &mut buffer),
+        Err(ErrorKind::ReadbackOutOfBounds)
+    ));
+    canvas.delete_image(image);
+    assert!(matches!(
+        canvas.screenshot_area(0, 0, 10, 10, ReadbackFormat::Rgb8, &mut buffer),
+        Err(ErrorKind::ImageIdNotFound)
+    ));
+    canvas.set_render_target(RenderTarget::Screen);
+
+    let pixels = [RGBA8::new(1, 2, 3, 4), RGBA8::new(5, 6, 7, 8)];
+    ReadbackFormat::Bgra8.write_pixels(pixels, &mut buffer);
+    assert_eq!(buffer[..8], [3, 2, 1, 4, 7, 6, 5, 8]);
+    ReadbackFormat::Rgb8.write_pixels(pixels, &mut buffer);
+    assert_eq!(buffer[..6], [1, 2, 3, 5, 6, 7]);
+}
+
+#[test]
+fn test_gradient_along_path() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let mut path = Path::new();
+    path.move_to(10., 50.);
+    path.line_to(50., 50.);
+    path.line_to(50., 90.);
+
+    let paint = Paint::linear_gradient_along_path(Color::black(), Color::white())
+        .with_line_width(4.)
+        .with_stencil_strokes(false);
+    canvas.stroke_path(&path, &paint);
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let CommandType::Stroke { params } = &commands[1].cmd_type else {
+        panic!("expected a stroke");
+    };
+    assert_eq!(params.path_gradient, 1.0);
+
+    // The cap fringes are kept below 1 and above 2, the path goes from 1 to 2 with the corner half way
+    let v: Vec<_> = recorded_verts.borrow().iter().map(|vertex| vertex.v).collect();
+    assert_eq!(v, [0.0, 0.0, 1.0, 1.0, 1.5, 1.5, 2.0, 2.0, 3.0, 3.0]);
+}
+
+#[test]
+fn test_focal_radial_gradient() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+    canvas.translate(10., 0.);
+
+    let mut path = Path::new();
+    path.rect(0., 0., 100., 100.);
+
+    let paint = Paint::focal_radial_gradient(30., 40., 5., 50., 50., 40., Color::white(), Color::black());
+    canvas.fill_path(&path, &paint);
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let CommandType::ConvexFill { params } = &commands[1].cmd_type else {
+        panic!("expected a convex fill");
+    };
+    assert_eq!(params.shader_type, ShaderType::FillGradient);
+    assert_eq!(params.focal_gradient, 1.0);
+    assert_eq!(params.extent, [20., 10.]);
+    assert_eq!((params.radius, params.feather), (5., 40.));
+
+    // The paint space has its origin at the focal point
+    let [a, b, _, _, c, d, _, _, x, y, ..] = params.paint_mat;
+    assert_eq!(Transform2D::new(a, b, c, d, x, y).transform_point(40., 40.), (0., 0.));
+}
+
+#[test]
+fn test_gradient_spread() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let mut path = Path::new();
+    path.rect(0., 0., 100., 100.);
+
+    let stripes =
+        Paint::linear_gradient(0., 0., 10., 0., Color::white(), Color::black()).with_gradient_spread(Spread::Repeat);
+    assert_eq!(stripes.gradient_spread(), Spread::Repeat);
+    canvas.fill_path(&path, &stripes);
+
+    let rings =
+        Paint::radial_gradient(50., 50., 0., 10., Color::white(), Color::black()).with_gradient_spread(Spread::Reflect);
+    canvas.fill_path(&path, &rings);
+    canvas.fill_path(
+        &path,
+        &Paint::linear_gradient(0., 0., 10., 0., Color::white(), Color::black()),
+    );
+
+    // Other paints have no gradient to spread
+    let color = Paint::color(Color::white()).with_gradient_spread(Spread::Repeat);
+    assert_eq!(color.gradient_spread(), Spread::Pad);
+
+    canvas.flush_to_surface(&());
+
+    let spreads: Vec<_> = recorded_commands
+        .borrow()
+        .iter()
+        .filter_map(|command| match &command.cmd_type {
+            CommandType::ConvexFill { params } => Some(params.gradient_spread),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(spreads, [1.0, 2.0, 0.0]);
+}
+
+#[test]
+fn test_fill_path_cached() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let square = |size: f32| {
+        let mut path = Path::new();
+        path.rect(10., 10., size, size);
+        path
+    };
+    let paint = Paint::color(Color::black());
+
+    canvas.fill_path_cached(1, &square(20.), &paint);
+    let cached = canvas.path_caches[&1].path_cache.contours.as_ptr();
+
+    // A rebuilt path with the same points reuses the tessellation
+    canvas.fill_path_cached(1, &square(20.), &paint);
+    assert_eq!(canvas.path_caches[&1].path_cache.contours.as_ptr(), cached);
+    canvas.flush_to_surface(&());
+
+    let key = canvas.path_caches[&1].key;
+    canvas.fill_path_cached(1, &square(30.), &paint);
+    assert_ne!(canvas.path_caches[&1].key, key);
+
+    // Keys that weren't drawn with during a frame are released
+    canvas.fill_path_cached(2, &square(20.), &paint);
+    canvas.flush_to_surface(&());
+    canvas.fill_path_cached(2, &square(20.), &paint);
+    canvas.flush_to_surface(&());
+    assert!(!canvas.path_caches.contains_key(&1));
+    assert!(canvas.path_caches.contains_key(&2));
+
+    // Changing a path that was drawn before changes its key
+    let mut path = square(20.);
+    canvas.fill_path_cached(3, &path, &paint);
+    let key = canvas.path_caches[&3].key;
+    path.line_to(50., 50.);
+    canvas.fill_path_cached(3, &path, &paint);
+    assert_ne!(canvas.path_caches[&3].key, key);
+}
+
+#[test]
+fn test_draw_quads() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let tiles = canvas
+        .create_image_empty(32, 32, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+
+    let quad = |x0: f32, color: Option<Color>| TexturedQuad {
+        quad: Quad {
+            x0,
+            y0: 0.,
+            s0: 0.,
+            t0: 0.,
+            x1: x0 + 10.,
+            y1: 10.,
+            s1: 1.,
+            t1: 1.,
+        },
+        color,
+    };
+    let quads = [quad(0., None), quad(10., None), quad(20., Some(Color::rgb(255, 0, 0)))];
+    canvas.draw_quads(tiles, &quads, &Paint::color(Color::white()));
+    canvas.flush_to_surface(&());
+
+    // Quads without a color take the paint and are drawn together, the tinted quad is drawn separately
+    let commands = recorded_commands.borrow();
+    let quad_commands: Vec<_> = commands
+        .iter()
+        .filter(|cmd| cmd.glyph_texture == GlyphTexture::Image(tiles))
+        .collect();
+    let vert_counts: Vec<_> = quad_commands.iter().map(|cmd| cmd.triangles_verts.unwrap().1).collect();
+    assert_eq!(vert_counts, [12, 6]);
+
+    let CommandType::Triangles { params } = &quad_commands[1].cmd_type else {
+        panic!("expected triangles");
+    };
+    assert_eq!(params.glyph_texture_type, 5);
+    assert_eq!(params.inner_col, [1., 0., 0., 1.]);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn test_text_rasterization_settings() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+    let paint = Paint::color(Color::black())
+        .with_font(&[font])
+        .with_text_gamma(1.8)
+        .with_text_contrast(0.2);
+    canvas.fill_text(10., 50., "x", &paint).unwrap();
+    let rendered_glyphs = canvas.glyph_atlas.rendered_glyphs.borrow().len();
+
+    // Hinted and darkened glyphs are rasterized separately
+    canvas
+        .fill_text(10., 50., "x", &paint.clone().with_text_hinting(TextHinting::Slight))
+        .unwrap();
+    canvas
+        .fill_text(10., 50., "x", &paint.clone().with_stem_darkening(0.3))
+        .unwrap();
+    assert_eq!(canvas.glyph_atlas.rendered_glyphs.borrow().len(), rendered_glyphs + 2);
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let Some(CommandType::Triangles { params }) = commands.iter().map(|cmd| &cmd.cmd_type).next_back() else {
+        panic!("expected glyph triangles");
+    };
+    assert_eq!((params.mask_gamma, params.mask_contrast), (1.8, 0.2));
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn test_transformed_glyph_quads() {
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(200, 200, 1.);
+
+    let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+    let paint = Paint::color(Color::black()).with_font(&[font]);
+
+    // Returns the corners of the quads glyphs were drawn with
+    let mut draw_rotated = |paint: &Paint| {
+        canvas.save();
+        canvas.translate(100., 100.);
+        canvas.rotate(0.5);
+        canvas.fill_text(0., 0., "HHHH", paint).unwrap();
+        canvas.restore();
+        canvas.flush_to_surface(&());
+
+        let verts = recorded_verts.borrow();
+        recorded_commands
+            .borrow()
+            .iter()
+            .filter(|cmd| matches!(cmd.glyph_texture, GlyphTexture::AlphaMask(..)))
+            .flat_map(|cmd| {
+                let (start, count) = cmd.triangles_verts.unwrap();
+                verts[start..start + count].chunks(6).map(|quad| quad.to_vec())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let is_axis_aligned = |quad: &Vec<Vertex>| {
+        quad.iter()
+            .all(|vert| [quad[0].x, quad[1].x].contains(&vert.x) && [quad[0].y, quad[1].y].contains(&vert.y))
+    };
+
+    let sampled_quads = draw_rotated(&paint);
+    assert_eq!(sampled_quads.len(), 4);
+    assert!(!sampled_quads.iter().any(is_axis_aligned));
+
+    let transformed_quads = draw_rotated(&paint.with_rasterize_transformed_text(true));
+    assert_eq!(transformed_quads.len(), 4);
+    assert!(transformed_quads.iter().all(is_axis_aligned));
+
+    // Rasterized glyphs are placed at whole pixels along the rotated baseline
+    assert!(transformed_quads
+        .iter()
+        .flatten()
+        .all(|vert| vert.x.fract() == 0.0 && vert.y.fract() == 0.0));
+    let (first, last) = (&transformed_quads[0][0], &transformed_quads[3][0]);
+    let angle = (last.y - first.y).atan2(last.x - first.x);
+    assert!((angle - 0.5).abs() < 0.05, "glyphs placed along {angle}");
+    assert!((95.0..110.0).contains(&first.x) && (85.0..110.0).contains(&first.y));
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn test_text_decoration() {
+    let mut canvas = Canvas::new(renderer::Void).unwrap();
+    canvas.set_size(200, 100, 1.);
+
+    let font = canvas.add_font("examples/assets/Roboto-Regular.ttf").unwrap();
+    let paint = Paint::color(Color::black()).with_font(&[font]).with_font_size(20.);
+    let metrics = canvas.measure_font(&paint).unwrap();
+    assert!(metrics.underline_position() < 0.0 && metrics.underline_thickness() > 0.0);
+    assert!(metrics.strikeout_position() > 0.0 && metrics.strikeout_thickness() > 0.0);
+
+    canvas.fill_text(50., 50., "xx", &paint).unwrap();
+    let text_bounds = canvas.content_bounds().unwrap();
+    canvas.flush_to_surface(&());
+
+    let decorated = paint.with_text_decoration(TextDecoration::UNDERLINE | TextDecoration::OVERLINE);
+    let layout = canvas.fill_text(50., 50., "xx", &decorated).unwrap();
+    let bounds = canvas.content_bounds().unwrap();
+    assert!(bounds.y + bounds.h > text_bounds.y + text_bounds.h && bounds.y < text_bounds.y);
+    assert!(bounds.x <= layout.x && bounds.x + bounds.w >= layout.x + layout.width());
+}
+
+#[test]
+fn test_image_source_rect() {
+    use renderer::CommandType;
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let sprites = canvas
+        .create_image_empty(64, 16, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+
+    let mut path = Path::new();
+    path.rect(10., 10., 20., 20.);
+    let paint = Paint::image(sprites, 10., 10., 20., 20., 0., 1.).with_source_rect(Some(Rect::new(16., 0., 16., 16.)));
+    assert_eq!(paint.source_rect(), Some(Rect::new(16., 0., 16., 16.)));
+    canvas.fill_path(&path, &paint);
+    canvas.flush_to_surface(&());
+
+    let commands = recorded_commands.borrow();
+    let Some(CommandType::ConvexFill { params }) = commands.iter().map(|cmd| &cmd.cmd_type).next_back() else {
+        panic!("expected a convex fill");
+    };
+
+    // The pattern of the whole image is scaled so that the sprite covers 20x20, and sampling stays half a texel
+    // inside of the sprite
+    assert_eq!(params.extent, [80., 20.]);
+    assert_eq!(params.tex_rect, [16.5 / 64., 0.5 / 16., 31.5 / 64., 15.5 / 16.]);
+}
+
+#[test]
+fn test_stroke_width_profile() {
+    let renderer = RecordingRenderer::default();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let mut path = Path::new();
+    path.move_to(10., 50.);
+    path.line_to(90., 50.);
+
+    let paint = Paint::color(Color::black())
+        .with_line_width(4.)
+        .with_anti_alias(false)
+        .with_stencil_strokes(false);
+    canvas.stroke_path_with_width_profile(&path, &paint, |t| 1. + t);
+    canvas.flush_to_surface(&());
+
+    // The stroke widens from the line width at its start to twice of it at its end
+    let verts = recorded_verts.borrow();
+    let edges_at = |x: f32| {
+        let mut ys: Vec<_> = verts
+            .iter()
+            .filter(|vertex| vertex.x == x)
+            .map(|vertex| vertex.y)
+            .collect();
+        ys.sort_by(f32::total_cmp);
+        ys.dedup();
+        ys
+    };
+    assert_eq!(edges_at(10.), [48., 52.]);
+    assert_eq!(edges_at(90.), [46., 54.]);
+}
+
+#[test]
+fn test_tessellation_quality() {
+    let renderer = RecordingRenderer::default();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let mut path = Path::new();
+    path.move_to(0., 0.);
+    path.bezier_to(100., 0., 0., 100., 100., 100.);
+
+    let stroke_vertex_count = |canvas: &mut Canvas<RecordingRenderer>| {
+        canvas.stroke_path(&path, &Paint::color(Color::black()));
+        canvas.flush_to_surface(&());
+        recorded_verts.borrow().len()
+    };
+
+    let default = stroke_vertex_count(&mut canvas);
+
+    canvas.set_tessellation_quality(TessellationQuality {
+        tolerance: 0.01,
+        ..Default::default()
+    });
+    let fine = stroke_vertex_count(&mut canvas);
+
+    canvas.set_tessellation_quality(TessellationQuality {
+        tolerance: 10.0,
+        ..Default::default()
+    });
+    let coarse = stroke_vertex_count(&mut canvas);
+
+    assert!(fine > default);
+    assert!(coarse < default);
+}
+
+#[test]
+fn test_paint_fringe_width() {
+    let renderer = RecordingRenderer::default();
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+
+    let max_x = |canvas: &mut Canvas<RecordingRenderer>, paint: &Paint| {
+        canvas.fill_path(&path, paint);
+        canvas.flush_to_surface(&());
+        recorded_verts
+            .borrow()
+            .iter()
+            .map(|vert| vert.x)
+            .fold(f32::MIN, f32::max)
+    };
+
+    let paint = Paint::color(Color::black());
+    let default = max_x(&mut canvas, &paint);
+    let wide = max_x(&mut canvas, &paint.clone().with_fringe_width(FringeWidth::Pixels(4.)));
+    let none = max_x(&mut canvas, &paint.clone().with_fringe_width(FringeWidth::Pixels(0.)));
+
+    assert!(wide > default);
+    assert!(none < default);
+    assert_eq!(none, 40.);
+
+    // Like the default fringe, overrides are in pixels and scaled by the device pixel ratio
+    canvas.set_size(100, 100, 2.);
+    let default = max_x(&mut canvas, &paint);
+    let pixel = max_x(&mut canvas, &paint.clone().with_fringe_width(FringeWidth::Pixels(1.)));
+    assert_eq!(pixel, default);
+    assert!(default < 40.5);
+}
+
+#[test]
+fn test_multisampled_screen_has_no_fringe() {
+    let renderer = RecordingRenderer {
+        sample_count: 4,
+        ..Default::default()
+    };
+    let recorded_verts = renderer.last_verts.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let mut path = Path::new();
+    path.rect(10., 10., 30., 30.);
+
+    let max_x = |canvas: &mut Canvas<RecordingRenderer>| {
+        canvas.fill_path(&path, &Paint::color(Color::black()));
+        canvas.flush_to_surface(&());
+        recorded_verts
+            .borrow()
+            .iter()
+            .map(|vert| vert.x)
+            .fold(f32::MIN, f32::max)
+    };
+
+    assert_eq!(max_x(&mut canvas), 40.);
+
+    // Images aren't multisampled, so they still get the fringe
+    let image = canvas
+        .create_image_empty(100, 100, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    canvas.set_render_target(RenderTarget::Image(image));
+    assert!(max_x(&mut canvas) > 40.);
+}
+
+#[test]
+fn test_blur_image() {
+    use renderer::{CommandType, Params};
+
+    let renderer = RecordingRenderer::default();
+    let recorded_commands = renderer.last_commands.clone();
+    let mut canvas = Canvas::new(renderer).unwrap();
+    canvas.set_size(100, 100, 1.);
+
+    let source = canvas
+        .create_image_empty(64, 64, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let target = canvas
+        .create_image_empty(64, 64, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+
+    canvas.blur_image(source, target, 10.);
+    canvas.flush_to_surface(&());
+
+    let blurs: Vec<_> = recorded_commands
+        .borrow()
+        .iter()
+        .filter_map(|command| match command.cmd_type {
+            CommandType::RenderFilteredImage {
+                target_image,
+                filter: ImageFilter::FastBlur { radius },
+            } => Some((command.image, target_image, radius)),
+            _ => None,
         })
+        .collect();
+    assert_eq!(blurs, [(Some(source), target, 10.)]);
+
+    // Each level halves the image and doubles the radius, the offset of the samples covers the rest
+    assert_eq!(Params::dual_kawase_levels(10., 64, 64), (2, 2.5));
+    assert_eq!(Params::dual_kawase_levels(1., 64, 64), (1, 0.5));
+    // Small images are never halved below a single pixel
+    assert_eq!(Params::dual_kawase_levels(100., 4, 64), (2, 25.));
+}
+
+#[cfg(feature = "image-loading")]
+#[test]
+fn test_dynamic_image_conversions() {
+    let mut canvas = Canvas::new(RecordingRenderer::default()).unwrap();
+
+    let formats = [
+        (DynamicImage::new_luma8(4, 4), PixelFormat::Gray8),
+        (DynamicImage::new_luma16(4, 4), PixelFormat::Gray8),
+        (DynamicImage::new_luma_a8(4, 4), PixelFormat::Rgba8),
+        (DynamicImage::new_rgb16(4, 4), PixelFormat::Rgb8),
+        (DynamicImage::new_rgba32f(4, 4), PixelFormat::Rgba8),
+    ];
+    for (image, format) in formats {
+        let id = canvas
+            .create_image_from_dynamic_image(&image, ImageFlags::empty())
+            .unwrap();
+        assert_eq!(canvas.image_info(id).unwrap().format(), format);
+    }
+
+    // Sources of images without 8 bits per channel are converted when they are uploaded
+    assert!(matches!(
+        ImageSource::from_dynamic_image(&DynamicImage::new_rgba8(1, 1)),
+        ImageSource::Rgba(_)
     ));
+    let image = DynamicImage::new_rgb16(4, 2);
+    let src = ImageSource::from_dynamic_image(&image);
+    assert!(matches!(src, ImageSource::Dynamic(_)));
+    assert_eq!(src.format(), PixelFormat::Rgb8);
+    assert_eq!(src.dimensions(), crate::image::Size::new(4, 2));
+    assert!(matches!(ImageData::new(src).unwrap(), ImageData::Rgb(_)));
+    let id = canvas.create_image(src, ImageFlags::empty()).unwrap();
+    assert_eq!(canvas.image_info(id).unwrap().format(), PixelFormat::Rgb8);
+    assert!(canvas
+        .update_image(id, ImageSource::try_from(&image).unwrap(), 0, 0)
+        .is_ok());
+
+    // Views are converted to the format of the updated image
+    let target = canvas
+        .create_image_empty(4, 4, PixelFormat::Rgba8, ImageFlags::empty())
+        .unwrap();
+    let image = DynamicImage::new_rgb8(8, 8);
+    assert!(canvas
+        .update_image_from_dynamic_image(target, &image.view(2, 2, 4, 4), 0, 0)
+        .is_ok());
+
+    let premultiplied = ImgVec::new(vec![RGBA8::new(64, 128, 0, 128), RGBA8::new(10, 10, 10, 0)], 2, 1);
+    let image = unpremultiplied_dynamic_image(premultiplied).to_rgba8();
+    assert_eq!(image.get_pixel(0, 0).0, [128, 255, 0, 128]);
+    assert_eq!(image.get_pixel(1, 0).0, [0, 0, 0, 0]);
 }
@@ -3,7 +3,24 @@
 
 use std::rc::Rc;
 
-use crate::{geometry::Position, Align, Baseline, Color, FillRule, FontId, ImageId, LineCap, LineJoin};
+use slotmap::DefaultKey;
+
+use crate::{geometry::Position, Color, FillRule, ImageId, LineCap, LineJoin, Rect, Spread, Transform2D};
+#[cfg(feature = "text")]
+use crate::{Align, Baseline, FontId, Script, TextDecoration, TextHinting};
+
+/// A handle to a paint owned by a [`Canvas`](crate::Canvas).
+///
+/// Commands drawn with a paint id pick up changes made to the paint until they are flushed. Gradients and image
+/// patterns are shared through paint handles as well, there are no separate gradient or image handles.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PaintId(pub(crate) DefaultKey);
+
+/// A handle to a custom fill shader created with [`Canvas::create_shader()`](crate::Canvas::create_shader).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ShaderId(pub(crate) DefaultKey);
 
 #[derive(Copy, Clone, Debug, PartialEq, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -151,7 +168,6 @@ impl GradientColors {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PaintFlavor {
     Color(Color),
-    #[cfg_attr(feature = "serde", serde(skip))]
     Image {
         id: ImageId,
         center: Position,
@@ -159,11 +175,13 @@ pub enum PaintFlavor {
         height: f32,
         angle: f32,
         tint: Color,
+        source: Option<Rect>,
     },
     LinearGradient {
         start: Position,
         end: Position,
         colors: GradientColors,
+        spread: Spread,
     },
     BoxGradient {
         pos: Position,
@@ -178,6 +196,25 @@ pub enum PaintFlavor {
         in_radius: f32,
         out_radius: f32,
         colors: GradientColors,
+        spread: Spread,
+    },
+    // Radial gradient between two circles that aren't necessarily concentric
+    FocalRadialGradient {
+        focal: Position,
+        focal_radius: f32,
+        center: Position,
+        radius: f32,
+        colors: GradientColors,
+        spread: Spread,
+    },
+    // Gradient along the length of stroked paths
+    PathGradient {
+        colors: GradientColors,
+    },
+    Custom {
+        shader: ShaderId,
+        uniforms: [f32; 8],
+        alpha: f32,
     },
 }
 
@@ -191,6 +228,9 @@ impl PaintFlavor {
             Self::Image { tint, .. } => {
                 tint.a *= a;
             }
+            Self::Custom { alpha, .. } => {
+                *alpha *= a;
+            }
             Self::LinearGradient { colors, .. } => {
                 colors.mul_alpha(a);
             }
@@ -200,6 +240,12 @@ impl PaintFlavor {
             Self::RadialGradient { colors, .. } => {
                 colors.mul_alpha(a);
             }
+            Self::FocalRadialGradient { colors, .. } => {
+                colors.mul_alpha(a);
+            }
+            Self::PathGradient { colors } => {
+                colors.mul_alpha(a);
+            }
         }
     }
 
@@ -208,6 +254,8 @@ impl PaintFlavor {
             Self::LinearGradient { colors, .. } => Some(colors),
             Self::BoxGradient { colors, .. } => Some(colors),
             Self::RadialGradient { colors, .. } => Some(colors),
+            Self::FocalRadialGradient { colors, .. } => Some(colors),
+            Self::PathGradient { colors } => Some(colors),
             _ => None,
         }
     }
@@ -219,18 +267,24 @@ impl PaintFlavor {
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum GlyphTexture {
     #[default]
     None,
-    AlphaMask(ImageId),
-    ColorTexture(ImageId),
+    // Glyph atlas image and layer
+    AlphaMask(ImageId, usize),
+    ColorTexture(ImageId, usize),
+    // An image of textured quads, multiplied with the paint
+    Image(ImageId),
 }
 
 impl GlyphTexture {
     pub(crate) fn image_id(&self) -> Option<ImageId> {
         match self {
             GlyphTexture::None => None,
-            GlyphTexture::AlphaMask(image_id) | GlyphTexture::ColorTexture(image_id) => Some(*image_id),
+            GlyphTexture::AlphaMask(image_id, _)
+            | GlyphTexture::ColorTexture(image_id, _)
+            | GlyphTexture::Image(image_id) => Some(*image_id),
         }
     }
 }
@@ -259,29 +313,68 @@ impl Default for StrokeSettings {
     }
 }
 
+#[cfg(feature = "text")]
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextSettings {
     #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) font_ids: [Option<FontId>; 8],
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) script_fonts: Vec<(Script, [Option<FontId>; 8])>,
     pub(crate) font_size: f32,
     pub(crate) letter_spacing: f32,
     pub(crate) text_baseline: Baseline,
     pub(crate) text_align: Align,
+    pub(crate) rasterize_transformed: bool,
+    pub(crate) hinting: TextHinting,
+    pub(crate) stem_darkening: f32,
+    pub(crate) gamma: f32,
+    pub(crate) contrast: f32,
+    pub(crate) decoration: TextDecoration,
 }
 
+#[cfg(feature = "text")]
 impl Default for TextSettings {
     fn default() -> Self {
         Self {
             font_ids: Default::default(),
+            script_fonts: Vec::new(),
             font_size: 16.0,
             letter_spacing: 0.0,
             text_baseline: Default::default(),
             text_align: Default::default(),
+            rasterize_transformed: false,
+            hinting: TextHinting::default(),
+            stem_darkening: 0.0,
+            gamma: 1.0,
+            contrast: 0.0,
+            decoration: TextDecoration::empty(),
         }
     }
 }
 
+#[cfg(feature = "text")]
+impl TextSettings {
+    pub(crate) fn font_ids_for_script(&self, script: Script) -> [Option<FontId>; 8] {
+        self.script_fonts
+            .iter()
+            .find(|(s, _)| *s == script)
+            .map_or(self.font_ids, |(_, font_ids)| *font_ids)
+    }
+}
+
+/// Width of the anti-aliasing fringe drawn around shapes, see [`Paint::set_fringe_width`].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FringeWidth {
+    /// The fringe width of the canvas, one pixel of the render target unless changed by
+    /// [`Canvas::set_tessellation_quality`](crate::Canvas::set_tessellation_quality).
+    #[default]
+    Canvas,
+    /// A fringe of the given width in pixels of the render target. A width of 0 disables the fringe.
+    Pixels(f32),
+}
+
 /// Struct controlling how graphical shapes are rendered.
 ///
 /// The Paint struct is a relatively lightweight object which contains all the information needed to
@@ -311,9 +404,12 @@ impl Default for TextSettings {
 pub struct Paint {
     pub(crate) flavor: PaintFlavor,
     pub(crate) shape_anti_alias: bool,
+    pub(crate) fringe_width: FringeWidth,
     pub(crate) stroke: StrokeSettings,
+    #[cfg(feature = "text")]
     pub(crate) text: TextSettings,
     pub(crate) fill_rule: FillRule,
+    pub(crate) transform: Transform2D,
 }
 
 impl Default for Paint {
@@ -321,9 +417,12 @@ impl Default for Paint {
         Self {
             flavor: PaintFlavor::Color(Color::white()),
             shape_anti_alias: true,
+            fringe_width: FringeWidth::Canvas,
             stroke: StrokeSettings::default(),
+            #[cfg(feature = "text")]
             text: TextSettings::default(),
             fill_rule: Default::default(),
+            transform: Transform2D::identity(),
         }
     }
 }
@@ -370,6 +469,7 @@ impl Paint {
             height,
             angle,
             tint: Color::rgbaf(1.0, 1.0, 1.0, alpha),
+            source: None,
         })
     }
 
@@ -383,6 +483,7 @@ impl Paint {
             height,
             angle,
             tint,
+            source: None,
         })
     }
 
@@ -413,6 +514,7 @@ impl Paint {
             start: Position { x: start_x, y: start_y },
             end: Position { x: end_x, y: end_y },
             colors: GradientColors::TwoStop { start_color, end_color },
+            spread: Spread::Pad,
         })
     }
     /// Creates and returns a linear gradient paint with two or more stops.
@@ -448,6 +550,41 @@ impl Paint {
             start: Position { x: start_x, y: start_y },
             end: Position { x: end_x, y: end_y },
             colors: GradientColors::from_stops(stops),
+            spread: Spread::Pad,
+        })
+    }
+
+    /// Creates and returns a gradient that follows the length of stroked paths, from `start_color` at the
+    /// start of the path to `end_color` at its end.
+    ///
+    /// The gradient spans all sub-paths of a path, in the order they were added. Fills with this paint use
+    /// the start color.
+    ///
+    /// # Example
+    /// ```
+    /// use femtovg::{Paint, Path, Color, Canvas, renderer::Void};
+    ///
+    /// let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+    ///
+    /// let progress = Paint::linear_gradient_along_path(Color::rgb(0, 128, 255), Color::rgb(255, 0, 128))
+    ///     .with_line_width(8.0);
+    /// let mut path = Path::new();
+    /// path.arc(50.0, 50.0, 40.0, 0.0, 4.0, femtovg::Solidity::Hole);
+    /// canvas.stroke_path(&path, &progress);
+    /// ```
+    pub fn linear_gradient_along_path(start_color: Color, end_color: Color) -> Self {
+        Self::with_flavor(PaintFlavor::PathGradient {
+            colors: GradientColors::TwoStop { start_color, end_color },
+        })
+    }
+
+    /// Creates and returns a gradient with two or more stops that follows the length of stroked paths. A
+    /// stop at 0.0 is at the start of the path, a stop at 1.0 at its end.
+    ///
+    /// See [`Self::linear_gradient_along_path()`].
+    pub fn linear_gradient_stops_along_path(stops: impl IntoIterator<Item = (f32, Color)>) -> Self {
+        Self::with_flavor(PaintFlavor::PathGradient {
+            colors: GradientColors::from_stops(stops),
         })
     }
 
@@ -545,6 +682,7 @@ impl Paint {
                 start_color: inner_color,
                 end_color: outer_color,
             },
+            spread: Spread::Pad,
         })
     }
 
@@ -589,6 +727,95 @@ impl Paint {
             in_radius,
             out_radius,
             colors: GradientColors::from_stops(stops),
+            spread: Spread::Pad,
+        })
+    }
+
+    /// Creates and returns a two-point radial gradient.
+    ///
+    /// The gradient goes from the focal circle at (`fx`,`fy`) with radius `focal_radius` to the circle at
+    /// (`cx`,`cy`) with radius `radius`, like `createRadialGradient()` of the HTML canvas and radial gradients with
+    /// a focal point in SVG. Moving the focal circle off the center gives asymmetric highlights, areas not covered
+    /// by any circle between the two are left transparent. `inner_color` specifies the color at the focal circle
+    /// and `outer_color` the color at the end circle.
+    ///
+    /// # Example
+    /// ```
+    /// use femtovg::{Paint, Path, Color, Canvas, renderer::Void};
+    ///
+    /// let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+    ///
+    /// let highlight = Paint::focal_radial_gradient(
+    ///    40.0,
+    ///    40.0,
+    ///    0.0,
+    ///    50.0,
+    ///    50.0,
+    ///    20.0,
+    ///    Color::rgb(255, 255, 255),
+    ///    Color::rgb(0, 0, 128),
+    /// );
+    ///
+    /// let mut path = Path::new();
+    /// path.circle(50.0, 50.0, 20.0);
+    /// canvas.fill_path(&path, &highlight);
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn focal_radial_gradient(
+        fx: f32,
+        fy: f32,
+        focal_radius: f32,
+        cx: f32,
+        cy: f32,
+        radius: f32,
+        inner_color: Color,
+        outer_color: Color,
+    ) -> Self {
+        Self::with_flavor(PaintFlavor::FocalRadialGradient {
+            focal: Position { x: fx, y: fy },
+            focal_radius,
+            center: Position { x: cx, y: cy },
+            radius,
+            colors: GradientColors::TwoStop {
+                start_color: inner_color,
+                end_color: outer_color,
+            },
+            spread: Spread::Pad,
+        })
+    }
+
+    /// Creates and returns a multi-stop two-point radial gradient.
+    ///
+    /// The gradient goes from the focal circle at (`fx`,`fy`) with radius `focal_radius` at offset 0.0 to the
+    /// circle at (`cx`,`cy`) with radius `radius` at offset 1.0, see [`Paint::focal_radial_gradient()`].
+    pub fn focal_radial_gradient_stops(
+        fx: f32,
+        fy: f32,
+        focal_radius: f32,
+        cx: f32,
+        cy: f32,
+        radius: f32,
+        stops: impl IntoIterator<Item = (f32, Color)>,
+    ) -> Self {
+        Self::with_flavor(PaintFlavor::FocalRadialGradient {
+            focal: Position { x: fx, y: fy },
+            focal_radius,
+            center: Position { x: cx, y: cy },
+            radius,
+            colors: GradientColors::from_stops(stops),
+            spread: Spread::Pad,
+        })
+    }
+
+    /// Creates a new paint that is shaded by a custom shader.
+    ///
+    /// The `uniforms` are passed to the shader as is, see [`Canvas::create_shader()`](crate::Canvas::create_shader)
+    /// for how the shader accesses them.
+    pub fn custom(shader: ShaderId, uniforms: [f32; 8]) -> Self {
+        Self::with_flavor(PaintFlavor::Custom {
+            shader,
+            uniforms,
+            alpha: 1.0,
         })
     }
 
@@ -604,6 +831,34 @@ impl Paint {
         self
     }
 
+    /// Returns the part of the image an image paint draws, if set.
+    #[inline]
+    pub fn source_rect(&self) -> Option<Rect> {
+        match self.flavor {
+            PaintFlavor::Image { source, .. } => source,
+            _ => None,
+        }
+    }
+
+    /// Sets the part of the image, in image pixels, that an image paint draws, such as a sprite of a
+    /// sprite sheet. The region is then stretched over the `width` and `height` of the pattern instead of
+    /// the whole image. Texture coordinates are kept half a texel inside of the region, so neighbouring
+    /// pixels don't bleed in at its edges. Outside of the pattern the edge pixels of the region are
+    /// repeated. Has no effect on other paints.
+    #[inline]
+    pub fn set_source_rect(&mut self, rect: Option<Rect>) {
+        if let PaintFlavor::Image { source, .. } = &mut self.flavor {
+            *source = rect;
+        }
+    }
+
+    /// Returns the paint with the source rectangle of the image set to the specified value.
+    #[inline]
+    pub fn with_source_rect(mut self, rect: Option<Rect>) -> Self {
+        self.set_source_rect(rect);
+        self
+    }
+
     /// Returns the current anti-alias setting.
     #[inline]
     pub fn anti_alias(&self) -> bool {
@@ -623,6 +878,27 @@ impl Paint {
         self
     }
 
+    /// Returns the width of the anti-aliasing fringe of shapes drawn with this paint.
+    #[inline]
+    pub fn fringe_width(&self) -> FringeWidth {
+        self.fringe_width
+    }
+
+    /// Overrides the width of the anti-aliasing fringe of shapes drawn with this paint. This is useful for
+    /// rendering into images at a different scale than the screen, for softer or crisper edges, or for disabling
+    /// the fringe with a width of 0 while the renderer still applies multisampling.
+    #[inline]
+    pub fn set_fringe_width(&mut self, width: FringeWidth) {
+        self.fringe_width = width;
+    }
+
+    /// Returns the paint with the anti-aliasing fringe width set to the specified value.
+    #[inline]
+    pub fn with_fringe_width(mut self, width: FringeWidth) -> Self {
+        self.set_fringe_width(width);
+        self
+    }
+
     /// Returns whether higher quality stencil strokes are used.
     #[inline]
     pub fn stencil_strokes(&self) -> bool {
@@ -752,6 +1028,81 @@ impl Paint {
         self
     }
 
+    /// Returns the current fill rule for filling paths.
+    #[inline]
+    pub fn fill_rule(&self) -> FillRule {
+        self.fill_rule
+    }
+
+    /// Sets the fill rule for filling paths.
+    #[inline]
+    pub fn set_fill_rule(&mut self, rule: FillRule) {
+        self.fill_rule = rule;
+    }
+
+    /// Returns the paint with the fill rule set to the specified value.
+    #[inline]
+    pub fn with_fill_rule(mut self, rule: FillRule) -> Self {
+        self.set_fill_rule(rule);
+        self
+    }
+
+    /// Returns the transform of the gradient or image pattern.
+    #[inline]
+    pub fn paint_transform(&self) -> Transform2D {
+        self.transform
+    }
+
+    /// Sets a transform that is applied to the gradient or image pattern, before the current
+    /// transform of the canvas.
+    ///
+    /// This moves the pattern independently of the path geometry, for example to rotate a linear
+    /// gradient inside a fixed shape or to pan an image fill. It applies to filled and stroked paths,
+    /// text is drawn without it.
+    #[inline]
+    pub fn set_paint_transform(&mut self, transform: Transform2D) {
+        self.transform = transform;
+    }
+
+    /// Returns the paint with the gradient or image pattern transform set to the specified value.
+    #[inline]
+    pub fn with_paint_transform(mut self, transform: Transform2D) -> Self {
+        self.set_paint_transform(transform);
+        self
+    }
+
+    /// Returns how the gradient of the paint continues beyond its start and end, `Spread::Pad` for paints that
+    /// aren't linear or radial gradients.
+    pub fn gradient_spread(&self) -> Spread {
+        match self.flavor {
+            PaintFlavor::LinearGradient { spread, .. }
+            | PaintFlavor::RadialGradient { spread, .. }
+            | PaintFlavor::FocalRadialGradient { spread, .. } => spread,
+            _ => Spread::Pad,
+        }
+    }
+
+    /// Sets how the gradient of the paint continues beyond its start and end, to repeat stripes without listing
+    /// every one of them as stops for example. Only linear and radial gradients are affected.
+    pub fn set_gradient_spread(&mut self, spread: Spread) {
+        match &mut self.flavor {
+            PaintFlavor::LinearGradient { spread: current, .. }
+            | PaintFlavor::RadialGradient { spread: current, .. }
+            | PaintFlavor::FocalRadialGradient { spread: current, .. } => *current = spread,
+            _ => {}
+        }
+    }
+
+    /// Returns the paint with the gradient spread set to the specified value.
+    #[inline]
+    pub fn with_gradient_spread(mut self, spread: Spread) -> Self {
+        self.set_gradient_spread(spread);
+        self
+    }
+}
+
+#[cfg(feature = "text")]
+impl Paint {
     /// Sets the font.
     pub fn set_font(&mut self, font_ids: &[FontId]) {
         self.text.font_ids = Default::default();
@@ -768,6 +1119,34 @@ impl Paint {
         self
     }
 
+    /// Sets the font used for text runs of the given script, overriding the font set with
+    /// [`Paint::set_font`] (e.g. a CJK face for `script::HAN` and a different one for Latin text).
+    ///
+    /// Words consisting only of characters shared between scripts, such as spaces, digits and
+    /// punctuation, use the font of the preceding word. Passing an empty slice removes the override.
+    pub fn set_font_for_script(&mut self, script: Script, font_ids: &[FontId]) {
+        self.text.script_fonts.retain(|(s, _)| *s != script);
+
+        if font_ids.is_empty() {
+            return;
+        }
+
+        let mut ids: [Option<FontId>; 8] = Default::default();
+
+        for (i, id) in font_ids.iter().take(8).enumerate() {
+            ids[i] = Some(*id);
+        }
+
+        self.text.script_fonts.push((script, ids));
+    }
+
+    /// Returns the paint with the font for the given script set to the specified value.
+    #[inline]
+    pub fn with_font_for_script(mut self, script: Script, font_ids: &[FontId]) -> Self {
+        self.set_font_for_script(script, font_ids);
+        self
+    }
+
     /// Returns the current font size for text operations.
     #[inline]
     pub fn font_size(&self) -> f32 {
@@ -844,22 +1223,128 @@ impl Paint {
         self
     }
 
-    /// Returns the current fill rule for filling paths.
+    /// Returns whether glyphs are rasterized with the current transform baked in.
     #[inline]
-    pub fn fill_rule(&self) -> FillRule {
-        self.fill_rule
+    pub fn rasterize_transformed_text(&self) -> bool {
+        self.text.rasterize_transformed
     }
 
-    /// Sets the fill rule for filling paths.
+    /// Sets whether glyphs are rasterized with the current transform baked in.
+    ///
+    /// By default glyphs are rasterized axis-aligned and the resulting bitmaps are transformed, which
+    /// looks blurry for rotated or skewed text. When enabled, glyphs are rasterized for the
+    /// (quantized) rotation, skew and scale of the current transform instead, at the cost of
+    /// additional glyph atlas space. Color glyphs and text drawn as paths are not affected.
     #[inline]
-    pub fn set_fill_rule(&mut self, rule: FillRule) {
-        self.fill_rule = rule;
+    pub fn set_rasterize_transformed_text(&mut self, enabled: bool) {
+        self.text.rasterize_transformed = enabled;
     }
 
-    /// Returns the paint with the fill rule set to the specified value.
+    /// Returns the paint with transformed glyph rasterization set to the specified value.
     #[inline]
-    pub fn with_fill_rule(mut self, rule: FillRule) -> Self {
-        self.set_fill_rule(rule);
+    pub fn with_rasterize_transformed_text(mut self, enabled: bool) -> Self {
+        self.set_rasterize_transformed_text(enabled);
+        self
+    }
+
+    /// Returns how glyph outlines are fitted to the pixel grid.
+    #[inline]
+    pub fn text_hinting(&self) -> TextHinting {
+        self.text.hinting
+    }
+
+    /// Sets how glyph outlines are fitted to the pixel grid when they're rasterized. Hinting makes small text
+    /// crisper at the expense of the exact shapes and positions of the glyphs.
+    #[inline]
+    pub fn set_text_hinting(&mut self, hinting: TextHinting) {
+        self.text.hinting = hinting;
+    }
+
+    /// Returns the paint with the text hinting set to the specified value.
+    #[inline]
+    pub fn with_text_hinting(mut self, hinting: TextHinting) -> Self {
+        self.set_text_hinting(hinting);
+        self
+    }
+
+    /// Returns how many device pixels the stems of glyphs are thickened by.
+    #[inline]
+    pub fn stem_darkening(&self) -> f32 {
+        self.text.stem_darkening
+    }
+
+    /// Sets how many device pixels the stems of glyphs are thickened by when they're rasterized, 0 by default.
+    /// A fraction of a pixel keeps thin strokes of small text from looking washed out.
+    #[inline]
+    pub fn set_stem_darkening(&mut self, amount: f32) {
+        self.text.stem_darkening = amount.max(0.0);
+    }
+
+    /// Returns the paint with the stem darkening set to the specified value.
+    #[inline]
+    pub fn with_stem_darkening(mut self, amount: f32) -> Self {
+        self.set_stem_darkening(amount);
+        self
+    }
+
+    /// Returns the gamma the coverage of glyphs is blended with.
+    #[inline]
+    pub fn text_gamma(&self) -> f32 {
+        self.text.gamma
+    }
+
+    /// Sets the gamma the coverage of glyphs is blended with, 1 by default. Values above 1 make the
+    /// antialiased edges of glyphs more opaque, which suits dark text on light backgrounds, values below 1 make
+    /// them more transparent.
+    #[inline]
+    pub fn set_text_gamma(&mut self, gamma: f32) {
+        self.text.gamma = gamma;
+    }
+
+    /// Returns the paint with the text gamma set to the specified value.
+    #[inline]
+    pub fn with_text_gamma(mut self, gamma: f32) -> Self {
+        self.set_text_gamma(gamma);
+        self
+    }
+
+    /// Returns the contrast the coverage of glyphs is blended with.
+    #[inline]
+    pub fn text_contrast(&self) -> f32 {
+        self.text.contrast
+    }
+
+    /// Sets the contrast the coverage of glyphs is blended with, 0 by default. Positive values sharpen the
+    /// antialiased edges of glyphs, negative values soften them.
+    #[inline]
+    pub fn set_text_contrast(&mut self, contrast: f32) {
+        self.text.contrast = contrast;
+    }
+
+    /// Returns the paint with the text contrast set to the specified value.
+    #[inline]
+    pub fn with_text_contrast(mut self, contrast: f32) -> Self {
+        self.set_text_contrast(contrast);
+        self
+    }
+
+    /// Returns the lines drawn along with text.
+    #[inline]
+    pub fn text_decoration(&self) -> TextDecoration {
+        self.text.decoration
+    }
+
+    /// Sets the lines drawn along with text, like underline and strikethrough. The lines span the advance of the
+    /// text and are filled or stroked with the paint just like the glyphs.
+    #[inline]
+    pub fn set_text_decoration(&mut self, decoration: TextDecoration) {
+        self.text.decoration = decoration;
+    }
+
+    /// Returns the paint with the text decoration set to the specified value.
+    #[inline]
+    pub fn with_text_decoration(mut self, decoration: TextDecoration) -> Self {
+        self.set_text_decoration(decoration);
         self
     }
 }
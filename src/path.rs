@@ -1,18 +1,42 @@
 use std::{
-    cell::{RefCell, RefMut},
+    cell::{Cell, RefCell, RefMut},
     f32::consts::PI,
+    hash::{Hash, Hasher},
+    ops::Range,
     slice,
 };
 
-use crate::geometry::{Position, Transform2D, Vector};
+use fnv::FnvHasher;
+
+use crate::{
+    geometry::{Bounds, Position, Transform2D, Vector},
+    FillRule, LineCap, LineJoin, Paint, Rect,
+};
+#[cfg(feature = "text")]
 use rustybuzz::ttf_parser;
 
 mod cache;
 pub use cache::{Convexity, PathCache};
 
+#[cfg(feature = "kurbo")]
+mod kurbo;
+
+#[cfg(feature = "lyon")]
+mod lyon;
+
 // Length proportional to radius of a cubic bezier handle for 90deg arcs.
 const KAPPA90: f32 = 0.552_284_8; // 0.552_284_749_3;
 
+// Maximum distance of a quarter circle approximated by a cubic bezier from the true circle,
+// relative to the radius.
+const QUARTER_CIRCLE_ERROR: f32 = 2.7e-4;
+
+// Tolerance used by `ellipse()` and `circle()`, matching the default tessellation tolerance.
+const ELLIPSE_TOLERANCE: f32 = 0.25;
+
+// Number of line segments bezier segments are split into when testing whether points are inside of sub-paths.
+const POLYGON_BEZIER_STEPS: usize = 8;
+
 /// Specifies whether a shape is solid or a hole when adding it to a path.
 ///
 /// The default value is `Solid`.
@@ -25,7 +49,7 @@ pub enum Solidity {
     Hole = 2,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 #[repr(u8)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PackedVerb {
@@ -37,6 +61,16 @@ pub enum PackedVerb {
     Close,
 }
 
+impl PackedVerb {
+    fn num_coordinates(self) -> usize {
+        match self {
+            Self::MoveTo | Self::LineTo => 1,
+            Self::BezierTo => 3,
+            Self::Solid | Self::Hole | Self::Close => 0,
+        }
+    }
+}
+
 /// A verb describes how to interpret one or more points to continue the countour
 /// of a [`Path`].
 #[derive(Copy, Clone, Debug)]
@@ -100,6 +134,9 @@ pub struct Path {
     dist_tol: f32,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) cache: RefCell<Option<(u64, PathCache)>>,
+    // Hash of the verbs and points, computed on the first content cache key and reset with the cache
+    #[cfg_attr(feature = "serde", serde(skip))]
+    content_hash: Cell<Option<u64>>,
 }
 
 impl Path {
@@ -138,9 +175,14 @@ impl Path {
         // The path cache saves a flattened and transformed version of the path. If client code calls
         // (fill|stroke)_path repeatedly with the same Path under the same transform circumstances then it will be
         // retrieved from cache. I'm not sure if transform.cache_key() is actually good enough for this
-        // and if it will produce the correct cache keys under different float edge cases.
+        // and if it will produce the correct cache keys under different float edge cases. The tolerances are part of
+        // the key, so that changing the tessellation quality rebuilds the cache.
 
-        let key = transform.cache_key();
+        let key = {
+            let mut hasher = FnvHasher::default();
+            (transform.cache_key(), tess_tol.to_bits(), dist_tol.to_bits()).hash(&mut hasher);
+            hasher.finish()
+        };
 
         // this shouldn't need a bool once non lexic lifetimes are stable
         let mut needs_rebuild = true;
@@ -157,21 +199,49 @@ impl Path {
         RefMut::map(self.cache.borrow_mut(), |cache| &mut cache.as_mut().unwrap().1)
     }
 
+    // Identifies the flattened path under the transform and tolerances by the verbs and points of the path, so that
+    // caches kept outside of the path can tell whether it changed.
+    pub(crate) fn content_cache_key(&self, transform: &Transform2D, tess_tol: f32, dist_tol: f32) -> u64 {
+        let content_hash = self.content_hash.get().unwrap_or_else(|| {
+            let mut hasher = FnvHasher::default();
+
+            self.verbs.hash(&mut hasher);
+
+            for pos in &self.coords {
+                (pos.x.to_bits(), pos.y.to_bits()).hash(&mut hasher);
+            }
+
+            let content_hash = hasher.finish();
+            self.content_hash.set(Some(content_hash));
+            content_hash
+        });
+
+        let mut hasher = FnvHasher::default();
+        (
+            content_hash,
+            transform.cache_key(),
+            tess_tol.to_bits(),
+            dist_tol.to_bits(),
+        )
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
     // Path funcs
 
     /// Starts a new sub-path with the specified point as the first point.
     pub fn move_to(&mut self, x: f32, y: f32) {
-        self.append(&[PackedVerb::MoveTo], &[Position { x, y }]);
+        self.append_verbs(&[PackedVerb::MoveTo], &[Position { x, y }]);
     }
 
     /// Adds a line segment from the last point in the path to the specified point.
     pub fn line_to(&mut self, x: f32, y: f32) {
-        self.append(&[PackedVerb::LineTo], &[Position { x, y }]);
+        self.append_verbs(&[PackedVerb::LineTo], &[Position { x, y }]);
     }
 
     /// Adds a cubic bezier segment from the last point in the path via two control points to the specified point.
     pub fn bezier_to(&mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) {
-        self.append(
+        self.append_verbs(
             &[PackedVerb::BezierTo],
             &[
                 Position { x: c1x, y: c1y },
@@ -189,19 +259,19 @@ impl Path {
         let pos1 = pos0 + (cpos - pos0) * (2.0 / 3.0);
         let pos2 = pos + (cpos - pos) * (2.0 / 3.0);
 
-        self.append(&[PackedVerb::BezierTo], &[pos1, pos2, pos]);
+        self.append_verbs(&[PackedVerb::BezierTo], &[pos1, pos2, pos]);
     }
 
     /// Closes the current sub-path with a line segment.
     pub fn close(&mut self) {
-        self.append(&[PackedVerb::Close], &[]);
+        self.append_verbs(&[PackedVerb::Close], &[]);
     }
 
     /// Sets the current sub-path winding, see [`Solidity`].
     pub fn solidity(&mut self, solidity: Solidity) {
         match solidity {
-            Solidity::Solid => self.append(&[PackedVerb::Solid], &[]),
-            Solidity::Hole => self.append(&[PackedVerb::Hole], &[]),
+            Solidity::Solid => self.append_verbs(&[PackedVerb::Solid], &[]),
+            Solidity::Hole => self.append_verbs(&[PackedVerb::Hole], &[]),
         }
     }
 
@@ -269,7 +339,7 @@ impl Path {
             ptanpos = tanpos;
         }
 
-        self.append(&commands, &coords);
+        self.append_verbs(&commands, &coords);
     }
 
     /// Adds an arc segment at the corner defined by the last path point and two specified points.
@@ -323,7 +393,7 @@ impl Path {
 
     /// Creates a new rectangle shaped sub-path.
     pub fn rect(&mut self, x: f32, y: f32, w: f32, h: f32) {
-        self.append(
+        self.append_verbs(
             &[
                 PackedVerb::MoveTo,
                 PackedVerb::LineTo,
@@ -380,7 +450,7 @@ impl Path {
             let rx_tl = rad_top_left.min(halfw) * w.signum();
             let ry_tl = rad_top_left.min(halfh) * h.signum();
 
-            self.append(
+            self.append_verbs(
                 &[
                     PackedVerb::MoveTo,
                     PackedVerb::LineTo,
@@ -454,8 +524,25 @@ impl Path {
     }
 
     /// Creates a new ellipse shaped sub-path.
+    ///
+    /// Small ellipses are made of four bezier segments, larger ones use more segments so that they
+    /// stay within a quarter of a unit of the true ellipse. See [`Self::ellipse_with_tolerance()`].
     pub fn ellipse(&mut self, cx: f32, cy: f32, rx: f32, ry: f32) {
-        self.append(
+        self.ellipse_with_tolerance(cx, cy, rx, ry, ELLIPSE_TOLERANCE);
+    }
+
+    /// Creates a new ellipse shaped sub-path using as many bezier segments as needed for the outline
+    /// to stay within `tolerance` of the true ellipse.
+    ///
+    /// The tolerance is in path units, so for paths drawn at a scale it should be divided by that scale.
+    pub fn ellipse_with_tolerance(&mut self, cx: f32, cy: f32, rx: f32, ry: f32, tolerance: f32) {
+        let segments = ellipse_segments(rx.abs().max(ry.abs()), tolerance);
+
+        if segments > 4 {
+            return self.ellipse_segmented(cx, cy, rx, ry, segments);
+        }
+
+        self.append_verbs(
             &[
                 PackedVerb::MoveTo,
                 PackedVerb::BezierTo,
@@ -487,20 +574,605 @@ impl Path {
         );
     }
 
+    fn ellipse_segmented(&mut self, cx: f32, cy: f32, rx: f32, ry: f32, segments: usize) {
+        let da = PI * 2.0 / segments as f32;
+        let kappa = 4.0 / 3.0 * (da / 4.0).tan();
+
+        let cpos = Position { x: cx, y: cy };
+        let point = |a: f32| cpos + Vector::x(rx * a.cos()) + Vector::y(ry * a.sin());
+        let tangent = |a: f32| (Vector::x(-rx * a.sin()) + Vector::y(ry * a.cos())) * kappa;
+
+        let mut verbs = Vec::with_capacity(segments + 2);
+        let mut coords = Vec::with_capacity(segments * 3 + 1);
+
+        // Same starting point and direction as the four segment ellipse
+        verbs.push(PackedVerb::MoveTo);
+        coords.push(point(PI));
+
+        for i in 0..segments {
+            let a0 = PI - da * i as f32;
+            let a1 = a0 - da;
+
+            verbs.push(PackedVerb::BezierTo);
+            coords.extend_from_slice(&[point(a0) - tangent(a0), point(a1) + tangent(a1), point(a1)]);
+        }
+
+        verbs.push(PackedVerb::Close);
+
+        self.append_verbs(&verbs, &coords);
+    }
+
     /// Creates a new circle shaped sub-path.
+    ///
+    /// See [`Self::ellipse()`] for how many segments are used.
     pub fn circle(&mut self, cx: f32, cy: f32, r: f32) {
         self.ellipse(cx, cy, r, r);
     }
 
+    /// Creates a new circle shaped sub-path that stays within `tolerance` of the true circle.
+    ///
+    /// See [`Self::ellipse_with_tolerance()`].
+    pub fn circle_with_tolerance(&mut self, cx: f32, cy: f32, r: f32, tolerance: f32) {
+        self.ellipse_with_tolerance(cx, cy, r, r, tolerance);
+    }
+
+    /// Appends all sub-paths of `other` to this path.
+    pub fn append(&mut self, other: &Self) {
+        self.append_verbs(&other.verbs, &other.coords);
+    }
+
+    /// Appends all sub-paths of `other` to this path, with `transform` applied to their points.
+    ///
+    /// This allows stamping a reusable shape (an icon or a glyph outline) at multiple positions
+    /// without replaying its verbs manually.
+    pub fn append_transformed(&mut self, other: &Self, transform: &Transform2D) {
+        let coords: Vec<Position> = other
+            .coords
+            .iter()
+            .map(|pos| {
+                let (x, y) = transform.transform_point(pos.x, pos.y);
+                Position { x, y }
+            })
+            .collect();
+
+        self.append_verbs(&other.verbs, &coords);
+    }
+
+    /// Reverses the direction of every sub-path.
+    ///
+    /// Sub-paths whose winding is set with [`Self::solidity()`] are still filled according to it.
+    pub fn reverse_subpaths(&mut self) {
+        let subpaths = self.subpaths();
+
+        self.rebuild_subpaths(&subpaths, &vec![true; subpaths.len()], false);
+    }
+
+    /// Returns the signed area of each sub-path, closed back to its start as when filled.
+    ///
+    /// The area is positive for sub-paths that run counter-clockwise on screen, the winding of
+    /// [`Solidity::Solid`] sub-paths, and negative for clockwise sub-paths like holes.
+    pub fn subpath_areas(&self) -> Vec<f32> {
+        self.subpaths()
+            .iter()
+            .map(|subpath| self.subpath_area(subpath))
+            .collect()
+    }
+
+    /// Reorients the sub-paths so that the path is filled with either fill rule like it was with `fill_rule`.
+    ///
+    /// Sub-paths with a filled inside become solid and run counter-clockwise, the others become holes and run
+    /// clockwise, replacing any winding set with [`Self::solidity()`]. This fixes imported geometry with inconsistent
+    /// windings, whose holes go missing when filled with [`FillRule::NonZero`]. Sub-paths are expected not to
+    /// cross each other.
+    pub fn normalize_windings(&mut self, fill_rule: FillRule) {
+        let subpaths = self.subpaths();
+
+        let polygons: Vec<_> = subpaths.iter().map(|subpath| self.subpath_polygon(subpath)).collect();
+        let directions: Vec<_> = subpaths
+            .iter()
+            .map(|subpath| self.subpath_area(subpath).signum() as i32)
+            .collect();
+
+        // Sub-paths with an explicit winding are filled in that direction
+        let fill_directions: Vec<_> = subpaths
+            .iter()
+            .zip(&directions)
+            .map(|(subpath, &direction)| match self.subpath_solidity(subpath) {
+                Some(Solidity::Solid) => 1,
+                Some(Solidity::Hole) => -1,
+                None => direction,
+            })
+            .collect();
+
+        let reverse: Vec<_> = (0..subpaths.len())
+            .map(|i| {
+                // The winding just outside of the sub-path, at its start, comes from the other sub-paths
+                let pos = self.coords[subpaths[i].coords.start];
+                let windings = (0..subpaths.len())
+                    .filter(|&j| j != i)
+                    .map(|j| winding_number(&polygons[j], pos) * directions[j] * fill_directions[j]);
+
+                let filled_inside = match fill_rule {
+                    FillRule::NonZero => windings.sum::<i32>() + fill_directions[i] != 0,
+                    FillRule::EvenOdd => windings.map(|winding| winding.rem_euclid(2)).sum::<i32>() % 2 == 0,
+                };
+
+                let target = if filled_inside { 1 } else { -1 };
+
+                directions[i] != 0 && directions[i] != target
+            })
+            .collect();
+
+        self.rebuild_subpaths(&subpaths, &reverse, true);
+    }
+
+    /// Returns the bounding box of the path, following the curvature of bezier segments.
+    ///
+    /// Unlike [`Canvas::path_bbox()`](crate::Canvas::path_bbox), this doesn't need a canvas, so it's available for
+    /// layout and culling before anything is drawn. The bounds of an empty path are an empty rectangle at the origin.
+    pub fn bounds(&self) -> Rect {
+        let mut bounds = Bounds::default();
+        let mut last = Position::default();
+
+        for (_, coords) in self.verb_coords() {
+            match *coords {
+                [pos] => bounds.add_point(pos),
+                [c1, c2, pos] => {
+                    bounds.add_point(pos);
+
+                    for t in cubic_extrema(last.x, c1.x, c2.x, pos.x).chain(cubic_extrema(last.y, c1.y, c2.y, pos.y)) {
+                        bounds.add_point(cubic_point(last, c1, c2, pos, t));
+                    }
+                }
+                _ => continue,
+            }
+
+            last = coords[coords.len() - 1];
+        }
+
+        bounds.to_rect()
+    }
+
+    /// Returns the bounding box of the path stroked with `paint`.
+    ///
+    /// Besides half the line width around the path, the bounds include square caps and miter joins that stay within
+    /// the miter limit of the paint. Like [`Self::bounds()`] they are computed without a canvas, in the coordinates of
+    /// the path.
+    pub fn stroke_bounds(&self, paint: &Paint) -> Rect {
+        let half_width = paint.line_width() * 0.5;
+        let geometry = self.bounds();
+
+        if self.coords.is_empty() {
+            return geometry;
+        }
+
+        let mut bounds = Bounds::default();
+
+        bounds.add_point(Position {
+            x: geometry.x - half_width,
+            y: geometry.y - half_width,
+        });
+        bounds.add_point(Position {
+            x: geometry.x + geometry.w + half_width,
+            y: geometry.y + geometry.h + half_width,
+        });
+
+        for subpath in self.subpaths() {
+            let (segments, closed) = self.subpath_segments(&subpath);
+
+            let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+                continue;
+            };
+
+            if paint.line_join() == LineJoin::Miter {
+                let joins = segments.windows(2).map(|pair| (&pair[0], &pair[1]));
+                let closing_join = closed.then_some((last, first));
+
+                for (incoming, outgoing) in joins.chain(closing_join) {
+                    if let Some(tip) = miter_tip(incoming, outgoing, half_width, paint.miter_limit()) {
+                        bounds.add_point(tip);
+                    }
+                }
+            }
+
+            if !closed {
+                // Square caps extend the stroke by half its width beyond the ends
+                let caps = [
+                    (paint.line_cap_start(), first.start, -first.start_dir),
+                    (paint.line_cap_end(), last.end, last.end_dir),
+                ];
+
+                for (cap, pos, dir) in caps {
+                    if cap == LineCap::Square {
+                        let normal = dir.orthogonal() * half_width;
+                        let end = pos + dir * half_width;
+
+                        bounds.add_point(end + normal);
+                        bounds.add_point(end - normal);
+                    }
+                }
+            }
+        }
+
+        bounds.to_rect()
+    }
+
+    // Iterates over the verbs of the path along with their coordinates.
+    fn verb_coords(&self) -> impl Iterator<Item = (PackedVerb, &[Position])> {
+        self.subpath_verbs(&Subpath {
+            verbs: 0..self.verbs.len(),
+            coords: 0..self.coords.len(),
+        })
+    }
+
+    // The segments of the sub-path with a non-zero length, and whether the sub-path is closed. The segments of closed
+    // sub-paths end where they started.
+    fn subpath_segments(&self, subpath: &Subpath) -> (Vec<Segment>, bool) {
+        let start = self.coords[subpath.coords.start];
+        let mut last = start;
+        let mut closed = false;
+        let mut segments = Vec::new();
+
+        for (verb, coords) in self.subpath_verbs(subpath) {
+            let segment = match *coords {
+                [pos] if verb == PackedVerb::LineTo => Segment::new(last, last, pos, pos),
+                [c1, c2, pos] => Segment::new(last, c1, c2, pos),
+                _ => {
+                    closed |= verb == PackedVerb::Close;
+                    continue;
+                }
+            };
+
+            segments.extend(segment);
+            last = coords[coords.len() - 1];
+        }
+
+        if closed {
+            segments.extend(Segment::new(last, last, start, start));
+        }
+
+        (segments, closed)
+    }
+
+    // Splits the path into sub-paths, each starting with a `MoveTo`. Verbs before the first one belong to none.
+    fn subpaths(&self) -> Vec<Subpath> {
+        let mut subpaths: Vec<Subpath> = Vec::new();
+        let mut coord = 0;
+
+        for (index, &verb) in self.verbs.iter().enumerate() {
+            if verb == PackedVerb::MoveTo {
+                subpaths.push(Subpath {
+                    verbs: index..index,
+                    coords: coord..coord,
+                });
+            }
+
+            coord += verb.num_coordinates();
+
+            if let Some(subpath) = subpaths.last_mut() {
+                subpath.verbs.end = index + 1;
+                subpath.coords.end = coord;
+            }
+        }
+
+        subpaths
+    }
+
+    // Iterates over the verbs of the sub-path along with their coordinates.
+    fn subpath_verbs<'a>(&'a self, subpath: &Subpath) -> impl Iterator<Item = (PackedVerb, &'a [Position])> {
+        let mut coords = &self.coords[subpath.coords.clone()];
+
+        self.verbs[subpath.verbs.clone()].iter().map(move |&verb| {
+            let (verb_coords, rest) = coords.split_at(verb.num_coordinates());
+            coords = rest;
+            (verb, verb_coords)
+        })
+    }
+
+    // The signed area of the sub-path, using the exact area under bezier segments.
+    fn subpath_area(&self, subpath: &Subpath) -> f32 {
+        let start = self.coords[subpath.coords.start];
+        let mut last = start;
+        let mut twice_area = 0.0;
+
+        for (_, coords) in self.subpath_verbs(subpath) {
+            match *coords {
+                [pos] => twice_area += cross(last, pos),
+                [c1, c2, pos] => {
+                    twice_area += 0.6 * cross(last, c1)
+                        + 0.3 * cross(last, c2)
+                        + 0.1 * cross(last, pos)
+                        + 0.3 * cross(c1, c2)
+                        + 0.3 * cross(c1, pos)
+                        + 0.6 * cross(c2, pos);
+                }
+                _ => continue,
+            }
+
+            last = coords[coords.len() - 1];
+        }
+
+        twice_area += cross(last, start);
+
+        // The y axis points down, so counter-clockwise sub-paths have a negative area in the usual orientation
+        -0.5 * twice_area
+    }
+
+    // The points of the sub-path with bezier segments split into lines.
+    fn subpath_polygon(&self, subpath: &Subpath) -> Vec<Position> {
+        let mut points: Vec<Position> = Vec::new();
+
+        for (_, coords) in self.subpath_verbs(subpath) {
+            match *coords {
+                [pos] => points.push(pos),
+                [c1, c2, pos] => {
+                    let start = points.last().copied().unwrap_or(pos);
+
+                    points.extend(
+                        (1..=POLYGON_BEZIER_STEPS)
+                            .map(|step| cubic_point(start, c1, c2, pos, step as f32 / POLYGON_BEZIER_STEPS as f32)),
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        points
+    }
+
+    // The winding set on the sub-path with `solidity()`, the last one if there are several.
+    fn subpath_solidity(&self, subpath: &Subpath) -> Option<Solidity> {
+        self.verbs[subpath.verbs.clone()]
+            .iter()
+            .rev()
+            .find_map(|verb| match verb {
+                PackedVerb::Solid => Some(Solidity::Solid),
+                PackedVerb::Hole => Some(Solidity::Hole),
+                _ => None,
+            })
+    }
+
+    // Rebuilds the path from its sub-paths, reversing those flagged in `reverse`. Reversed sub-paths start at their
+    // last point and run through their segments backwards, followed by their verbs without coordinates.
+    fn rebuild_subpaths(&mut self, subpaths: &[Subpath], reverse: &[bool], remove_solidity: bool) {
+        let Some(first) = subpaths.first() else {
+            return;
+        };
+
+        let mut verbs = self.verbs[..first.verbs.start].to_vec();
+        let mut coords = self.coords[..first.coords.start].to_vec();
+
+        for (subpath, &reverse) in subpaths.iter().zip(reverse) {
+            let subpath_verbs = &self.verbs[subpath.verbs.clone()];
+            let subpath_coords = &self.coords[subpath.coords.clone()];
+
+            let keep = |verb: &&PackedVerb| !(remove_solidity && matches!(verb, PackedVerb::Solid | PackedVerb::Hole));
+
+            if reverse {
+                let mut end = subpath_coords.len() - 1;
+
+                verbs.push(PackedVerb::MoveTo);
+                coords.push(subpath_coords[end]);
+
+                for verb in subpath_verbs.iter().rev() {
+                    match verb {
+                        PackedVerb::LineTo => {
+                            verbs.push(PackedVerb::LineTo);
+                            coords.push(subpath_coords[end - 1]);
+                            end -= 1;
+                        }
+                        PackedVerb::BezierTo => {
+                            verbs.push(PackedVerb::BezierTo);
+                            coords.extend([
+                                subpath_coords[end - 1],
+                                subpath_coords[end - 2],
+                                subpath_coords[end - 3],
+                            ]);
+                            end -= 3;
+                        }
+                        _ => (),
+                    }
+                }
+
+                verbs.extend(
+                    subpath_verbs
+                        .iter()
+                        .filter(|verb| verb.num_coordinates() == 0)
+                        .filter(keep),
+                );
+            } else {
+                verbs.extend(subpath_verbs.iter().filter(keep));
+                coords.extend_from_slice(subpath_coords);
+            }
+        }
+
+        if let Some(&last) = coords.last() {
+            self.last_pos = last;
+        }
+
+        self.verbs = verbs;
+        self.coords = coords;
+        self.cache.get_mut().take();
+        self.content_hash.take();
+    }
+
+    /// Applies `transform` to all points of the path in place.
+    pub fn transform(&mut self, transform: &Transform2D) {
+        for pos in self.coords.iter_mut().chain(std::iter::once(&mut self.last_pos)) {
+            let (x, y) = transform.transform_point(pos.x, pos.y);
+            *pos = Position { x, y };
+        }
+
+        self.cache.get_mut().take();
+        self.content_hash.take();
+    }
+
     /// Appends a slice of verbs and coordinates to the path.
-    fn append(&mut self, verbs: &[PackedVerb], coords: &[Position]) {
-        if !coords.is_empty() {
-            self.last_pos = coords[coords.len() - 1];
+    ///
+    /// Verbs with non-finite coordinates are skipped, as a single NaN or infinite point would corrupt the
+    /// tessellation of the whole path.
+    fn append_verbs(&mut self, verbs: &[PackedVerb], coords: &[Position]) {
+        if coords.iter().all(Position::is_finite) {
+            if !coords.is_empty() {
+                self.last_pos = coords[coords.len() - 1];
+            }
+
+            self.verbs.extend_from_slice(verbs);
+            self.coords.extend_from_slice(coords);
+        } else {
+            log::debug!("Skipping path verbs with non-finite coordinates");
+
+            let mut coords = coords;
+
+            for &verb in verbs {
+                let (verb_coords, rest) = coords.split_at(verb.num_coordinates());
+                coords = rest;
+
+                if verb_coords.iter().all(Position::is_finite) {
+                    if let Some(&last) = verb_coords.last() {
+                        self.last_pos = last;
+                    }
+
+                    self.verbs.push(verb);
+                    self.coords.extend_from_slice(verb_coords);
+                }
+            }
+        }
+
+        self.cache.get_mut().take();
+        self.content_hash.take();
+    }
+}
+
+// A segment of a path with the directions it starts and ends in, for the joins and caps of strokes.
+struct Segment {
+    start: Position,
+    end: Position,
+    start_dir: Vector,
+    end_dir: Vector,
+}
+
+impl Segment {
+    // Creates a segment from the points of a bezier, lines have control points at their ends. Returns `None` for
+    // segments without a length.
+    fn new(p0: Position, p1: Position, p2: Position, p3: Position) -> Option<Self> {
+        let mut start_dir = [p1, p2, p3]
+            .into_iter()
+            .map(|pos| pos - p0)
+            .find(|dir| dir.mag2() > 0.0)?;
+        let mut end_dir = [p2, p1, p0]
+            .into_iter()
+            .map(|pos| p3 - pos)
+            .find(|dir| dir.mag2() > 0.0)?;
+
+        start_dir.normalize();
+        end_dir.normalize();
+
+        Some(Self {
+            start: p0,
+            end: p3,
+            start_dir,
+            end_dir,
+        })
+    }
+}
+
+// The tip of the miter join between two segments, `None` if it's beveled because it exceeds the miter limit.
+fn miter_tip(incoming: &Segment, outgoing: &Segment, half_width: f32, miter_limit: f32) -> Option<Position> {
+    let miter = (incoming.end_dir.orthogonal() + outgoing.start_dir.orthogonal()) * 0.5;
+    let miter_len2 = miter.mag2();
+
+    if miter_len2 * miter_limit * miter_limit < 1.0 {
+        return None;
+    }
+
+    // The tip is on the outside of the turn
+    let offset = miter * (half_width / miter_len2);
+    let turn = outgoing.start_dir - incoming.end_dir;
+
+    Some(if offset.dot(turn) > 0.0 {
+        incoming.end - offset
+    } else {
+        incoming.end + offset
+    })
+}
+
+// Returns the parameters within (0, 1) at which the cubic bezier with the coordinates has a minimum or maximum.
+fn cubic_extrema(p0: f32, p1: f32, p2: f32, p3: f32) -> impl Iterator<Item = f32> {
+    // Roots of the derivative a t² + b t + c
+    let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+    let b = 2.0 * (p0 - 2.0 * p1 + p2);
+    let c = p1 - p0;
+
+    let roots = if a.abs() < 1e-12 {
+        [(b != 0.0).then(|| -c / b), None]
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+
+        if discriminant < 0.0 {
+            [None, None]
+        } else {
+            let sqrt = discriminant.sqrt();
+            [Some((-b + sqrt) / (2.0 * a)), Some((-b - sqrt) / (2.0 * a))]
+        }
+    };
+
+    roots.into_iter().flatten().filter(|t| *t > 0.0 && *t < 1.0)
+}
+
+// A sub-path of a `Path`, as ranges of its verbs and coordinates.
+struct Subpath {
+    verbs: Range<usize>,
+    coords: Range<usize>,
+}
+
+fn cross(a: Position, b: Position) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn cubic_point(p0: Position, p1: Position, p2: Position, p3: Position, t: f32) -> Position {
+    let mt = 1.0 - t;
+    let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+
+    Position {
+        x: a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+        y: a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+    }
+}
+
+// Returns how many times the closed polygon winds around `pos`, counting counter-clockwise turns on screen as
+// positive.
+fn winding_number(polygon: &[Position], pos: Position) -> i32 {
+    let mut winding = 0;
+
+    for (i, &p0) in polygon.iter().enumerate() {
+        let p1 = polygon[(i + 1) % polygon.len()];
+        let side = (p1.x - p0.x) * (pos.y - p0.y) - (pos.x - p0.x) * (p1.y - p0.y);
+
+        if p0.y <= pos.y {
+            if p1.y > pos.y && side > 0.0 {
+                winding += 1;
+            }
+        } else if p1.y <= pos.y && side < 0.0 {
+            winding -= 1;
         }
+    }
+
+    // Edges turning around the point are counted in the usual orientation, where the y axis points up
+    -winding
+}
 
-        self.verbs.extend_from_slice(verbs);
-        self.coords.extend_from_slice(coords);
+// Returns the number of bezier segments, a multiple of four, needed to approximate a full ellipse
+// with the given radius within `tolerance`.
+fn ellipse_segments(radius: f32, tolerance: f32) -> usize {
+    if !radius.is_finite() || tolerance <= 0.0 || tolerance.is_nan() {
+        return 4;
     }
+
+    // The error of a bezier arc grows with the sixth power of its angle.
+    let quarters = (QUARTER_CIRCLE_ERROR * radius / tolerance).powf(1.0 / 6.0).ceil();
+
+    quarters.clamp(1.0, 64.0) as usize * 4
 }
 
 /// An iterator over the verbs and coordinates of a path.
@@ -524,6 +1196,7 @@ impl<'a> Iterator for PathIter<'a> {
     }
 }
 
+#[cfg(feature = "text")]
 impl ttf_parser::OutlineBuilder for Path {
     fn move_to(&mut self, x: f32, y: f32) {
         self.move_to(x, y);
@@ -102,6 +102,18 @@ impl Contour {
     fn point_count(&self) -> usize {
         self.point_range.end - self.point_range.start
     }
+
+    // Returns the length of the segments of the contour, including the closing one if it's closed.
+    fn length(&self, points: &[Point]) -> f32 {
+        let points = &points[self.point_range.clone()];
+        let segments = if self.closed {
+            points.len()
+        } else {
+            points.len().saturating_sub(1)
+        };
+
+        points[..segments].iter().map(|point| point.len).sum()
+    }
 }
 
 struct PointPairsIter<'a> {
@@ -131,7 +143,11 @@ impl<'a> Iterator for PointPairsIter<'a> {
 pub struct PathCache {
     pub(crate) contours: Vec<Contour>,
     pub(crate) bounds: Bounds,
+    // Whether points made non-finite by the transform were left out
+    pub(crate) out_of_range: bool,
     points: Vec<Point>,
+    // Fringe width, line join and miter limit of the fill vertices in the contours, until a stroke replaces them
+    expanded_fill: Option<(f32, LineJoin, f32)>,
 }
 
 impl PathCache {
@@ -236,28 +252,103 @@ impl PathCache {
                 }
             }
 
-            for i in 0..contour.point_count() {
-                let p1 = points.get(i).copied().unwrap();
+            measure_segments(points, bounds);
 
-                let p0 = if i == 0 {
-                    points.last_mut().unwrap()
-                } else {
-                    points.get_mut(i - 1).unwrap()
-                };
+            true
+        });
 
-                p0.dpos = p1.pos - p0.pos;
-                p0.len = p0.dpos.normalize();
+        cache
+    }
 
-                bounds.minx = bounds.minx.min(p0.pos.x);
-                bounds.miny = bounds.miny.min(p0.pos.y);
-                bounds.maxx = bounds.maxx.max(p0.pos.x);
-                bounds.maxy = bounds.maxy.max(p0.pos.y);
-            }
+    /// Returns a copy of the path with its points moved onto the device pixel grid shifted by `offset`, if the
+    /// path is made of horizontal and vertical lines only.
+    ///
+    /// Fills pass `None` as `end_offsets`, which treats every contour as closed. Strokes pass the offsets of the
+    /// first and last point of open contours along their first and last line, so that butt caps end on pixel
+    /// boundaries while the rest of the stroke is centered on the pixels it covers.
+    pub(crate) fn snap_to_pixel_grid(&self, offset: f32, end_offsets: Option<[f32; 2]>) -> Option<Self> {
+        const TOLERANCE: f32 = 1e-3;
 
-            true
+        let is_horizontal = |p0: &Point, p1: &Point| (p1.pos.y - p0.pos.y).abs() < TOLERANCE;
+        let is_axis_aligned = |p0: &Point, p1: &Point| is_horizontal(p0, p1) || (p1.pos.x - p0.pos.x).abs() < TOLERANCE;
+        let is_closed = |contour: &Contour| contour.closed || end_offsets.is_none();
+
+        let snappable = self.contours.iter().all(|contour| {
+            let points = &self.points[contour.point_range.clone()];
+
+            points.windows(2).all(|pair| is_axis_aligned(&pair[0], &pair[1]))
+                && (!is_closed(contour) || is_axis_aligned(&points[points.len() - 1], &points[0]))
         });
 
-        cache
+        if !snappable {
+            return None;
+        }
+
+        let snap = |value: f32, offset: f32| (value - offset).round() + offset;
+        let coincide = |p0: &Point, p1: &Point| p0.pos.x == p1.pos.x && p0.pos.y == p1.pos.y;
+
+        // Snaps the coordinate of an end point along the line to its neighbour with a different offset
+        let snap_end = |snapped: &mut Point, point: &Point, neighbour: &Point, offset: f32| {
+            if is_horizontal(point, neighbour) {
+                snapped.pos.x = snap(point.pos.x, offset);
+            } else {
+                snapped.pos.y = snap(point.pos.y, offset);
+            }
+        };
+
+        let mut snapped = Self::default();
+
+        for contour in &self.contours {
+            let points = &self.points[contour.point_range.clone()];
+            let mut contour_points: Vec<Point> = points
+                .iter()
+                .map(|point| Point::new(snap(point.pos.x, offset), snap(point.pos.y, offset), point.flags))
+                .collect();
+
+            if let (Some([start_offset, end_offset]), false) = (end_offsets, contour.closed) {
+                let last = points.len() - 1;
+                snap_end(&mut contour_points[0], &points[0], &points[1], start_offset);
+                snap_end(&mut contour_points[last], &points[last], &points[last - 1], end_offset);
+            }
+
+            // Lines shorter than a pixel may have collapsed
+            contour_points.dedup_by(|point, previous| {
+                let duplicate = coincide(point, previous);
+                if duplicate {
+                    previous.flags |= point.flags;
+                }
+                duplicate
+            });
+
+            if contour.closed
+                && contour_points.len() > 1
+                && coincide(&contour_points[0], &contour_points[contour_points.len() - 1])
+            {
+                contour_points.pop();
+            }
+
+            // Fills that collapsed to a line cover nothing
+            let min_points = if end_offsets.is_some() { 2 } else { 3 };
+            if contour_points.len() < min_points {
+                continue;
+            }
+
+            let start = snapped.points.len();
+            snapped.points.extend(contour_points);
+            measure_segments(&mut snapped.points[start..], &mut snapped.bounds);
+
+            snapped.contours.push(Contour {
+                point_range: start..snapped.points.len(),
+                closed: contour.closed,
+                bevel: 0,
+                solidity: contour.solidity,
+                fill: Vec::new(),
+                stroke: Vec::new(),
+                convexity: contour.convexity,
+            });
+        }
+
+        Some(snapped)
     }
 
     fn add_contour(&mut self) {
@@ -270,6 +361,12 @@ impl PathCache {
     }
 
     fn add_point(&mut self, x: f32, y: f32, flags: PointFlags, dist_tol: f32) {
+        // Points made non-finite by the transform would corrupt the vertices of the whole contour
+        if !x.is_finite() || !y.is_finite() {
+            self.out_of_range = true;
+            return;
+        }
+
         if let Some(contour) = self.contours.last_mut() {
             let new_point = Point::new(x, y, flags);
 
@@ -517,9 +614,15 @@ impl PathCache {
     }
 
     pub(crate) fn expand_fill(&mut self, fringe_width: f32, line_join: LineJoin, miter_limit: f32) {
+        if self.expanded_fill == Some((fringe_width, line_join, miter_limit)) {
+            return;
+        }
+
+        self.expanded_fill = Some((fringe_width, line_join, miter_limit));
+
         let has_fringe = fringe_width > 0.0;
 
-        self.calculate_joins(fringe_width, line_join, miter_limit);
+        self.calculate_joins(fringe_width, None, line_join, miter_limit);
 
         // Calculate max vertex usage.
         for contour in &mut self.contours {
@@ -622,20 +725,70 @@ impl PathCache {
         line_join: LineJoin,
         miter_limit: f32,
         tess_tol: f32,
+        along_path: bool,
+        width_profile: Option<&dyn Fn(f32) -> f32>,
     ) {
+        self.expanded_fill = None;
+
         let ncap = curve_divisions(stroke_width, PI, tess_tol);
 
+        let base_width = stroke_width;
         let stroke_width = stroke_width + (fringe_width * 0.5);
 
+        // Width of the stroke at the position `t` along the path
+        let width_at = |t: f32| match width_profile {
+            Some(profile) => base_width * profile(t).max(0.0) + (fringe_width * 0.5),
+            None => stroke_width,
+        };
+
         // Disable the gradient used for antialiasing when antialiasing is not enabled.
         let (u0, u1) = if fringe_width == 0.0 { (0.5, 0.5) } else { (0.0, 1.0) };
 
-        self.calculate_joins(stroke_width, line_join, miter_limit);
+        // Length of all contours, to store the position along the path in the vertices or vary the width
+        let track_len = along_path || width_profile.is_some();
+        let total_len: f32 = if track_len {
+            self.contours.iter().map(|contour| contour.length(&self.points)).sum()
+        } else {
+            0.0
+        };
+        let path_position = |len: f32| if total_len > 0.0 { len / total_len } else { 0.0 };
+
+        // Width of the stroke at each point, so that joins are decided with the width they are drawn with
+        let point_widths = width_profile.map(|_| {
+            let mut widths = vec![stroke_width; self.points.len()];
+            let mut contour_start_len = 0.0;
+
+            for contour in &self.contours {
+                let mut len = contour_start_len;
+
+                for index in contour.point_range.clone() {
+                    widths[index] = width_at(path_position(len));
+                    len += self.points[index].len;
+                }
+
+                contour_start_len += contour.length(&self.points);
+            }
+
+            widths
+        });
+
+        self.calculate_joins(stroke_width, point_widths.as_deref(), line_join, miter_limit);
+
+        let mut contour_start_len = 0.0;
 
         for contour in &mut self.contours {
             contour.stroke.clear();
 
+            let mut len = contour_start_len;
+
             for (i, (p0, p1)) in contour.point_pairs(&self.points).enumerate() {
+                let start_cap_verts = contour.stroke.len();
+
+                // The first pair of a closed contour is the join at its first point
+                let end_len = if i > 0 { len + p0.len } else { len };
+                let start_width = width_at(path_position(len));
+                let width = width_at(path_position(end_len));
+
                 // Add start cap
                 if !contour.closed && i == 1 {
                     match line_cap_start {
@@ -643,7 +796,7 @@ impl PathCache {
                             &mut contour.stroke,
                             p0,
                             p0,
-                            stroke_width,
+                            start_width,
                             -fringe_width * 0.5,
                             fringe_width,
                             u0,
@@ -653,44 +806,35 @@ impl PathCache {
                             &mut contour.stroke,
                             p0,
                             p0,
-                            stroke_width,
-                            stroke_width - fringe_width,
+                            start_width,
+                            start_width - fringe_width,
                             fringe_width,
                             u0,
                             u1,
                         ),
                         LineCap::Round => {
-                            round_cap_start(&mut contour.stroke, p0, p0, stroke_width, ncap as usize, u0, u1)
+                            round_cap_start(&mut contour.stroke, p0, p0, start_width, ncap as usize, u0, u1)
                         }
                     }
                 }
 
+                let join_verts = contour.stroke.len();
+
                 if (i > 0 && i < contour.point_count() - 1) || contour.closed {
                     if p1.flags.contains(PointFlags::BEVEL) || p1.flags.contains(PointFlags::INNERBEVEL) {
                         if line_join == LineJoin::Round {
-                            round_join(
-                                &mut contour.stroke,
-                                p0,
-                                p1,
-                                stroke_width,
-                                stroke_width,
-                                u0,
-                                u1,
-                                ncap as usize,
-                            );
+                            round_join(&mut contour.stroke, p0, p1, width, width, u0, u1, ncap as usize);
                         } else {
-                            bevel_join(&mut contour.stroke, p0, p1, stroke_width, stroke_width, u0, u1);
+                            bevel_join(&mut contour.stroke, p0, p1, width, width, u0, u1);
                         }
                     } else {
-                        contour
-                            .stroke
-                            .push(Vertex::pos(p1.pos + p1.dmpos * stroke_width, u0, 1.0));
-                        contour
-                            .stroke
-                            .push(Vertex::pos(p1.pos - p1.dmpos * stroke_width, u1, 1.0));
+                        contour.stroke.push(Vertex::pos(p1.pos + p1.dmpos * width, u0, 1.0));
+                        contour.stroke.push(Vertex::pos(p1.pos - p1.dmpos * width, u1, 1.0));
                     }
                 }
 
+                let end_cap_verts = contour.stroke.len();
+
                 // Add end cap
                 if !contour.closed && i == contour.point_count() - 1 {
                     match line_cap_end {
@@ -698,7 +842,7 @@ impl PathCache {
                             &mut contour.stroke,
                             p1,
                             p0,
-                            stroke_width,
+                            width,
                             -fringe_width * 0.5,
                             fringe_width,
                             u0,
@@ -708,17 +852,24 @@ impl PathCache {
                             &mut contour.stroke,
                             p1,
                             p0,
-                            stroke_width,
-                            stroke_width - fringe_width,
+                            width,
+                            width - fringe_width,
                             fringe_width,
                             u0,
                             u1,
                         ),
-                        LineCap::Round => {
-                            round_cap_end(&mut contour.stroke, p1, p0, stroke_width, ncap as usize, u0, u1)
-                        }
+                        LineCap::Round => round_cap_end(&mut contour.stroke, p1, p0, width, ncap as usize, u0, u1),
                     }
                 }
+
+                if along_path {
+                    let verts = &mut contour.stroke;
+                    set_path_position(&mut verts[start_cap_verts..join_verts], path_position(len), false);
+                    set_path_position(&mut verts[join_verts..end_cap_verts], path_position(end_len), false);
+                    set_path_position(&mut verts[end_cap_verts..], path_position(end_len), true);
+                }
+
+                len = end_len;
             }
 
             if contour.closed {
@@ -729,13 +880,33 @@ impl PathCache {
                     .stroke
                     .push(Vertex::new(contour.stroke[1].x, contour.stroke[1].y, u1, 1.0));
             }
+
+            if track_len {
+                contour_start_len += contour.length(&self.points);
+            }
+
+            // The stroke of closed contours ends where it started
+            if along_path && contour.closed {
+                let verts = contour.stroke.len();
+                set_path_position(
+                    &mut contour.stroke[verts - 2..],
+                    path_position(contour_start_len),
+                    false,
+                );
+            }
         }
     }
 
-    fn calculate_joins(&mut self, stroke_width: f32, line_join: LineJoin, miter_limit: f32) {
-        let inv_stroke_width = if stroke_width > 0.0 { 1.0 / stroke_width } else { 0.0 };
-
+    // `point_widths` overrides `stroke_width` for each point of the path when the width varies along it.
+    fn calculate_joins(
+        &mut self,
+        stroke_width: f32,
+        point_widths: Option<&[f32]>,
+        line_join: LineJoin,
+        miter_limit: f32,
+    ) {
         for contour in &mut self.contours {
+            let point_widths = point_widths.map(|widths| &widths[contour.point_range.clone()]);
             let points = &mut self.points[contour.point_range.clone()];
             let mut nleft = 0;
 
@@ -831,6 +1002,8 @@ impl PathCache {
                 }
 
                 // Calculate if we should use bevel or miter for inner join.
+                let width = point_widths.map_or(stroke_width, |widths| widths[i]);
+                let inv_stroke_width = if width > 0.0 { 1.0 / width } else { 0.0 };
                 let limit = (p0.len.min(p1.len) * inv_stroke_width).max(1.01);
 
                 if (dmr2 * limit * limit) < 1.0 {
@@ -908,6 +1081,28 @@ impl PathCache {
     }
 }
 
+// Computes the direction and length of the segment starting at each point of a contour and extends the bounds
+// by the points.
+fn measure_segments(points: &mut [Point], bounds: &mut Bounds) {
+    for i in 0..points.len() {
+        let p1 = points[i];
+
+        let p0 = if i == 0 {
+            points.last_mut().unwrap()
+        } else {
+            &mut points[i - 1]
+        };
+
+        p0.dpos = p1.pos - p0.pos;
+        p0.len = p0.dpos.normalize();
+
+        bounds.minx = bounds.minx.min(p0.pos.x);
+        bounds.miny = bounds.miny.min(p0.pos.y);
+        bounds.maxx = bounds.maxx.max(p0.pos.x);
+        bounds.maxy = bounds.maxy.max(p0.pos.y);
+    }
+}
+
 fn curve_divisions(radius: f32, arc: f32, tol: f32) -> u32 {
     let da = (radius / (radius + tol)).acos() * 2.0;
 
@@ -968,6 +1163,18 @@ fn round_cap_end(verts: &mut Vec<Vertex>, p0: &Point, p1: &Point, w: f32, ncap:
     }
 }
 
+// Stores the position `t` along the path in the v coordinate of stroke vertices, keeping the fringe of the caps:
+// the start cap fringe goes from 0 to 1, the path from 1 to 2 and the end cap fringe from 2 to 3.
+fn set_path_position(verts: &mut [Vertex], t: f32, end_cap: bool) {
+    for vertex in verts {
+        vertex.v = match vertex.v < 1.0 {
+            true if end_cap => 3.0 - vertex.v,
+            true => vertex.v,
+            false => 1.0 + t,
+        };
+    }
+}
+
 fn choose_bevel(bevel: bool, p0: &Point, p1: &Point, w: f32) -> (Position, Position) {
     if bevel {
         (p1.pos + p0.dpos.orthogonal() * w, p1.pos + p1.dpos.orthogonal() * w)
@@ -1124,6 +1331,110 @@ mod tests {
 
         assert_eq!(path_cache.contours[0].convexity, Convexity::Concave);
     }
+
+    #[test]
+    fn fill_expansion_is_cached() {
+        let mut path = Path::new();
+        path.rect(10.0, 10.0, 20.0, 20.0);
+
+        let mut path_cache = PathCache::new(path.verbs(), &Transform2D::identity(), 0.25, 0.01);
+        path_cache.expand_fill(1.0, LineJoin::Miter, 2.4);
+        let fill = path_cache.contours[0].fill.clone();
+        assert!(!fill.is_empty());
+
+        // Expanding with the same parameters keeps the vertices
+        path_cache.contours[0].fill.clear();
+        path_cache.expand_fill(1.0, LineJoin::Miter, 2.4);
+        assert!(path_cache.contours[0].fill.is_empty());
+
+        // A different fringe or a stroke in between expand the fill again
+        path_cache.expand_fill(0.5, LineJoin::Miter, 2.4);
+        assert!(!path_cache.contours[0].fill.is_empty());
+
+        path_cache.expand_stroke(
+            1.0,
+            0.5,
+            LineCap::Butt,
+            LineCap::Butt,
+            LineJoin::Miter,
+            10.0,
+            0.25,
+            false,
+            None,
+        );
+        path_cache.expand_fill(1.0, LineJoin::Miter, 2.4);
+        assert_eq!(path_cache.contours[0].fill.len(), fill.len());
+        assert!(!path_cache.contours[0].stroke.is_empty());
+    }
+
+    #[test]
+    fn joins_use_the_width_along_the_path() {
+        // a sharp turn between two long segments
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(20.0, 0.0);
+        path.line_to(0.0, 4.0);
+
+        let inner_bevel = |width_profile: Option<&dyn Fn(f32) -> f32>| {
+            let mut path_cache = PathCache::new(path.verbs(), &Transform2D::identity(), 0.25, 0.01);
+            path_cache.expand_stroke(
+                1.0,
+                0.0,
+                LineCap::Butt,
+                LineCap::Butt,
+                LineJoin::Miter,
+                10.0,
+                0.25,
+                false,
+                width_profile,
+            );
+            path_cache.points[1].flags.contains(PointFlags::INNERBEVEL)
+        };
+
+        // The inner join of a thin stroke fits between the segments, ten times wider it doesn't
+        assert!(!inner_bevel(None));
+        assert!(inner_bevel(Some(&|_| 10.0)));
+    }
+
+    #[test]
+    fn axis_aligned_paths_snap_to_pixel_grid() {
+        let positions = |path_cache: &PathCache| -> Vec<(f32, f32)> {
+            path_cache
+                .points
+                .iter()
+                .map(|point| (point.pos.x, point.pos.y))
+                .collect()
+        };
+
+        let transform = Transform2D::identity();
+
+        let mut rect = Path::new();
+        rect.rect(10.3, 10.6, 20.0, 0.2);
+        let path_cache = PathCache::new(rect.verbs(), &transform, 0.25, 0.01);
+
+        // The rect collapses to a line and is dropped
+        let snapped = path_cache.snap_to_pixel_grid(0.0, None).unwrap();
+        assert!(snapped.contours.is_empty());
+
+        let mut line = Path::new();
+        line.move_to(10.2, 20.3);
+        line.line_to(50.7, 20.3);
+        line.line_to(50.7, 40.2);
+        let path_cache = PathCache::new(line.verbs(), &transform, 0.25, 0.01);
+
+        // The ends of butt caps are snapped to pixel boundaries, the rest to pixel centers
+        let snapped = path_cache.snap_to_pixel_grid(0.5, Some([0.0, 0.5])).unwrap();
+        assert_eq!(positions(&snapped), [(10.0, 20.5), (50.5, 20.5), (50.5, 40.5)]);
+        assert_eq!(snapped.bounds.minx, 10.0);
+        assert_eq!(snapped.points[0].len, 40.5);
+
+        // Paths with curves or diagonal lines are left alone
+        let mut diagonal = Path::new();
+        diagonal.move_to(10.2, 20.3);
+        diagonal.line_to(50.7, 25.0);
+        let path_cache = PathCache::new(diagonal.verbs(), &transform, 0.25, 0.01);
+        assert!(path_cache.snap_to_pixel_grid(0.5, Some([0.0, 0.0])).is_none());
+    }
 }
 
 /*
@@ -0,0 +1,206 @@
+use kurbo::{Affine, BezPath, PathEl, Point, Shape};
+
+use super::{Path, PathIter, Verb};
+use crate::Transform2D;
+
+impl FromIterator<PathEl> for Path {
+    fn from_iter<I: IntoIterator<Item = PathEl>>(elements: I) -> Self {
+        let mut path = Self::new();
+
+        for element in elements {
+            match element {
+                PathEl::MoveTo(p) => path.move_to(p.x as f32, p.y as f32),
+                PathEl::LineTo(p) => path.line_to(p.x as f32, p.y as f32),
+                PathEl::QuadTo(c, p) => path.quad_to(c.x as f32, c.y as f32, p.x as f32, p.y as f32),
+                PathEl::CurveTo(c1, c2, p) => path.bezier_to(
+                    c1.x as f32,
+                    c1.y as f32,
+                    c2.x as f32,
+                    c2.y as f32,
+                    p.x as f32,
+                    p.y as f32,
+                ),
+                PathEl::ClosePath => path.close(),
+            }
+        }
+
+        path
+    }
+}
+
+impl From<&BezPath> for Path {
+    fn from(path: &BezPath) -> Self {
+        path.iter().collect()
+    }
+}
+
+impl From<&Path> for BezPath {
+    fn from(path: &Path) -> Self {
+        path.kurbo_elements().collect()
+    }
+}
+
+impl From<Affine> for Transform2D {
+    fn from(affine: Affine) -> Self {
+        Self(affine.as_coeffs().map(|coeff| coeff as f32))
+    }
+}
+
+impl From<Transform2D> for Affine {
+    fn from(transform: Transform2D) -> Self {
+        Self::new(transform.0.map(f64::from))
+    }
+}
+
+impl Path {
+    /// Creates a path from a [`kurbo`] shape, such as a `Circle`, `RoundedRect` or `Line`.
+    ///
+    /// `tolerance` is the maximum distance of the curves in the path from the shape, for shapes that
+    /// kurbo approximates with béziers.
+    pub fn from_shape(shape: &impl Shape, tolerance: f64) -> Self {
+        shape.path_elements(tolerance).collect()
+    }
+
+    /// Returns an iterator over the path as [`kurbo`] path elements.
+    ///
+    /// Segments that are added after `close()` without a `move_to()` start a new sub-path at the
+    /// start of the closed one. Segments before the first `move_to()` and the winding set with
+    /// `solidity()` have no kurbo equivalent and are skipped.
+    pub fn kurbo_elements(&self) -> impl Iterator<Item = PathEl> + '_ {
+        KurboElements {
+            verbs: self.verbs(),
+            first: None,
+            open: false,
+            pending: None,
+        }
+    }
+}
+
+struct KurboElements<'a> {
+    verbs: PathIter<'a>,
+    // Start of the current, or last closed, sub-path
+    first: Option<Point>,
+    open: bool,
+    pending: Option<PathEl>,
+}
+
+impl Iterator for KurboElements<'_> {
+    type Item = PathEl;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(element) = self.pending.take() {
+            return Some(element);
+        }
+
+        for verb in self.verbs.by_ref() {
+            let segment = match verb {
+                Verb::MoveTo(x, y) => {
+                    let at = point(x, y);
+
+                    self.open = true;
+                    self.first = Some(at);
+
+                    return Some(PathEl::MoveTo(at));
+                }
+                Verb::LineTo(x, y) => PathEl::LineTo(point(x, y)),
+                Verb::BezierTo(c1x, c1y, c2x, c2y, x, y) => {
+                    PathEl::CurveTo(point(c1x, c1y), point(c2x, c2y), point(x, y))
+                }
+                Verb::Close if self.open => {
+                    self.open = false;
+
+                    return Some(PathEl::ClosePath);
+                }
+                Verb::Close | Verb::Solid | Verb::Hole => continue,
+            };
+
+            if self.open {
+                return Some(segment);
+            }
+
+            if let Some(first) = self.first {
+                self.open = true;
+                self.pending = Some(segment);
+
+                return Some(PathEl::MoveTo(first));
+            }
+        }
+
+        None
+    }
+}
+
+fn point(x: f32, y: f32) -> Point {
+    Point::new(x as f64, y as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kurbo_round_trip() {
+        let mut path = Path::new();
+        path.line_to(5.0, 5.0);
+        path.rect(0.0, 0.0, 10.0, 10.0);
+        path.line_to(0.0, 20.0);
+
+        let bez_path = BezPath::from(&path);
+
+        assert_eq!(
+            bez_path.elements(),
+            &[
+                PathEl::MoveTo(Point::new(0.0, 0.0)),
+                PathEl::LineTo(Point::new(0.0, 10.0)),
+                PathEl::LineTo(Point::new(10.0, 10.0)),
+                PathEl::LineTo(Point::new(10.0, 0.0)),
+                PathEl::ClosePath,
+                PathEl::MoveTo(Point::new(0.0, 0.0)),
+                PathEl::LineTo(Point::new(0.0, 20.0)),
+            ]
+        );
+
+        let path = Path::from(&bez_path);
+        assert_eq!(path.kurbo_elements().collect::<Vec<_>>(), bez_path.elements());
+    }
+
+    #[test]
+    fn kurbo_shapes() {
+        let rect = Path::from_shape(&kurbo::Rect::new(0.0, 0.0, 10.0, 20.0), 0.1);
+        assert_eq!(
+            rect.kurbo_elements().collect::<Vec<_>>(),
+            &[
+                PathEl::MoveTo(Point::new(0.0, 0.0)),
+                PathEl::LineTo(Point::new(10.0, 0.0)),
+                PathEl::LineTo(Point::new(10.0, 20.0)),
+                PathEl::LineTo(Point::new(0.0, 20.0)),
+                PathEl::ClosePath,
+            ]
+        );
+
+        let circle = Path::from_shape(&kurbo::Circle::new((5.0, 5.0), 5.0), 0.1);
+        let ends = circle
+            .verbs()
+            .filter_map(|verb| match verb {
+                Verb::BezierTo(.., x, y) => Some(point(x, y)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(ends.len() >= 4);
+        assert!(ends
+            .iter()
+            .all(|end| (end.distance(Point::new(5.0, 5.0)) - 5.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn kurbo_affine() {
+        let transform = Transform2D::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0);
+        let affine = Affine::from(transform);
+
+        assert_eq!(affine.as_coeffs(), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(Transform2D::from(affine), transform);
+
+        let (x, y) = transform.transform_point(7.0, 8.0);
+        assert_eq!(affine * Point::new(7.0, 8.0), point(x, y));
+    }
+}
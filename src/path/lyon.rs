@@ -0,0 +1,239 @@
+use lyon_path::{math::Point, PathEvent};
+
+use super::{Path, PathIter, Verb};
+
+impl FromIterator<PathEvent> for Path {
+    fn from_iter<I: IntoIterator<Item = PathEvent>>(events: I) -> Self {
+        let mut path = Self::new();
+
+        for event in events {
+            match event {
+                PathEvent::Begin { at } => path.move_to(at.x, at.y),
+                PathEvent::Line { to, .. } => path.line_to(to.x, to.y),
+                PathEvent::Quadratic { ctrl, to, .. } => path.quad_to(ctrl.x, ctrl.y, to.x, to.y),
+                PathEvent::Cubic { ctrl1, ctrl2, to, .. } => {
+                    path.bezier_to(ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y);
+                }
+                PathEvent::End { close: true, .. } => path.close(),
+                PathEvent::End { close: false, .. } => {}
+            }
+        }
+
+        path
+    }
+}
+
+impl From<&lyon_path::Path> for Path {
+    fn from(path: &lyon_path::Path) -> Self {
+        path.iter().collect()
+    }
+}
+
+impl From<&Path> for lyon_path::Path {
+    fn from(path: &Path) -> Self {
+        let mut builder = Self::builder();
+
+        for event in path.lyon_events() {
+            builder.path_event(event);
+        }
+
+        builder.build()
+    }
+}
+
+impl Path {
+    /// Returns an iterator over the path as [`lyon_path`] events.
+    ///
+    /// Every sub-path is wrapped in `Begin` and `End` events. Segments that are added after
+    /// `close()` without a `move_to()` start a new sub-path at the start of the closed one. Segments
+    /// before the first `move_to()` and the winding set with `solidity()` have no lyon equivalent and
+    /// are skipped.
+    pub fn lyon_events(&self) -> impl Iterator<Item = PathEvent> + '_ {
+        LyonEvents {
+            verbs: self.verbs(),
+            first: None,
+            current: Point::zero(),
+            open: false,
+            pending: None,
+        }
+    }
+}
+
+struct LyonEvents<'a> {
+    verbs: PathIter<'a>,
+    // Start of the current, or last closed, sub-path
+    first: Option<Point>,
+    current: Point,
+    open: bool,
+    pending: Option<PathEvent>,
+}
+
+impl LyonEvents<'_> {
+    // Returns the event for a segment to `to`, beginning a new sub-path first if needed.
+    fn segment(&mut self, to: Point, event: impl FnOnce(Point) -> PathEvent) -> Option<PathEvent> {
+        if self.open {
+            let event = event(self.current);
+            self.current = to;
+            return Some(event);
+        }
+
+        let first = self.first?;
+
+        self.open = true;
+        self.current = to;
+        self.pending = Some(event(first));
+
+        Some(PathEvent::Begin { at: first })
+    }
+}
+
+impl Iterator for LyonEvents<'_> {
+    type Item = PathEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.pending.take() {
+            return Some(event);
+        }
+
+        loop {
+            let Some(verb) = self.verbs.next() else {
+                if self.open {
+                    self.open = false;
+
+                    return Some(PathEvent::End {
+                        last: self.current,
+                        first: self.first.unwrap_or(self.current),
+                        close: false,
+                    });
+                }
+
+                return None;
+            };
+
+            let event = match verb {
+                Verb::MoveTo(x, y) => {
+                    let at = Point::new(x, y);
+                    let end = self.open.then(|| PathEvent::End {
+                        last: self.current,
+                        first: self.first.unwrap_or(self.current),
+                        close: false,
+                    });
+
+                    self.open = true;
+                    self.first = Some(at);
+                    self.current = at;
+
+                    match end {
+                        Some(end) => {
+                            self.pending = Some(PathEvent::Begin { at });
+                            Some(end)
+                        }
+                        None => Some(PathEvent::Begin { at }),
+                    }
+                }
+                Verb::LineTo(x, y) => {
+                    let to = Point::new(x, y);
+                    self.segment(to, |from| PathEvent::Line { from, to })
+                }
+                Verb::BezierTo(c1x, c1y, c2x, c2y, x, y) => {
+                    let to = Point::new(x, y);
+                    self.segment(to, |from| PathEvent::Cubic {
+                        from,
+                        ctrl1: Point::new(c1x, c1y),
+                        ctrl2: Point::new(c2x, c2y),
+                        to,
+                    })
+                }
+                Verb::Close if self.open => {
+                    let first = self.first.unwrap_or(self.current);
+
+                    self.open = false;
+                    let last = std::mem::replace(&mut self.current, first);
+
+                    Some(PathEvent::End {
+                        last,
+                        first,
+                        close: true,
+                    })
+                }
+                Verb::Close | Verb::Solid | Verb::Hole => None,
+            };
+
+            if event.is_some() {
+                return event;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lyon_round_trip() {
+        let mut path = Path::new();
+        path.rect(0.0, 0.0, 10.0, 10.0);
+        path.move_to(20.0, 20.0);
+        path.bezier_to(25.0, 20.0, 30.0, 25.0, 30.0, 30.0);
+
+        let lyon_path = lyon_path::Path::from(&path);
+        let events: Vec<_> = lyon_path.iter().collect();
+
+        assert_eq!(events.len(), 8);
+        assert_eq!(
+            events[4],
+            PathEvent::End {
+                last: Point::new(10.0, 0.0),
+                first: Point::new(0.0, 0.0),
+                close: true
+            }
+        );
+        assert!(matches!(events[7], PathEvent::End { close: false, .. }));
+
+        let path = Path::from(&lyon_path);
+        assert_eq!(path.lyon_events().collect::<Vec<_>>(), events);
+    }
+
+    #[test]
+    fn lyon_segments_after_close() {
+        let mut path = Path::new();
+        path.line_to(5.0, 5.0);
+        path.move_to(0.0, 0.0);
+        path.line_to(10.0, 0.0);
+        path.close();
+        path.line_to(0.0, 10.0);
+
+        let events: Vec<_> = path.lyon_events().collect();
+
+        assert_eq!(
+            events,
+            vec![
+                PathEvent::Begin {
+                    at: Point::new(0.0, 0.0)
+                },
+                PathEvent::Line {
+                    from: Point::new(0.0, 0.0),
+                    to: Point::new(10.0, 0.0)
+                },
+                PathEvent::End {
+                    last: Point::new(10.0, 0.0),
+                    first: Point::new(0.0, 0.0),
+                    close: true
+                },
+                PathEvent::Begin {
+                    at: Point::new(0.0, 0.0)
+                },
+                PathEvent::Line {
+                    from: Point::new(0.0, 0.0),
+                    to: Point::new(0.0, 10.0)
+                },
+                PathEvent::End {
+                    last: Point::new(0.0, 10.0),
+                    first: Point::new(0.0, 0.0),
+                    close: false
+                },
+            ]
+        );
+    }
+}
@@ -1,29 +1,47 @@
 //! Module containing renderer implementations.
 
+use bitflags::bitflags;
 use imgref::ImgVec;
 use rgb::RGBA8;
 
 use crate::{
-    geometry::Position, paint::GlyphTexture, Color, CompositeOperationState, ErrorKind, FillRule, ImageFilter, ImageId,
-    ImageInfo, ImageSource, ImageStore,
+    geometry::Position,
+    paint::{GlyphTexture, StrokeSettings},
+    Color, CompositeOperationState, ErrorKind, FillRule, ImageFilter, ImageId, ImageInfo, ImageSource, ImageStore,
+    PaintId, ReadbackFormat, Scissor, ShaderId, Transform2D, Verb,
 };
 
 mod opengl;
 pub use opengl::OpenGl;
+#[cfg(all(feature = "glutin-window", not(target_arch = "wasm32")))]
+pub use opengl::OpenGlSurface;
 
 #[cfg(feature = "wgpu")]
 mod wgpu;
 #[cfg(feature = "wgpu")]
-pub use wgpu::WGPURenderer;
+pub use wgpu::{WGPURenderer, WGPUSurface};
+
+#[cfg(all(feature = "metal", target_vendor = "apple"))]
+mod metal;
+#[cfg(all(feature = "metal", target_vendor = "apple"))]
+pub use metal::{Metal, MtlTexture};
 
 mod void;
 pub use void::Void;
 
+mod recorder;
+pub use recorder::{Frame, Recorder};
+pub(crate) use recorder::{FrameImage, ImageOp};
+
+mod svg_export;
+pub use svg_export::SvgRenderer;
+
 mod params;
 pub(crate) use params::Params;
 
 /// Represents a drawable object.
 #[derive(Copy, Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Drawable {
     pub(crate) fill_verts: Option<(usize, usize)>,
     pub(crate) stroke_verts: Option<(usize, usize)>,
@@ -31,6 +49,7 @@ pub struct Drawable {
 
 /// Defines different types of commands that can be executed by the renderer.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CommandType {
     /// Set the render target (screen or image).
     SetRenderTarget(RenderTarget),
@@ -79,6 +98,7 @@ pub enum CommandType {
 
 /// Represents a command that can be executed by the renderer.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Command {
     pub(crate) cmd_type: CommandType,
     pub(crate) drawables: Vec<Drawable>,
@@ -87,6 +107,35 @@ pub struct Command {
     pub(crate) glyph_texture: GlyphTexture,
     pub(crate) fill_rule: FillRule,
     pub(crate) composite_operation: CompositeOperationState,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) paint_binding: Option<PaintBinding>,
+    // The paths the drawables were tessellated from, only kept for renderers that draw paths themselves
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) outlines: Vec<Outline>,
+    // Layer of the image set as render target by a SetRenderTarget command, glyph atlases backed by a texture
+    // array render to their other layers
+    pub(crate) target_layer: usize,
+}
+
+/// The state a command was drawn with, needed to compute its params from a paint resource at flush time.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct PaintBinding {
+    pub(crate) id: PaintId,
+    pub(crate) transform: Transform2D,
+    pub(crate) scissor: Scissor,
+    pub(crate) stroke_width: f32,
+    pub(crate) fringe_width: f32,
+    pub(crate) alpha: f32,
+}
+
+/// A path as it was filled or stroked, see [`Renderer::draws_path_outlines()`].
+#[derive(Clone, Debug)]
+pub(crate) struct Outline {
+    pub(crate) verbs: Vec<Verb>,
+    // Transform from the coordinates of the verbs to the screen
+    pub(crate) transform: Transform2D,
+    // The stroke settings of strokes, fills have none
+    pub(crate) stroke: Option<StrokeSettings>,
 }
 
 impl Command {
@@ -100,12 +149,102 @@ impl Command {
             glyph_texture: Default::default(),
             fill_rule: Default::default(),
             composite_operation: Default::default(),
+            paint_binding: None,
+            outlines: Vec::new(),
+            target_layer: 0,
+        }
+    }
+
+    // Returns true if `other` can be drawn by appending its drawables to this command.
+    pub(crate) fn can_merge(&self, other: &Self) -> bool {
+        let same_params = match (&self.cmd_type, &other.cmd_type) {
+            (CommandType::ConvexFill { params }, CommandType::ConvexFill { params: other_params })
+            | (CommandType::Stroke { params }, CommandType::Stroke { params: other_params }) => params == other_params,
+            _ => false,
+        };
+
+        same_params
+            && self.image == other.image
+            && self.glyph_texture == other.glyph_texture
+            && self.fill_rule == other.fill_rule
+            && self.composite_operation == other.composite_operation
+            && self.triangles_verts.is_none()
+            && other.triangles_verts.is_none()
+            && self.paint_binding.is_none()
+            && other.paint_binding.is_none()
+    }
+
+    // Returns true if drawing `other` after this command doesn't need a different pipeline, texture or blend
+    // state.
+    pub(crate) fn shares_state(&self, other: &Self) -> bool {
+        std::mem::discriminant(&self.cmd_type) == std::mem::discriminant(&other.cmd_type)
+            && self.image == other.image
+            && self.glyph_texture == other.glyph_texture
+            && self.composite_operation == other.composite_operation
+    }
+
+    // Shifts the vertex ranges of this command, used when moving it into a larger vertex buffer.
+    pub(crate) fn offset_verts(&mut self, offset: usize) {
+        let shift = |verts: &mut Option<(usize, usize)>| {
+            if let Some((start, _)) = verts {
+                *start += offset;
+            }
+        };
+
+        for drawable in &mut self.drawables {
+            shift(&mut drawable.fill_verts);
+            shift(&mut drawable.stroke_verts);
+        }
+        shift(&mut self.triangles_verts);
+    }
+
+    // Returns the vertex ranges of the drawables and triangles of this command.
+    pub(crate) fn vert_ranges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.drawables
+            .iter()
+            .flat_map(|drawable| [drawable.fill_verts, drawable.stroke_verts])
+            .chain([self.triangles_verts])
+            .flatten()
+    }
+
+    // Returns the custom shader each params of this command is drawn with.
+    pub(crate) fn custom_shaders(&self) -> impl Iterator<Item = ShaderId> + '_ {
+        let params = match &self.cmd_type {
+            CommandType::ConvexFill { params } | CommandType::Stroke { params } | CommandType::Triangles { params } => {
+                [Some(params), None]
+            }
+            CommandType::ConcaveFill {
+                stencil_params,
+                fill_params,
+            } => [Some(stencil_params), Some(fill_params)],
+            CommandType::StencilStroke { params1, params2 } => [Some(params1), Some(params2)],
+            _ => [None, None],
+        };
+
+        params.into_iter().flatten().filter_map(|params| params.custom_shader)
+    }
+
+    // Replaces every image referenced by this command.
+    pub(crate) fn map_images(&mut self, mut f: impl FnMut(ImageId) -> ImageId) {
+        self.image = self.image.map(&mut f);
+        self.glyph_texture = match self.glyph_texture {
+            GlyphTexture::None => GlyphTexture::None,
+            GlyphTexture::AlphaMask(id, layer) => GlyphTexture::AlphaMask(f(id), layer),
+            GlyphTexture::ColorTexture(id, layer) => GlyphTexture::ColorTexture(f(id), layer),
+            GlyphTexture::Image(id) => GlyphTexture::Image(f(id)),
+        };
+
+        match &mut self.cmd_type {
+            CommandType::SetRenderTarget(RenderTarget::Image(id)) => *id = f(*id),
+            CommandType::RenderFilteredImage { target_image, .. } => *target_image = f(*target_image),
+            _ => (),
         }
     }
 }
 
 /// Represents different render targets (screen or image).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum RenderTarget {
     /// Render to the screen.
     Screen,
@@ -113,6 +252,61 @@ pub enum RenderTarget {
     Image(ImageId),
 }
 
+/// Limits and optional features of a renderer, returned by
+/// [`Canvas::capabilities`](crate::Canvas::capabilities).
+///
+/// Use it to choose atlas and image sizes and to fall back when a feature isn't available.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RendererCapabilities {
+    /// Largest width and height of an image, in pixels.
+    pub max_texture_size: usize,
+    /// Whether images whose size isn't a power of two can repeat and have mipmaps.
+    pub npot_textures: bool,
+    /// Whether the graphics API supports instanced drawing.
+    pub instancing: bool,
+    /// Whether the graphics API supports advanced blend equations (multiply, screen, overlay, ...).
+    pub advanced_blending: bool,
+    /// Whether floating point textures can be rendered to.
+    pub float_render_targets: bool,
+    /// Compressed texture formats supported by the graphics API.
+    pub compressed_formats: CompressedFormats,
+    /// Highest number of samples per pixel the screen can be rendered with, 1 without multisampling support.
+    pub max_samples: u32,
+    /// Largest number of layers of a texture array, 1 without texture array support. Glyph atlases grow by
+    /// adding layers to their texture instead of allocating new textures.
+    pub max_texture_array_layers: usize,
+}
+
+impl Default for RendererCapabilities {
+    /// Conservative capabilities, available everywhere.
+    fn default() -> Self {
+        Self {
+            max_texture_size: 2048,
+            npot_textures: true,
+            instancing: false,
+            advanced_blending: false,
+            float_render_targets: false,
+            compressed_formats: CompressedFormats::empty(),
+            max_samples: 1,
+            max_texture_array_layers: 1,
+        }
+    }
+}
+
+bitflags! {
+    /// Set of compressed texture format families.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct CompressedFormats: u32 {
+        /// BC1 to BC7 (S3TC/DXT and BPTC), common on desktop GPUs.
+        const BC = 1;
+        /// ETC2 and EAC.
+        const ETC2 = 1 << 1;
+        /// ASTC with low dynamic range.
+        const ASTC = 1 << 2;
+    }
+}
+
 /// The main renderer trait that the [Canvas](../struct.Canvas.html) draws to.
 pub trait Renderer {
     /// Associated image type.
@@ -127,6 +321,31 @@ pub trait Renderer {
     /// Set the size of the renderer.
     fn set_size(&mut self, width: u32, height: u32, dpi: f32);
 
+    /// Returns the number of samples per pixel the screen is rendered with.
+    ///
+    /// The canvas doesn't draw anti-aliasing fringes on the screen when it's greater than 1.
+    fn sample_count(&self) -> u32 {
+        1
+    }
+
+    /// Returns whether text is always drawn as filled glyph outlines instead of from a glyph atlas, for renderers
+    /// with resolution-independent output (default implementation returns false).
+    fn draws_text_as_paths(&self) -> bool {
+        false
+    }
+
+    /// Returns whether fill and stroke commands should carry the paths they were drawn from, for renderers that
+    /// write the paths instead of drawing their tessellation (default implementation returns false).
+    fn draws_path_outlines(&self) -> bool {
+        false
+    }
+
+    /// Returns the limits and optional features of the renderer (default implementation returns
+    /// [`RendererCapabilities::default()`]).
+    fn capabilities(&self) -> RendererCapabilities {
+        RendererCapabilities::default()
+    }
+
     /// Render the specified commands.
     fn render(
         &mut self,
@@ -150,6 +369,20 @@ pub trait Renderer {
     fn update_image(&mut self, image: &mut Self::Image, data: ImageSource, x: usize, y: usize)
         -> Result<(), ErrorKind>;
 
+    /// Update a layer of an image with several layers, see [`ImageInfo::layers()`] (default implementation
+    /// returns an error).
+    #[allow(unused_variables)]
+    fn update_image_layer(
+        &mut self,
+        image: &mut Self::Image,
+        data: ImageSource,
+        x: usize,
+        y: usize,
+        layer: usize,
+    ) -> Result<(), ErrorKind> {
+        Err(ErrorKind::UnsupportedOperation)
+    }
+
     /// Get the native texture associated with an image (default implementation returns an error).
     #[allow(unused_variables)]
     fn get_native_texture(&self, image: &Self::Image) -> Result<Self::NativeTexture, ErrorKind> {
@@ -159,8 +392,41 @@ pub trait Renderer {
     /// Delete an image.
     fn delete_image(&mut self, image: Self::Image, image_id: ImageId);
 
+    /// Compile a custom fill shader (default implementation returns an error).
+    #[allow(unused_variables)]
+    fn create_shader(&mut self, id: ShaderId, source: &str) -> Result<(), ErrorKind> {
+        Err(ErrorKind::UnsupportedOperation)
+    }
+
+    /// Delete a custom fill shader.
+    #[allow(unused_variables)]
+    fn delete_shader(&mut self, id: ShaderId) {}
+
     /// Take a screenshot of the current render target.
     fn screenshot(&mut self) -> Result<ImgVec<RGBA8>, ErrorKind>;
+
+    /// Reads back an area of the current render target, with the top left corner at `x`, `y`, into `buffer`.
+    ///
+    /// The default implementation crops a full [`Self::screenshot()`].
+    fn screenshot_area(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        format: ReadbackFormat,
+        buffer: &mut [u8],
+    ) -> Result<(), ErrorKind> {
+        let screenshot = self.screenshot()?;
+
+        if x + width > screenshot.width() || y + height > screenshot.height() {
+            return Err(ErrorKind::ReadbackOutOfBounds);
+        }
+
+        format.write_pixels(screenshot.sub_image(x, y, width, height).pixels(), buffer);
+
+        Ok(())
+    }
 }
 
 /// Marker trait for renderers that don't have a surface.
@@ -173,6 +439,7 @@ use bytemuck::{Pod, Zeroable};
 
 /// Vertex struct for specifying triangle geometry.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Default, Pod, Zeroable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 pub struct Vertex {
     /// X-coordinate of the vertex.
@@ -206,6 +473,7 @@ impl Vertex {
 ///
 /// The default value is `FillGradient`.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ShaderType {
     /// Fill gradient shader.
     #[default]
@@ -224,6 +492,8 @@ pub enum ShaderType {
     TextureCopyUnclipped,
     /// Fill color shader without clipping, used for clear_rect()
     FillColorUnclipped,
+    /// Custom fill shader.
+    FillCustom,
 }
 
 impl ShaderType {
@@ -238,6 +508,7 @@ impl ShaderType {
             Self::FillColor => 5,
             Self::TextureCopyUnclipped => 6,
             Self::FillColorUnclipped => 7,
+            Self::FillCustom => 8,
         }
     }
 
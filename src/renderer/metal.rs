@@ -0,0 +1,1252 @@
+use std::ffi::c_void;
+
+use fnv::FnvHashMap;
+use rgb::bytemuck;
+
+use crate::{
+    image::ImageStore, paint::GlyphTexture, renderer::ShaderType, BlendFactor, CompositeOperationState, ErrorKind,
+    FillRule, ImageFlags, ImageId, ImageInfo, ImageSource, PixelFormat, RenderTarget, Scissor, ShaderId,
+};
+
+use super::{Command, CommandType, CompressedFormats, Params, Renderer, RendererCapabilities, Vertex};
+
+pub use metal;
+
+const UNIFORMARRAY_SIZE: usize = 16;
+
+struct UniformArray([f32; UNIFORMARRAY_SIZE * 4]);
+
+impl UniformArray {
+    fn as_slice(&self) -> &[f32] {
+        &self.0
+    }
+}
+
+impl From<&Params> for UniformArray {
+    fn from(params: &Params) -> Self {
+        let mut arr = [0.0; UNIFORMARRAY_SIZE * 4];
+
+        arr[0..12].copy_from_slice(&params.scissor_mat);
+        arr[12..24].copy_from_slice(&params.paint_mat);
+        arr[24..28].copy_from_slice(&params.inner_col);
+        arr[28..32].copy_from_slice(&params.outer_col);
+        arr[32..34].copy_from_slice(&params.scissor_ext);
+        arr[34..36].copy_from_slice(&params.scissor_scale);
+        arr[36..38].copy_from_slice(&params.extent);
+        arr[38] = params.radius;
+        arr[39] = params.feather;
+        arr[40] = params.stroke_mult;
+        arr[41] = params.stroke_thr;
+        arr[42] = params.tex_type;
+        arr[43] = params.shader_type.to_f32();
+        arr[44] = params.glyph_texture_type as f32;
+        arr[45] = params.image_blur_filter_sigma;
+        arr[46..48].copy_from_slice(&params.image_blur_filter_direction);
+        arr[48..51].copy_from_slice(&params.image_blur_filter_coeff);
+        arr[51] = params.custom_alpha;
+        arr[52] = params.path_gradient;
+        arr[53] = params.mask_gamma;
+        arr[54] = params.mask_contrast;
+        arr[55] = params.focal_gradient;
+        arr[56..60].copy_from_slice(&params.tex_rect);
+        arr[60] = params.gradient_spread;
+        arr[61] = params.image_blur_filter_kawase;
+
+        Self(arr)
+    }
+}
+
+/// An image allocated by the Metal renderer.
+pub struct MtlTexture {
+    texture: metal::Texture,
+    info: ImageInfo,
+}
+
+impl MtlTexture {
+    /// Returns the underlying Metal texture.
+    pub fn texture(&self) -> &metal::TextureRef {
+        &self.texture
+    }
+
+    /// Returns the image info of this texture.
+    pub fn info(&self) -> ImageInfo {
+        self.info
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum StencilTest {
+    Disabled,
+    // Winding count for concave fills, front faces increment and back faces decrement
+    FillStencil,
+    // Anti-aliased fringes of concave fills are drawn where the stencil is still zero
+    FillFringe(u32),
+    // Covers the fill where the stencil is non-zero and resets it along the way
+    Fill(u32),
+    // Draws the stroke base once per pixel
+    StrokeBase,
+    StrokeAntiAlias,
+    StrokeClear,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+struct PipelineState {
+    shader_type: ShaderType,
+    enable_glyph_texture: bool,
+    render_to_texture: bool,
+    // `None` disables color writes.
+    blend: Option<CompositeOperationState>,
+    pixel_format: metal::MTLPixelFormat,
+    // Library of the custom shader for `ShaderType::FillCustom`
+    custom_shader: Option<ShaderId>,
+}
+
+// The texture that was last rendered to, read back by screenshots.
+struct LastTarget {
+    texture: metal::Texture,
+    // Images are rendered upside down
+    flipped: bool,
+}
+
+// The render command encoder of the target that is currently drawn to.
+struct Pass {
+    encoder: metal::RenderCommandEncoder,
+    pixel_format: metal::MTLPixelFormat,
+    render_to_texture: bool,
+}
+
+/// Metal renderer.
+///
+/// The surface passed to [`Canvas::flush_to_surface`](crate::Canvas::flush_to_surface) is the texture to render into,
+/// usually the texture of the next drawable of a `CAMetalLayer`. The command buffer is committed at the end of the
+/// flush without waiting for it, presenting the drawable is left to the caller. Screenshots read back the texture
+/// that was rendered to last and wait for the GPU, which requires a texture that isn't `framebufferOnly`.
+pub struct Metal {
+    device: metal::Device,
+    queue: metal::CommandQueue,
+    library: metal::Library,
+    custom_libraries: FnvHashMap<ShaderId, metal::Library>,
+    empty_texture: metal::Texture,
+    stencil_texture: Option<metal::Texture>,
+    stencil_textures_for_images: FnvHashMap<ImageId, metal::Texture>,
+    pipeline_cache: FnvHashMap<PipelineState, metal::RenderPipelineState>,
+    depth_stencil_cache: FnvHashMap<StencilTest, metal::DepthStencilState>,
+    sampler_cache: FnvHashMap<ImageFlags, metal::SamplerState>,
+    last_target: Option<LastTarget>,
+}
+
+impl Metal {
+    /// Creates a new renderer for the device, compiling the shaders and creating a command queue.
+    pub fn new(device: &metal::DeviceRef) -> Result<Self, ErrorKind> {
+        let library = device
+            .new_library_with_source(include_str!("metal/shader.metal"), &metal::CompileOptions::new())
+            .map_err(ErrorKind::ShaderCompileError)?;
+
+        let descriptor = metal::TextureDescriptor::new();
+        descriptor.set_texture_type(metal::MTLTextureType::D2);
+        descriptor.set_pixel_format(metal::MTLPixelFormat::RGBA8Unorm);
+        descriptor.set_width(1);
+        descriptor.set_height(1);
+        descriptor.set_usage(metal::MTLTextureUsage::ShaderRead);
+        let empty_texture = device.new_texture(&descriptor);
+        empty_texture.replace_region(
+            metal::MTLRegion::new_2d(0, 0, 1, 1),
+            0,
+            [0u8; 4].as_ptr() as *const c_void,
+            4,
+        );
+
+        Ok(Self {
+            device: device.to_owned(),
+            queue: device.new_command_queue(),
+            library,
+            custom_libraries: FnvHashMap::default(),
+            empty_texture,
+            stencil_texture: None,
+            stencil_textures_for_images: FnvHashMap::default(),
+            pipeline_cache: FnvHashMap::default(),
+            depth_stencil_cache: FnvHashMap::default(),
+            sampler_cache: FnvHashMap::default(),
+            last_target: None,
+        })
+    }
+
+    /// Returns the device this renderer was created with.
+    pub fn device(&self) -> &metal::DeviceRef {
+        &self.device
+    }
+
+    /// Returns the command queue the frames are submitted to.
+    pub fn command_queue(&self) -> &metal::CommandQueueRef {
+        &self.queue
+    }
+
+    fn new_stencil_texture(&self, width: u64, height: u64) -> metal::Texture {
+        let descriptor = metal::TextureDescriptor::new();
+        descriptor.set_texture_type(metal::MTLTextureType::D2);
+        descriptor.set_pixel_format(metal::MTLPixelFormat::Stencil8);
+        descriptor.set_width(width);
+        descriptor.set_height(height);
+        descriptor.set_storage_mode(metal::MTLStorageMode::Private);
+        descriptor.set_usage(metal::MTLTextureUsage::RenderTarget);
+        self.device.new_texture(&descriptor)
+    }
+
+    fn begin_pass(
+        &mut self,
+        command_buffer: &metal::CommandBufferRef,
+        vertex_buffer: &metal::BufferRef,
+        surface: &metal::TextureRef,
+        images: &ImageStore<MtlTexture>,
+        target: RenderTarget,
+        load_action: metal::MTLLoadAction,
+    ) -> Pass {
+        let (texture, render_to_texture) = match target {
+            RenderTarget::Screen => (surface, false),
+            // Images never have several layers without texture array support
+            RenderTarget::Image(id) => match images.get(id) {
+                Some(image) => (&*image.texture, true),
+                None => (surface, false),
+            },
+        };
+
+        let (width, height) = (texture.width(), texture.height());
+
+        self.last_target = Some(LastTarget {
+            texture: texture.to_owned(),
+            flipped: render_to_texture,
+        });
+
+        let stencil_texture = match target {
+            RenderTarget::Image(id) if render_to_texture => {
+                let stencil = self
+                    .stencil_textures_for_images
+                    .get(&id)
+                    .filter(|stencil| stencil.width() == width && stencil.height() == height)
+                    .cloned();
+                stencil.unwrap_or_else(|| {
+                    let stencil = self.new_stencil_texture(width, height);
+                    self.stencil_textures_for_images.insert(id, stencil.clone());
+                    stencil
+                })
+            }
+            _ => {
+                if let Some(stencil) = &self.stencil_texture {
+                    if stencil.width() != width || stencil.height() != height {
+                        self.stencil_texture = None;
+                    }
+                }
+                let stencil = self
+                    .stencil_texture
+                    .take()
+                    .unwrap_or_else(|| self.new_stencil_texture(width, height));
+                self.stencil_texture = Some(stencil.clone());
+                stencil
+            }
+        };
+
+        let descriptor = metal::RenderPassDescriptor::new();
+
+        let color_attachment = descriptor.color_attachments().object_at(0).unwrap();
+        color_attachment.set_texture(Some(texture));
+        color_attachment.set_load_action(load_action);
+        color_attachment.set_clear_color(metal::MTLClearColor::new(0.0, 0.0, 0.0, 0.0));
+        color_attachment.set_store_action(metal::MTLStoreAction::Store);
+
+        // Every draw leaves the stencil buffer zeroed behind, so it doesn't need to survive between passes.
+        let stencil_attachment = descriptor.stencil_attachment().unwrap();
+        stencil_attachment.set_texture(Some(&stencil_texture));
+        stencil_attachment.set_load_action(metal::MTLLoadAction::Clear);
+        stencil_attachment.set_clear_stencil(0);
+        stencil_attachment.set_store_action(metal::MTLStoreAction::DontCare);
+
+        let encoder = command_buffer.new_render_command_encoder(descriptor).to_owned();
+
+        let view_size = [width as f32, height as f32];
+
+        encoder.set_viewport(metal::MTLViewport {
+            originX: 0.0,
+            originY: 0.0,
+            width: view_size[0] as f64,
+            height: view_size[1] as f64,
+            znear: 0.0,
+            zfar: 1.0,
+        });
+        encoder.set_vertex_buffer(0, Some(vertex_buffer), 0);
+        encoder.set_vertex_bytes(
+            1,
+            std::mem::size_of_val(&view_size) as u64,
+            view_size.as_ptr() as *const c_void,
+        );
+        // Images are rendered upside down, which flips the winding order as well.
+        encoder.set_front_facing_winding(if render_to_texture {
+            metal::MTLWinding::Clockwise
+        } else {
+            metal::MTLWinding::CounterClockwise
+        });
+
+        Pass {
+            encoder,
+            pixel_format: texture.pixel_format(),
+            render_to_texture,
+        }
+    }
+
+    fn pipeline(&mut self, state: PipelineState) -> Result<&metal::RenderPipelineStateRef, ErrorKind> {
+        if !self.pipeline_cache.contains_key(&state) {
+            let pipeline = self.new_pipeline(state)?;
+            self.pipeline_cache.insert(state, pipeline);
+        }
+
+        Ok(&self.pipeline_cache[&state])
+    }
+
+    fn new_pipeline(&self, state: PipelineState) -> Result<metal::RenderPipelineState, ErrorKind> {
+        let constants = metal::FunctionConstantValues::new();
+        let shader_type = state.shader_type.to_u8() as i32;
+        constants.set_constant_value_at_index(&shader_type as *const i32 as *const c_void, metal::MTLDataType::Int, 0);
+        constants.set_constant_value_at_index(
+            &state.enable_glyph_texture as *const bool as *const c_void,
+            metal::MTLDataType::Bool,
+            1,
+        );
+        constants.set_constant_value_at_index(
+            &state.render_to_texture as *const bool as *const c_void,
+            metal::MTLDataType::Bool,
+            2,
+        );
+
+        let library = state
+            .custom_shader
+            .and_then(|id| self.custom_libraries.get(&id))
+            .unwrap_or(&self.library);
+
+        let vertex_function = library
+            .get_function("vs_main", Some(constants.clone()))
+            .map_err(ErrorKind::ShaderCompileError)?;
+        let fragment_function = library
+            .get_function("fs_main", Some(constants))
+            .map_err(ErrorKind::ShaderCompileError)?;
+
+        let descriptor = metal::RenderPipelineDescriptor::new();
+        descriptor.set_vertex_function(Some(&vertex_function));
+        descriptor.set_fragment_function(Some(&fragment_function));
+        descriptor.set_stencil_attachment_pixel_format(metal::MTLPixelFormat::Stencil8);
+
+        let attachment = descriptor.color_attachments().object_at(0).unwrap();
+        attachment.set_pixel_format(state.pixel_format);
+
+        if let Some(blend) = state.blend {
+            attachment.set_blending_enabled(true);
+            attachment.set_source_rgb_blend_factor(blend_factor(blend.src_rgb));
+            attachment.set_destination_rgb_blend_factor(blend_factor(blend.dst_rgb));
+            attachment.set_rgb_blend_operation(metal::MTLBlendOperation::Add);
+            attachment.set_source_alpha_blend_factor(blend_factor(blend.src_alpha));
+            attachment.set_destination_alpha_blend_factor(blend_factor(blend.dst_alpha));
+            attachment.set_alpha_blend_operation(metal::MTLBlendOperation::Add);
+        } else {
+            attachment.set_write_mask(metal::MTLColorWriteMask::empty());
+        }
+
+        self.device
+            .new_render_pipeline_state(&descriptor)
+            .map_err(ErrorKind::ShaderLinkError)
+    }
+
+    fn depth_stencil_state(&mut self, stencil_test: StencilTest) -> &metal::DepthStencilStateRef {
+        let device = &self.device;
+
+        self.depth_stencil_cache.entry(stencil_test).or_insert_with(|| {
+            let face = |compare, pass_op, mask| {
+                let descriptor = metal::StencilDescriptor::new();
+                descriptor.set_stencil_compare_function(compare);
+                descriptor.set_stencil_failure_operation(metal::MTLStencilOperation::Keep);
+                descriptor.set_depth_failure_operation(metal::MTLStencilOperation::Keep);
+                descriptor.set_depth_stencil_pass_operation(pass_op);
+                descriptor.set_read_mask(mask);
+                descriptor.set_write_mask(mask);
+                descriptor
+            };
+
+            use metal::{MTLCompareFunction as Compare, MTLStencilOperation as Op};
+
+            let (front, back) = match stencil_test {
+                StencilTest::Disabled => (face(Compare::Always, Op::Keep, 0xff), None),
+                StencilTest::FillStencil => (
+                    face(Compare::Always, Op::IncrementWrap, 0xff),
+                    Some(face(Compare::Always, Op::DecrementWrap, 0xff)),
+                ),
+                StencilTest::FillFringe(mask) => (face(Compare::Equal, Op::Keep, mask), None),
+                StencilTest::Fill(mask) => {
+                    let descriptor = face(Compare::NotEqual, Op::Zero, mask);
+                    descriptor.set_stencil_failure_operation(Op::Zero);
+                    (descriptor, None)
+                }
+                StencilTest::StrokeBase => (face(Compare::Equal, Op::IncrementClamp, 0xff), None),
+                StencilTest::StrokeAntiAlias => (face(Compare::Equal, Op::Keep, 0xff), None),
+                StencilTest::StrokeClear => (face(Compare::Always, Op::Zero, 0xff), None),
+            };
+
+            let descriptor = metal::DepthStencilDescriptor::new();
+            descriptor.set_depth_compare_function(Compare::Always);
+            descriptor.set_depth_write_enabled(false);
+            descriptor.set_front_face_stencil(Some(&front));
+            descriptor.set_back_face_stencil(Some(back.as_ref().unwrap_or(&front)));
+
+            device.new_depth_stencil_state(&descriptor)
+        })
+    }
+
+    fn sampler(&mut self, flags: ImageFlags) -> &metal::SamplerStateRef {
+        let flags = flags & (ImageFlags::REPEAT_X | ImageFlags::REPEAT_Y | ImageFlags::NEAREST);
+        let device = &self.device;
+
+        self.sampler_cache.entry(flags).or_insert_with(|| {
+            let address_mode = |repeat| {
+                if repeat {
+                    metal::MTLSamplerAddressMode::Repeat
+                } else {
+                    metal::MTLSamplerAddressMode::ClampToEdge
+                }
+            };
+            let filter = if flags.contains(ImageFlags::NEAREST) {
+                metal::MTLSamplerMinMagFilter::Nearest
+            } else {
+                metal::MTLSamplerMinMagFilter::Linear
+            };
+
+            let descriptor = metal::SamplerDescriptor::new();
+            descriptor.set_address_mode_s(address_mode(flags.contains(ImageFlags::REPEAT_X)));
+            descriptor.set_address_mode_t(address_mode(flags.contains(ImageFlags::REPEAT_Y)));
+            descriptor.set_min_filter(filter);
+            descriptor.set_mag_filter(filter);
+            device.new_sampler(&descriptor)
+        })
+    }
+
+    fn bind_texture(&mut self, pass: &Pass, images: &ImageStore<MtlTexture>, index: u64, image: Option<ImageId>) {
+        let image = image.and_then(|id| images.get(id));
+        let flags = image.map_or(ImageFlags::empty(), |image| image.info.flags());
+        let texture = image.map_or(&*self.empty_texture, |image| &*image.texture);
+
+        pass.encoder.set_fragment_texture(index, Some(texture));
+        pass.encoder
+            .set_fragment_sampler_state(index, Some(self.sampler(flags)));
+    }
+
+    fn set_state(
+        &mut self,
+        pass: &Pass,
+        images: &ImageStore<MtlTexture>,
+        params: &Params,
+        image: Option<ImageId>,
+        glyph_texture: GlyphTexture,
+        blend: Option<CompositeOperationState>,
+        stencil_test: StencilTest,
+        cull_mode: metal::MTLCullMode,
+    ) -> Result<(), ErrorKind> {
+        let pipeline = self.pipeline(PipelineState {
+            shader_type: params.shader_type,
+            enable_glyph_texture: params.uses_glyph_texture(),
+            render_to_texture: pass.render_to_texture,
+            blend,
+            pixel_format: pass.pixel_format,
+            // Paints with a deleted custom shader are drawn with their first uniforms as color
+            custom_shader: params.custom_shader.filter(|id| self.custom_libraries.contains_key(id)),
+        })?;
+        pass.encoder.set_render_pipeline_state(pipeline);
+
+        let depth_stencil_state = self.depth_stencil_state(stencil_test);
+        pass.encoder.set_depth_stencil_state(depth_stencil_state);
+        pass.encoder.set_stencil_reference_value(0);
+        pass.encoder.set_cull_mode(cull_mode);
+
+        let uniforms = UniformArray::from(params);
+        pass.encoder.set_fragment_bytes(
+            0,
+            std::mem::size_of_val(uniforms.as_slice()) as u64,
+            uniforms.as_slice().as_ptr() as *const c_void,
+        );
+
+        self.bind_texture(pass, images, 0, image);
+        self.bind_texture(pass, images, 1, glyph_texture.image_id());
+
+        Ok(())
+    }
+
+    fn convex_fill(
+        &mut self,
+        pass: &Pass,
+        images: &ImageStore<MtlTexture>,
+        cmd: &Command,
+        params: &Params,
+    ) -> Result<(), ErrorKind> {
+        let blend = Some(cmd.composite_operation);
+
+        for drawable in &cmd.drawables {
+            if let Some((start, count)) = drawable.fill_verts {
+                self.set_state(
+                    pass,
+                    images,
+                    params,
+                    cmd.image,
+                    cmd.glyph_texture,
+                    blend,
+                    StencilTest::Disabled,
+                    metal::MTLCullMode::Back,
+                )?;
+                draw(pass, metal::MTLPrimitiveType::Triangle, start, count);
+            }
+
+            if let Some((start, count)) = drawable.stroke_verts {
+                self.set_state(
+                    pass,
+                    images,
+                    params,
+                    cmd.image,
+                    cmd.glyph_texture,
+                    blend,
+                    StencilTest::Disabled,
+                    metal::MTLCullMode::Back,
+                )?;
+                draw(pass, metal::MTLPrimitiveType::TriangleStrip, start, count);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn concave_fill(
+        &mut self,
+        pass: &Pass,
+        images: &ImageStore<MtlTexture>,
+        cmd: &Command,
+        stencil_params: &Params,
+        fill_params: &Params,
+    ) -> Result<(), ErrorKind> {
+        let mask = match cmd.fill_rule {
+            FillRule::NonZero => 0xff,
+            FillRule::EvenOdd => 0x1,
+        };
+
+        if cmd.drawables.iter().any(|drawable| drawable.fill_verts.is_some()) {
+            self.set_state(
+                pass,
+                images,
+                stencil_params,
+                None,
+                GlyphTexture::None,
+                None,
+                StencilTest::FillStencil,
+                metal::MTLCullMode::None,
+            )?;
+
+            for drawable in &cmd.drawables {
+                if let Some((start, count)) = drawable.fill_verts {
+                    draw(pass, metal::MTLPrimitiveType::Triangle, start, count);
+                }
+            }
+        }
+
+        let blend = Some(cmd.composite_operation);
+
+        // Draw anti-aliased fringes.
+        if cmd.drawables.iter().any(|drawable| drawable.stroke_verts.is_some()) {
+            self.set_state(
+                pass,
+                images,
+                fill_params,
+                cmd.image,
+                cmd.glyph_texture,
+                blend,
+                StencilTest::FillFringe(mask),
+                metal::MTLCullMode::Back,
+            )?;
+
+            for drawable in &cmd.drawables {
+                if let Some((start, count)) = drawable.stroke_verts {
+                    draw(pass, metal::MTLPrimitiveType::TriangleStrip, start, count);
+                }
+            }
+        }
+
+        // Draw fill
+        if let Some((start, count)) = cmd.triangles_verts {
+            self.set_state(
+                pass,
+                images,
+                fill_params,
+                cmd.image,
+                cmd.glyph_texture,
+                blend,
+                StencilTest::Fill(mask),
+                metal::MTLCullMode::Back,
+            )?;
+            draw(pass, metal::MTLPrimitiveType::TriangleStrip, start, count);
+        }
+
+        Ok(())
+    }
+
+    fn stroke(
+        &mut self,
+        pass: &Pass,
+        images: &ImageStore<MtlTexture>,
+        cmd: &Command,
+        params: &Params,
+    ) -> Result<(), ErrorKind> {
+        if !cmd.drawables.iter().any(|drawable| drawable.stroke_verts.is_some()) {
+            return Ok(());
+        }
+
+        self.set_state(
+            pass,
+            images,
+            params,
+            cmd.image,
+            cmd.glyph_texture,
+            Some(cmd.composite_operation),
+            StencilTest::Disabled,
+            metal::MTLCullMode::Back,
+        )?;
+
+        for drawable in &cmd.drawables {
+            if let Some((start, count)) = drawable.stroke_verts {
+                draw(pass, metal::MTLPrimitiveType::TriangleStrip, start, count);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stencil_stroke(
+        &mut self,
+        pass: &Pass,
+        images: &ImageStore<MtlTexture>,
+        cmd: &Command,
+        params1: &Params,
+        params2: &Params,
+    ) -> Result<(), ErrorKind> {
+        if !cmd.drawables.iter().any(|drawable| drawable.stroke_verts.is_some()) {
+            return Ok(());
+        }
+
+        let blend = Some(cmd.composite_operation);
+
+        let passes = [
+            // Fill the stroke base without overlap
+            (params2, blend, StencilTest::StrokeBase),
+            // Draw anti-aliased pixels.
+            (params1, blend, StencilTest::StrokeAntiAlias),
+            // Clear stencil buffer
+            (params1, None, StencilTest::StrokeClear),
+        ];
+
+        for (params, blend, stencil_test) in passes {
+            self.set_state(
+                pass,
+                images,
+                params,
+                cmd.image,
+                cmd.glyph_texture,
+                blend,
+                stencil_test,
+                metal::MTLCullMode::Back,
+            )?;
+
+            for drawable in &cmd.drawables {
+                if let Some((start, count)) = drawable.stroke_verts {
+                    draw(pass, metal::MTLPrimitiveType::TriangleStrip, start, count);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn triangles(
+        &mut self,
+        pass: &Pass,
+        images: &ImageStore<MtlTexture>,
+        cmd: &Command,
+        params: &Params,
+    ) -> Result<(), ErrorKind> {
+        if let Some((start, count)) = cmd.triangles_verts {
+            self.set_state(
+                pass,
+                images,
+                params,
+                cmd.image,
+                cmd.glyph_texture,
+                Some(cmd.composite_operation),
+                StencilTest::Disabled,
+                metal::MTLCullMode::Back,
+            )?;
+            draw(pass, metal::MTLPrimitiveType::Triangle, start, count);
+        }
+
+        Ok(())
+    }
+
+    fn clear_rect(
+        &mut self,
+        pass: &Pass,
+        images: &ImageStore<MtlTexture>,
+        cmd: &Command,
+        color: crate::Color,
+    ) -> Result<(), ErrorKind> {
+        let mut params = Params::new(
+            images,
+            &Default::default(),
+            &crate::paint::PaintFlavor::Color(color),
+            &Default::default(),
+            &Scissor::default(),
+            0.,
+            0.,
+            0.,
+        );
+        params.shader_type = ShaderType::FillColorUnclipped;
+
+        if let Some((start, count)) = cmd.triangles_verts {
+            self.set_state(
+                pass,
+                images,
+                &params,
+                None,
+                GlyphTexture::None,
+                Some(CompositeOperationState::with_blend_factors(
+                    BlendFactor::One,
+                    BlendFactor::Zero,
+                )),
+                StencilTest::Disabled,
+                metal::MTLCullMode::None,
+            )?;
+            draw(pass, metal::MTLPrimitiveType::Triangle, start, count);
+        }
+
+        Ok(())
+    }
+
+    fn render_gaussian_blur(
+        &mut self,
+        command_buffer: &metal::CommandBufferRef,
+        vertex_buffer: &metal::BufferRef,
+        surface: &metal::TextureRef,
+        images: &mut ImageStore<MtlTexture>,
+        mut cmd: Command,
+        target_image: ImageId,
+        sigma: f32,
+    ) -> Result<(), ErrorKind> {
+        // The filtering happens in two passes, first a horizontal blur and then the vertial blur. The
+        // first pass therefore renders into an intermediate, temporarily allocated texture.
+
+        let Some(source_image_info) = cmd.image.and_then(|id| images.info(id)) else {
+            return Err(ErrorKind::ImageIdNotFound);
+        };
+
+        let image_paint = crate::Paint::image(
+            cmd.image.unwrap(),
+            0.,
+            0.,
+            source_image_info.width() as _,
+            source_image_info.height() as _,
+            0.,
+            1.,
+        );
+        let mut blur_params = Params::new(
+            images,
+            &Default::default(),
+            &image_paint.flavor,
+            &Default::default(),
+            &Scissor::default(),
+            0.,
+            0.,
+            0.,
+        );
+        blur_params.shader_type = ShaderType::FilterImage;
+
+        let gauss_coeff_x = 1. / ((2. * std::f32::consts::PI).sqrt() * sigma);
+        let gauss_coeff_y = f32::exp(-0.5 / (sigma * sigma));
+        let gauss_coeff_z = gauss_coeff_y * gauss_coeff_y;
+
+        blur_params.image_blur_filter_coeff[0] = gauss_coeff_x;
+        blur_params.image_blur_filter_coeff[1] = gauss_coeff_y;
+        blur_params.image_blur_filter_coeff[2] = gauss_coeff_z;
+
+        blur_params.image_blur_filter_direction = [1.0, 0.0];
+
+        // Keep the same upper bound on the standard deviation as the other renderers, so the fragment shader
+        // can use a fixed limit on the number of iterations.
+        blur_params.image_blur_filter_sigma = sigma.min(8.);
+
+        let horizontal_blur_buffer = images.alloc(self, source_image_info)?;
+
+        let pass = self.begin_pass(
+            command_buffer,
+            vertex_buffer,
+            surface,
+            images,
+            RenderTarget::Image(horizontal_blur_buffer),
+            metal::MTLLoadAction::Clear,
+        );
+        let mut result = self.triangles(&pass, images, &cmd, &blur_params);
+        pass.encoder.end_encoding();
+
+        if result.is_ok() {
+            let pass = self.begin_pass(
+                command_buffer,
+                vertex_buffer,
+                surface,
+                images,
+                RenderTarget::Image(target_image),
+                metal::MTLLoadAction::Clear,
+            );
+            blur_params.image_blur_filter_direction = [0.0, 1.0];
+            cmd.image = Some(horizontal_blur_buffer);
+            result = self.triangles(&pass, images, &cmd, &blur_params);
+            pass.encoder.end_encoding();
+        }
+
+        // The texture is retained by the command buffer until it completes.
+        images.remove(self, horizontal_blur_buffer);
+
+        result
+    }
+
+    fn render_dual_kawase_blur(
+        &mut self,
+        command_buffer: &metal::CommandBufferRef,
+        vertex_buffer: &metal::BufferRef,
+        surface: &metal::TextureRef,
+        images: &mut ImageStore<MtlTexture>,
+        mut cmd: Command,
+        target_image: ImageId,
+        radius: f32,
+    ) -> Result<(), ErrorKind> {
+        let Some(source_image) = cmd.image else {
+            return Err(ErrorKind::ImageIdNotFound);
+        };
+        let Some(source_image_info) = images.info(source_image) else {
+            return Err(ErrorKind::ImageIdNotFound);
+        };
+        let size = (source_image_info.width(), source_image_info.height());
+
+        let (levels, offset) = Params::dual_kawase_levels(radius, size.0, size.1);
+
+        let image_paint = crate::Paint::image(source_image, 0., 0., size.0 as _, size.1 as _, 0., 1.);
+        let mut blur_params = Params::new(
+            images,
+            &Default::default(),
+            &image_paint.flavor,
+            &Default::default(),
+            &Scissor::default(),
+            0.,
+            0.,
+            0.,
+        );
+        blur_params.shader_type = ShaderType::FilterImage;
+
+        // The image is halved into a chain of temporary images and then scaled back up through them, with the last
+        // pass rendering into the target image.
+        let level_size = |level: usize| ((size.0 >> level).max(1), (size.1 >> level).max(1));
+        let mut level_images = vec![source_image];
+        let mut result = Ok(());
+        for level in 1..=levels {
+            let (width, height) = level_size(level);
+            let info = ImageInfo::new(ImageFlags::PREMULTIPLIED, width, height, source_image_info.format());
+            match images.alloc(self, info) {
+                Ok(image) => level_images.push(image),
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+
+        if result.is_ok() {
+            let downsample_passes = (1..=levels).map(|level| (level - 1, level, false));
+            let upsample_passes = (0..levels).rev().map(|level| (level + 1, level, true));
+
+            for (from, to, upsample) in downsample_passes.chain(upsample_passes) {
+                let to_image = if to == 0 { target_image } else { level_images[to] };
+
+                let pass = self.begin_pass(
+                    command_buffer,
+                    vertex_buffer,
+                    surface,
+                    images,
+                    RenderTarget::Image(to_image),
+                    metal::MTLLoadAction::Clear,
+                );
+                blur_params.set_dual_kawase_pass(level_size(from), level_size(to), offset, upsample);
+                cmd.image = Some(level_images[from]);
+                result = self.triangles(&pass, images, &cmd, &blur_params);
+                pass.encoder.end_encoding();
+
+                if result.is_err() {
+                    break;
+                }
+
+                // only the source image may have straight alpha, the temporary images hold the premultiplied result
+                if blur_params.tex_type == 1.0 {
+                    blur_params.tex_type = 0.0;
+                }
+            }
+        }
+
+        // The textures are retained by the command buffer until it completes.
+        for image in level_images.into_iter().skip(1) {
+            images.remove(self, image);
+        }
+
+        result
+    }
+}
+
+impl Renderer for Metal {
+    type Image = MtlTexture;
+    type NativeTexture = metal::Texture;
+    type Surface = metal::Texture;
+
+    fn set_size(&mut self, _width: u32, _height: u32, _dpi: f32) {}
+
+    fn capabilities(&self) -> RendererCapabilities {
+        use metal::MTLGPUFamily;
+
+        let device = &self.device;
+        let mut compressed_formats = CompressedFormats::empty();
+        compressed_formats.set(CompressedFormats::BC, device.supports_BC_texture_compression());
+        compressed_formats.set(CompressedFormats::ETC2, device.supports_family(MTLGPUFamily::Apple1));
+        compressed_formats.set(CompressedFormats::ASTC, device.supports_family(MTLGPUFamily::Apple2));
+
+        RendererCapabilities {
+            max_texture_size: if device.supports_family(MTLGPUFamily::Apple3)
+                || device.supports_family(MTLGPUFamily::Mac2)
+            {
+                16384
+            } else {
+                8192
+            },
+            npot_textures: true,
+            instancing: true,
+            advanced_blending: false,
+            float_render_targets: true,
+            compressed_formats,
+            max_samples: 1,
+            max_texture_array_layers: 1,
+        }
+    }
+
+    fn render(
+        &mut self,
+        surface: &Self::Surface,
+        images: &mut ImageStore<Self::Image>,
+        verts: &[Vertex],
+        commands: Vec<Command>,
+    ) {
+        let command_buffer = self.queue.new_command_buffer().to_owned();
+
+        // Metal doesn't allow empty buffers, bind a single unused vertex instead.
+        let verts = if verts.is_empty() {
+            &[Vertex::default()][..]
+        } else {
+            verts
+        };
+        let vertex_bytes: &[u8] = bytemuck::cast_slice(verts);
+        let vertex_buffer = self.device.new_buffer_with_data(
+            vertex_bytes.as_ptr() as *const c_void,
+            vertex_bytes.len() as u64,
+            metal::MTLResourceOptions::CPUCacheModeWriteCombined,
+        );
+
+        let mut current_render_target = RenderTarget::Screen;
+        let mut pass = self.begin_pass(
+            &command_buffer,
+            &vertex_buffer,
+            surface,
+            images,
+            current_render_target,
+            metal::MTLLoadAction::Load,
+        );
+
+        for cmd in commands {
+            let result = match cmd.cmd_type {
+                CommandType::SetRenderTarget(target) => {
+                    pass.encoder.end_encoding();
+                    current_render_target = target;
+                    pass = self.begin_pass(
+                        &command_buffer,
+                        &vertex_buffer,
+                        surface,
+                        images,
+                        target,
+                        metal::MTLLoadAction::Load,
+                    );
+                    Ok(())
+                }
+                CommandType::ClearRect { color } => self.clear_rect(&pass, images, &cmd, color),
+                CommandType::ConvexFill { ref params } => self.convex_fill(&pass, images, &cmd, params),
+                CommandType::ConcaveFill {
+                    ref stencil_params,
+                    ref fill_params,
+                } => self.concave_fill(&pass, images, &cmd, stencil_params, fill_params),
+                CommandType::Stroke { ref params } => self.stroke(&pass, images, &cmd, params),
+                CommandType::StencilStroke {
+                    ref params1,
+                    ref params2,
+                } => self.stencil_stroke(&pass, images, &cmd, params1, params2),
+                CommandType::Triangles { ref params } => self.triangles(&pass, images, &cmd, params),
+                CommandType::RenderFilteredImage { target_image, filter } => match filter {
+                    crate::ImageFilter::GaussianBlur { sigma } => {
+                        pass.encoder.end_encoding();
+                        let result = self.render_gaussian_blur(
+                            &command_buffer,
+                            &vertex_buffer,
+                            surface,
+                            images,
+                            cmd,
+                            target_image,
+                            sigma,
+                        );
+                        pass = self.begin_pass(
+                            &command_buffer,
+                            &vertex_buffer,
+                            surface,
+                            images,
+                            current_render_target,
+                            metal::MTLLoadAction::Load,
+                        );
+                        result
+                    }
+                    crate::ImageFilter::FastBlur { radius } => {
+                        pass.encoder.end_encoding();
+                        let result = self.render_dual_kawase_blur(
+                            &command_buffer,
+                            &vertex_buffer,
+                            surface,
+                            images,
+                            cmd,
+                            target_image,
+                            radius,
+                        );
+                        pass = self.begin_pass(
+                            &command_buffer,
+                            &vertex_buffer,
+                            surface,
+                            images,
+                            current_render_target,
+                            metal::MTLLoadAction::Load,
+                        );
+                        result
+                    }
+                },
+            };
+
+            if let Err(error) = result {
+                log::error!("Failed to render command: {error:?}");
+            }
+        }
+
+        pass.encoder.end_encoding();
+
+        command_buffer.commit();
+    }
+
+    fn alloc_image(&mut self, info: ImageInfo) -> Result<Self::Image, ErrorKind> {
+        let descriptor = metal::TextureDescriptor::new();
+        descriptor.set_texture_type(metal::MTLTextureType::D2);
+        descriptor.set_pixel_format(match info.format() {
+            PixelFormat::Rgb8 | PixelFormat::Rgba8 => metal::MTLPixelFormat::RGBA8Unorm,
+            PixelFormat::Gray8 => metal::MTLPixelFormat::R8Unorm,
+        });
+        descriptor.set_width(info.width() as u64);
+        descriptor.set_height(info.height() as u64);
+        descriptor.set_usage(metal::MTLTextureUsage::ShaderRead | metal::MTLTextureUsage::RenderTarget);
+
+        Ok(MtlTexture {
+            texture: self.device.new_texture(&descriptor),
+            info,
+        })
+    }
+
+    fn create_image_from_native_texture(
+        &mut self,
+        native_texture: Self::NativeTexture,
+        info: ImageInfo,
+    ) -> Result<Self::Image, ErrorKind> {
+        Ok(MtlTexture {
+            texture: native_texture,
+            info,
+        })
+    }
+
+    fn update_image(
+        &mut self,
+        image: &mut Self::Image,
+        data: ImageSource,
+        x: usize,
+        y: usize,
+    ) -> Result<(), ErrorKind> {
+        use rgb::ComponentBytes;
+
+        let size = data.dimensions();
+
+        if x + size.width > image.info.width() || y + size.height > image.info.height() {
+            return Err(ErrorKind::ImageUpdateOutOfBounds);
+        }
+
+        let converted_rgba;
+        let (bytes, bpp) = match data {
+            ImageSource::Rgb(img) => {
+                converted_rgba = img
+                    .pixels()
+                    .map(|rgb| rgb::Rgba {
+                        r: rgb.r,
+                        g: rgb.g,
+                        b: rgb.b,
+                        a: 255,
+                    })
+                    .collect::<Vec<_>>();
+                (converted_rgba.as_bytes(), 4)
+            }
+            ImageSource::Rgba(img) => (img.buf().as_bytes(), 4),
+            ImageSource::Gray(img) => (img.buf().as_bytes(), 1),
+            #[cfg(feature = "image-loading")]
+            ImageSource::Dynamic(_) => return Err(ErrorKind::UnsupportedImageFormat),
+            #[cfg(target_arch = "wasm32")]
+            ImageSource::HtmlImageElement(..) | ImageSource::ImageBitmap(..) => {
+                return Err(ErrorKind::UnsupportedImageFormat)
+            }
+        };
+
+        image.texture.replace_region(
+            metal::MTLRegion::new_2d(x as u64, y as u64, size.width as u64, size.height as u64),
+            0,
+            bytes.as_ptr() as *const c_void,
+            (bpp * size.width) as u64,
+        );
+
+        Ok(())
+    }
+
+    fn get_native_texture(&self, image: &Self::Image) -> Result<Self::NativeTexture, ErrorKind> {
+        Ok(image.texture.clone())
+    }
+
+    fn delete_image(&mut self, image: Self::Image, image_id: ImageId) {
+        self.stencil_textures_for_images.remove(&image_id);
+        drop(image);
+    }
+
+    fn create_shader(&mut self, id: ShaderId, source: &str) -> Result<(), ErrorKind> {
+        let source = format!(
+            "#define CUSTOM_SHADER\n{}\n{source}",
+            include_str!("metal/shader.metal")
+        );
+        let library = self
+            .device
+            .new_library_with_source(&source, &metal::CompileOptions::new())
+            .map_err(ErrorKind::ShaderCompileError)?;
+
+        self.custom_libraries.insert(id, library);
+
+        Ok(())
+    }
+
+    fn delete_shader(&mut self, id: ShaderId) {
+        self.custom_libraries.remove(&id);
+        self.pipeline_cache.retain(|state, _| state.custom_shader != Some(id));
+    }
+
+    fn screenshot(&mut self) -> Result<imgref::ImgVec<rgb::RGBA8>, ErrorKind> {
+        use metal::MTLPixelFormat;
+
+        let Some(target) = &self.last_target else {
+            return Err(ErrorKind::UnsupportedOperation);
+        };
+        let texture = &target.texture;
+
+        // Drawables of layers that are `framebufferOnly` can't be read back.
+        if texture.framebuffer_only() {
+            return Err(ErrorKind::UnsupportedOperation);
+        }
+
+        let bytes_per_pixel = match texture.pixel_format() {
+            MTLPixelFormat::RGBA8Unorm
+            | MTLPixelFormat::RGBA8Unorm_sRGB
+            | MTLPixelFormat::BGRA8Unorm
+            | MTLPixelFormat::BGRA8Unorm_sRGB => 4,
+            MTLPixelFormat::R8Unorm => 1,
+            _ => return Err(ErrorKind::UnsupportedImageFormat),
+        };
+
+        let (width, height) = (texture.width(), texture.height());
+        let bytes_per_row = width * bytes_per_pixel;
+        let buffer = self
+            .device
+            .new_buffer(bytes_per_row * height, metal::MTLResourceOptions::StorageModeShared);
+
+        // Commands on the same queue run in order, so this waits for the frames rendered before as well.
+        let command_buffer = self.queue.new_command_buffer();
+        let encoder = command_buffer.new_blit_command_encoder();
+        encoder.copy_from_texture_to_buffer(
+            texture,
+            0,
+            0,
+            metal::MTLOrigin { x: 0, y: 0, z: 0 },
+            metal::MTLSize {
+                width,
+                height,
+                depth: 1,
+            },
+            &buffer,
+            0,
+            bytes_per_row,
+            bytes_per_row * height,
+            metal::MTLBlitOption::empty(),
+        );
+        encoder.end_encoding();
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        let bytes =
+            unsafe { std::slice::from_raw_parts(buffer.contents() as *const u8, (bytes_per_row * height) as usize) };
+
+        let mut pixels = bytes
+            .chunks_exact(bytes_per_pixel as usize)
+            .map(|pixel| match texture.pixel_format() {
+                MTLPixelFormat::BGRA8Unorm | MTLPixelFormat::BGRA8Unorm_sRGB => {
+                    rgb::RGBA8::new(pixel[2], pixel[1], pixel[0], pixel[3])
+                }
+                MTLPixelFormat::R8Unorm => rgb::RGBA8::new(pixel[0], pixel[0], pixel[0], 255),
+                _ => rgb::RGBA8::new(pixel[0], pixel[1], pixel[2], pixel[3]),
+            })
+            .collect::<Vec<_>>();
+
+        if target.flipped {
+            pixels = pixels.chunks_exact(width as usize).rev().flatten().copied().collect();
+        }
+
+        Ok(imgref::ImgVec::new(pixels, width as usize, height as usize))
+    }
+}
+
+fn draw(pass: &Pass, primitive_type: metal::MTLPrimitiveType, start: usize, count: usize) {
+    pass.encoder.draw_primitives(primitive_type, start as u64, count as u64);
+}
+
+fn blend_factor(factor: BlendFactor) -> metal::MTLBlendFactor {
+    match factor {
+        BlendFactor::Zero => metal::MTLBlendFactor::Zero,
+        BlendFactor::One => metal::MTLBlendFactor::One,
+        BlendFactor::SrcColor => metal::MTLBlendFactor::SourceColor,
+        BlendFactor::OneMinusSrcColor => metal::MTLBlendFactor::OneMinusSourceColor,
+        BlendFactor::DstColor => metal::MTLBlendFactor::DestinationColor,
+        BlendFactor::OneMinusDstColor => metal::MTLBlendFactor::OneMinusDestinationColor,
+        BlendFactor::SrcAlpha => metal::MTLBlendFactor::SourceAlpha,
+        BlendFactor::OneMinusSrcAlpha => metal::MTLBlendFactor::OneMinusSourceAlpha,
+        BlendFactor::DstAlpha => metal::MTLBlendFactor::DestinationAlpha,
+        BlendFactor::OneMinusDstAlpha => metal::MTLBlendFactor::OneMinusDestinationAlpha,
+        BlendFactor::SrcAlphaSaturate => metal::MTLBlendFactor::SourceAlphaSaturated,
+    }
+}
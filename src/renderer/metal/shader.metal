@@ -0,0 +1,357 @@
+#include <metal_stdlib>
+using namespace metal;
+
+#define UNIFORMARRAY_SIZE 16
+
+struct Uniforms {
+    float4 frag[UNIFORMARRAY_SIZE];
+};
+
+#define scissorMat float3x3(u.frag[0].xyz, u.frag[1].xyz, u.frag[2].xyz)
+#define paintMat float3x3(u.frag[3].xyz, u.frag[4].xyz, u.frag[5].xyz)
+#define innerCol u.frag[6]
+#define outerCol u.frag[7]
+#define scissorExt u.frag[8].xy
+#define scissorScale u.frag[8].zw
+#define extent u.frag[9].xy
+#define radius u.frag[9].z
+#define feather u.frag[9].w
+#define strokeMult u.frag[10].x
+#define strokeThr u.frag[10].y
+#define texType int(u.frag[10].z)
+#define glyphTextureType int(u.frag[11].x)
+#define imageBlurFilterDirection u.frag[11].yz
+#define imageBlurFilterSigma u.frag[11].w
+#define imageBlurFilterCoeff u.frag[12].xyz
+#define customUniforms0 u.frag[6]
+#define customUniforms1 u.frag[7]
+#define customAlpha u.frag[12].w
+#define pathGradient u.frag[13].x
+#define maskGamma u.frag[13].y
+#define maskContrast u.frag[13].z
+#define focalGradient u.frag[13].w
+#define texRect u.frag[14]
+#define gradientSpread u.frag[15].x
+#define imageBlurFilterKawase u.frag[15].y
+
+#define SHADER_TYPE_FillGradient 0
+#define SHADER_TYPE_FillImage 1
+#define SHADER_TYPE_Stencil 2
+#define SHADER_TYPE_FillImageGradient 3
+#define SHADER_TYPE_FilterImage 4
+#define SHADER_TYPE_FillColor 5
+#define SHADER_TYPE_TextureCopyUnclipped 6
+#define SHADER_TYPE_FillColorUnclipped 7
+#define SHADER_TYPE_FillCustom 8
+
+constant int shader_type [[function_constant(0)]];
+constant bool enable_glyph_texture [[function_constant(1)]];
+constant bool render_to_texture [[function_constant(2)]];
+
+struct Vertex {
+    float2 vertex;
+    float2 tcoord;
+};
+
+struct VertexOutput {
+    float4 position [[position]];
+    float2 ftcoord;
+    float2 fpos;
+};
+
+vertex VertexOutput vs_main(
+    const device Vertex* vertices [[buffer(0)]],
+    constant float2& viewSize [[buffer(1)]],
+    uint vid [[vertex_id]]
+) {
+    Vertex in = vertices[vid];
+    VertexOutput out;
+    out.ftcoord = in.tcoord;
+    out.fpos = in.vertex;
+    if (render_to_texture) {
+        out.position = float4(2.0 * in.vertex.x / viewSize.x - 1.0, 2.0 * in.vertex.y / viewSize.y - 1.0, 0, 1);
+    } else {
+        out.position = float4(2.0 * in.vertex.x / viewSize.x - 1.0, 1.0 - 2.0 * in.vertex.y / viewSize.y, 0, 1);
+    }
+    return out;
+}
+
+float sdroundrect(float2 pt, float2 ext, float rad) {
+    float2 ext2 = ext - float2(rad, rad);
+    float2 d = abs(pt) - ext2;
+    return min(max(d.x, d.y), 0.0) + length(max(d, 0.0)) - rad;
+}
+
+// Scissoring
+float scissorMask(float2 p, constant Uniforms& u) {
+    float2 sc = (abs((scissorMat * float3(p, 1.0)).xy) - scissorExt);
+    sc = float2(0.5, 0.5) - sc * scissorScale;
+    return clamp(sc.x, 0.0, 1.0) * clamp(sc.y, 0.0, 1.0);
+}
+
+// Strokes with a gradient along the path store the position along the path in v: the start cap fringe goes
+// from 0 to 1, the path from 1 to 2 and the end cap fringe from 2 to 3.
+float capMask(VertexOutput in, constant Uniforms& u) {
+    return pathGradient == 0.0 ? min(1.0, in.ftcoord.y) : min(1.0, min(in.ftcoord.y, 3.0 - in.ftcoord.y));
+}
+
+// Stroke - from [0..1] to clipped pyramid, where the slope is 1px.
+float strokeMask(VertexOutput in, constant Uniforms& u) {
+    return min(1.0, (1.0 - abs(in.ftcoord.x * 2.0 - 1.0)) * strokeMult) * capMask(in, u);
+}
+
+// Position a gradient is sampled at, before the paint transform
+float2 gradientPos(VertexOutput in, constant Uniforms& u) {
+    return pathGradient == 0.0 ? in.fpos : float2(clamp(in.ftcoord.y - 1.0, 0.0, 1.0), 0.0);
+}
+
+// Two-point radial gradient from the focal circle at the origin with `radius` to the circle at `extent` with
+// `feather` as its radius. Returns the gradient position of the largest circle through the point in x and 0 in y if
+// no circle with a positive radius passes through it.
+float2 focalGradientPos(float2 pt, constant Uniforms& u) {
+    float dr = feather - radius;
+    float a = dot(extent, extent) - dr * dr;
+    float b = dot(pt, extent) + radius * dr;
+    float c = dot(pt, pt) - radius * radius;
+
+    if (abs(a) < 1e-6) {
+        if (abs(b) < 1e-6) return float2(0.0);
+        float t = c / (2.0 * b);
+        return float2(t, radius + t * dr >= 0.0 ? 1.0 : 0.0);
+    }
+
+    float discriminant = b * b - a * c;
+    if (discriminant < 0.0) return float2(0.0);
+
+    float root = sqrt(discriminant);
+    float t0 = max((b + root) / a, (b - root) / a);
+    float t1 = min((b + root) / a, (b - root) / a);
+    if (radius + t0 * dr >= 0.0) return float2(t0, 1.0);
+    return float2(t1, radius + t1 * dr >= 0.0 ? 1.0 : 0.0);
+}
+
+// Maps a gradient position outside of 0 to 1 back into that range, depending on the spread mode
+float spreadGradient(float t, constant Uniforms& u) {
+    if (gradientSpread == 1.0) return fract(t);
+    if (gradientSpread == 2.0) return 1.0 - abs(t - 2.0 * floor(t * 0.5) - 1.0);
+    return clamp(t, 0.0, 1.0);
+}
+
+// Gradient position in x, coverage in y
+float2 gradientOffset(VertexOutput in, constant Uniforms& u) {
+    float2 pt = (paintMat * float3(gradientPos(in, u), 1.0)).xy;
+
+    if (focalGradient != 0.0) {
+        float2 focal = focalGradientPos(pt, u);
+        return float2(spreadGradient(focal.x, u), focal.y);
+    }
+
+    // Box gradient
+    return float2(spreadGradient((sdroundrect(pt, extent, radius) + feather * 0.5) / feather, u), 1.0);
+}
+
+float4 renderGradient(VertexOutput in, constant Uniforms& u) {
+    float2 d = gradientOffset(in, u);
+    return mix(innerCol, outerCol, d.x) * d.y;
+}
+
+// Image-based Gradient; sample a texture using the gradient position.
+float4 renderImageGradient(VertexOutput in, constant Uniforms& u, texture2d<float> tex, sampler smp) {
+    float2 d = gradientOffset(in, u);
+    return tex.sample(smp, float2(d.x, 0.0)) * d.y;
+}
+
+// Keeps the texture coordinates of images drawn from a source rectangle within it
+float2 clampToTexRect(float2 pt, constant Uniforms& u) {
+    return texRect.z > 0.0 ? clamp(pt, texRect.xy, texRect.zw) : pt;
+}
+
+// Applies the gamma and contrast of the text to the coverage of an alpha mask glyph
+float adjustMaskCoverage(float coverage, constant Uniforms& u) {
+    if (maskGamma > 0.0) {
+        coverage = pow(coverage, 1.0 / maskGamma);
+    }
+    return clamp((coverage - 0.5) * (1.0 + maskContrast) + 0.5, 0.0, 1.0);
+}
+
+float4 renderImage(VertexOutput in, constant Uniforms& u, texture2d<float> tex, sampler smp) {
+    // Calculate color from texture
+    float2 pt = clampToTexRect((paintMat * float3(in.fpos, 1.0)).xy / extent, u);
+
+    float4 color = tex.sample(smp, pt);
+
+    if (texType == 1) color = float4(color.xyz * color.w, color.w);
+    if (texType == 2) color = float4(color.x);
+
+    // Apply color tint and alpha.
+    color *= innerCol;
+    return color;
+}
+
+float4 renderPlainTextureCopy(VertexOutput in, constant Uniforms& u, texture2d<float> tex, sampler smp) {
+    float4 color = tex.sample(smp, clampToTexRect(in.ftcoord, u));
+
+    if (texType == 1) color = float4(color.xyz * color.w, color.w);
+    if (texType == 2) color = float4(color.x);
+    // Apply color tint and alpha.
+    color *= innerCol;
+    return color;
+}
+
+// One pass of a dual Kawase blur, imageBlurFilterDirection holds the offset of the samples in texture coordinates
+float4 renderKawaseFilteredImage(VertexOutput in, constant Uniforms& u, texture2d<float> tex, sampler smp) {
+    float2 uv = in.fpos / extent;
+    float2 o = imageBlurFilterDirection;
+    float4 color;
+
+    if (imageBlurFilterKawase == 1.0) {
+        // downsample
+        color = tex.sample(smp, uv) * 4.0;
+        color += tex.sample(smp, uv - o);
+        color += tex.sample(smp, uv + o);
+        color += tex.sample(smp, uv + float2(o.x, -o.y));
+        color += tex.sample(smp, uv - float2(o.x, -o.y));
+        color /= 8.0;
+    } else {
+        // upsample
+        color = tex.sample(smp, uv + float2(-o.x * 2.0, 0.0));
+        color += tex.sample(smp, uv + float2(o.x * 2.0, 0.0));
+        color += tex.sample(smp, uv + float2(0.0, -o.y * 2.0));
+        color += tex.sample(smp, uv + float2(0.0, o.y * 2.0));
+        color += tex.sample(smp, uv - o) * 2.0;
+        color += tex.sample(smp, uv + o) * 2.0;
+        color += tex.sample(smp, uv + float2(o.x, -o.y)) * 2.0;
+        color += tex.sample(smp, uv - float2(o.x, -o.y)) * 2.0;
+        color /= 12.0;
+    }
+
+    if (texType == 1) color = float4(color.xyz * color.w, color.w);
+    if (texType == 2) color = float4(color.x);
+
+    return color;
+}
+
+float4 renderFilteredImage(VertexOutput in, constant Uniforms& u, texture2d<float> tex, sampler smp) {
+    if (imageBlurFilterKawase != 0.0) return renderKawaseFilteredImage(in, u, tex, smp);
+
+    float sampleCount = ceil(1.5 * imageBlurFilterSigma);
+
+    float3 gaussian_coeff = imageBlurFilterCoeff;
+
+    float4 color_sum = tex.sample(smp, in.fpos / extent) * gaussian_coeff.x;
+    float coefficient_sum = gaussian_coeff.x;
+    gaussian_coeff.xy *= gaussian_coeff.yz;
+
+    for (float i = 1.0; i <= 12.0; i += 1.0) {
+        // Sigma has an upper bound of 8, imposed on the Rust side.
+        if (i >= sampleCount) {
+            break;
+        }
+        color_sum += tex.sample(smp, (in.fpos - i * imageBlurFilterDirection) / extent) * gaussian_coeff.x;
+        color_sum += tex.sample(smp, (in.fpos + i * imageBlurFilterDirection) / extent) * gaussian_coeff.x;
+        coefficient_sum += 2.0 * gaussian_coeff.x;
+
+        // Compute the coefficients incrementally:
+        // https://developer.nvidia.com/gpugems/gpugems3/part-vi-gpu-computing/chapter-40-incremental-computation-gaussian
+        gaussian_coeff.xy *= gaussian_coeff.yz;
+    }
+
+    float4 color = color_sum / coefficient_sum;
+
+    if (texType == 1) color = float4(color.xyz * color.w, color.w);
+    if (texType == 2) color = float4(color.x);
+
+    return color;
+}
+
+#ifdef CUSTOM_SHADER
+// Provided by the custom shader source, which is appended to this file
+float4 renderCustom(float2 pt, constant Uniforms& u);
+#endif
+
+fragment float4 fs_main(
+    VertexOutput in [[stage_in]],
+    constant Uniforms& u [[buffer(0)]],
+    texture2d<float> tex [[texture(0)]],
+    sampler smp [[sampler(0)]],
+    texture2d<float> glyphtex [[texture(1)]],
+    sampler glyphsmp [[sampler(1)]]
+) {
+    float4 result;
+
+    float strokeAlpha = 1.0;
+    if (shader_type != SHADER_TYPE_TextureCopyUnclipped && shader_type != SHADER_TYPE_FillColorUnclipped && shader_type != SHADER_TYPE_FilterImage) {
+        strokeAlpha = strokeMask(in, u);
+        if (strokeAlpha < strokeThr) {
+            discard_fragment();
+        }
+    }
+
+    switch (shader_type) {
+        case SHADER_TYPE_FillGradient:
+            // Gradient
+            result = renderGradient(in, u);
+            break;
+        case SHADER_TYPE_FillImageGradient:
+            // Image-based Gradient; sample a texture using the gradient position.
+            result = renderImageGradient(in, u, tex, smp);
+            break;
+        case SHADER_TYPE_FillImage:
+            // Image
+            result = renderImage(in, u, tex, smp);
+            break;
+        case SHADER_TYPE_FillColor:
+            // Plain color fill
+            result = innerCol;
+            break;
+        case SHADER_TYPE_TextureCopyUnclipped:
+            // Plain texture copy, unclipped
+            return renderPlainTextureCopy(in, u, tex, smp);
+        case SHADER_TYPE_Stencil:
+            // Stencil fill
+            result = float4(1, 1, 1, 1);
+            break;
+        case SHADER_TYPE_FilterImage:
+            // Filter Image
+            return renderFilteredImage(in, u, tex, smp);
+        case SHADER_TYPE_FillColorUnclipped:
+            // Plain color fill
+            return innerCol;
+        case SHADER_TYPE_FillCustom:
+#ifdef CUSTOM_SHADER
+            // Custom shader, evaluated in paint space
+            result = renderCustom((paintMat * float3(in.fpos, 1.0)).xy, u) * customAlpha;
+            break;
+#endif
+        default:
+            // Paints with a deleted custom shader are drawn with their first uniforms as color
+            result = innerCol;
+            break;
+    }
+
+    float scissor = scissorMask(in.fpos, u);
+
+    if (enable_glyph_texture) {
+        // Textured tris
+        float4 mask = glyphtex.sample(glyphsmp, in.ftcoord);
+
+        // 5 and 6 are images multiplied with the paint, with straight and premultiplied alpha
+        if (glyphTextureType == 1) {
+            mask = float4(adjustMaskCoverage(mask.x, u));
+        } else if (glyphTextureType == 5) {
+            mask = float4(mask.xyz * mask.w, mask.w);
+        } else if (glyphTextureType != 6) {
+            result = float4(1, 1, 1, 1);
+            mask = float4(mask.xyz * mask.w, mask.w);
+        }
+
+        mask *= scissor;
+        result *= mask;
+    } else if (shader_type != SHADER_TYPE_Stencil && shader_type != SHADER_TYPE_FilterImage) {
+        // Not stencil fill
+        // Combine alpha
+        result *= strokeAlpha * scissor;
+    }
+
+    return result;
+}
@@ -12,13 +12,16 @@ use rgb::RGBA8;
 
 use crate::{
     renderer::{GlyphTexture, ImageId, Vertex},
-    BlendFactor, Color, CompositeOperationState, ErrorKind, FillRule, ImageFilter, ImageInfo, ImageSource, ImageStore,
-    Scissor,
+    BlendFactor, Color, CompositeOperationState, ErrorKind, FillRule, ImageFilter, ImageFlags, ImageInfo, ImageSource,
+    ImageStore, ReadbackFormat, Scissor, ShaderId,
 };
 
 use glow::HasContext;
 
-use super::{Command, CommandType, Params, RenderTarget, Renderer, ShaderType, SurfacelessRenderer};
+use super::{
+    Command, CommandType, CompressedFormats, Params, RenderTarget, Renderer, RendererCapabilities, ShaderType,
+    SurfacelessRenderer,
+};
 
 mod program;
 use program::MainProgram;
@@ -26,6 +29,11 @@ use program::MainProgram;
 mod gl_texture;
 use gl_texture::GlTexture;
 
+#[cfg(all(feature = "glutin-window", not(target_arch = "wasm32")))]
+mod window;
+#[cfg(all(feature = "glutin-window", not(target_arch = "wasm32")))]
+pub use window::OpenGlSurface;
+
 mod framebuffer;
 use framebuffer::Framebuffer;
 
@@ -37,20 +45,41 @@ pub struct OpenGl {
     debug: bool,
     antialias: bool,
     is_opengles_2_0: bool,
+    // OpenGL ES 2.0 and WebGL 1 without extensions can't repeat or mipmap non-power-of-two textures
+    limited_npot_textures: bool,
+    capabilities: RendererCapabilities,
     view: [f32; 2],
     screen_view: [f32; 2],
     // All types of the vertex/fragment shader, indexed by shader_type when has_glyph_texture is true
     main_programs_with_glyph_texture: [Option<MainProgram>; 7],
     // Same shader programs but with has_glyph_texture being false
     main_programs_without_glyph_texture: [Option<MainProgram>; 7],
+    // Custom shader programs, without and with glyph texture
+    custom_programs: FnvHashMap<ShaderId, [MainProgram; 2]>,
     current_program: u8,
     current_program_needs_glyph_texture: bool,
+    current_custom_shader: Option<ShaderId>,
     vert_arr: Option<<glow::Context as glow::HasContext>::VertexArray>,
     vert_buff: Option<<glow::Context as glow::HasContext>::Buffer>,
-    framebuffers: FnvHashMap<ImageId, Result<Framebuffer, ErrorKind>>,
+    // Framebuffers of image render targets, by image and layer
+    framebuffers: FnvHashMap<(ImageId, usize), Result<Framebuffer, ErrorKind>>,
     context: Rc<glow::Context>,
     screen_target: Option<Framebuffer>,
     current_render_target: RenderTarget,
+    current_render_target_layer: usize,
+    // Samples of the default framebuffer, which is already multisampled if the context was created that way
+    screen_samples: u32,
+    requested_samples: u32,
+    sample_count: u32,
+    // Multisampled framebuffer the screen is drawn into, resolved into the screen target at the end of a render
+    msaa_target: Option<Framebuffer>,
+    msaa_size: (u32, u32),
+    msaa_dirty: bool,
+    // Halved temporary images of the last dual Kawase blur, reused while the size of the blurred images stays the same
+    blur_level_images: Vec<ImageId>,
+    // Canvas of a web worker, which has to be resized by the renderer
+    #[cfg(target_arch = "wasm32")]
+    offscreen_canvas: Option<web_sys::OffscreenCanvas>,
 }
 
 impl OpenGl {
@@ -93,23 +122,66 @@ impl OpenGl {
     }
 
     /// Creates a new OpenGL renderer from an HTML canvas element in a WASM32 target.
+    ///
+    /// A WebGL 2 context is used if available, WebGL 1 otherwise.
     #[cfg(target_arch = "wasm32")]
     pub fn new_from_html_canvas(canvas: &web_sys::HtmlCanvasElement) -> Result<Self, ErrorKind> {
+        Self::new_from_web_canvas(|context_id, attrs| {
+            canvas
+                .get_context_with_context_options(context_id, attrs)
+                .ok()
+                .flatten()
+                .map(Into::into)
+        })
+    }
+
+    /// Creates a new OpenGL renderer from an offscreen canvas in a WASM32 target, such as one that was
+    /// transferred to a web worker with `transferControlToOffscreen()`.
+    ///
+    /// A WebGL 2 context is used if available, WebGL 1 otherwise. The page can't resize a transferred canvas, so
+    /// [`Canvas::set_size()`](crate::Canvas::set_size) resizes its drawing buffer instead. Images can be uploaded
+    /// from an [`ImageSource::ImageBitmap`], as there are no image elements in workers.
+    #[cfg(target_arch = "wasm32")]
+    pub fn new_from_offscreen_canvas(canvas: &web_sys::OffscreenCanvas) -> Result<Self, ErrorKind> {
+        let mut renderer = Self::new_from_web_canvas(|context_id, attrs| {
+            canvas
+                .get_context_with_context_options(context_id, attrs)
+                .ok()
+                .flatten()
+                .map(Into::into)
+        })?;
+        renderer.offscreen_canvas = Some(canvas.clone());
+
+        Ok(renderer)
+    }
+
+    // Creates the renderer with a WebGL context from `get_context`, which is called with the context id and
+    // attributes like `getContext()` of a canvas.
+    #[cfg(target_arch = "wasm32")]
+    fn new_from_web_canvas(
+        get_context: impl Fn(&str, &wasm_bindgen::JsValue) -> Option<wasm_bindgen::JsValue>,
+    ) -> Result<Self, ErrorKind> {
         let attrs = web_sys::WebGlContextAttributes::new();
         attrs.set_stencil(true);
         attrs.set_antialias(false);
 
         use wasm_bindgen::JsCast;
-        let webgl2_context = match canvas.get_context_with_context_options("webgl2", &attrs) {
-            Ok(Some(context)) => context.dyn_into::<web_sys::WebGl2RenderingContext>().unwrap(),
-            _ => {
-                return Err(ErrorKind::GeneralError(
-                    "Canvas::getContext failed to retrieve WebGL 2 context".to_owned(),
-                ))
+        let context = match get_context("webgl2", &attrs) {
+            Some(context) => {
+                glow::Context::from_webgl2_context(context.dyn_into::<web_sys::WebGl2RenderingContext>().unwrap())
             }
+            _ => match get_context("webgl", &attrs) {
+                Some(context) => {
+                    glow::Context::from_webgl1_context(context.dyn_into::<web_sys::WebGlRenderingContext>().unwrap())
+                }
+                _ => {
+                    return Err(ErrorKind::GeneralError(
+                        "Canvas::getContext failed to retrieve WebGL context".to_owned(),
+                    ))
+                }
+            },
         };
 
-        let context = glow::Context::from_webgl2_context(webgl2_context);
         Self::new_from_context(context, true)
     }
 
@@ -117,8 +189,63 @@ impl OpenGl {
         let debug = cfg!(debug_assertions);
         let antialias = true;
 
+        // OpenGL ES 2.0 and WebGL 1 (reported as OpenGL ES 2.0 by glow) only have some features as extensions
+        let version = context.version();
+        let is_es2 = version.is_embedded && version.major < 3;
+        let extensions = context.supported_extensions();
+        let has_extension = |name: &str| extensions.contains(&format!("GL_{name}")) || extensions.contains(name);
+
+        let limited_npot_textures = is_es2 && !has_extension("OES_texture_npot");
+        let has_vertex_arrays = !is_es2 || has_extension("OES_vertex_array_object");
+        let screen_samples = unsafe { context.get_parameter_i32(glow::SAMPLES) }.max(1) as u32;
+
+        let mut compressed_formats = CompressedFormats::empty();
+        if has_extension("EXT_texture_compression_s3tc") || has_extension("WEBGL_compressed_texture_s3tc") {
+            compressed_formats |= CompressedFormats::BC;
+        }
+        if (version.is_embedded && version.major >= 3)
+            || has_extension("ARB_ES3_compatibility")
+            || has_extension("WEBGL_compressed_texture_etc")
+        {
+            compressed_formats |= CompressedFormats::ETC2;
+        }
+        if has_extension("KHR_texture_compression_astc_ldr") || has_extension("WEBGL_compressed_texture_astc") {
+            compressed_formats |= CompressedFormats::ASTC;
+        }
+
+        let capabilities = RendererCapabilities {
+            max_texture_size: unsafe { context.get_parameter_i32(glow::MAX_TEXTURE_SIZE) } as usize,
+            npot_textures: !limited_npot_textures,
+            instancing: (version.major, version.minor) >= (3, 1)
+                || (version.is_embedded && version.major >= 3)
+                || has_extension("ARB_instanced_arrays")
+                || has_extension("ANGLE_instanced_arrays")
+                || has_extension("EXT_instanced_arrays"),
+            advanced_blending: has_extension("KHR_blend_equation_advanced")
+                || has_extension("NV_blend_equation_advanced"),
+            float_render_targets: (!version.is_embedded && version.major >= 3)
+                || has_extension("EXT_color_buffer_float"),
+            compressed_formats,
+            // Glyph atlas texture arrays are sampled with GLSL ES 3.0 shaders
+            max_texture_array_layers: if (version.is_embedded && version.major >= 3)
+                || has_extension("ARB_ES3_compatibility")
+            {
+                unsafe { context.get_parameter_i32(glow::MAX_ARRAY_TEXTURE_LAYERS) }.max(1) as usize
+            } else {
+                1
+            },
+            // Multisampled renderbuffers and blitting need OpenGL (ES) 3
+            max_samples: if is_es2 {
+                screen_samples
+            } else {
+                (unsafe { context.get_parameter_i32(glow::MAX_SAMPLES) }.max(1) as u32).max(screen_samples)
+            },
+        };
+
         let context = Rc::new(context);
 
+        let texture_arrays = capabilities.max_texture_array_layers > 1;
+
         let generate_shader_program_variants = |with_glyph_texture| -> Result<_, ErrorKind> {
             Ok([
                 Some(MainProgram::new(
@@ -126,36 +253,52 @@ impl OpenGl {
                     antialias,
                     ShaderType::FillGradient,
                     with_glyph_texture,
+                    texture_arrays,
                 )?),
                 Some(MainProgram::new(
                     &context,
                     antialias,
                     ShaderType::FillImage,
                     with_glyph_texture,
+                    texture_arrays,
                 )?),
                 if with_glyph_texture {
                     // No stencil fill with glyph texture
                     None
                 } else {
-                    Some(MainProgram::new(&context, antialias, ShaderType::Stencil, false)?)
+                    Some(MainProgram::new(
+                        &context,
+                        antialias,
+                        ShaderType::Stencil,
+                        false,
+                        false,
+                    )?)
                 },
                 Some(MainProgram::new(
                     &context,
                     antialias,
                     ShaderType::FillImageGradient,
                     with_glyph_texture,
+                    texture_arrays,
                 )?),
                 if with_glyph_texture {
                     // Image filter is unrelated to glyph rendering
                     None
                 } else {
-                    Some(MainProgram::new(&context, antialias, ShaderType::FilterImage, false)?)
+                    Some(MainProgram::new(
+                        &context,
+                        antialias,
+                        ShaderType::FilterImage,
+                        false,
+                        false,
+                    )?)
                 },
                 Some(MainProgram::new(
                     &context,
                     antialias,
                     ShaderType::FillColor,
                     with_glyph_texture,
+                    texture_arrays,
                 )?),
                 if with_glyph_texture {
                     // Texture blitting is unrelated to glyph rendering
@@ -166,6 +309,7 @@ impl OpenGl {
                         antialias,
                         ShaderType::TextureCopyUnclipped,
                         false,
+                        texture_arrays,
                     )?)
                 },
             ])
@@ -178,30 +322,63 @@ impl OpenGl {
             debug,
             antialias,
             is_opengles_2_0: false,
+            limited_npot_textures,
+            capabilities,
             view: [0.0, 0.0],
             screen_view: [0.0, 0.0],
             main_programs_with_glyph_texture,
             main_programs_without_glyph_texture,
+            custom_programs: Default::default(),
             current_program: 0,
             current_program_needs_glyph_texture: true,
+            current_custom_shader: None,
             vert_arr: None,
             vert_buff: None,
             framebuffers: Default::default(),
             context,
             screen_target: None,
             current_render_target: RenderTarget::Screen,
+            current_render_target_layer: 0,
+            screen_samples,
+            requested_samples: 1,
+            sample_count: screen_samples,
+            msaa_target: None,
+            msaa_size: (0, 0),
+            msaa_dirty: false,
+            blur_level_images: Vec::new(),
+            #[cfg(target_arch = "wasm32")]
+            offscreen_canvas: None,
         };
 
         unsafe {
             opengl.is_opengles_2_0 = is_opengles_2_0;
 
-            opengl.vert_arr = opengl.context.create_vertex_array().ok();
+            // Without vertex array objects the attribute pointers are set up on every render
+            if has_vertex_arrays {
+                opengl.vert_arr = opengl.context.create_vertex_array().ok();
+            }
             opengl.vert_buff = opengl.context.create_buffer().ok();
         }
 
         Ok(opengl)
     }
 
+    /// Makes the renderer draw the screen with the requested number of samples per pixel, resolving them into
+    /// the screen target at the end of each render. Shapes drawn on a multisampled screen don't get an
+    /// anti-aliasing fringe.
+    ///
+    /// The count is clamped to what the context supports, [`Renderer::sample_count()`] returns the one that's
+    /// actually used. Contexts whose default framebuffer is already multisampled keep drawing to it directly.
+    /// Multisampling isn't available on OpenGL ES 2.0 and WebGL 1.
+    pub fn with_sample_count(mut self, samples: u32) -> Self {
+        self.requested_samples = samples;
+        if self.screen_samples <= 1 {
+            self.msaa_target = None;
+            self.sample_count = samples.clamp(1, self.capabilities.max_samples);
+        }
+        self
+    }
+
     /// Checks if the renderer is using OpenGL ES.
     pub fn is_opengles(&self) -> bool {
         self.is_opengles_2_0
@@ -457,22 +634,37 @@ impl OpenGl {
         glyph_tex: GlyphTexture,
     ) {
         self.select_main_program(paint);
-        let arr = UniformArray::from(paint);
+
+        // Texture arrays are bound to their own texture units
+        let is_array = |texture: &&GlTexture| texture.target() == glow::TEXTURE_2D_ARRAY;
+        let tex = image_tex.and_then(|id| images.get(id));
+        let tex_array = tex.filter(is_array);
+        let glyphtex = glyph_tex.image_id().and_then(|id| images.get(id));
+        let glyphtex_array = glyphtex.filter(is_array);
+
+        let arr = uniforms(paint, tex_array.is_some(), glyphtex_array.is_some());
         self.main_program().set_config(arr.as_slice());
         self.check_error("set_uniforms uniforms");
 
-        let tex = image_tex.and_then(|id| images.get(id)).map(GlTexture::id);
-
         unsafe {
             self.context.active_texture(glow::TEXTURE0);
-            self.context.bind_texture(glow::TEXTURE_2D, tex);
-        }
-
-        let glyphtex = glyph_tex.image_id().and_then(|id| images.get(id).map(GlTexture::id));
+            self.context
+                .bind_texture(glow::TEXTURE_2D, tex.filter(|_| tex_array.is_none()).map(GlTexture::id));
 
-        unsafe {
             self.context.active_texture(glow::TEXTURE0 + 1);
-            self.context.bind_texture(glow::TEXTURE_2D, glyphtex);
+            self.context.bind_texture(
+                glow::TEXTURE_2D,
+                glyphtex.filter(|_| glyphtex_array.is_none()).map(GlTexture::id),
+            );
+
+            if self.capabilities.max_texture_array_layers > 1 {
+                self.context.active_texture(glow::TEXTURE0 + 2);
+                self.context
+                    .bind_texture(glow::TEXTURE_2D_ARRAY, glyphtex_array.map(GlTexture::id));
+                self.context.active_texture(glow::TEXTURE0 + 3);
+                self.context
+                    .bind_texture(glow::TEXTURE_2D_ARRAY, tex_array.map(GlTexture::id));
+            }
         }
 
         self.check_error("set_uniforms texture");
@@ -494,27 +686,32 @@ impl OpenGl {
     }
 
     fn set_target(&mut self, images: &ImageStore<GlTexture>, target: RenderTarget) {
+        self.set_target_layer(images, target, 0);
+    }
+
+    fn set_target_layer(&mut self, images: &ImageStore<GlTexture>, target: RenderTarget, layer: usize) {
         self.current_render_target = target;
-        match (target, &self.screen_target) {
-            (RenderTarget::Screen, None) => unsafe {
-                Framebuffer::unbind(&self.context);
-                self.view = self.screen_view;
-                self.context.viewport(0, 0, self.view[0] as i32, self.view[1] as i32);
-            },
-            (RenderTarget::Screen, Some(framebuffer)) => {
-                framebuffer.bind();
+        self.current_render_target_layer = layer;
+        match target {
+            RenderTarget::Screen => {
+                if let Some(msaa_target) = &self.msaa_target {
+                    msaa_target.bind();
+                    self.msaa_dirty = true;
+                } else {
+                    self.bind_screen_target();
+                }
                 self.view = self.screen_view;
                 unsafe {
                     self.context.viewport(0, 0, self.view[0] as i32, self.view[1] as i32);
                 }
             }
-            (RenderTarget::Image(id), _) => {
+            RenderTarget::Image(id) => {
                 let context = self.context.clone();
                 if let Some(texture) = images.get(id) {
                     if let Ok(fb) = self
                         .framebuffers
-                        .entry(id)
-                        .or_insert_with(|| Framebuffer::new(&context, texture))
+                        .entry((id, layer))
+                        .or_insert_with(|| Framebuffer::new(&context, texture, layer))
                     {
                         fb.bind();
 
@@ -531,6 +728,46 @@ impl OpenGl {
         }
     }
 
+    fn bind_screen_target(&self) {
+        match &self.screen_target {
+            Some(framebuffer) => framebuffer.bind(),
+            None => Framebuffer::unbind(&self.context),
+        }
+    }
+
+    // (Re)creates the multisampled framebuffer when multisampling was requested but the screen isn't multisampled
+    fn resize_msaa_target(&mut self, width: u32, height: u32) {
+        if self.screen_samples > 1 || self.sample_count <= 1 {
+            return;
+        }
+
+        if self.msaa_target.is_some() && self.msaa_size == (width, height) {
+            return;
+        }
+
+        self.msaa_target = None;
+        self.msaa_size = (width, height);
+
+        let samples = self.requested_samples.clamp(1, self.capabilities.max_samples);
+
+        match Framebuffer::new_multisampled(&self.context, width.max(1), height.max(1), samples) {
+            Ok(framebuffer) => {
+                // The implementation may pick a higher sample count than requested
+                framebuffer.bind();
+                self.sample_count = unsafe { self.context.get_parameter_i32(glow::SAMPLES) }.max(1) as u32;
+                self.msaa_target = Some(framebuffer);
+            }
+            Err(err) => {
+                log::warn!("Multisampling disabled: {err}");
+                self.sample_count = 1;
+            }
+        }
+
+        if self.current_render_target == RenderTarget::Screen {
+            self.bind_screen_target();
+        }
+    }
+
     /// Make the "Screen" `RenderTarget` actually render to a framebuffer object. This is useful when
     /// embedding femtovg into another program where final composition is handled by an external task.
     /// The given `framebuffer_object` must refer to a Framebuffer Object created on the current OpenGL
@@ -545,16 +782,174 @@ impl OpenGl {
         }
     }
 
+    /// Like [`Self::set_screen_target()`], but for a Framebuffer Object that has no stencil attachment.
+    /// A stencil renderbuffer of the given size is created and attached to it, and detached again when
+    /// the screen target is changed. The Framebuffer Object itself stays owned by the caller.
+    pub fn set_screen_target_with_stencil(
+        &mut self,
+        framebuffer_object: <glow::Context as glow::HasContext>::Framebuffer,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ErrorKind> {
+        self.screen_target = None;
+        self.screen_target = Some(Framebuffer::from_external_with_stencil(
+            &self.context,
+            framebuffer_object,
+            width,
+            height,
+        )?);
+        Ok(())
+    }
+
+    /// Make the "Screen" `RenderTarget` render into an externally owned texture of the given size, for
+    /// example a render target of a frame graph that femtovg draws a UI layer into. A framebuffer object
+    /// with a stencil attachment is created for it, the texture itself is never deleted by femtovg.
+    ///
+    /// Call [`Canvas::set_size()`](crate::Canvas::set_size) with the size of the texture before drawing.
+    pub fn set_screen_target_texture(
+        &mut self,
+        texture: <glow::Context as glow::HasContext>::Texture,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ErrorKind> {
+        self.screen_target = None;
+        self.screen_target = Some(Framebuffer::from_texture(&self.context, texture, width, height)?);
+        Ok(())
+    }
+
     fn render_filtered_image(
         &mut self,
         images: &mut ImageStore<GlTexture>,
         cmd: Command,
         target_image: ImageId,
         filter: ImageFilter,
-    ) {
+    ) -> Result<(), ErrorKind> {
         match filter {
-            ImageFilter::GaussianBlur { sigma } => self.render_gaussian_blur(images, cmd, target_image, sigma),
+            ImageFilter::GaussianBlur { sigma } => {
+                self.render_gaussian_blur(images, cmd, target_image, sigma);
+                Ok(())
+            }
+            ImageFilter::FastBlur { radius } => self.render_dual_kawase_blur(images, cmd, target_image, radius),
+        }
+    }
+
+    fn render_dual_kawase_blur(
+        &mut self,
+        images: &mut ImageStore<GlTexture>,
+        mut cmd: Command,
+        target_image: ImageId,
+        radius: f32,
+    ) -> Result<(), ErrorKind> {
+        let original_render_target = self.current_render_target;
+        let source_image = cmd.image.ok_or(ErrorKind::ImageIdNotFound)?;
+        let source_image_info = images.info(source_image).ok_or(ErrorKind::ImageIdNotFound)?;
+        let target_image_info = images.info(target_image).ok_or(ErrorKind::ImageIdNotFound)?;
+        let size = (source_image_info.width(), source_image_info.height());
+
+        if target_image_info.size() != source_image_info.size() {
+            return Err(ErrorKind::UnsupportedImageSize);
+        }
+
+        // Without a radius the blur is a copy of the source image, which a single pass at no offset does
+        let (levels, offset) = if radius > 0.0 {
+            Params::dual_kawase_levels(radius, size.0, size.1)
+        } else {
+            (0, 0.0)
+        };
+
+        let image_paint = crate::Paint::image(source_image, 0., 0., size.0 as _, size.1 as _, 0., 1.);
+        let mut blur_params = Params::new(
+            images,
+            &Default::default(),
+            &image_paint.flavor,
+            &Default::default(),
+            &Scissor::default(),
+            0.,
+            0.,
+            0.,
+        );
+        blur_params.shader_type = ShaderType::FilterImage;
+
+        // The image is halved into a chain of temporary images and then scaled back up through them, with the last
+        // pass rendering into the target image.
+        let level_size = |level: usize| ((size.0 >> level).max(1), (size.1 >> level).max(1));
+        let mut level_images = vec![source_image];
+        level_images.extend(self.dual_kawase_level_images(images, &source_image_info, levels)?);
+
+        let downsample_passes = (1..=levels).map(|level| (level - 1, level, false));
+        let upsample_passes = (0..levels).rev().map(|level| (level + 1, level, true));
+        let copy_pass = (levels == 0).then_some((0, 0, false));
+
+        for (from, to, upsample) in downsample_passes.chain(upsample_passes).chain(copy_pass) {
+            let to_image = if to == 0 { target_image } else { level_images[to] };
+
+            blur_params.set_dual_kawase_pass(level_size(from), level_size(to), offset, upsample);
+
+            self.set_target(images, RenderTarget::Image(to_image));
+            self.main_program().set_view(self.view);
+
+            let (width, height) = level_size(to);
+            self.clear_rect(0, 0, width as _, height as _, Color::rgbaf(0., 0., 0., 0.));
+
+            cmd.image = Some(level_images[from]);
+            self.triangles(images, &cmd, &blur_params);
+
+            // only the source image may have straight alpha, the temporary images hold the premultiplied result
+            if blur_params.tex_type == 1.0 {
+                blur_params.tex_type = 0.0;
+            }
         }
+
+        // restore previous render target and view
+        self.set_target(images, original_render_target);
+        self.main_program().set_view(self.view);
+
+        Ok(())
+    }
+
+    // Returns the temporary images of the given number of halved levels of an image, allocating those that aren't
+    // cached yet.
+    fn dual_kawase_level_images(
+        &mut self,
+        images: &mut ImageStore<GlTexture>,
+        source_image_info: &ImageInfo,
+        levels: usize,
+    ) -> Result<Vec<ImageId>, ErrorKind> {
+        let mut level_images = mem::take(&mut self.blur_level_images);
+        let level_info = |level: usize| {
+            ImageInfo::new(
+                ImageFlags::PREMULTIPLIED,
+                (source_image_info.width() >> level).max(1),
+                (source_image_info.height() >> level).max(1),
+                source_image_info.format(),
+            )
+        };
+
+        // images of another size or format can't be reused
+        let reusable = level_images
+            .first()
+            .and_then(|&image| images.info(image))
+            .is_some_and(|info| info.size() == level_info(1).size() && info.format() == source_image_info.format());
+        if !reusable {
+            for image in level_images.drain(..) {
+                images.remove(self, image);
+            }
+        }
+
+        while level_images.len() < levels {
+            match images.alloc(self, level_info(level_images.len() + 1)) {
+                Ok(image) => level_images.push(image),
+                Err(error) => {
+                    self.blur_level_images = level_images;
+                    return Err(error);
+                }
+            }
+        }
+
+        self.blur_level_images = level_images.clone();
+        level_images.truncate(levels);
+
+        Ok(level_images)
     }
 
     fn render_gaussian_blur(
@@ -645,6 +1040,10 @@ impl OpenGl {
     }
 
     fn main_program(&self) -> &MainProgram {
+        if let Some(programs) = self.current_custom_shader.and_then(|id| self.custom_programs.get(&id)) {
+            return &programs[usize::from(self.current_program_needs_glyph_texture)];
+        }
+
         let programs = if self.current_program_needs_glyph_texture {
             &self.main_programs_with_glyph_texture
         } else {
@@ -656,8 +1055,17 @@ impl OpenGl {
     }
 
     fn select_main_program(&mut self, params: &Params) {
-        let program_index = params.shader_type.to_u8();
+        let custom_shader = params.custom_shader.filter(|id| self.custom_programs.contains_key(id));
+
+        // Paints with a deleted custom shader are drawn with their first uniforms as color
+        let program_index = if params.shader_type == ShaderType::FillCustom && custom_shader.is_none() {
+            ShaderType::FillColor.to_u8()
+        } else {
+            params.shader_type.to_u8()
+        };
+
         if program_index != self.current_program
+            || custom_shader != self.current_custom_shader
             || params.uses_glyph_texture() != self.current_program_needs_glyph_texture
         {
             unsafe {
@@ -670,12 +1078,15 @@ impl OpenGl {
             self.main_program().unbind();
             self.current_program = program_index;
             self.current_program_needs_glyph_texture = params.uses_glyph_texture();
+            self.current_custom_shader = custom_shader;
 
             let program = self.main_program();
             program.bind();
-            // Bind the two uniform samplers to texture units
+            // Bind the uniform samplers to texture units
             program.set_tex(0);
             program.set_glyphtex(1);
+            program.set_glyphtex_array(2);
+            program.set_tex_array(3);
             program.set_view(self.view);
         }
     }
@@ -687,16 +1098,35 @@ impl Renderer for OpenGl {
     type Surface = ();
 
     fn set_size(&mut self, width: u32, height: u32, _dpi: f32) {
+        // Resizing clears the drawing buffer, so only do it when the size changes
+        #[cfg(target_arch = "wasm32")]
+        if let Some(canvas) = &self.offscreen_canvas {
+            if (canvas.width(), canvas.height()) != (width, height) {
+                canvas.set_width(width);
+                canvas.set_height(height);
+            }
+        }
+
         self.view[0] = width as f32;
         self.view[1] = height as f32;
 
         self.screen_view = self.view;
 
+        self.resize_msaa_target(width, height);
+
         unsafe {
             self.context.viewport(0, 0, width as i32, height as i32);
         }
     }
 
+    fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    fn capabilities(&self) -> RendererCapabilities {
+        self.capabilities
+    }
+
     fn get_native_texture(&self, image: &Self::Image) -> Result<Self::NativeTexture, ErrorKind> {
         Ok(image.id())
     }
@@ -709,6 +1139,7 @@ impl Renderer for OpenGl {
         commands: Vec<Command>,
     ) {
         self.current_program = 0;
+        self.current_custom_shader = None;
         self.main_program().bind();
 
         unsafe {
@@ -727,8 +1158,16 @@ impl Renderer for OpenGl {
             self.context.bind_texture(glow::TEXTURE_2D, None);
             self.context.active_texture(glow::TEXTURE0 + 1);
             self.context.bind_texture(glow::TEXTURE_2D, None);
+            if self.capabilities.max_texture_array_layers > 1 {
+                self.context.active_texture(glow::TEXTURE0 + 2);
+                self.context.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+                self.context.active_texture(glow::TEXTURE0 + 3);
+                self.context.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+            }
 
-            self.context.bind_vertex_array(self.vert_arr);
+            if self.vert_arr.is_some() {
+                self.context.bind_vertex_array(self.vert_arr);
+            }
 
             let vertex_size = mem::size_of::<Vertex>();
 
@@ -751,6 +1190,12 @@ impl Renderer for OpenGl {
             );
         }
 
+        // The previous render left the resolved screen bound
+        if self.msaa_target.is_some() && self.current_render_target == RenderTarget::Screen {
+            self.set_target(images, RenderTarget::Screen);
+            self.main_program().set_view(self.view);
+        }
+
         self.check_error("render prepare");
 
         for cmd in commands {
@@ -778,11 +1223,13 @@ impl Renderer for OpenGl {
                     }
                 }
                 CommandType::SetRenderTarget(target) => {
-                    self.set_target(images, target);
+                    self.set_target_layer(images, target, cmd.target_layer);
                     self.main_program().set_view(self.view);
                 }
                 CommandType::RenderFilteredImage { target_image, filter } => {
-                    self.render_filtered_image(images, cmd, target_image, filter)
+                    if let Err(error) = self.render_filtered_image(images, cmd, target_image, filter) {
+                        log::error!("Failed to filter image: {error:?}");
+                    }
                 }
             }
         }
@@ -790,20 +1237,40 @@ impl Renderer for OpenGl {
         unsafe {
             self.context.disable_vertex_attrib_array(0);
             self.context.disable_vertex_attrib_array(1);
-            self.context.bind_vertex_array(None);
+
+            if self.vert_arr.is_some() {
+                self.context.bind_vertex_array(None);
+            }
 
             self.context.disable(glow::CULL_FACE);
             self.context.bind_buffer(glow::ARRAY_BUFFER, None);
             self.context.bind_texture(glow::TEXTURE_2D, None);
         }
 
+        if self.msaa_dirty {
+            if let Some(msaa_target) = &self.msaa_target {
+                msaa_target.blit(self.screen_target.as_ref(), self.msaa_size.0, self.msaa_size.1);
+            }
+            self.msaa_dirty = false;
+
+            // Leave the resolved screen bound, so that it can be read back with `screenshot()`
+            match self.current_render_target {
+                RenderTarget::Screen => self.bind_screen_target(),
+                target => self.set_target_layer(images, target, self.current_render_target_layer),
+            }
+        }
+
         self.main_program().unbind();
 
         self.check_error("render done");
     }
 
     fn alloc_image(&mut self, info: ImageInfo) -> Result<Self::Image, ErrorKind> {
-        Self::Image::new(&self.context, info, self.is_opengles_2_0)
+        if info.width() > self.capabilities.max_texture_size || info.height() > self.capabilities.max_texture_size {
+            return Err(ErrorKind::UnsupportedImageSize);
+        }
+
+        Self::Image::new(&self.context, info, self.is_opengles_2_0, self.limited_npot_textures)
     }
 
     fn create_image_from_native_texture(
@@ -821,14 +1288,46 @@ impl Renderer for OpenGl {
         x: usize,
         y: usize,
     ) -> Result<(), ErrorKind> {
-        image.update(&self.context, data, x, y, self.is_opengles_2_0)
+        image.update(&self.context, data, x, y, 0, self.is_opengles_2_0)
+    }
+
+    fn update_image_layer(
+        &mut self,
+        image: &mut Self::Image,
+        data: ImageSource,
+        x: usize,
+        y: usize,
+        layer: usize,
+    ) -> Result<(), ErrorKind> {
+        image.update(&self.context, data, x, y, layer, self.is_opengles_2_0)
     }
 
     fn delete_image(&mut self, image: Self::Image, image_id: ImageId) {
-        self.framebuffers.remove(&image_id);
+        self.framebuffers.retain(|&(id, _), _| id != image_id);
         image.delete(&self.context);
     }
 
+    fn create_shader(&mut self, id: ShaderId, source: &str) -> Result<(), ErrorKind> {
+        let programs = [
+            MainProgram::new_custom(&self.context, self.antialias, source, false, false)?,
+            MainProgram::new_custom(
+                &self.context,
+                self.antialias,
+                source,
+                true,
+                self.capabilities.max_texture_array_layers > 1,
+            )?,
+        ];
+
+        self.custom_programs.insert(id, programs);
+
+        Ok(())
+    }
+
+    fn delete_shader(&mut self, id: ShaderId) {
+        self.custom_programs.remove(&id);
+    }
+
     fn screenshot(&mut self) -> Result<ImgVec<RGBA8>, ErrorKind> {
         //let mut image = image::RgbaImage::new(self.view[0] as u32, self.view[1] as u32);
         let w = self.view[0] as usize;
@@ -868,6 +1367,40 @@ impl Renderer for OpenGl {
 
         Ok(ImgVec::new(flipped, w, h))
     }
+
+    fn screenshot_area(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        format: ReadbackFormat,
+        buffer: &mut [u8],
+    ) -> Result<(), ErrorKind> {
+        if x + width > self.view[0] as usize || y + height > self.view[1] as usize {
+            return Err(ErrorKind::ReadbackOutOfBounds);
+        }
+
+        // RGBA is the only format that reading back is guaranteed to support on OpenGL ES
+        let mut pixels = vec![RGBA8::default(); width * height];
+
+        unsafe {
+            self.context.read_pixels(
+                x as i32,
+                self.view[1] as i32 - (y + height) as i32,
+                width as i32,
+                height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(pixels.align_to_mut().1),
+            );
+        }
+
+        // Rows are read bottom to top
+        format.write_pixels(pixels.rchunks(width.max(1)).flatten().copied(), buffer);
+
+        Ok(())
+    }
 }
 
 impl SurfacelessRenderer for OpenGl {
@@ -891,3 +1424,56 @@ impl Drop for OpenGl {
         }
     }
 }
+
+// Returns the uniforms of a draw with `params`, telling the shader which of its textures are texture arrays.
+fn uniforms(params: &Params, image_texture_array: bool, glyph_texture_array: bool) -> UniformArray {
+    let mut arr = UniformArray::from(params);
+
+    if image_texture_array {
+        arr.set_image_texture_array(1.0);
+    }
+
+    if glyph_texture_array {
+        // Glyph texture types sampled from texture arrays follow the regular ones
+        arr.set_glyph_texture_type(f32::from(params.glyph_texture_type) + 2.0);
+    }
+
+    arr
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+    use crate::{renderer::Void, PaintFlavor, PixelFormat, Transform2D};
+
+    #[test]
+    fn texture_array_uniforms() {
+        let mut images = ImageStore::new();
+        let info = ImageInfo::new(ImageFlags::empty(), 4, 4, PixelFormat::Rgba8).with_layers(4);
+        let atlas = images.alloc(&mut Void, info).unwrap();
+
+        let params = Params::new(
+            &images,
+            &Transform2D::identity(),
+            &PaintFlavor::Color(Color::black()),
+            &GlyphTexture::AlphaMask(atlas, 3),
+            &Scissor::default(),
+            1.0,
+            1.0,
+            -1.0,
+        );
+
+        // The layer of the glyph atlas is passed along as a uniform, not in the texture coordinates
+        let arr = uniforms(&params, false, true);
+        let frag = arr.as_slice();
+        assert_eq!(frag[44], 3.0);
+        assert_eq!(frag[62], 3.0);
+        assert_eq!(frag[63], 0.0);
+
+        // Texture arrays drawn as images, like the glyph atlas in the debug overlay, are sampled from their own unit
+        let arr = uniforms(&params, true, false);
+        let frag = arr.as_slice();
+        assert_eq!(frag[44], 1.0);
+        assert_eq!(frag[63], 1.0);
+    }
+}
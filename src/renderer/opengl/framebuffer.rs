@@ -9,7 +9,11 @@ use super::GlTexture;
 pub struct Framebuffer {
     context: Rc<glow::Context>,
     fbo: <glow::Context as glow::HasContext>::Framebuffer,
+    // Only set for multisampled framebuffers, which don't render into a texture
+    color_rbo: Option<<glow::Context as glow::HasContext>::Renderbuffer>,
     stencil_rbo: Option<<glow::Context as glow::HasContext>::Renderbuffer>,
+    // External framebuffers are only borrowed and must not be deleted
+    owned: bool,
 }
 
 impl Framebuffer {
@@ -17,29 +21,200 @@ impl Framebuffer {
         Self {
             context: context.clone(),
             fbo,
+            color_rbo: None,
             stencil_rbo: None,
+            owned: false,
         }
     }
-    pub fn new(context: &Rc<glow::Context>, texture: &GlTexture) -> Result<Self, ErrorKind> {
-        let fbo = unsafe { context.create_framebuffer().unwrap() };
+
+    // Borrows an external framebuffer that has no stencil attachment and attaches one that is
+    // detached again on drop.
+    pub fn from_external_with_stencil(
+        context: &Rc<glow::Context>,
+        fbo: <glow::Context as glow::HasContext>::Framebuffer,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, ErrorKind> {
         unsafe {
             context.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
         }
 
+        let stencil_rbo = Self::attach_stencil(context, width, height);
+
+        unsafe {
+            context.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+
+        Ok(Self {
+            context: context.clone(),
+            fbo,
+            color_rbo: None,
+            stencil_rbo: Some(stencil_rbo?),
+            owned: false,
+        })
+    }
+
+    // Creates a framebuffer rendering into `texture`, or one of its layers if it's a texture array.
+    pub fn new(context: &Rc<glow::Context>, texture: &GlTexture, layer: usize) -> Result<Self, ErrorKind> {
         let width = texture.info().width() as u32;
         let height = texture.info().height() as u32;
 
-        unsafe {
+        if texture.target() == glow::TEXTURE_2D_ARRAY {
+            Self::with_color_attachment(context, width, height, |context| unsafe {
+                context.framebuffer_texture_layer(
+                    glow::FRAMEBUFFER,
+                    glow::COLOR_ATTACHMENT0,
+                    Some(texture.id()),
+                    0,
+                    layer as i32,
+                );
+            })
+        } else {
+            Self::from_texture(context, texture.id(), width, height)
+        }
+    }
+
+    // Creates a framebuffer with a stencil attachment that renders into `texture`, which stays owned
+    // by the caller.
+    pub fn from_texture(
+        context: &Rc<glow::Context>,
+        texture: <glow::Context as glow::HasContext>::Texture,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, ErrorKind> {
+        Self::with_color_attachment(context, width, height, |context| unsafe {
             context.framebuffer_texture_2d(
                 glow::FRAMEBUFFER,
                 glow::COLOR_ATTACHMENT0,
                 glow::TEXTURE_2D,
-                Some(texture.id()),
+                Some(texture),
                 0,
             );
+        })
+    }
+
+    // Creates a framebuffer with a stencil attachment, `attach_color` attaches the color buffer while it's bound.
+    fn with_color_attachment(
+        context: &Rc<glow::Context>,
+        width: u32,
+        height: u32,
+        attach_color: impl FnOnce(&glow::Context),
+    ) -> Result<Self, ErrorKind> {
+        let fbo = unsafe { context.create_framebuffer().map_err(ErrorKind::RenderTargetError)? };
+
+        // Deletes the framebuffer when dropped if attaching fails
+        let mut framebuffer = Self {
+            context: context.clone(),
+            fbo,
+            color_rbo: None,
+            stencil_rbo: None,
+            owned: true,
         };
 
-        let stencil_rbo = unsafe { context.create_renderbuffer().unwrap() };
+        unsafe {
+            context.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+        }
+
+        attach_color(context);
+
+        framebuffer.stencil_rbo = Some(Self::attach_stencil(context, width, height)?);
+
+        unsafe {
+            context.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+
+        Ok(framebuffer)
+    }
+
+    // Creates a framebuffer with multisampled color and stencil renderbuffers, which is resolved by blitting it
+    // into another framebuffer.
+    pub fn new_multisampled(
+        context: &Rc<glow::Context>,
+        width: u32,
+        height: u32,
+        samples: u32,
+    ) -> Result<Self, ErrorKind> {
+        let fbo = unsafe { context.create_framebuffer().map_err(ErrorKind::RenderTargetError)? };
+
+        // Deletes the framebuffer and the renderbuffers created so far when dropped on errors
+        let mut framebuffer = Self {
+            context: context.clone(),
+            fbo,
+            color_rbo: None,
+            stencil_rbo: None,
+            owned: true,
+        };
+
+        unsafe {
+            let color_rbo = context.create_renderbuffer().map_err(ErrorKind::RenderTargetError)?;
+            framebuffer.color_rbo = Some(color_rbo);
+            let stencil_rbo = context.create_renderbuffer().map_err(ErrorKind::RenderTargetError)?;
+            framebuffer.stencil_rbo = Some(stencil_rbo);
+
+            context.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+
+            for (rbo, format, attachment) in [
+                (color_rbo, glow::RGBA8, glow::COLOR_ATTACHMENT0),
+                (stencil_rbo, glow::STENCIL_INDEX8, glow::STENCIL_ATTACHMENT),
+            ] {
+                context.bind_renderbuffer(glow::RENDERBUFFER, Some(rbo));
+                context.renderbuffer_storage_multisample(
+                    glow::RENDERBUFFER,
+                    samples as i32,
+                    format,
+                    width as i32,
+                    height as i32,
+                );
+                context.framebuffer_renderbuffer(glow::FRAMEBUFFER, attachment, glow::RENDERBUFFER, Some(rbo));
+            }
+
+            context.bind_renderbuffer(glow::RENDERBUFFER, None);
+        }
+
+        let status = unsafe { context.check_framebuffer_status(glow::FRAMEBUFFER) };
+        Self::unbind(context);
+
+        if status != glow::FRAMEBUFFER_COMPLETE {
+            return Err(ErrorKind::RenderTargetError(format!(
+                "({status}) Multisampled framebuffer not complete"
+            )));
+        }
+
+        Ok(framebuffer)
+    }
+
+    // Copies the color buffer into `target`, or the default framebuffer if it's `None`, resolving the samples of
+    // a multisampled framebuffer.
+    pub fn blit(&self, target: Option<&Self>, width: u32, height: u32) {
+        unsafe {
+            // Blitting is affected by the scissor test and color mask
+            self.context.disable(glow::SCISSOR_TEST);
+            self.context.color_mask(true, true, true, true);
+            self.context.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.fbo));
+            self.context
+                .bind_framebuffer(glow::DRAW_FRAMEBUFFER, target.map(|target| target.fbo));
+            self.context.blit_framebuffer(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                0,
+                0,
+                width as i32,
+                height as i32,
+                glow::COLOR_BUFFER_BIT,
+                glow::NEAREST,
+            );
+        }
+    }
+
+    // Attaches a new stencil renderbuffer to the bound framebuffer and checks that it is complete.
+    fn attach_stencil(
+        context: &Rc<glow::Context>,
+        width: u32,
+        height: u32,
+    ) -> Result<<glow::Context as glow::HasContext>::Renderbuffer, ErrorKind> {
+        let stencil_rbo = unsafe { context.create_renderbuffer().map_err(ErrorKind::RenderTargetError)? };
         unsafe {
             context.bind_renderbuffer(glow::RENDERBUFFER, Some(stencil_rbo));
             context.renderbuffer_storage(glow::RENDERBUFFER, glow::STENCIL_INDEX8, width as i32, height as i32);
@@ -73,17 +248,15 @@ impl Framebuffer {
                     _ => format!("({status}) Framebuffer not complete!"),
                 };
 
+                context.framebuffer_renderbuffer(glow::FRAMEBUFFER, glow::STENCIL_ATTACHMENT, glow::RENDERBUFFER, None);
+                context.bind_framebuffer(glow::FRAMEBUFFER, None);
+                context.delete_renderbuffer(stencil_rbo);
+
                 return Err(ErrorKind::RenderTargetError(reason));
             }
-
-            context.bind_framebuffer(glow::FRAMEBUFFER, None);
         }
 
-        Ok(Self {
-            context: context.clone(),
-            fbo,
-            stencil_rbo: Some(stencil_rbo),
-        })
+        Ok(stencil_rbo)
     }
 
     pub fn bind(&self) {
@@ -127,10 +300,26 @@ impl Framebuffer {
 impl Drop for Framebuffer {
     fn drop(&mut self) {
         unsafe {
-            self.context.delete_framebuffer(self.fbo);
+            if self.owned {
+                self.context.delete_framebuffer(self.fbo);
+            } else if self.stencil_rbo.is_some() {
+                self.context.bind_framebuffer(glow::FRAMEBUFFER, Some(self.fbo));
+                self.context.framebuffer_renderbuffer(
+                    glow::FRAMEBUFFER,
+                    glow::STENCIL_ATTACHMENT,
+                    glow::RENDERBUFFER,
+                    None,
+                );
+                self.context.bind_framebuffer(glow::FRAMEBUFFER, None);
+            }
+
             if let Some(stencil_rbo) = self.stencil_rbo {
                 self.context.delete_renderbuffer(stencil_rbo);
             }
+
+            if let Some(color_rbo) = self.color_rbo {
+                self.context.delete_renderbuffer(color_rbo);
+            }
         }
     }
 }
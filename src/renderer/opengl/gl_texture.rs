@@ -7,6 +7,8 @@ use crate::{ErrorKind, ImageFlags, ImageInfo, ImageSource, PixelFormat};
 pub struct GlTexture {
     id: <glow::Context as glow::HasContext>::Texture,
     info: ImageInfo,
+    // TEXTURE_2D, or TEXTURE_2D_ARRAY for images with several layers
+    target: u32,
     owned: bool,
 }
 
@@ -15,15 +17,27 @@ impl GlTexture {
         Self {
             id: texture,
             info,
+            target: glow::TEXTURE_2D,
             owned: false,
         }
     }
-    pub fn new(context: &Rc<glow::Context>, info: ImageInfo, opengles_2_0: bool) -> Result<Self, ErrorKind> {
+    pub fn new(
+        context: &Rc<glow::Context>,
+        info: ImageInfo,
+        opengles_2_0: bool,
+        limited_npot: bool,
+    ) -> Result<Self, ErrorKind> {
         //let size = src.dimensions();
 
+        let target = if info.layers() > 1 {
+            glow::TEXTURE_2D_ARRAY
+        } else {
+            glow::TEXTURE_2D
+        };
+
         let id = unsafe {
-            let id = context.create_texture().unwrap();
-            context.bind_texture(glow::TEXTURE_2D, Some(id));
+            let id = context.create_texture().map_err(ErrorKind::GeneralError)?;
+            context.bind_texture(target, Some(id));
             context.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
             if !opengles_2_0 {
                 context.pixel_store_i32(glow::UNPACK_ROW_LENGTH, info.width() as i32);
@@ -33,9 +47,30 @@ impl GlTexture {
             id
         };
 
-        let texture = Self { id, info, owned: true };
+        let texture = Self {
+            id,
+            info,
+            target,
+            owned: true,
+        };
 
         match info.format() {
+            // Only glyph atlases have several layers, and they're always RGBA
+            PixelFormat::Rgba8 if info.layers() > 1 => unsafe {
+                context.tex_image_3d(
+                    target,
+                    0,
+                    glow::RGBA8 as i32,
+                    texture.info.width() as i32,
+                    texture.info.height() as i32,
+                    texture.info.layers() as i32,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    None,
+                );
+            },
+            _ if info.layers() > 1 => return Err(ErrorKind::UnsupportedImageFormat),
             PixelFormat::Gray8 => unsafe {
                 let internal_format = if opengles_2_0 { glow::LUMINANCE } else { glow::R8 };
                 let format = if opengles_2_0 { internal_format } else { glow::RED };
@@ -82,63 +117,61 @@ impl GlTexture {
             },
         }
 
-        let flags = texture.info.flags();
+        let mut flags = texture.info.flags();
+
+        // Non-power-of-two textures are incomplete with mipmaps or repeat wrapping without full NPOT
+        // support, so they are clamped and sampled without mipmaps instead
+        if limited_npot && !(info.width().is_power_of_two() && info.height().is_power_of_two()) {
+            flags.remove(ImageFlags::GENERATE_MIPMAPS | ImageFlags::REPEAT_X | ImageFlags::REPEAT_Y);
+        }
 
         if flags.contains(ImageFlags::GENERATE_MIPMAPS) {
             if flags.contains(ImageFlags::NEAREST) {
                 unsafe {
-                    context.tex_parameter_i32(
-                        glow::TEXTURE_2D,
-                        glow::TEXTURE_MIN_FILTER,
-                        glow::NEAREST_MIPMAP_NEAREST as i32,
-                    );
+                    context.tex_parameter_i32(target, glow::TEXTURE_MIN_FILTER, glow::NEAREST_MIPMAP_NEAREST as i32);
                 }
             } else {
                 unsafe {
-                    context.tex_parameter_i32(
-                        glow::TEXTURE_2D,
-                        glow::TEXTURE_MIN_FILTER,
-                        glow::LINEAR_MIPMAP_LINEAR as i32,
-                    );
+                    context.tex_parameter_i32(target, glow::TEXTURE_MIN_FILTER, glow::LINEAR_MIPMAP_LINEAR as i32);
                 }
             }
         } else if flags.contains(ImageFlags::NEAREST) {
             unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
+                context.tex_parameter_i32(target, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
             }
         } else {
             unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
+                context.tex_parameter_i32(target, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
             }
         }
 
         if flags.contains(ImageFlags::NEAREST) {
             unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
+                context.tex_parameter_i32(target, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
             }
         } else {
             unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
+                context.tex_parameter_i32(target, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
             }
         }
 
         if flags.contains(ImageFlags::REPEAT_X) {
             unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
+                context.tex_parameter_i32(target, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
             }
         } else {
             unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
+                context.tex_parameter_i32(target, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
             }
         }
 
         if flags.contains(ImageFlags::REPEAT_Y) {
             unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
+                context.tex_parameter_i32(target, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
             }
         } else {
             unsafe {
-                context.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+                context.tex_parameter_i32(target, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
             }
         }
 
@@ -153,13 +186,13 @@ impl GlTexture {
 
         if flags.contains(ImageFlags::GENERATE_MIPMAPS) {
             unsafe {
-                context.generate_mipmap(glow::TEXTURE_2D);
+                context.generate_mipmap(target);
                 //glow::TexParameteri(glow::TEXTURE_2D, glow::GENERATE_MIPMAP, glow::TRUE);
             }
         }
 
         unsafe {
-            context.bind_texture(glow::TEXTURE_2D, None);
+            context.bind_texture(target, None);
         }
 
         Ok(texture)
@@ -169,16 +202,29 @@ impl GlTexture {
         self.id
     }
 
+    pub fn target(&self) -> u32 {
+        self.target
+    }
+
     pub fn update(
         &mut self,
         context: &Rc<glow::Context>,
         src: ImageSource,
         x: usize,
         y: usize,
+        layer: usize,
         opengles_2_0: bool,
     ) -> Result<(), ErrorKind> {
         let size = src.dimensions();
 
+        if layer >= self.info.layers() {
+            return Err(ErrorKind::ImageUpdateOutOfBounds);
+        }
+
+        if self.target == glow::TEXTURE_2D_ARRAY {
+            return self.update_layer(context, src, x, y, layer);
+        }
+
         if x + size.width > self.info.width() {
             return Err(ErrorKind::ImageUpdateOutOfBounds);
         }
@@ -191,6 +237,12 @@ impl GlTexture {
             return Err(ErrorKind::ImageUpdateWithDifferentFormat);
         }
 
+        // the image store converts images of the image crate before they reach the renderer
+        #[cfg(feature = "image-loading")]
+        if let ImageSource::Dynamic(_) = src {
+            return Err(ErrorKind::UnsupportedImageFormat);
+        }
+
         unsafe {
             context.bind_texture(glow::TEXTURE_2D, Some(self.id));
             context.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
@@ -253,6 +305,20 @@ impl GlTexture {
                     image_element,
                 )
             },
+            #[cfg(target_arch = "wasm32")]
+            ImageSource::ImageBitmap(image_bitmap) => unsafe {
+                context.tex_sub_image_2d_with_image_bitmap(
+                    glow::TEXTURE_2D,
+                    0,
+                    x as i32,
+                    y as i32,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    image_bitmap,
+                )
+            },
+            #[cfg(feature = "image-loading")]
+            ImageSource::Dynamic(_) => unreachable!(),
         }
 
         if self.info.flags().contains(ImageFlags::GENERATE_MIPMAPS) {
@@ -275,6 +341,52 @@ impl GlTexture {
         Ok(())
     }
 
+    // Updates a layer of a texture array, which is only created for RGBA glyph atlases
+    fn update_layer(
+        &mut self,
+        context: &Rc<glow::Context>,
+        src: ImageSource,
+        x: usize,
+        y: usize,
+        layer: usize,
+    ) -> Result<(), ErrorKind> {
+        let size = src.dimensions();
+
+        if x + size.width > self.info.width() || y + size.height > self.info.height() {
+            return Err(ErrorKind::ImageUpdateOutOfBounds);
+        }
+
+        let ImageSource::Rgba(data) = src else {
+            return Err(ErrorKind::ImageUpdateWithDifferentFormat);
+        };
+
+        unsafe {
+            context.bind_texture(self.target, Some(self.id));
+            context.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+            context.pixel_store_i32(glow::UNPACK_ROW_LENGTH, data.stride() as i32);
+
+            context.tex_sub_image_3d(
+                self.target,
+                0,
+                x as i32,
+                y as i32,
+                layer as i32,
+                size.width as i32,
+                size.height as i32,
+                1,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(data.buf().align_to().1),
+            );
+
+            context.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
+            context.pixel_store_i32(glow::UNPACK_ROW_LENGTH, 0);
+            context.bind_texture(self.target, None);
+        }
+
+        Ok(())
+    }
+
     pub fn delete(self, context: &Rc<glow::Context>) {
         if self.owned {
             unsafe {
@@ -1,7 +1,7 @@
 
 precision highp float;
 
-#define UNIFORMARRAY_SIZE 14
+#define UNIFORMARRAY_SIZE 16
 
 uniform vec4 frag[UNIFORMARRAY_SIZE];
 
@@ -22,9 +22,26 @@ uniform vec4 frag[UNIFORMARRAY_SIZE];
 #define imageBlurFilterDirection frag[11].yz
 #define imageBlurFilterSigma frag[11].w
 #define imageBlurFilterCoeff frag[12].xyz
+#define customUniforms0 frag[6]
+#define customUniforms1 frag[7]
+#define customAlpha frag[12].w
+#define pathGradient frag[13].x
+#define maskGamma frag[13].y
+#define maskContrast frag[13].z
+#define focalGradient frag[13].w
+#define texRect frag[14]
+#define gradientSpread frag[15].x
+#define imageBlurFilterKawase frag[15].y
+#define glyphTextureLayer frag[15].z
+#define imageTextureArray frag[15].w
 
 uniform sampler2D tex;
 uniform sampler2D glyphtex;
+#ifdef TEXTURE_ARRAYS
+// Image paints sample the first layer of texture arrays, glyphs the layer of their glyph atlas
+uniform mediump sampler2DArray texArray;
+uniform mediump sampler2DArray glyphtexArray;
+#endif
 uniform vec2 viewSize;
 
 varying vec2 ftcoord;
@@ -37,6 +54,7 @@ varying vec2 fpos;
  #define SHADER_TYPE_FilterImage 4
  #define SHADER_TYPE_FillColor 5
  #define SHADER_TYPE_TextureCopyUnclipped 6
+ #define SHADER_TYPE_FillCustom 8
 
 float sdroundrect(vec2 pt, vec2 ext, float rad) {
     vec2 ext2 = ext - vec2(rad,rad);
@@ -52,37 +70,110 @@ float scissorMask(vec2 p) {
 }
 
 #ifdef EDGE_AA
+// Strokes with a gradient along the path store the position along the path in v: the start cap fringe goes
+// from 0 to 1, the path from 1 to 2 and the end cap fringe from 2 to 3.
+float capMask() {
+    return pathGradient == 0.0 ? min(1.0, ftcoord.y) : min(1.0, min(ftcoord.y, 3.0 - ftcoord.y));
+}
+
 // Stroke - from [0..1] to clipped pyramid, where the slope is 1px.
 float strokeMask() {
-    return min(1.0, (1.0-abs(ftcoord.x*2.0-1.0))*strokeMult) * min(1.0, ftcoord.y);
+    return min(1.0, (1.0-abs(ftcoord.x*2.0-1.0))*strokeMult) * capMask();
     // Using this smoothstep preduces maybe better results when combined with fringe_width of 2, but it may look blurrier
     // maybe this should be controlled via flag
     //return smoothstep(0.0, 1.0, (1.0-abs(ftcoord.x*2.0-1.0))*strokeMult) * smoothstep(0.0, 1.0, ftcoord.y);
 }
 #endif
 
-vec4 renderGradient() {
-    // Calculate gradient color using box gradient
-    vec2 pt = (paintMat * vec3(fpos, 1.0)).xy;
+// Position a gradient is sampled at, before the paint transform
+vec2 gradientPos() {
+    return pathGradient == 0.0 ? fpos : vec2(clamp(ftcoord.y - 1.0, 0.0, 1.0), 0.0);
+}
+
+// Two-point radial gradient from the focal circle at the origin with `radius` to the circle at `extent` with
+// `feather` as its radius. Returns the gradient position of the largest circle through the point in x and 0 in y if
+// no circle with a positive radius passes through it.
+vec2 focalGradientPos(vec2 pt) {
+    float dr = feather - radius;
+    float a = dot(extent, extent) - dr * dr;
+    float b = dot(pt, extent) + radius * dr;
+    float c = dot(pt, pt) - radius * radius;
+
+    if (abs(a) < 1e-6) {
+        if (abs(b) < 1e-6) return vec2(0.0);
+        float t = c / (2.0 * b);
+        return vec2(t, radius + t * dr >= 0.0 ? 1.0 : 0.0);
+    }
+
+    float discriminant = b * b - a * c;
+    if (discriminant < 0.0) return vec2(0.0);
+
+    float root = sqrt(discriminant);
+    float t0 = max((b + root) / a, (b - root) / a);
+    float t1 = min((b + root) / a, (b - root) / a);
+    if (radius + t0 * dr >= 0.0) return vec2(t0, 1.0);
+    return vec2(t1, radius + t1 * dr >= 0.0 ? 1.0 : 0.0);
+}
+
+// Maps a gradient position outside of 0 to 1 back into that range, depending on the spread mode
+float spreadGradient(float t) {
+    if (gradientSpread == 1.0) return fract(t);
+    if (gradientSpread == 2.0) return 1.0 - abs(mod(t, 2.0) - 1.0);
+    return clamp(t, 0.0, 1.0);
+}
+
+// Gradient position in x, coverage in y
+vec2 gradientOffset() {
+    vec2 pt = (paintMat * vec3(gradientPos(), 1.0)).xy;
+
+    if (focalGradient != 0.0) {
+        vec2 focal = focalGradientPos(pt);
+        return vec2(spreadGradient(focal.x), focal.y);
+    }
 
-    float d = clamp((sdroundrect(pt, extent, radius) + feather*0.5) / feather, 0.0, 1.0);
-    return mix(innerCol,outerCol,d);
+    // Box gradient
+    return vec2(spreadGradient((sdroundrect(pt, extent, radius) + feather*0.5) / feather), 1.0);
+}
+
+vec4 renderGradient() {
+    vec2 d = gradientOffset();
+    return mix(innerCol,outerCol,d.x) * d.y;
 }
 
 // Image-based Gradient; sample a texture using the gradient position.
 vec4 renderImageGradient() {
-    // Calculate gradient color using box gradient
-    vec2 pt = (paintMat * vec3(fpos, 1.0)).xy;
+    vec2 d = gradientOffset();
+    return texture2D(tex, vec2(d.x, 0.0)) * d.y;
+}
+
+// Keeps the texture coordinates of images drawn from a source rectangle within it
+vec2 clampToTexRect(vec2 pt) {
+    return texRect.z > 0.0 ? clamp(pt, texRect.xy, texRect.zw) : pt;
+}
+
+// Applies the gamma and contrast of the text to the coverage of an alpha mask glyph
+float adjustMaskCoverage(float coverage) {
+    if (maskGamma > 0.0) {
+        coverage = pow(coverage, 1.0 / maskGamma);
+    }
+    return clamp((coverage - 0.5) * (1.0 + maskContrast) + 0.5, 0.0, 1.0);
+}
 
-    float d = clamp((sdroundrect(pt, extent, radius) + feather*0.5) / feather, 0.0, 1.0);
-    return texture2D(tex, vec2(d, 0.0));//mix(innerCol,outerCol,d);
+// Samples the image of image paints, which is either a texture or a texture array
+vec4 sampleImage(vec2 pt) {
+#ifdef TEXTURE_ARRAYS
+    if (imageTextureArray != 0.0) {
+        return texture(texArray, vec3(pt, 0.0));
+    }
+#endif
+    return texture2D(tex, pt);
 }
 
 vec4 renderImage() {
     // Calculate color from texture
-    vec2 pt = (paintMat * vec3(fpos, 1.0)).xy / extent;
+    vec2 pt = clampToTexRect((paintMat * vec3(fpos, 1.0)).xy / extent);
 
-    vec4 color = texture2D(tex, pt);
+    vec4 color = sampleImage(pt);
 
     if (texType == 1) color = vec4(color.xyz * color.w, color.w);
     if (texType == 2) color = vec4(color.x);
@@ -93,7 +184,7 @@ vec4 renderImage() {
 }
 
 vec4 renderPlainTextureCopy() {
-    vec4 color = texture2D(tex, ftcoord);
+    vec4 color = sampleImage(clampToTexRect(ftcoord));
     if (texType == 1) color = vec4(color.xyz * color.w, color.w);
     if (texType == 2) color = vec4(color.x);
     // Apply color tint and alpha.
@@ -101,7 +192,47 @@ vec4 renderPlainTextureCopy() {
     return color;
 }
 
+#if SELECT_SHADER == SHADER_TYPE_FillCustom
+// Provided by the custom shader source, which is appended to this file
+vec4 renderCustom(vec2 pt);
+#endif
+
+// One pass of a dual Kawase blur, imageBlurFilterDirection holds the offset of the samples in texture coordinates
+vec4 renderKawaseFilteredImage() {
+    vec2 uv = fpos.xy / extent;
+    vec2 o = imageBlurFilterDirection;
+    vec4 color;
+
+    if (imageBlurFilterKawase == 1.0) {
+        // downsample
+        color = texture2D(tex, uv) * 4.0;
+        color += texture2D(tex, uv - o);
+        color += texture2D(tex, uv + o);
+        color += texture2D(tex, uv + vec2(o.x, -o.y));
+        color += texture2D(tex, uv - vec2(o.x, -o.y));
+        color /= 8.0;
+    } else {
+        // upsample
+        color = texture2D(tex, uv + vec2(-o.x * 2.0, 0.0));
+        color += texture2D(tex, uv + vec2(o.x * 2.0, 0.0));
+        color += texture2D(tex, uv + vec2(0.0, -o.y * 2.0));
+        color += texture2D(tex, uv + vec2(0.0, o.y * 2.0));
+        color += texture2D(tex, uv - o) * 2.0;
+        color += texture2D(tex, uv + o) * 2.0;
+        color += texture2D(tex, uv + vec2(o.x, -o.y)) * 2.0;
+        color += texture2D(tex, uv - vec2(o.x, -o.y)) * 2.0;
+        color /= 12.0;
+    }
+
+    if (texType == 1) color = vec4(color.xyz * color.w, color.w);
+    if (texType == 2) color = vec4(color.x);
+
+    return color;
+}
+
 vec4 renderFilteredImage() {
+    if (imageBlurFilterKawase != 0.0) return renderKawaseFilteredImage();
+
     float sampleCount = ceil(1.5 * imageBlurFilterSigma);
 
     vec3 gaussian_coeff = imageBlurFilterCoeff;
@@ -168,6 +299,9 @@ void main(void) {
 #elif SELECT_SHADER == SHADER_TYPE_FilterImage
     // Filter Image
     result = renderFilteredImage();
+#elif SELECT_SHADER == SHADER_TYPE_FillCustom
+    // Custom shader, evaluated in paint space
+    result = renderCustom((paintMat * vec3(fpos, 1.0)).xy) * customAlpha;
 #else
 #error A shader variant must be selected with the SELECT_SHADER pre-processor variable
 #endif
@@ -176,11 +310,24 @@ void main(void) {
 
 #ifdef ENABLE_GLYPH_TEXTURE
     // Textured tris
+#ifdef TEXTURE_ARRAYS
+    vec4 mask;
+    if (glyphTextureType == 3 || glyphTextureType == 4) {
+        mask = texture(glyphtexArray, vec3(ftcoord, glyphTextureLayer));
+    } else {
+        mask = texture2D(glyphtex, ftcoord);
+    }
+#else
     vec4 mask = texture2D(glyphtex, ftcoord);
+#endif
 
-    if (glyphTextureType == 1) {
-        mask = vec4(mask.x);
-    } else {
+    // 1 and 3 are alpha masks, 2 and 4 color textures, the latter of each sampled from a texture array.
+    // 5 and 6 are images multiplied with the paint, with straight and premultiplied alpha.
+    if (glyphTextureType == 1 || glyphTextureType == 3) {
+        mask = vec4(adjustMaskCoverage(mask.x));
+    } else if (glyphTextureType == 5) {
+        mask = vec4(mask.xyz * mask.w, mask.w);
+    } else if (glyphTextureType != 6) {
         result = vec4(1, 1, 1, 1);
         mask = vec4(mask.xyz * mask.w, mask.w);
     }
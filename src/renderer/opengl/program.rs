@@ -6,6 +6,12 @@ use crate::{renderer::ShaderType, ErrorKind};
 
 const GLSL_VERSION: &str = "#version 100";
 
+// Programs sampling texture arrays are compiled as GLSL ES 3.0, with the GLSL ES 1.0 keywords of the shader sources
+// mapped to their replacements
+const GLSL_ES3_VERSION: &str = "#version 300 es";
+const GLSL_ES3_VERTEX_DEFS: &str = "#define attribute in\n#define varying out";
+const GLSL_ES3_FRAGMENT_DEFS: &str = "#define varying in\n#define texture2D texture\nout highp vec4 fragColor;\n#define gl_FragColor fragColor\n#define TEXTURE_ARRAYS 1";
+
 pub struct Shader {
     context: Rc<glow::Context>,
     id: <glow::Context as glow::HasContext>::Shader,
@@ -135,6 +141,8 @@ pub struct MainProgram {
     loc_viewsize: <glow::Context as glow::HasContext>::UniformLocation,
     loc_tex: Option<<glow::Context as glow::HasContext>::UniformLocation>,
     loc_glyphtex: Option<<glow::Context as glow::HasContext>::UniformLocation>,
+    loc_tex_array: Option<<glow::Context as glow::HasContext>::UniformLocation>,
+    loc_glyphtex_array: Option<<glow::Context as glow::HasContext>::UniformLocation>,
     loc_frag: Option<<glow::Context as glow::HasContext>::UniformLocation>,
 }
 
@@ -144,6 +152,36 @@ impl MainProgram {
         antialias: bool,
         shader_type: ShaderType,
         with_glyph_texture: bool,
+        texture_arrays: bool,
+    ) -> Result<Self, ErrorKind> {
+        Self::with_source(context, antialias, shader_type, with_glyph_texture, texture_arrays, "")
+    }
+
+    // Creates a program for a custom shader, `source` must define `vec4 renderCustom(vec2 pt)`.
+    pub(crate) fn new_custom(
+        context: &Rc<glow::Context>,
+        antialias: bool,
+        source: &str,
+        with_glyph_texture: bool,
+        texture_arrays: bool,
+    ) -> Result<Self, ErrorKind> {
+        Self::with_source(
+            context,
+            antialias,
+            ShaderType::FillCustom,
+            with_glyph_texture,
+            texture_arrays,
+            source,
+        )
+    }
+
+    fn with_source(
+        context: &Rc<glow::Context>,
+        antialias: bool,
+        shader_type: ShaderType,
+        with_glyph_texture: bool,
+        texture_arrays: bool,
+        custom_source: &str,
     ) -> Result<Self, ErrorKind> {
         let shader_defs = if antialias { "#define EDGE_AA 1" } else { "" };
         let select_shader_type = format!(
@@ -155,13 +193,26 @@ impl MainProgram {
                 ""
             }
         );
-        let vert_shader_src = format!("{}\n{}\n{}", GLSL_VERSION, shader_defs, include_str!("main-vs.glsl"));
-        let frag_shader_src = format!(
+        let (version, vert_defs, frag_defs) = if texture_arrays {
+            (GLSL_ES3_VERSION, GLSL_ES3_VERTEX_DEFS, GLSL_ES3_FRAGMENT_DEFS)
+        } else {
+            (GLSL_VERSION, "", "")
+        };
+        let vert_shader_src = format!(
             "{}\n{}\n{}\n{}",
-            GLSL_VERSION,
+            version,
+            vert_defs,
+            shader_defs,
+            include_str!("main-vs.glsl")
+        );
+        let frag_shader_src = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            version,
+            frag_defs,
             shader_defs,
             select_shader_type,
-            include_str!("main-fs.glsl")
+            include_str!("main-fs.glsl"),
+            custom_source
         );
 
         let vert_shader = Shader::new(context, &vert_shader_src, glow::VERTEX_SHADER)?;
@@ -172,6 +223,8 @@ impl MainProgram {
         let loc_viewsize = program.uniform_location("viewSize").unwrap();
         let loc_tex = program.uniform_location("tex");
         let loc_glyphtex = program.uniform_location("glyphtex");
+        let loc_tex_array = program.uniform_location("texArray");
+        let loc_glyphtex_array = program.uniform_location("glyphtexArray");
         let loc_frag = program.uniform_location("frag");
 
         Ok(Self {
@@ -180,6 +233,8 @@ impl MainProgram {
             loc_viewsize,
             loc_tex,
             loc_glyphtex,
+            loc_tex_array,
+            loc_glyphtex_array,
             loc_frag,
         })
     }
@@ -196,6 +251,18 @@ impl MainProgram {
         }
     }
 
+    pub(crate) fn set_tex_array(&self, tex: i32) {
+        unsafe {
+            self.context.uniform_1_i32(self.loc_tex_array.as_ref(), tex);
+        }
+    }
+
+    pub(crate) fn set_glyphtex_array(&self, tex: i32) {
+        unsafe {
+            self.context.uniform_1_i32(self.loc_glyphtex_array.as_ref(), tex);
+        }
+    }
+
     pub(crate) fn set_view(&self, view: [f32; 2]) {
         unsafe {
             self.context.uniform_2_f32_slice(Some(&self.loc_viewsize), &view);
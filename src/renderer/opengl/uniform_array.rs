@@ -1,16 +1,12 @@
 use super::Params;
 
-const UNIFORMARRAY_SIZE: usize = 14;
+const UNIFORMARRAY_SIZE: usize = 16;
 
 pub struct UniformArray([f32; UNIFORMARRAY_SIZE * 4]);
 
 impl Default for UniformArray {
     fn default() -> Self {
-        Self([
-            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
-            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
-            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
-        ])
+        Self([0.0; UNIFORMARRAY_SIZE * 4])
     }
 }
 
@@ -86,6 +82,46 @@ impl UniformArray {
     pub fn set_image_blur_filter_coeff(&mut self, coeff: [f32; 3]) {
         self.0[48..51].copy_from_slice(&coeff);
     }
+
+    pub fn set_custom_alpha(&mut self, alpha: f32) {
+        self.0[51] = alpha;
+    }
+
+    pub fn set_path_gradient(&mut self, path_gradient: f32) {
+        self.0[52] = path_gradient;
+    }
+
+    pub fn set_mask_gamma(&mut self, gamma: f32) {
+        self.0[53] = gamma;
+    }
+
+    pub fn set_mask_contrast(&mut self, contrast: f32) {
+        self.0[54] = contrast;
+    }
+
+    pub fn set_focal_gradient(&mut self, focal_gradient: f32) {
+        self.0[55] = focal_gradient;
+    }
+
+    pub fn set_tex_rect(&mut self, rect: [f32; 4]) {
+        self.0[56..60].copy_from_slice(&rect);
+    }
+
+    pub fn set_gradient_spread(&mut self, spread: f32) {
+        self.0[60] = spread;
+    }
+
+    pub fn set_image_blur_filter_kawase(&mut self, kawase: f32) {
+        self.0[61] = kawase;
+    }
+
+    pub fn set_glyph_texture_layer(&mut self, layer: f32) {
+        self.0[62] = layer;
+    }
+
+    pub fn set_image_texture_array(&mut self, texture_array: f32) {
+        self.0[63] = texture_array;
+    }
 }
 
 impl From<&Params> for UniformArray {
@@ -109,6 +145,15 @@ impl From<&Params> for UniformArray {
         arr.set_image_blur_filter_direction(params.image_blur_filter_direction);
         arr.set_image_blur_filter_sigma(params.image_blur_filter_sigma);
         arr.set_image_blur_filter_coeff(params.image_blur_filter_coeff);
+        arr.set_custom_alpha(params.custom_alpha);
+        arr.set_path_gradient(params.path_gradient);
+        arr.set_mask_gamma(params.mask_gamma);
+        arr.set_mask_contrast(params.mask_contrast);
+        arr.set_focal_gradient(params.focal_gradient);
+        arr.set_tex_rect(params.tex_rect);
+        arr.set_gradient_spread(params.gradient_spread);
+        arr.set_image_blur_filter_kawase(params.image_blur_filter_kawase);
+        arr.set_glyph_texture_layer(params.glyph_texture_layer);
 
         arr
     }
@@ -0,0 +1,270 @@
+use std::{num::NonZeroU32, ptr};
+
+use glutin::{
+    config::{Config, ConfigTemplateBuilder, GlConfig},
+    context::{ContextApi, ContextAttributesBuilder, NotCurrentGlContext, PossiblyCurrentContext},
+    display::{Display, DisplayApiPreference, GlDisplay},
+    surface::{GlSurface, Surface, SurfaceAttributesBuilder, WindowSurface},
+};
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle};
+use raw_window_handle_05 as rwh_05;
+
+use crate::{Canvas, ErrorKind};
+
+use super::OpenGl;
+
+/// The OpenGL context and window surface of a canvas created with [`OpenGl::new_from_window()`].
+pub struct OpenGlSurface {
+    context: PossiblyCurrentContext,
+    surface: Surface<WindowSurface>,
+}
+
+impl OpenGlSurface {
+    /// Returns the OpenGL context, which is current on the thread the surface was created on.
+    pub fn context(&self) -> &PossiblyCurrentContext {
+        &self.context
+    }
+
+    /// Returns the window surface, to set its swap interval for example.
+    pub fn surface(&self) -> &Surface<WindowSurface> {
+        &self.surface
+    }
+
+    /// Resizes the surface, call this along with [`Canvas::set_size()`].
+    pub fn resize(&self, width: u32, height: u32) {
+        self.surface.resize(&self.context, non_zero(width), non_zero(height));
+    }
+
+    /// Renders the commands of the canvas to the back buffer of the window and swaps it to the front.
+    pub fn present(&self, canvas: &mut Canvas<OpenGl>) -> Result<(), ErrorKind> {
+        canvas.flush_to_surface(&());
+
+        self.surface.swap_buffers(&self.context).map_err(glutin_error)
+    }
+}
+
+impl OpenGl {
+    /// Creates an OpenGL context and surface for a window and a canvas of the given size that renders to it.
+    ///
+    /// The window is anything implementing the `HasWindowHandle` and `HasDisplayHandle` traits of
+    /// [raw-window-handle](https://docs.rs/raw-window-handle) 0.6, like a `winit::window::Window`.
+    ///
+    /// The context is made current on the calling thread. CGL is used on macOS, WGL (falling back to EGL) on
+    /// Windows and EGL everywhere else. Requires the `glutin-window` feature.
+    ///
+    /// # Safety
+    ///
+    /// The window must outlive the returned surface.
+    pub unsafe fn new_from_window(
+        window: &(impl HasWindowHandle + HasDisplayHandle),
+        width: u32,
+        height: u32,
+        dpi: f32,
+    ) -> Result<(Canvas<Self>, OpenGlSurface), ErrorKind> {
+        let raw_window_handle = window_handle(window)?;
+        let raw_display_handle = display_handle(window)?;
+
+        #[cfg(target_os = "macos")]
+        let preference = DisplayApiPreference::Cgl;
+        #[cfg(windows)]
+        let preference = DisplayApiPreference::WglThenEgl(Some(raw_window_handle));
+        #[cfg(not(any(target_os = "macos", windows)))]
+        let preference = DisplayApiPreference::Egl;
+
+        let display = Display::new(raw_display_handle, preference).map_err(glutin_error)?;
+
+        // The canvas antialiases on its own and needs a stencil buffer for filling paths
+        let template = ConfigTemplateBuilder::new()
+            .with_alpha_size(8)
+            .with_stencil_size(8)
+            .compatible_with_native_window(raw_window_handle)
+            .build();
+        let config = display
+            .find_configs(template)
+            .map_err(glutin_error)?
+            .reduce(|best, config: Config| {
+                if config.num_samples() < best.num_samples() {
+                    config
+                } else {
+                    best
+                }
+            })
+            .ok_or_else(|| ErrorKind::GeneralError("No suitable OpenGL config found".to_owned()))?;
+
+        let context_attributes = ContextAttributesBuilder::new().build(Some(raw_window_handle));
+        let fallback_context_attributes = ContextAttributesBuilder::new()
+            .with_context_api(ContextApi::Gles(None))
+            .build(Some(raw_window_handle));
+        let context = display
+            .create_context(&config, &context_attributes)
+            .or_else(|_| display.create_context(&config, &fallback_context_attributes))
+            .map_err(glutin_error)?;
+
+        let surface_attributes = SurfaceAttributesBuilder::<WindowSurface>::new().build(
+            raw_window_handle,
+            non_zero(width),
+            non_zero(height),
+        );
+        let surface = display
+            .create_window_surface(&config, &surface_attributes)
+            .map_err(glutin_error)?;
+
+        let context = context.make_current(&surface).map_err(glutin_error)?;
+
+        let mut canvas = Canvas::new(Self::new_from_glutin_display(&display)?)?;
+        canvas.set_size(width, height, dpi);
+
+        Ok((canvas, OpenGlSurface { context, surface }))
+    }
+}
+
+// Converts the window handle to the raw-window-handle 0.5 one glutin expects, for the platforms glutin supports
+fn window_handle(window: &impl HasWindowHandle) -> Result<rwh_05::RawWindowHandle, ErrorKind> {
+    let handle = window.window_handle().map_err(handle_error)?;
+
+    Ok(match handle.as_raw() {
+        RawWindowHandle::Xlib(xlib) => {
+            let mut handle = rwh_05::XlibWindowHandle::empty();
+            handle.window = xlib.window;
+            handle.visual_id = xlib.visual_id;
+            rwh_05::RawWindowHandle::Xlib(handle)
+        }
+        RawWindowHandle::Xcb(xcb) => {
+            let mut handle = rwh_05::XcbWindowHandle::empty();
+            handle.window = xcb.window.get();
+            handle.visual_id = xcb.visual_id.map_or(0, NonZeroU32::get);
+            rwh_05::RawWindowHandle::Xcb(handle)
+        }
+        RawWindowHandle::Wayland(wayland) => {
+            let mut handle = rwh_05::WaylandWindowHandle::empty();
+            handle.surface = wayland.surface.as_ptr();
+            rwh_05::RawWindowHandle::Wayland(handle)
+        }
+        RawWindowHandle::Win32(win32) => {
+            let mut handle = rwh_05::Win32WindowHandle::empty();
+            handle.hwnd = win32.hwnd.get() as *mut _;
+            handle.hinstance = win32.hinstance.map_or(0, |hinstance| hinstance.get()) as *mut _;
+            rwh_05::RawWindowHandle::Win32(handle)
+        }
+        RawWindowHandle::AppKit(appkit) => {
+            let mut handle = rwh_05::AppKitWindowHandle::empty();
+            handle.ns_view = appkit.ns_view.as_ptr();
+            rwh_05::RawWindowHandle::AppKit(handle)
+        }
+        RawWindowHandle::AndroidNdk(android) => {
+            let mut handle = rwh_05::AndroidNdkWindowHandle::empty();
+            handle.a_native_window = android.a_native_window.as_ptr();
+            rwh_05::RawWindowHandle::AndroidNdk(handle)
+        }
+        handle => return Err(unsupported_handle(handle)),
+    })
+}
+
+fn display_handle(window: &impl HasDisplayHandle) -> Result<rwh_05::RawDisplayHandle, ErrorKind> {
+    let handle = window.display_handle().map_err(handle_error)?;
+
+    Ok(match handle.as_raw() {
+        RawDisplayHandle::Xlib(xlib) => {
+            let mut handle = rwh_05::XlibDisplayHandle::empty();
+            handle.display = xlib.display.map_or(ptr::null_mut(), |display| display.as_ptr());
+            handle.screen = xlib.screen;
+            rwh_05::RawDisplayHandle::Xlib(handle)
+        }
+        RawDisplayHandle::Xcb(xcb) => {
+            let mut handle = rwh_05::XcbDisplayHandle::empty();
+            handle.connection = xcb.connection.map_or(ptr::null_mut(), |connection| connection.as_ptr());
+            handle.screen = xcb.screen;
+            rwh_05::RawDisplayHandle::Xcb(handle)
+        }
+        RawDisplayHandle::Wayland(wayland) => {
+            let mut handle = rwh_05::WaylandDisplayHandle::empty();
+            handle.display = wayland.display.as_ptr();
+            rwh_05::RawDisplayHandle::Wayland(handle)
+        }
+        RawDisplayHandle::Windows(_) => rwh_05::RawDisplayHandle::Windows(rwh_05::WindowsDisplayHandle::empty()),
+        RawDisplayHandle::AppKit(_) => rwh_05::RawDisplayHandle::AppKit(rwh_05::AppKitDisplayHandle::empty()),
+        RawDisplayHandle::Android(_) => rwh_05::RawDisplayHandle::Android(rwh_05::AndroidDisplayHandle::empty()),
+        handle => return Err(unsupported_handle(handle)),
+    })
+}
+
+fn unsupported_handle(handle: impl std::fmt::Debug) -> ErrorKind {
+    ErrorKind::GeneralError(format!("Unsupported window handle {handle:?}"))
+}
+
+fn handle_error(error: raw_window_handle::HandleError) -> ErrorKind {
+    ErrorKind::GeneralError(error.to_string())
+}
+
+fn non_zero(size: u32) -> NonZeroU32 {
+    NonZeroU32::new(size.max(1)).unwrap()
+}
+
+fn glutin_error(error: glutin::error::Error) -> ErrorKind {
+    ErrorKind::GeneralError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroU32, ptr::NonNull};
+
+    use raw_window_handle::{
+        DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, OrbitalWindowHandle, RawDisplayHandle,
+        RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle, WindowHandle, XcbDisplayHandle, XcbWindowHandle,
+    };
+
+    use super::{display_handle, rwh_05, window_handle};
+    use crate::ErrorKind;
+
+    struct TestWindow(RawWindowHandle, RawDisplayHandle);
+
+    impl HasWindowHandle for TestWindow {
+        fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+            Ok(unsafe { WindowHandle::borrow_raw(self.0) })
+        }
+    }
+
+    impl HasDisplayHandle for TestWindow {
+        fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+            Ok(unsafe { DisplayHandle::borrow_raw(self.1) })
+        }
+    }
+
+    #[test]
+    fn window_handle_conversion() {
+        let mut surface = 0u8;
+        let mut display = 0u8;
+        let wayland = TestWindow(
+            WaylandWindowHandle::new(NonNull::from(&mut surface).cast()).into(),
+            WaylandDisplayHandle::new(NonNull::from(&mut display).cast()).into(),
+        );
+
+        match (window_handle(&wayland), display_handle(&wayland)) {
+            (Ok(rwh_05::RawWindowHandle::Wayland(window)), Ok(rwh_05::RawDisplayHandle::Wayland(display_handle))) => {
+                assert_eq!(window.surface, &mut surface as *mut u8 as *mut _);
+                assert_eq!(display_handle.display, &mut display as *mut u8 as *mut _);
+            }
+            handles => panic!("unexpected handles {handles:?}"),
+        }
+
+        let mut xcb_window = XcbWindowHandle::new(NonZeroU32::new(7).unwrap());
+        xcb_window.visual_id = NonZeroU32::new(3);
+        let xcb = TestWindow(xcb_window.into(), XcbDisplayHandle::new(None, 1).into());
+
+        match (window_handle(&xcb), display_handle(&xcb)) {
+            (Ok(rwh_05::RawWindowHandle::Xcb(window)), Ok(rwh_05::RawDisplayHandle::Xcb(display))) => {
+                assert_eq!((window.window, window.visual_id), (7, 3));
+                assert!(display.connection.is_null());
+                assert_eq!(display.screen, 1);
+            }
+            handles => panic!("unexpected handles {handles:?}"),
+        }
+
+        // glutin can't create surfaces for the other platforms
+        let orbital = TestWindow(
+            OrbitalWindowHandle::new(NonNull::from(&mut surface).cast()).into(),
+            WaylandDisplayHandle::new(NonNull::from(&mut display).cast()).into(),
+        );
+        assert!(matches!(window_handle(&orbital), Err(ErrorKind::GeneralError(_))));
+    }
+}
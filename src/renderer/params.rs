@@ -1,12 +1,13 @@
 use crate::{
     geometry::Position,
     paint::{GlyphTexture, GradientColors},
-    ImageFlags, ImageStore, PaintFlavor, PixelFormat, Scissor, Transform2D,
+    ImageFlags, ImageStore, PaintFlavor, PixelFormat, Rect, Scissor, ShaderId, Spread, Transform2D,
 };
 
 use super::ShaderType;
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Params {
     pub(crate) scissor_mat: [f32; 12],
     pub(crate) paint_mat: [f32; 12],
@@ -21,10 +22,30 @@ pub struct Params {
     pub(crate) stroke_thr: f32,
     pub(crate) tex_type: f32,
     pub(crate) shader_type: ShaderType,
-    pub(crate) glyph_texture_type: u8, // 0 -> no glyph rendering, 1 -> alpha mask, 2 -> color texture
+    // 0 -> no glyph rendering, 1 -> alpha mask, 2 -> color texture, 5 -> image with straight alpha,
+    // 6 -> premultiplied image
+    pub(crate) glyph_texture_type: u8,
+    // Layer of glyph atlases with several layers
+    pub(crate) glyph_texture_layer: f32,
     pub(crate) image_blur_filter_direction: [f32; 2],
     pub(crate) image_blur_filter_sigma: f32,
     pub(crate) image_blur_filter_coeff: [f32; 3],
+    // 0 -> Gaussian blur, 1 -> downsampling pass of a dual Kawase blur, 2 -> upsampling pass of a dual Kawase blur
+    pub(crate) image_blur_filter_kawase: f32,
+    pub(crate) custom_shader: Option<ShaderId>,
+    pub(crate) custom_alpha: f32,
+    // 1 if the paint is sampled at the position along the stroke stored in the v coordinate of the vertices
+    pub(crate) path_gradient: f32,
+    // 1 if the paint is a two-point radial gradient, from the focal circle at the origin of the paint space with
+    // `radius` to the circle at `extent` with `feather` as its radius
+    pub(crate) focal_gradient: f32,
+    // How gradient positions outside of 0 to 1 are mapped: 0 -> pad, 1 -> repeat, 2 -> reflect
+    pub(crate) gradient_spread: f32,
+    // Texture coordinates image paints are clamped to, all zero if they aren't clamped
+    pub(crate) tex_rect: [f32; 4],
+    // Gamma and contrast applied to the coverage of alpha mask glyphs, the coverage is unchanged if the gamma is 0
+    pub(crate) mask_gamma: f32,
+    pub(crate) mask_contrast: f32,
 }
 
 impl Params {
@@ -64,10 +85,19 @@ impl Params {
 
         params.glyph_texture_type = match glyph_texture {
             GlyphTexture::None => 0,
-            GlyphTexture::AlphaMask(_) => 1,
-            GlyphTexture::ColorTexture(_) => 2,
+            GlyphTexture::AlphaMask(..) => 1,
+            GlyphTexture::ColorTexture(..) => 2,
+            GlyphTexture::Image(id) => match images.info(*id) {
+                Some(info) if info.format() == PixelFormat::Gray8 => 1,
+                Some(info) if info.flags().contains(ImageFlags::PREMULTIPLIED) => 6,
+                _ => 5,
+            },
         };
 
+        if let &GlyphTexture::AlphaMask(_, layer) | &GlyphTexture::ColorTexture(_, layer) = glyph_texture {
+            params.glyph_texture_layer = layer as f32;
+        }
+
         let inv_transform;
 
         match &paint_flavor {
@@ -85,24 +115,49 @@ impl Params {
                 height,
                 angle,
                 tint,
+                source,
             } => {
                 let Some(image_info) = images.info(*id) else {
                     return params;
                 };
 
-                params.extent[0] = *width;
-                params.extent[1] = *height;
+                let flip_y = image_info.flags().contains(ImageFlags::FLIP_Y);
+
+                // A source rectangle is drawn with a pattern of the whole image, scaled and offset so that the
+                // rectangle covers the pattern size
+                let (offset, width, height) = match source.filter(|source| !source.is_empty()) {
+                    Some(source) => {
+                        params.tex_rect = source_tex_rect(source, image_info.width(), image_info.height(), flip_y);
+
+                        let scale_x = width / source.w;
+                        let scale_y = height / source.h;
+
+                        (
+                            Position {
+                                x: -source.x * scale_x,
+                                y: -source.y * scale_y,
+                            },
+                            image_info.width() as f32 * scale_x,
+                            image_info.height() as f32 * scale_y,
+                        )
+                    }
+                    None => (Position::default(), *width, *height),
+                };
+
+                params.extent[0] = width;
+                params.extent[1] = height;
 
                 let color = tint;
 
                 params.inner_col = color.premultiplied().to_array();
                 params.outer_col = color.premultiplied().to_array();
 
-                let mut transform = Transform2D::rotation(*angle);
+                let mut transform = Transform2D::translation(offset.x, offset.y);
+                transform *= Transform2D::rotation(*angle);
                 transform.translate(*cx, *cy);
                 transform *= *global_transform;
 
-                if image_info.flags().contains(ImageFlags::FLIP_Y) {
+                if flip_y {
                     let mut m1 = Transform2D::translation(0.0, height * 0.5);
                     m1 *= transform;
 
@@ -132,43 +187,23 @@ impl Params {
                 };
             }
             PaintFlavor::LinearGradient {
-                start: Position { x: start_x, y: start_y },
-                end: Position { x: end_x, y: end_y },
+                start,
+                end,
                 colors,
+                spread,
             } => {
-                let large = 1e5f32;
-                let mut dx = end_x - start_x;
-                let mut dy = end_y - start_y;
-                let d = dx.hypot(dy);
-
-                if d > 0.0001 {
-                    dx /= d;
-                    dy /= d;
-                } else {
-                    dx = 0.0;
-                    dy = 1.0;
-                }
-
-                let mut transform = Transform2D([dy, -dx, dx, dy, start_x - dx * large, start_y - dy * large]);
-
+                params.set_gradient_spread(*spread);
+                let mut transform = params.set_linear_gradient(*start, *end, colors);
                 transform *= *global_transform;
-
                 inv_transform = transform.inverse();
-
-                params.extent[0] = large;
-                params.extent[1] = large + d * 0.5;
-                params.feather = 1.0f32.max(d);
-
-                match colors {
-                    GradientColors::TwoStop { start_color, end_color } => {
-                        params.inner_col = start_color.premultiplied().to_array();
-                        params.outer_col = end_color.premultiplied().to_array();
-                        params.shader_type = ShaderType::FillGradient;
-                    }
-                    GradientColors::MultiStop { .. } => {
-                        params.shader_type = ShaderType::FillImageGradient;
-                    }
-                }
+            }
+            PaintFlavor::PathGradient { colors } => {
+                // A linear gradient sampled at (t, 0), where t goes from 0 to 1 along the stroke, unaffected by the
+                // canvas transform
+                let start = Position { x: 0.0, y: 0.0 };
+                let end = Position { x: 1.0, y: 0.0 };
+                inv_transform = params.set_linear_gradient(start, end, colors).inverse();
+                params.path_gradient = 1.0;
             }
             &PaintFlavor::BoxGradient {
                 pos: Position { x, y },
@@ -202,7 +237,10 @@ impl Params {
                 in_radius,
                 out_radius,
                 colors,
+                spread,
             } => {
+                params.set_gradient_spread(*spread);
+
                 let r = (in_radius + out_radius) * 0.5;
                 let f = out_radius - in_radius;
 
@@ -225,6 +263,48 @@ impl Params {
                     }
                 }
             }
+            &PaintFlavor::FocalRadialGradient {
+                focal: Position { x: fx, y: fy },
+                focal_radius,
+                center: Position { x: cx, y: cy },
+                radius,
+                colors,
+                spread,
+            } => {
+                params.set_gradient_spread(*spread);
+
+                let mut transform = Transform2D::translation(*fx, *fy);
+                transform *= *global_transform;
+                inv_transform = transform.inverse();
+
+                params.extent = [cx - fx, cy - fy];
+                params.radius = focal_radius.max(0.0);
+                params.feather = radius.max(0.0);
+                params.focal_gradient = 1.0;
+                match colors {
+                    GradientColors::TwoStop { start_color, end_color } => {
+                        params.inner_col = start_color.premultiplied().to_array();
+                        params.outer_col = end_color.premultiplied().to_array();
+                        params.shader_type = ShaderType::FillGradient;
+                    }
+                    GradientColors::MultiStop { .. } => {
+                        params.shader_type = ShaderType::FillImageGradient;
+                    }
+                }
+            }
+            &PaintFlavor::Custom {
+                shader,
+                uniforms,
+                alpha,
+            } => {
+                inv_transform = global_transform.inverse();
+
+                params.inner_col.copy_from_slice(&uniforms[..4]);
+                params.outer_col.copy_from_slice(&uniforms[4..]);
+                params.custom_alpha = *alpha;
+                params.custom_shader = Some(*shader);
+                params.shader_type = ShaderType::FillCustom;
+            }
         }
 
         params.paint_mat = inv_transform.to_mat3x4();
@@ -232,7 +312,90 @@ impl Params {
         params
     }
 
+    // Returns how many times a dual Kawase blur of `radius` halves an image of the given size and the offset of its
+    // samples in half texels. Each level about doubles the radius of the blur, the offset makes up the rest.
+    pub(crate) fn dual_kawase_levels(radius: f32, width: usize, height: usize) -> (usize, f32) {
+        let max_levels = (width.min(height).max(1).ilog2() as usize).clamp(1, 8);
+        let levels = ((radius / 1.5).max(1.0).log2() as usize).clamp(1, max_levels);
+
+        (levels, radius.max(0.0) / (1 << levels) as f32)
+    }
+
+    // Sets up a pass of a dual Kawase blur that samples a texture of `source_size` around every pixel of a target of
+    // `target_size`, `offset` half texels away.
+    pub(crate) fn set_dual_kawase_pass(
+        &mut self,
+        source_size: (usize, usize),
+        target_size: (usize, usize),
+        offset: f32,
+        upsample: bool,
+    ) {
+        self.extent = [target_size.0 as f32, target_size.1 as f32];
+        self.image_blur_filter_direction = [offset * 0.5 / source_size.0 as f32, offset * 0.5 / source_size.1 as f32];
+        self.image_blur_filter_kawase = if upsample { 2.0 } else { 1.0 };
+    }
+
+    fn set_gradient_spread(&mut self, spread: Spread) {
+        self.gradient_spread = match spread {
+            Spread::Pad => 0.0,
+            Spread::Repeat => 1.0,
+            Spread::Reflect => 2.0,
+        };
+    }
+
+    // Sets up a linear gradient from `start` to `end` and returns its untransformed paint transform.
+    fn set_linear_gradient(&mut self, start: Position, end: Position, colors: &GradientColors) -> Transform2D {
+        let large = 1e5f32;
+        let mut dx = end.x - start.x;
+        let mut dy = end.y - start.y;
+        let d = dx.hypot(dy);
+
+        if d > 0.0001 {
+            dx /= d;
+            dy /= d;
+        } else {
+            dx = 0.0;
+            dy = 1.0;
+        }
+
+        self.extent[0] = large;
+        self.extent[1] = large + d * 0.5;
+        self.feather = 1.0f32.max(d);
+
+        match colors {
+            GradientColors::TwoStop { start_color, end_color } => {
+                self.inner_col = start_color.premultiplied().to_array();
+                self.outer_col = end_color.premultiplied().to_array();
+                self.shader_type = ShaderType::FillGradient;
+            }
+            GradientColors::MultiStop { .. } => {
+                self.shader_type = ShaderType::FillImageGradient;
+            }
+        }
+
+        Transform2D([dy, -dx, dx, dy, start.x - dx * large, start.y - dy * large])
+    }
+
     pub(crate) fn uses_glyph_texture(self) -> bool {
         self.glyph_texture_type != 0
     }
 }
+
+// Texture coordinates of the centers of the outermost pixels of `source`, which image paints are clamped to so that
+// neighbouring pixels don't bleed in.
+fn source_tex_rect(source: Rect, image_width: usize, image_height: usize, flip_y: bool) -> [f32; 4] {
+    let (width, height) = (image_width as f32, image_height as f32);
+
+    // Regions narrower than a pixel are sampled at their center
+    let inset_x = (source.w * 0.5).min(0.5);
+    let inset_y = (source.h * 0.5).min(0.5);
+
+    let y = if flip_y { height - source.y - source.h } else { source.y };
+
+    [
+        (source.x + inset_x) / width,
+        (y + inset_y) / height,
+        (source.x + source.w - inset_x) / width,
+        (y + source.h - inset_y) / height,
+    ]
+}
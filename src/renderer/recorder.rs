@@ -0,0 +1,294 @@
+use std::{
+    cell::RefCell,
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use fnv::FnvHashMap;
+use imgref::ImgVec;
+use rgb::RGBA8;
+
+use crate::{image::ImageData, ErrorKind, ImageInfo, ImageSource, ImageStore, ShaderId};
+
+use super::{Command, CommandType, ImageId, RenderTarget, Renderer, SurfacelessRenderer, Vertex};
+
+static NEXT_RECORDER_ID: AtomicU64 = AtomicU64::new(0);
+
+// An image referenced by a frame, either allocated by its recorder or shared by the canvas the frame is
+// appended to.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) enum FrameImage {
+    Recorded(u64),
+    Shared(ImageId),
+}
+
+// Image operations are identified by a serial number that is unique within their recorder.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) enum ImageOp {
+    Alloc {
+        serial: u64,
+        info: ImageInfo,
+    },
+    Update {
+        serial: u64,
+        data: ImageData,
+        x: usize,
+        y: usize,
+    },
+    Delete {
+        serial: u64,
+    },
+}
+
+/// The drawing commands, vertices and image uploads of one or more flushes of a [`Recorder`] canvas.
+///
+/// A frame can be sent to another thread and drawn there with
+/// [`Canvas::append_frame`](crate::Canvas::append_frame).
+///
+/// With the `serde` feature frames can be serialized, to capture them to disk for bug reports, replay them in
+/// tests or stream them to another process that owns the graphics context. A frame is self-contained: it
+/// carries the pixels of the images and glyph atlases it allocated, and refers to the images of the canvas it
+/// is appended to by the ids passed to [`Canvas::import_image`](crate::Canvas::import_image). Custom shaders
+/// are referred to by their id, so they have to be created in the same order on the canvas the frame is
+/// appended to.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Frame {
+    pub(crate) recorder: u64,
+    pub(crate) image_ops: Vec<ImageOp>,
+    #[cfg_attr(feature = "serde", serde(with = "frame_images"))]
+    pub(crate) images: FnvHashMap<ImageId, FrameImage>,
+    pub(crate) commands: Vec<Command>,
+    pub(crate) verts: Vec<Vertex>,
+    pub(crate) render_target: RenderTarget,
+}
+
+impl Frame {
+    /// Returns true if the frame contains no drawing commands.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+// The images of a frame are serialized as a list of pairs, as formats like JSON only have maps with string keys.
+#[cfg(feature = "serde")]
+mod frame_images {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{FnvHashMap, FrameImage, ImageId};
+
+    pub fn serialize<S: Serializer>(
+        images: &FnvHashMap<ImageId, FrameImage>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(images)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<FnvHashMap<ImageId, FrameImage>, D::Error> {
+        Ok(Vec::<(ImageId, FrameImage)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+/// Renderer that records commands into a [`Frame`] instead of drawing them.
+///
+/// A `Canvas<Recorder>` doesn't need a graphics context, so it can be used on any thread. Frames are taken
+/// with [`Canvas::record_frame`](crate::Canvas::record_frame).
+///
+/// Images of the canvas the frames are appended to can be drawn after registering them with
+/// [`Canvas::import_image`](crate::Canvas::import_image). Fonts are shared with a
+/// [`FontSet`](crate::FontSet).
+pub struct Recorder {
+    id: u64,
+    next_serial: u64,
+    image_ops: Rc<RefCell<Vec<ImageOp>>>,
+    images: FnvHashMap<ImageId, FrameImage>,
+    commands: Vec<Command>,
+    verts: Vec<Vertex>,
+    render_target: RenderTarget,
+    render_target_layer: usize,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recorder {
+    /// Creates a new recorder.
+    pub fn new() -> Self {
+        // Ids are randomized so that the images of frames serialized by recorders of different processes
+        // don't get mixed up when they're appended to the same canvas.
+        let serial = NEXT_RECORDER_ID.fetch_add(1, Ordering::Relaxed);
+
+        Self {
+            id: RandomState::new().hash_one(serial),
+            next_serial: 0,
+            image_ops: Default::default(),
+            images: FnvHashMap::default(),
+            commands: Vec::new(),
+            verts: Vec::new(),
+            render_target: RenderTarget::Screen,
+            render_target_layer: 0,
+        }
+    }
+
+    pub(crate) fn take_frame(&mut self) -> Frame {
+        Frame {
+            recorder: self.id,
+            image_ops: std::mem::take(&mut *self.image_ops.borrow_mut()),
+            images: std::mem::take(&mut self.images),
+            commands: std::mem::take(&mut self.commands),
+            verts: std::mem::take(&mut self.verts),
+            render_target: self.render_target,
+        }
+    }
+}
+
+impl Renderer for Recorder {
+    type Image = RecordedImage;
+    // Image of the canvas the frames are appended to.
+    type NativeTexture = ImageId;
+    type Surface = ();
+
+    fn set_size(&mut self, _width: u32, _height: u32, _dpi: f32) {}
+
+    fn render(
+        &mut self,
+        _surface: &Self::Surface,
+        images: &mut ImageStore<RecordedImage>,
+        verts: &[Vertex],
+        commands: Vec<Command>,
+    ) {
+        // Frames always start out drawing to the screen
+        if self.commands.is_empty() && self.render_target != RenderTarget::Screen {
+            let mut cmd = Command::new(CommandType::SetRenderTarget(self.render_target));
+            cmd.target_layer = self.render_target_layer;
+            self.commands.push(cmd);
+        }
+
+        let offset = self.verts.len();
+
+        for mut cmd in commands {
+            if let CommandType::SetRenderTarget(target) = cmd.cmd_type {
+                self.render_target = target;
+                self.render_target_layer = cmd.target_layer;
+            }
+
+            // The params were resolved by the recording canvas, its paint handles mean nothing elsewhere
+            cmd.paint_binding = None;
+            cmd.offset_verts(offset);
+            cmd.map_images(|id| {
+                if let Some(image) = images.get(id) {
+                    self.images.insert(id, image.source);
+                }
+                id
+            });
+
+            self.commands.push(cmd);
+        }
+
+        self.verts.extend_from_slice(verts);
+    }
+
+    fn alloc_image(&mut self, info: ImageInfo) -> Result<Self::Image, ErrorKind> {
+        let serial = self.next_serial;
+        self.next_serial += 1;
+
+        self.image_ops.borrow_mut().push(ImageOp::Alloc { serial, info });
+
+        Ok(RecordedImage {
+            source: FrameImage::Recorded(serial),
+            info,
+            image_ops: self.image_ops.clone(),
+        })
+    }
+
+    fn create_image_from_native_texture(
+        &mut self,
+        native_texture: Self::NativeTexture,
+        info: ImageInfo,
+    ) -> Result<Self::Image, ErrorKind> {
+        Ok(RecordedImage {
+            source: FrameImage::Shared(native_texture),
+            info,
+            image_ops: self.image_ops.clone(),
+        })
+    }
+
+    fn update_image(
+        &mut self,
+        image: &mut Self::Image,
+        data: ImageSource,
+        x: usize,
+        y: usize,
+    ) -> Result<(), ErrorKind> {
+        // Shared images are read-only
+        let FrameImage::Recorded(serial) = image.source else {
+            return Err(ErrorKind::UnsupportedOperation);
+        };
+
+        let size = data.dimensions();
+
+        if x + size.width > image.info.width() || y + size.height > image.info.height() {
+            return Err(ErrorKind::ImageUpdateOutOfBounds);
+        }
+
+        self.image_ops.borrow_mut().push(ImageOp::Update {
+            serial,
+            data: ImageData::new(data)?,
+            x,
+            y,
+        });
+
+        Ok(())
+    }
+
+    fn get_native_texture(&self, image: &Self::Image) -> Result<Self::NativeTexture, ErrorKind> {
+        match image.source {
+            FrameImage::Shared(id) => Ok(id),
+            FrameImage::Recorded(_) => Err(ErrorKind::UnsupportedImageFormat),
+        }
+    }
+
+    fn delete_image(&mut self, image: Self::Image, _image_id: ImageId) {
+        drop(image);
+    }
+
+    // Frames refer to the shaders created in the same order on the canvas they are appended to
+    fn create_shader(&mut self, _id: ShaderId, _source: &str) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+
+    fn screenshot(&mut self) -> Result<ImgVec<RGBA8>, ErrorKind> {
+        Err(ErrorKind::UnsupportedOperation)
+    }
+}
+
+impl SurfacelessRenderer for Recorder {
+    fn render_surfaceless(&mut self, images: &mut ImageStore<Self::Image>, verts: &[Vertex], commands: Vec<Command>) {
+        self.render(&(), images, verts, commands);
+    }
+}
+
+/// Image allocated by a [`Recorder`].
+pub struct RecordedImage {
+    source: FrameImage,
+    info: ImageInfo,
+    image_ops: Rc<RefCell<Vec<ImageOp>>>,
+}
+
+impl Drop for RecordedImage {
+    // Images are also replaced without being deleted when reallocated, so record the deletion here.
+    fn drop(&mut self) {
+        if let FrameImage::Recorded(serial) = self.source {
+            self.image_ops.borrow_mut().push(ImageOp::Delete { serial });
+        }
+    }
+}
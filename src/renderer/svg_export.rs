@@ -0,0 +1,711 @@
+use std::fmt::Write;
+
+use fnv::FnvHashMap;
+use imgref::ImgVec;
+use rgb::RGBA8;
+
+use crate::{
+    Color, ErrorKind, FillRule, ImageInfo, ImageSource, ImageStore, LineCap, LineJoin, Solidity, Transform2D, Verb,
+};
+
+use super::{
+    Command, CommandType, ImageId, Outline, Params, RenderTarget, Renderer, ShaderType, SurfacelessRenderer, Vertex,
+};
+
+// Half extent of linear gradients along their direction, see `Params::set_linear_gradient`
+const LINEAR_GRADIENT_EXTENT: f32 = 1e5;
+
+// Largest number of stops sampled from the texture of a multi-stop gradient
+const MAX_GRADIENT_STOPS: usize = 64;
+
+/// Renderer that writes what is drawn to the screen into an SVG document instead of drawing it.
+///
+/// Filled and stroked paths are written as SVG paths with their original curves, transform and stroke settings,
+/// and text is written as glyph outlines. Colors, linear and radial gradients and image patterns become the
+/// matching SVG paints, with the images embedded as PNG if the `svg-export-images` feature is enabled. Box gradients
+/// and gradients along strokes are approximated with a solid color, strokes with a width profile use the width of
+/// their paint and strokes with different caps at their ends use the cap of their start. Custom shaders, image
+/// filters, bitmap glyphs and drawing into images are skipped.
+///
+/// Take the document with [`Canvas::export_svg()`](crate::Canvas::export_svg).
+pub struct SvgRenderer {
+    width: u32,
+    height: u32,
+    defs: String,
+    body: String,
+    next_id: usize,
+    // Ids of the definitions of the images embedded into the document so far
+    embedded_images: FnvHashMap<ImageId, usize>,
+    // The last scissor a clip path was defined for and its id
+    last_clip: Option<([f32; 12], [f32; 2], usize)>,
+    render_target: RenderTarget,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SvgRenderer {
+    /// Creates a new SVG renderer.
+    pub fn new() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            defs: String::new(),
+            body: String::new(),
+            next_id: 0,
+            embedded_images: FnvHashMap::default(),
+            last_clip: None,
+            render_target: RenderTarget::Screen,
+        }
+    }
+
+    pub(crate) fn take_document(&mut self) -> String {
+        let mut document = String::new();
+
+        let _ = writeln!(
+            document,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            self.width, self.height
+        );
+        if !self.defs.is_empty() {
+            let _ = writeln!(document, "<defs>\n{}</defs>", self.defs);
+        }
+        document.push_str(&self.body);
+        document.push_str("</svg>\n");
+
+        self.clear();
+
+        document
+    }
+
+    // Discards everything written so far.
+    fn clear(&mut self) {
+        self.defs.clear();
+        self.body.clear();
+        self.embedded_images.clear();
+        self.last_clip = None;
+    }
+
+    fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn clear_rect(&mut self, verts: &[Vertex], cmd: &Command, color: Color) {
+        let Some((start, _)) = cmd.triangles_verts else {
+            return;
+        };
+
+        let (x0, y0) = (verts[start].x, verts[start].y);
+        let (x1, y1) = (verts[start + 1].x, verts[start + 1].y);
+
+        // Clearing the whole screen discards everything drawn before, along with the definitions it used
+        if x0 <= 0.0 && y0 <= 0.0 && x1 >= self.width as f32 && y1 >= self.height as f32 {
+            self.clear();
+        }
+
+        if color.a > 0.0 {
+            let _ = writeln!(
+                self.body,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                num(x0),
+                num(y0),
+                num(x1 - x0),
+                num(y1 - y0),
+                color_attributes("fill", color.premultiplied().to_array())
+            );
+        }
+    }
+
+    // Writes the path of an outline, filled or stroked with the paint of `params`.
+    fn draw(&mut self, images: &ImageStore<SvgImage>, cmd: &Command, params: &Params, outline: &Outline) {
+        let mirrored = {
+            let Transform2D([a, b, c, d, ..]) = outline.transform;
+            a * d - b * c < 0.0
+        };
+        let data = path_data(&outline.verbs, mirrored);
+        if data.is_empty() {
+            return;
+        }
+
+        let prefix = if outline.stroke.is_some() { "stroke" } else { "fill" };
+        let Some(paint) = self.paint(images, cmd.image, params, &outline.transform, prefix) else {
+            return;
+        };
+
+        let style = match &outline.stroke {
+            Some(stroke) => {
+                let cap = match stroke.line_cap_start {
+                    LineCap::Butt => "butt",
+                    LineCap::Round => "round",
+                    LineCap::Square => "square",
+                };
+                let join = match stroke.line_join {
+                    LineJoin::Miter => "miter",
+                    LineJoin::Round => "round",
+                    LineJoin::Bevel => "bevel",
+                };
+                format!(
+                    r#"fill="none" {paint} stroke-width="{}" stroke-linecap="{cap}" stroke-linejoin="{join}" stroke-miterlimit="{}""#,
+                    num(stroke.line_width),
+                    num(stroke.miter_limit.max(1.0))
+                )
+            }
+            None => {
+                let fill_rule = match cmd.fill_rule {
+                    FillRule::NonZero => "nonzero",
+                    FillRule::EvenOdd => "evenodd",
+                };
+                format!(r#"fill-rule="{fill_rule}" {paint}"#)
+            }
+        };
+
+        let transform = if outline.transform == Transform2D::identity() {
+            String::new()
+        } else {
+            format!(r#" transform="{}""#, matrix(outline.transform))
+        };
+
+        // The clip path is in screen coordinates, so it's applied to a group around the transformed path
+        match self.clip(params) {
+            Some(clip) => {
+                let _ = writeln!(
+                    self.body,
+                    r#"<g clip-path="url(#c{clip})"><path d="{data}"{transform} {style}/></g>"#
+                );
+            }
+            None => {
+                let _ = writeln!(self.body, r#"<path d="{data}"{transform} {style}/>"#);
+            }
+        }
+    }
+
+    // Returns the fill or stroke attributes of the paint of `params` for a path drawn with `transform`, defining
+    // gradients and patterns as needed. `prefix` is "fill" or "stroke".
+    fn paint(
+        &mut self,
+        images: &ImageStore<SvgImage>,
+        image: Option<ImageId>,
+        params: &Params,
+        transform: &Transform2D,
+        prefix: &str,
+    ) -> Option<String> {
+        // Paints are in screen coordinates, while their path is drawn in the coordinates of its transform
+        let paint_transform = paint_transform(params.paint_mat) * transform.inverse();
+
+        match params.shader_type {
+            ShaderType::FillColor | ShaderType::FillColorUnclipped => Some(color_attributes(prefix, params.inner_col)),
+            ShaderType::FillGradient => {
+                let stops = [(0.0, params.inner_col), (1.0, params.outer_col)];
+                Some(self.gradient(params, paint_transform, &stops, prefix))
+            }
+            ShaderType::FillImageGradient => {
+                let pixels = &images.get(image?)?.pixels;
+                let count = pixels.width().clamp(2, MAX_GRADIENT_STOPS);
+                let stops: Vec<_> = (0..count)
+                    .map(|index| {
+                        let offset = index as f32 / (count - 1) as f32;
+                        let x = (offset * (pixels.width() - 1) as f32).round() as usize;
+                        let RGBA8 { r, g, b, a } = pixels.buf()[x];
+                        (offset, [r, g, b, a].map(|c| c as f32 / 255.0))
+                    })
+                    .collect();
+                Some(self.gradient(params, paint_transform, &stops, prefix))
+            }
+            ShaderType::FillImage => self.image_pattern(images, image?, params, paint_transform, prefix),
+            _ => None,
+        }
+    }
+
+    // Defines a gradient with the premultiplied colors at the given offsets and returns the paint attributes
+    // referring to it. Gradients that SVG can't express are approximated with the color halfway through them.
+    fn gradient(
+        &mut self,
+        params: &Params,
+        paint_transform: Transform2D,
+        stops: &[(f32, [f32; 4])],
+        prefix: &str,
+    ) -> String {
+        let [ext_x, ext_y] = params.extent;
+        let feather = params.feather;
+
+        let (element, geometry) = if params.path_gradient != 0.0 {
+            return color_attributes(prefix, sample_stops(stops, 0.5));
+        } else if params.focal_gradient != 0.0 {
+            let geometry = format!(
+                r#"fx="0" fy="0" fr="{}" cx="{}" cy="{}" r="{}""#,
+                num(params.radius),
+                num(ext_x),
+                num(ext_y),
+                num(feather)
+            );
+            ("radialGradient", geometry)
+        } else if ext_x == LINEAR_GRADIENT_EXTENT {
+            let geometry = format!(
+                r#"x1="0" y1="{}" x2="0" y2="{}""#,
+                num(ext_y - feather * 0.5),
+                num(ext_y + feather * 0.5)
+            );
+            ("linearGradient", geometry)
+        } else if ext_x == ext_y && params.radius == ext_x {
+            let geometry = format!(
+                r#"cx="0" cy="0" fr="{}" r="{}""#,
+                num((ext_x - feather * 0.5).max(0.0)),
+                num(ext_x + feather * 0.5)
+            );
+            ("radialGradient", geometry)
+        } else {
+            return color_attributes(prefix, sample_stops(stops, 0.5));
+        };
+
+        let spread = if params.gradient_spread == 1.0 {
+            "repeat"
+        } else if params.gradient_spread == 2.0 {
+            "reflect"
+        } else {
+            "pad"
+        };
+
+        let id = self.next_id();
+        let _ = writeln!(
+            self.defs,
+            r#"<{element} id="g{id}" gradientUnits="userSpaceOnUse" {geometry} gradientTransform="{}" spreadMethod="{spread}">"#,
+            matrix(paint_transform),
+        );
+        for (offset, color) in stops {
+            let _ = writeln!(
+                self.defs,
+                r#"<stop offset="{}" {}/>"#,
+                num(*offset),
+                color_attributes("stop", *color)
+            );
+        }
+        let _ = writeln!(self.defs, "</{element}>");
+
+        format!(r#"{prefix}="url(#g{id})""#)
+    }
+
+    fn image_pattern(
+        &mut self,
+        images: &ImageStore<SvgImage>,
+        id: ImageId,
+        params: &Params,
+        paint_transform: Transform2D,
+        prefix: &str,
+    ) -> Option<String> {
+        let image = images.get(id)?;
+        let (width, height) = (image.pixels.width(), image.pixels.height());
+        let [ext_x, ext_y] = params.extent;
+
+        let image_id = match self.embedded_images.get(&id) {
+            Some(image_id) => *image_id,
+            None => {
+                let data_url = image.png_data_url()?;
+                let image_id = self.next_id();
+                let _ = writeln!(
+                    self.defs,
+                    r#"<image id="i{image_id}" width="{width}" height="{height}" preserveAspectRatio="none" xlink:href="{data_url}"/>"#,
+                );
+                self.embedded_images.insert(id, image_id);
+                image_id
+            }
+        };
+
+        let pattern_id = self.next_id();
+        let _ = writeln!(
+            self.defs,
+            r##"<pattern id="p{pattern_id}" patternUnits="userSpaceOnUse" width="{}" height="{}" patternTransform="{}"><use xlink:href="#i{image_id}" transform="scale({} {})"/></pattern>"##,
+            num(ext_x),
+            num(ext_y),
+            matrix(paint_transform),
+            num(ext_x / width as f32),
+            num(ext_y / height as f32),
+        );
+
+        // Only the alpha of the tint is kept
+        Some(format!(
+            r#"{prefix}="url(#p{pattern_id})" {prefix}-opacity="{}""#,
+            num(params.inner_col[3])
+        ))
+    }
+
+    // Returns the id of the clip path for the scissor of `params`, defining the clip path if needed.
+    fn clip(&mut self, params: &Params) -> Option<usize> {
+        // The scissor matrix is the inverse of the scissor transform, it's only left zero without a scissor
+        if params.scissor_mat == [0.0; 12] {
+            return None;
+        }
+
+        let id = match self.last_clip {
+            Some((mat, ext, id)) if mat == params.scissor_mat && ext == params.scissor_ext => id,
+            _ => {
+                let id = self.next_id();
+                let [ext_x, ext_y] = params.scissor_ext;
+                let _ = writeln!(
+                    self.defs,
+                    r#"<clipPath id="c{id}"><rect x="{}" y="{}" width="{}" height="{}" transform="{}"/></clipPath>"#,
+                    num(-ext_x),
+                    num(-ext_y),
+                    num(ext_x * 2.0),
+                    num(ext_y * 2.0),
+                    matrix(paint_transform(params.scissor_mat)),
+                );
+                self.last_clip = Some((params.scissor_mat, params.scissor_ext, id));
+                id
+            }
+        };
+
+        Some(id)
+    }
+}
+
+impl Renderer for SvgRenderer {
+    type Image = SvgImage;
+    type NativeTexture = ();
+    type Surface = ();
+
+    fn set_size(&mut self, width: u32, height: u32, _dpi: f32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    fn draws_text_as_paths(&self) -> bool {
+        true
+    }
+
+    fn draws_path_outlines(&self) -> bool {
+        true
+    }
+
+    fn render(
+        &mut self,
+        _surface: &Self::Surface,
+        images: &mut ImageStore<SvgImage>,
+        verts: &[Vertex],
+        commands: Vec<Command>,
+    ) {
+        for cmd in commands {
+            if let CommandType::SetRenderTarget(target) = cmd.cmd_type {
+                self.render_target = target;
+                continue;
+            }
+
+            if self.render_target != RenderTarget::Screen {
+                continue;
+            }
+
+            let params = match &cmd.cmd_type {
+                CommandType::ClearRect { color } => {
+                    self.clear_rect(verts, &cmd, *color);
+                    continue;
+                }
+                CommandType::ConvexFill { params }
+                | CommandType::ConcaveFill {
+                    fill_params: params, ..
+                }
+                | CommandType::Stroke { params }
+                | CommandType::StencilStroke { params2: params, .. } => params,
+                // Glyphs from an atlas and image filters have no outlines
+                _ => continue,
+            };
+
+            for outline in &cmd.outlines {
+                self.draw(images, &cmd, params, outline);
+            }
+        }
+    }
+
+    fn alloc_image(&mut self, info: ImageInfo) -> Result<Self::Image, ErrorKind> {
+        Ok(SvgImage {
+            info,
+            pixels: ImgVec::new(
+                vec![RGBA8::default(); info.width() * info.height()],
+                info.width(),
+                info.height(),
+            ),
+        })
+    }
+
+    fn create_image_from_native_texture(
+        &mut self,
+        _native_texture: Self::NativeTexture,
+        _info: ImageInfo,
+    ) -> Result<Self::Image, ErrorKind> {
+        Err(ErrorKind::UnsupportedImageFormat)
+    }
+
+    fn update_image(
+        &mut self,
+        image: &mut Self::Image,
+        data: ImageSource,
+        x: usize,
+        y: usize,
+    ) -> Result<(), ErrorKind> {
+        let size = data.dimensions();
+
+        if x + size.width > image.info.width() || y + size.height > image.info.height() {
+            return Err(ErrorKind::ImageUpdateOutOfBounds);
+        }
+
+        let mut target = image.pixels.sub_image_mut(x, y, size.width, size.height);
+
+        // Pixels are kept like the shaders sample them, gray images are alpha masks
+        match data {
+            ImageSource::Rgb(src) => {
+                for (target, src) in target.pixels_mut().zip(src.pixels()) {
+                    *target = RGBA8::new(src.r, src.g, src.b, 255);
+                }
+            }
+            ImageSource::Rgba(src) => {
+                for (target, src) in target.pixels_mut().zip(src.pixels()) {
+                    *target = src;
+                }
+            }
+            ImageSource::Gray(src) => {
+                for (target, src) in target.pixels_mut().zip(src.pixels()) {
+                    let value = src.value();
+                    *target = RGBA8::new(value, value, value, value);
+                }
+            }
+            #[cfg(feature = "image-loading")]
+            ImageSource::Dynamic(_) => return Err(ErrorKind::UnsupportedImageFormat),
+            #[cfg(target_arch = "wasm32")]
+            ImageSource::HtmlImageElement(..) | ImageSource::ImageBitmap(..) => {
+                return Err(ErrorKind::UnsupportedImageFormat)
+            }
+        }
+
+        Ok(())
+    }
+
+    fn delete_image(&mut self, _image: Self::Image, image_id: ImageId) {
+        self.embedded_images.remove(&image_id);
+    }
+
+    fn screenshot(&mut self) -> Result<ImgVec<RGBA8>, ErrorKind> {
+        Err(ErrorKind::UnsupportedOperation)
+    }
+}
+
+impl SurfacelessRenderer for SvgRenderer {
+    fn render_surfaceless(&mut self, images: &mut ImageStore<Self::Image>, verts: &[Vertex], commands: Vec<Command>) {
+        self.render(&(), images, verts, commands);
+    }
+}
+
+/// Image allocated by an [`SvgRenderer`].
+pub struct SvgImage {
+    info: ImageInfo,
+    pixels: ImgVec<RGBA8>,
+}
+
+impl SvgImage {
+    // Returns a data URL of the image with straight alpha, encoded as PNG.
+    #[cfg(feature = "svg-export-images")]
+    fn png_data_url(&self) -> Option<String> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        use crate::{image::unpremultiplied_dynamic_image, ImageFlags, PixelFormat};
+
+        let premultiplied =
+            self.info.flags().contains(ImageFlags::PREMULTIPLIED) || self.info.format() == PixelFormat::Gray8;
+        let image = if premultiplied {
+            unpremultiplied_dynamic_image(self.pixels.clone())
+        } else {
+            let (buf, width, height) = self.pixels.clone().into_contiguous_buf();
+            let bytes = buf
+                .into_iter()
+                .flat_map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a])
+                .collect();
+            ::image::RgbaImage::from_raw(width as u32, height as u32, bytes)?.into()
+        };
+
+        let mut png = Vec::new();
+        image
+            .write_to(&mut std::io::Cursor::new(&mut png), ::image::ImageFormat::Png)
+            .ok()?;
+
+        Some(format!("data:image/png;base64,{}", STANDARD.encode(png)))
+    }
+
+    // Images can't be embedded without an encoder
+    #[cfg(not(feature = "svg-export-images"))]
+    fn png_data_url(&self) -> Option<String> {
+        None
+    }
+}
+
+// Rounds coordinates so that the document stays readable.
+fn num(value: f32) -> f32 {
+    (value * 1000.0).round() / 1000.0
+}
+
+// Returns the transform a matrix of the shaders maps from, those matrices are stored inverted.
+fn paint_transform(mat: [f32; 12]) -> Transform2D {
+    Transform2D([mat[0], mat[1], mat[4], mat[5], mat[8], mat[9]]).inverse()
+}
+
+fn matrix(transform: Transform2D) -> String {
+    let Transform2D([a, b, c, d, e, f]) = transform;
+    format!(
+        "matrix({} {} {} {} {} {})",
+        num(a),
+        num(b),
+        num(c),
+        num(d),
+        num(e),
+        num(f)
+    )
+}
+
+// Returns the attributes of a premultiplied color, `prefix` is "fill", "stroke" or "stop".
+fn color_attributes(prefix: &str, [r, g, b, a]: [f32; 4]) -> String {
+    let unpremultiply = |c: f32| if a > 0.0 { (c / a).clamp(0.0, 1.0) } else { 0.0 };
+    let [r, g, b] = [r, g, b].map(|c| (unpremultiply(c) * 255.0).round() as u8);
+    let color_name = if prefix == "stop" { "stop-color" } else { prefix };
+
+    format!(
+        r##"{color_name}="#{r:02x}{g:02x}{b:02x}" {prefix}-opacity="{}""##,
+        num(a.clamp(0.0, 1.0))
+    )
+}
+
+// Returns the premultiplied color of gradient stops at `offset`.
+fn sample_stops(stops: &[(f32, [f32; 4])], offset: f32) -> [f32; 4] {
+    let next = stops
+        .iter()
+        .position(|stop| stop.0 >= offset)
+        .unwrap_or(stops.len() - 1);
+    if next == 0 {
+        return stops[0].1;
+    }
+
+    let (offset0, color0) = stops[next - 1];
+    let (offset1, color1) = stops[next];
+    let t = if offset1 > offset0 {
+        (offset - offset0) / (offset1 - offset0)
+    } else {
+        0.0
+    };
+
+    std::array::from_fn(|channel| color0[channel] + (color1[channel] - color0[channel]) * t)
+}
+
+// A sub-path of an outline, with the control points and end point of each of its segments.
+#[derive(Default)]
+struct Contour {
+    start: [f32; 2],
+    segments: Vec<Vec<[f32; 2]>>,
+    closed: bool,
+    solidity: Option<Solidity>,
+}
+
+impl Contour {
+    // Returns the area of the polygon through all points, see `Contour::polygon_area` of the path cache.
+    fn area(&self) -> f32 {
+        let points: Vec<_> = std::iter::once(self.start)
+            .chain(self.segments.iter().flatten().copied())
+            .collect();
+
+        let mut area = 0.0;
+        for (index, p1) in points.iter().enumerate() {
+            let p0 = points[(index + points.len() - 1) % points.len()];
+            area += (p1[0] - p0[0]) * (p1[1] + p0[1]);
+        }
+
+        area * 0.5
+    }
+
+    fn reverse(&mut self) {
+        let mut start = self.start;
+        let mut segments = Vec::with_capacity(self.segments.len());
+
+        // Every segment is walked from its end back to the end of the previous one
+        for segment in &self.segments {
+            let mut reversed: Vec<_> = segment[..segment.len() - 1].iter().rev().copied().collect();
+            reversed.push(start);
+            start = segment[segment.len() - 1];
+            segments.push(reversed);
+        }
+        segments.reverse();
+
+        self.start = start;
+        self.segments = segments;
+    }
+
+    fn write(&self, data: &mut String) {
+        let _ = write!(data, "M{} {}", num(self.start[0]), num(self.start[1]));
+        for segment in &self.segments {
+            data.push(if segment.len() == 1 { 'L' } else { 'C' });
+            for (index, point) in segment.iter().enumerate() {
+                let separator = if index == 0 { "" } else { " " };
+                let _ = write!(data, "{separator}{} {}", num(point[0]), num(point[1]));
+            }
+        }
+        if self.closed {
+            data.push('Z');
+        }
+    }
+}
+
+// Returns the path data of the verbs. Like the path cache, contours with a solidity are reversed if their winding
+// doesn't match it on the screen, which the transform of the outline mirrors if `mirrored` is set.
+fn path_data(verbs: &[Verb], mirrored: bool) -> String {
+    let mut contours: Vec<Contour> = Vec::new();
+
+    for verb in verbs {
+        match *verb {
+            Verb::MoveTo(x, y) => contours.push(Contour {
+                start: [x, y],
+                ..Default::default()
+            }),
+            Verb::LineTo(x, y) => {
+                if let Some(contour) = contours.last_mut() {
+                    contour.segments.push(vec![[x, y]]);
+                }
+            }
+            Verb::BezierTo(c1x, c1y, c2x, c2y, x, y) => {
+                if let Some(contour) = contours.last_mut() {
+                    contour.segments.push(vec![[c1x, c1y], [c2x, c2y], [x, y]]);
+                }
+            }
+            Verb::Close => {
+                if let Some(contour) = contours.last_mut() {
+                    contour.closed = true;
+                }
+            }
+            Verb::Solid | Verb::Hole => {
+                if let Some(contour) = contours.last_mut() {
+                    contour.solidity = Some(if matches!(verb, Verb::Solid) {
+                        Solidity::Solid
+                    } else {
+                        Solidity::Hole
+                    });
+                }
+            }
+        }
+    }
+
+    let mut data = String::new();
+
+    for mut contour in contours {
+        if contour.segments.is_empty() {
+            continue;
+        }
+
+        if let Some(solidity) = contour.solidity {
+            let area = if mirrored { -contour.area() } else { contour.area() };
+
+            if (solidity == Solidity::Solid && area < 0.0) || (solidity == Solidity::Hole && area > 0.0) {
+                contour.reverse();
+            }
+        }
+
+        contour.write(&mut data);
+    }
+
+    data
+}
@@ -17,25 +17,26 @@ use crate::ImageInfo;
 use crate::RenderTarget;
 use crate::Scissor;
 
+use super::CompressedFormats;
 use super::Renderer;
+use super::RendererCapabilities;
 
 pub use wgpu;
 
+mod surface;
+pub use surface::WGPUSurface;
+
 use super::Params;
 use super::Vertex;
 
-const UNIFORMARRAY_SIZE: usize = 14;
+const UNIFORMARRAY_SIZE: usize = 16;
 
 #[derive(Clone, PartialEq)]
 pub struct UniformArray([f32; UNIFORMARRAY_SIZE * 4]);
 
 impl Default for UniformArray {
     fn default() -> Self {
-        Self([
-            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
-            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
-            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
-        ])
+        Self([0.0; UNIFORMARRAY_SIZE * 4])
     }
 }
 
@@ -111,6 +112,34 @@ impl UniformArray {
     pub fn set_image_blur_filter_coeff(&mut self, coeff: [f32; 3]) {
         self.0[48..51].copy_from_slice(&coeff);
     }
+
+    pub fn set_path_gradient(&mut self, path_gradient: f32) {
+        self.0[51] = path_gradient;
+    }
+
+    pub fn set_tex_rect(&mut self, rect: [f32; 4]) {
+        self.0[52..56].copy_from_slice(&rect);
+    }
+
+    pub fn set_mask_gamma(&mut self, gamma: f32) {
+        self.0[56] = gamma;
+    }
+
+    pub fn set_mask_contrast(&mut self, contrast: f32) {
+        self.0[57] = contrast;
+    }
+
+    pub fn set_focal_gradient(&mut self, focal_gradient: f32) {
+        self.0[58] = focal_gradient;
+    }
+
+    pub fn set_gradient_spread(&mut self, spread: f32) {
+        self.0[59] = spread;
+    }
+
+    pub fn set_image_blur_filter_kawase(&mut self, kawase: f32) {
+        self.0[60] = kawase;
+    }
 }
 
 impl From<&Params> for UniformArray {
@@ -134,6 +163,13 @@ impl From<&Params> for UniformArray {
         arr.set_image_blur_filter_direction(params.image_blur_filter_direction);
         arr.set_image_blur_filter_sigma(params.image_blur_filter_sigma);
         arr.set_image_blur_filter_coeff(params.image_blur_filter_coeff);
+        arr.set_path_gradient(params.path_gradient);
+        arr.set_tex_rect(params.tex_rect);
+        arr.set_mask_gamma(params.mask_gamma);
+        arr.set_mask_contrast(params.mask_contrast);
+        arr.set_focal_gradient(params.focal_gradient);
+        arr.set_gradient_spread(params.gradient_spread);
+        arr.set_image_blur_filter_kawase(params.image_blur_filter_kawase);
 
         arr
     }
@@ -166,6 +202,8 @@ pub struct WGPURenderer {
     viewport_bind_group_layout: Rc<wgpu::BindGroupLayout>,
     pipeline_layout: Rc<wgpu::PipelineLayout>,
     pipeline_cache: Rc<RefCell<HashMap<PipelineState, CachedPipeline>>>,
+    // Temporary textures of the halved levels of the fast blur, kept for images of the same size and format
+    blur_level_textures: Vec<Rc<wgpu::Texture>>,
 }
 
 impl WGPURenderer {
@@ -284,6 +322,7 @@ impl WGPURenderer {
             viewport_bind_group_layout,
             pipeline_layout,
             pipeline_cache: Default::default(),
+            blur_level_textures: Vec::new(),
         }
     }
 }
@@ -295,6 +334,34 @@ impl Renderer for WGPURenderer {
 
     fn set_size(&mut self, _width: u32, _height: u32, _dpi: f32) {}
 
+    fn capabilities(&self) -> RendererCapabilities {
+        let features = self.device.features();
+        let mut compressed_formats = CompressedFormats::empty();
+        compressed_formats.set(
+            CompressedFormats::BC,
+            features.contains(wgpu::Features::TEXTURE_COMPRESSION_BC),
+        );
+        compressed_formats.set(
+            CompressedFormats::ETC2,
+            features.contains(wgpu::Features::TEXTURE_COMPRESSION_ETC2),
+        );
+        compressed_formats.set(
+            CompressedFormats::ASTC,
+            features.contains(wgpu::Features::TEXTURE_COMPRESSION_ASTC),
+        );
+
+        RendererCapabilities {
+            max_texture_size: self.device.limits().max_texture_dimension_2d as usize,
+            npot_textures: true,
+            instancing: true,
+            advanced_blending: false,
+            float_render_targets: true,
+            compressed_formats,
+            max_samples: 1,
+            max_texture_array_layers: 1,
+        }
+    }
+
     fn render(
         &mut self,
         surface_texture: &Self::Surface,
@@ -374,6 +441,7 @@ impl Renderer for WGPURenderer {
                         RenderTarget::Screen => {
                             render_pass_builder.set_render_target_screen();
                         }
+                        // Images never have several layers without texture array support
                         RenderTarget::Image(image_id) => {
                             render_pass_builder.set_render_target_image(images, image_id, wgpu::LoadOp::Load);
                         }
@@ -451,6 +519,19 @@ impl Renderer for WGPURenderer {
                             target_image,
                         );
                     }
+                    crate::ImageFilter::FastBlur { radius } => {
+                        dual_kawase_blur_filter(
+                            &self.device,
+                            &mut self.blur_level_textures,
+                            &mut current_render_target,
+                            images,
+                            command,
+                            radius,
+                            &mut render_pass_builder,
+                            &mut pipeline_and_bindgroup_mapper,
+                            target_image,
+                        );
+                    }
                 },
             }
         }
@@ -465,6 +546,11 @@ impl Renderer for WGPURenderer {
     }
 
     fn alloc_image(&mut self, info: crate::ImageInfo) -> Result<Self::Image, crate::ErrorKind> {
+        let max_size = self.device.limits().max_texture_dimension_2d as usize;
+        if info.width() > max_size || info.height() > max_size {
+            return Err(crate::ErrorKind::UnsupportedImageSize);
+        }
+
         Ok(Image {
             texture: Rc::new(self.device.create_texture(&wgpu::TextureDescriptor {
                 label: None,
@@ -509,27 +595,39 @@ impl Renderer for WGPURenderer {
         y: usize,
     ) -> Result<(), crate::ErrorKind> {
         #[cfg(target_arch = "wasm32")]
-        if let crate::ImageSource::HtmlImageElement(htmlimage) = data {
-            self.queue.copy_external_image_to_texture(
-                &wgpu::ImageCopyExternalImage {
-                    source: wgpu::ExternalImageSource::HTMLImageElement(htmlimage.clone()),
-                    origin: wgpu::Origin2d::ZERO,
-                    flip_y: false,
-                },
-                wgpu::ImageCopyTextureTagged {
-                    texture: &image.texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                    color_space: wgpu::PredefinedColorSpace::Srgb,
-                    premultiplied_alpha: true,
-                },
-                wgpu::Extent3d {
-                    width: data.dimensions().width as _,
-                    height: data.dimensions().height as _,
-                    depth_or_array_layers: 1,
-                },
-            );
+        {
+            let external_source = match data {
+                crate::ImageSource::HtmlImageElement(htmlimage) => {
+                    Some(wgpu::ExternalImageSource::HTMLImageElement(htmlimage.clone()))
+                }
+                crate::ImageSource::ImageBitmap(bitmap) => Some(wgpu::ExternalImageSource::ImageBitmap(bitmap.clone())),
+                _ => None,
+            };
+
+            if let Some(source) = external_source {
+                self.queue.copy_external_image_to_texture(
+                    &wgpu::ImageCopyExternalImage {
+                        source,
+                        origin: wgpu::Origin2d::ZERO,
+                        flip_y: false,
+                    },
+                    wgpu::ImageCopyTextureTagged {
+                        texture: &image.texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                        color_space: wgpu::PredefinedColorSpace::Srgb,
+                        premultiplied_alpha: true,
+                    },
+                    wgpu::Extent3d {
+                        width: data.dimensions().width as _,
+                        height: data.dimensions().height as _,
+                        depth_or_array_layers: 1,
+                    },
+                );
+
+                return Ok(());
+            }
         }
 
         use rgb::ComponentBytes;
@@ -550,8 +648,10 @@ impl Renderer for WGPURenderer {
             }
             crate::ImageSource::Rgba(img) => (img.buf().as_bytes(), 4),
             crate::ImageSource::Gray(img) => (img.buf().as_bytes(), 1),
+            #[cfg(feature = "image-loading")]
+            crate::ImageSource::Dynamic(_) => return Err(crate::ErrorKind::UnsupportedImageFormat),
             #[cfg(target_arch = "wasm32")]
-            crate::ImageSource::HtmlImageElement(..) => {
+            crate::ImageSource::HtmlImageElement(..) | crate::ImageSource::ImageBitmap(..) => {
                 unreachable!()
             }
         };
@@ -704,6 +804,148 @@ fn gaussian_blur_filter(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
+fn dual_kawase_blur_filter(
+    device: &wgpu::Device,
+    blur_level_textures: &mut Vec<Rc<wgpu::Texture>>,
+    current_render_target: &mut RenderTarget,
+    images: &mut ImageStore<Image>,
+    command: super::Command,
+    radius: f32,
+    render_pass_builder: &mut RenderPassBuilder<'_>,
+    pipeline_and_bindgroup_mapper: &mut CommandToPipelineAndBindGroupMapper,
+    target_image: ImageId,
+) {
+    let blend_state = blend_state(&command).into();
+
+    let previous_render_target = *current_render_target;
+
+    let source_image = images.get(command.image.unwrap()).unwrap();
+    let size = (
+        source_image.texture.width() as usize,
+        source_image.texture.height() as usize,
+    );
+    let format = source_image.texture.format();
+
+    let (levels, offset) = Params::dual_kawase_levels(radius, size.0, size.1);
+
+    let image_paint = crate::Paint::image(command.image.unwrap(), 0., 0., size.0 as _, size.1 as _, 0., 1.);
+
+    let mut blur_params = Params::new(
+        images,
+        &Default::default(),
+        &image_paint.flavor,
+        &Default::default(),
+        &Scissor::default(),
+        0.,
+        0.,
+        0.,
+    );
+    blur_params.shader_type = ShaderType::FilterImage;
+
+    // The image is halved into a chain of temporary textures and then scaled back up through them, with the last
+    // pass rendering into the target image.
+    let level_size = |level: usize| ((size.0 >> level).max(1), (size.1 >> level).max(1));
+    let level_textures = dual_kawase_level_textures(device, blur_level_textures, size, format, levels);
+
+    let downsample_passes = (1..=levels).map(|level| (level - 1, level, false));
+    let upsample_passes = (0..levels).rev().map(|level| (level + 1, level, true));
+
+    for (from, to, upsample) in downsample_passes.chain(upsample_passes) {
+        if to == 0 {
+            render_pass_builder.set_render_target_image(
+                images,
+                target_image,
+                wgpu::LoadOp::Clear(wgpu::Color::default()),
+            );
+        } else {
+            render_pass_builder.set_render_target_texture(
+                &level_textures[to - 1],
+                None,
+                wgpu::LoadOp::Clear(wgpu::Color::default()),
+            );
+        }
+
+        blur_params.set_dual_kawase_pass(level_size(from), level_size(to), offset, upsample);
+
+        let source = if from == 0 {
+            command.image.map(ImageOrTexture::Image)
+        } else {
+            Some(ImageOrTexture::Texture(level_textures[from - 1].clone()))
+        };
+
+        if let Some((start, count)) = command.triangles_verts {
+            pipeline_and_bindgroup_mapper.update_renderpass(
+                render_pass_builder,
+                blend_state,
+                wgpu::PrimitiveTopology::TriangleList,
+                StencilTest::Disabled,
+                Some(wgpu::Face::Back),
+                &blur_params,
+                images,
+                source,
+                command.glyph_texture,
+            );
+            render_pass_builder.draw(start as u32..(start + count) as u32);
+        }
+
+        // only the source image may have straight alpha, the temporary textures hold the premultiplied result
+        if blur_params.tex_type == 1.0 {
+            blur_params.tex_type = 0.0;
+        }
+    }
+
+    *current_render_target = previous_render_target;
+    match *current_render_target {
+        RenderTarget::Screen => {
+            render_pass_builder.set_render_target_screen();
+        }
+        RenderTarget::Image(image_id) => {
+            render_pass_builder.set_render_target_image(images, image_id, wgpu::LoadOp::Load);
+        }
+    }
+}
+
+// Returns the temporary textures of the given number of halved levels of an image, creating those that aren't cached
+// yet.
+fn dual_kawase_level_textures(
+    device: &wgpu::Device,
+    blur_level_textures: &mut Vec<Rc<wgpu::Texture>>,
+    size: (usize, usize),
+    format: wgpu::TextureFormat,
+    levels: usize,
+) -> Vec<Rc<wgpu::Texture>> {
+    let level_size = |level: usize| ((size.0 >> level).max(1) as u32, (size.1 >> level).max(1) as u32);
+
+    // textures of another size or format can't be reused
+    let reusable = blur_level_textures
+        .first()
+        .is_some_and(|texture| (texture.width(), texture.height()) == level_size(1) && texture.format() == format);
+    if !reusable {
+        blur_level_textures.clear();
+    }
+
+    while blur_level_textures.len() < levels {
+        let (width, height) = level_size(blur_level_textures.len() + 1);
+        blur_level_textures.push(Rc::new(device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("blur level"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })));
+    }
+
+    blur_level_textures[..levels].to_vec()
+}
+
 fn triangles(
     command: &super::Command,
     pipeline_and_bindgroup_mapper: &mut CommandToPipelineAndBindGroupMapper,
@@ -12,10 +12,17 @@ struct Params {
     stroke_thr: f32,
     tex_type: f32,
     _unused_shader_type: f32,
-    glyph_texture_type: f32, // 0 -> no glyph rendering, 1 -> alpha mask, 2 -> color texture
+    glyph_texture_type: f32, // 0 -> no glyph rendering, 1 -> alpha mask, 2 -> color texture, 5 and 6 -> image
     image_blur_filter_sigma: f32,
     image_blur_filter_direction: vec2<f32>,
     image_blur_filter_coeff: vec3<f32>,
+    path_gradient: f32,
+    tex_rect: vec4<f32>,
+    mask_gamma: f32,
+    mask_contrast: f32,
+    focal_gradient: f32,
+    gradient_spread: f32,
+    image_blur_filter_kawase: f32, // 0 -> Gaussian blur, 1 -> Kawase downsample, 2 -> Kawase upsample
 }
 
 override shader_type: i32;
@@ -143,9 +150,12 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
         // Textured tris
         var mask: vec4<f32> = textureSample(glyph_texture, glyph_sampler, vertex.ftcoord);
 
+        // 5 and 6 are images multiplied with the paint, with straight and premultiplied alpha
         if (params.glyph_texture_type == 1) {
-            mask = vec4<f32>(mask.x);
-        } else {
+            mask = vec4<f32>(adjustMaskCoverage(mask.x, params));
+        } else if (params.glyph_texture_type == 5) {
+            mask = vec4<f32>(mask.xyz * mask.w, mask.w);
+        } else if (params.glyph_texture_type != 6) {
             result = vec4<f32>(1, 1, 1, 1);
             mask = vec4<f32>(mask.xyz * mask.w, mask.w);
         }
@@ -175,33 +185,116 @@ fn scissorMask(p: vec2<f32>, params: Params) -> f32 {
 }
 
 // Stroke - from [0..1] to clipped pyramid, where the slope is 1px.
+// Strokes with a gradient along the path store the position along the path in v: the start cap fringe goes
+// from 0 to 1, the path from 1 to 2 and the end cap fringe from 2 to 3.
+fn capMask(vertex: VertexOutput, params: Params) -> f32 {
+    if (params.path_gradient == 0.0) {
+        return min(1.0, vertex.ftcoord.y);
+    }
+    return min(1.0, min(vertex.ftcoord.y, 3.0 - vertex.ftcoord.y));
+}
+
 fn strokeMask(vertex: VertexOutput, params: Params) -> f32 {
-    return min(1.0, (1.0-abs(vertex.ftcoord.x*2.0-1.0))*params.stroke_mult) * min(1.0, vertex.ftcoord.y);
+    return min(1.0, (1.0-abs(vertex.ftcoord.x*2.0-1.0))*params.stroke_mult) * capMask(vertex, params);
     // Using this smoothstep preduces maybe better results when combined with fringe_width of 2, but it may look blurrier
     // maybe this should be controlled via flag
     //return smoothstep(0.0, 1.0, (1.0-abs(vertex.ftcoord.x*2.0-1.0))*params.stroke_mult) * smoothstep(0.0, 1.0, vertex.ftcoord.y);
 }
 
-fn renderGradient(vertex: VertexOutput, params: Params) -> vec4<f32> {
-    // Calculate gradient color using box gradient
-    let pt: vec2<f32> = (params.paint_mat * vec3<f32>(vertex.fpos, 1.0)).xy;
+// Position a gradient is sampled at, before the paint transform
+fn gradientPos(vertex: VertexOutput, params: Params) -> vec2<f32> {
+    if (params.path_gradient == 0.0) {
+        return vertex.fpos;
+    }
+    return vec2<f32>(clamp(vertex.ftcoord.y - 1.0, 0.0, 1.0), 0.0);
+}
 
-    let d: f32 = clamp((sdroundrect(pt, params.extent, params.radius) + params.feather*0.5) / params.feather, 0.0, 1.0);
-    return mix(params.inner_col,params.outer_col,d);
+// Two-point radial gradient from the focal circle at the origin with `radius` to the circle at `extent` with
+// `feather` as its radius. Returns the gradient position of the largest circle through the point in x and 0 in y if
+// no circle with a positive radius passes through it.
+fn focalGradientPos(pt: vec2<f32>, params: Params) -> vec2<f32> {
+    let dr = params.feather - params.radius;
+    let a = dot(params.extent, params.extent) - dr * dr;
+    let b = dot(pt, params.extent) + params.radius * dr;
+    let c = dot(pt, pt) - params.radius * params.radius;
+
+    if (abs(a) < 1e-6) {
+        if (abs(b) < 1e-6) {
+            return vec2<f32>(0.0);
+        }
+        let t = c / (2.0 * b);
+        return vec2<f32>(t, select(0.0, 1.0, params.radius + t * dr >= 0.0));
+    }
+
+    let discriminant = b * b - a * c;
+    if (discriminant < 0.0) {
+        return vec2<f32>(0.0);
+    }
+
+    let root = sqrt(discriminant);
+    let t0 = max((b + root) / a, (b - root) / a);
+    let t1 = min((b + root) / a, (b - root) / a);
+    if (params.radius + t0 * dr >= 0.0) {
+        return vec2<f32>(t0, 1.0);
+    }
+    return vec2<f32>(t1, select(0.0, 1.0, params.radius + t1 * dr >= 0.0));
+}
+
+// Maps a gradient position outside of 0 to 1 back into that range, depending on the spread mode
+fn spreadGradient(t: f32, params: Params) -> f32 {
+    if (params.gradient_spread == 1.0) {
+        return fract(t);
+    }
+    if (params.gradient_spread == 2.0) {
+        return 1.0 - abs(t - 2.0 * floor(t * 0.5) - 1.0);
+    }
+    return clamp(t, 0.0, 1.0);
+}
+
+// Gradient position in x, coverage in y
+fn gradientOffset(vertex: VertexOutput, params: Params) -> vec2<f32> {
+    let pt: vec2<f32> = (params.paint_mat * vec3<f32>(gradientPos(vertex, params), 1.0)).xy;
+
+    if (params.focal_gradient != 0.0) {
+        let focal = focalGradientPos(pt, params);
+        return vec2<f32>(spreadGradient(focal.x, params), focal.y);
+    }
+
+    // Box gradient
+    return vec2<f32>(spreadGradient((sdroundrect(pt, params.extent, params.radius) + params.feather*0.5) / params.feather, params), 1.0);
+}
+
+fn renderGradient(vertex: VertexOutput, params: Params) -> vec4<f32> {
+    let d = gradientOffset(vertex, params);
+    return mix(params.inner_col,params.outer_col,d.x) * d.y;
 }
 
 // Image-based Gradient; sample a texture using the gradient position.
 fn renderImageGradient(vertex: VertexOutput, params: Params) -> vec4<f32> {
-    // Calculate gradient color using box gradient
-    let pt: vec2<f32> = (params.paint_mat * vec3<f32>(vertex.fpos, 1.0)).xy;
+    let d = gradientOffset(vertex, params);
+    return textureSample(image_texture, image_sampler, vec2<f32>(d.x, 0.0)) * d.y;
+}
 
-    let d: f32 = clamp((sdroundrect(pt, params.extent, params.radius) + params.feather*0.5) / params.feather, 0.0, 1.0);
-    return textureSample(image_texture, image_sampler, vec2<f32>(d, 0.0));//mix(innerCol,outerCol,d);
+// Applies the gamma and contrast of the text to the coverage of an alpha mask glyph
+fn adjustMaskCoverage(coverage: f32, params: Params) -> f32 {
+    var adjusted = coverage;
+    if (params.mask_gamma > 0.0) {
+        adjusted = pow(adjusted, 1.0 / params.mask_gamma);
+    }
+    return clamp((adjusted - 0.5) * (1.0 + params.mask_contrast) + 0.5, 0.0, 1.0);
+}
+
+// Keeps the texture coordinates of images drawn from a source rectangle within it
+fn clampToTexRect(pt: vec2<f32>, params: Params) -> vec2<f32> {
+    if (params.tex_rect.z > 0.0) {
+        return clamp(pt, params.tex_rect.xy, params.tex_rect.zw);
+    }
+    return pt;
 }
 
 fn renderImage(vertex: VertexOutput, params: Params) -> vec4<f32> {
     // Calculate color from texture
-    let pt: vec2<f32> = (params.paint_mat * vec3<f32>(vertex.fpos, 1.0)).xy / params.extent;
+    let pt: vec2<f32> = clampToTexRect((params.paint_mat * vec3<f32>(vertex.fpos, 1.0)).xy / params.extent, params);
 
     var color: vec4<f32> = textureSample(image_texture, image_sampler, pt);
 
@@ -214,7 +307,7 @@ fn renderImage(vertex: VertexOutput, params: Params) -> vec4<f32> {
 }
 
 fn renderPlainTextureCopy(vertex: VertexOutput, params: Params) -> vec4<f32> {
-    var color: vec4<f32> = textureSample(image_texture, image_sampler, vertex.ftcoord);
+    var color: vec4<f32> = textureSample(image_texture, image_sampler, clampToTexRect(vertex.ftcoord, params));
 
     if (params.tex_type == 1) { color = vec4(color.xyz * color.w, color.w); }
     if (params.tex_type == 2) { color = vec4(color.x); }
@@ -223,7 +316,44 @@ fn renderPlainTextureCopy(vertex: VertexOutput, params: Params) -> vec4<f32> {
     return color;
 }
 
+// One pass of a dual Kawase blur, image_blur_filter_direction holds the offset of the samples in texture coordinates
+fn renderKawaseFilteredImage(vertex: VertexOutput, params: Params) -> vec4<f32> {
+    let uv = vertex.fpos.xy / params.extent;
+    let o = params.image_blur_filter_direction;
+    var color: vec4<f32>;
+
+    if (params.image_blur_filter_kawase == 1.0) {
+        // downsample
+        color = textureSample(image_texture, image_sampler, uv) * 4.0;
+        color += textureSample(image_texture, image_sampler, uv - o);
+        color += textureSample(image_texture, image_sampler, uv + o);
+        color += textureSample(image_texture, image_sampler, uv + vec2<f32>(o.x, -o.y));
+        color += textureSample(image_texture, image_sampler, uv - vec2<f32>(o.x, -o.y));
+        color /= 8.0;
+    } else {
+        // upsample
+        color = textureSample(image_texture, image_sampler, uv + vec2<f32>(-o.x * 2.0, 0.0));
+        color += textureSample(image_texture, image_sampler, uv + vec2<f32>(o.x * 2.0, 0.0));
+        color += textureSample(image_texture, image_sampler, uv + vec2<f32>(0.0, -o.y * 2.0));
+        color += textureSample(image_texture, image_sampler, uv + vec2<f32>(0.0, o.y * 2.0));
+        color += textureSample(image_texture, image_sampler, uv - o) * 2.0;
+        color += textureSample(image_texture, image_sampler, uv + o) * 2.0;
+        color += textureSample(image_texture, image_sampler, uv + vec2<f32>(o.x, -o.y)) * 2.0;
+        color += textureSample(image_texture, image_sampler, uv - vec2<f32>(o.x, -o.y)) * 2.0;
+        color /= 12.0;
+    }
+
+    if (params.tex_type == 1) { color = vec4<f32>(color.xyz * color.w, color.w); }
+    if (params.tex_type == 2) { color = vec4<f32>(color.x); }
+
+    return color;
+}
+
 fn renderFilteredImage(vertex: VertexOutput, params: Params) -> vec4<f32> {
+    if (params.image_blur_filter_kawase != 0.0) {
+        return renderKawaseFilteredImage(vertex, params);
+    }
+
     let sampleCount: f32 = ceil(1.5 * params.image_blur_filter_sigma);
 
     var gaussian_coeff: vec3<f32> = params.image_blur_filter_coeff;
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use crate::{Canvas, ErrorKind};
+
+use super::WGPURenderer;
+
+/// A window or HTML canvas surface to present a [`WGPURenderer`] canvas on.
+///
+/// On the web, WebGPU is used where the browser supports it (with the `webgpu` feature), falling
+/// back to WebGL 2 otherwise.
+pub struct WGPUSurface {
+    device: Arc<wgpu::Device>,
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+}
+
+impl WGPUSurface {
+    /// Creates a surface of the given size for `target` and a renderer for a matching device.
+    ///
+    /// On the web, `target` is typically a `wgpu::SurfaceTarget::Canvas` for an HTML canvas element, or a
+    /// `wgpu::SurfaceTarget::OffscreenCanvas` to render from a web worker. The size of an offscreen canvas follows
+    /// the size of the surface.
+    pub async fn new(
+        target: impl Into<wgpu::SurfaceTarget<'static>>,
+        width: u32,
+        height: u32,
+    ) -> Result<(Self, WGPURenderer), ErrorKind> {
+        let instance = wgpu::util::new_instance_with_webgpu_detection(wgpu::InstanceDescriptor::default()).await;
+
+        let surface = instance
+            .create_surface(target)
+            .map_err(|error| ErrorKind::RenderTargetError(error.to_string()))?;
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: Some(&surface),
+                ..Default::default()
+            })
+            .await
+            .ok_or_else(|| ErrorKind::GeneralError("No suitable graphics adapter found".to_owned()))?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    required_features: wgpu::Features::empty(),
+                    // WebGL 2 limits, with the texture sizes of the adapter to support images the size of the surface
+                    required_limits: wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+                    memory_hints: wgpu::MemoryHints::MemoryUsage,
+                },
+                None,
+            )
+            .await
+            .map_err(|error| ErrorKind::GeneralError(error.to_string()))?;
+
+        let mut config = surface
+            .get_default_config(&adapter, width.max(1), height.max(1))
+            .ok_or_else(|| ErrorKind::RenderTargetError("Surface is not supported by the adapter".to_owned()))?;
+
+        // The canvas blends in non-linear color space, so prefer a format without sRGB conversion
+        let capabilities = surface.get_capabilities(&adapter);
+        if let Some(format) = capabilities.formats.iter().find(|format| !format.is_srgb()) {
+            config.format = *format;
+        }
+
+        let device = Arc::new(device);
+        surface.configure(&device, &config);
+
+        let renderer = WGPURenderer::new(device.clone(), Arc::new(queue));
+
+        Ok((
+            Self {
+                device,
+                surface,
+                config,
+            },
+            renderer,
+        ))
+    }
+
+    /// Resizes the surface, call this along with [`Canvas::set_size()`].
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.config.width = width.max(1);
+        self.config.height = height.max(1);
+        self.surface.configure(&self.device, &self.config);
+    }
+
+    /// Renders the commands of the canvas to the next frame of the surface and presents it.
+    pub fn present(&self, canvas: &mut Canvas<WGPURenderer>) -> Result<(), ErrorKind> {
+        let frame = self
+            .surface
+            .get_current_texture()
+            .map_err(|error| ErrorKind::RenderTargetError(error.to_string()))?;
+
+        canvas.flush_to_surface(&frame.texture);
+        frame.present();
+
+        Ok(())
+    }
+}
+
+impl WGPURenderer {
+    /// Creates a surface for a window and a canvas of the given size that renders to it, see [`WGPUSurface::new()`].
+    ///
+    /// The window is anything implementing the `HasWindowHandle` and `HasDisplayHandle` traits of
+    /// [raw-window-handle](https://docs.rs/raw-window-handle) 0.6, like an `Arc<winit::window::Window>`.
+    pub async fn new_from_window(
+        window: impl wgpu::WindowHandle + 'static,
+        width: u32,
+        height: u32,
+        dpi: f32,
+    ) -> Result<(Canvas<Self>, WGPUSurface), ErrorKind> {
+        let (surface, renderer) = WGPUSurface::new(window, width, height).await?;
+
+        let mut canvas = Canvas::new(renderer)?;
+        canvas.set_size(width, height, dpi);
+
+        Ok((canvas, surface))
+    }
+}
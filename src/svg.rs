@@ -0,0 +1,335 @@
+//! Rendering of SVG documents parsed by [`usvg`].
+//!
+//! ```no_run
+//! use femtovg::{renderer::Void, svg::Svg, Canvas};
+//!
+//! let mut canvas = Canvas::new(Void).expect("Cannot create canvas");
+//!
+//! let data = std::fs::read("examples/assets/Ghostscript_Tiger.svg").expect("Cannot read file");
+//! let svg = Svg::from_data(&mut canvas, &data).expect("Cannot parse svg");
+//! svg.draw(&mut canvas);
+//! ```
+//!
+//! Fills, strokes, linear and radial gradients, group opacity and raster images (with the
+//! `image-loading` feature) are mapped to femtovg paints. femtovg can only clip to rectangles, so a
+//! clip path clips to its bounding box. Gradient transforms are applied to the gradient's end points,
+//! so skewed or non-uniformly scaled gradients are approximated. Radial gradients keep their focal
+//! point but drop the focal radius, which `usvg` doesn't provide. Patterns, masks, filters, blend
+//! modes and dashes are not supported and are skipped.
+//! Text is drawn from its outlines, which `usvg` only provides when its `text` feature is enabled.
+
+use usvg::tiny_skia_path::PathSegment;
+
+use crate::{
+    geometry::Rect, Canvas, Color, ErrorKind, FillRule, ImageId, LineCap, LineJoin, Paint, Path, Renderer, Spread,
+    Transform2D,
+};
+
+/// An SVG document converted to femtovg paths and paints, ready to be drawn any number of times.
+///
+/// Images embedded in the document are uploaded to the canvas it was created with and stay alive
+/// until [`Svg::delete()`] is called.
+pub struct Svg {
+    width: f32,
+    height: f32,
+    root: Group,
+    images: Vec<ImageId>,
+}
+
+impl Svg {
+    /// Parses SVG (or compressed SVGZ) data with default options and converts it for drawing on `canvas`.
+    pub fn from_data<T: Renderer>(canvas: &mut Canvas<T>, data: &[u8]) -> Result<Self, ErrorKind> {
+        let tree = usvg::Tree::from_data(data, &usvg::Options::default())?;
+
+        Ok(Self::from_tree(canvas, &tree))
+    }
+
+    /// Converts a parsed `usvg` tree for drawing on `canvas`.
+    pub fn from_tree<T: Renderer>(canvas: &mut Canvas<T>, tree: &usvg::Tree) -> Self {
+        let mut images = Vec::new();
+        let root = Group::new(canvas, tree.root(), &mut images);
+
+        Self {
+            width: tree.size().width(),
+            height: tree.size().height(),
+            root,
+            images,
+        }
+    }
+
+    /// Returns the width of the document.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Returns the height of the document.
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    /// Draws the document with its top-left corner at the origin of the current transform.
+    pub fn draw<T: Renderer>(&self, canvas: &mut Canvas<T>) {
+        self.root.draw(canvas);
+    }
+
+    /// Deletes the images that were created for this document.
+    pub fn delete<T: Renderer>(self, canvas: &mut Canvas<T>) {
+        for id in self.images {
+            canvas.delete_image(id);
+        }
+    }
+}
+
+struct Group {
+    transform: Transform2D,
+    opacity: f32,
+    // Clip rectangle in the coordinate system given by the transform, relative to the group
+    clip: Option<(Transform2D, Rect)>,
+    children: Vec<Node>,
+}
+
+struct PathNode {
+    path: Path,
+    fill: Option<Paint>,
+    stroke: Option<Paint>,
+    stroke_first: bool,
+}
+
+enum Node {
+    Group(Group),
+    Path(Box<PathNode>),
+}
+
+impl Group {
+    fn new<T: Renderer>(canvas: &mut Canvas<T>, group: &usvg::Group, images: &mut Vec<ImageId>) -> Self {
+        let clip = group.clip_path().map(|clip_path| {
+            let bounds = clip_path.root().bounding_box();
+
+            (
+                convert_transform(clip_path.transform()),
+                Rect::new(bounds.x(), bounds.y(), bounds.width(), bounds.height()),
+            )
+        });
+
+        let mut children = Vec::with_capacity(group.children().len());
+
+        for node in group.children() {
+            match node {
+                usvg::Node::Group(group) => children.push(Node::Group(Self::new(canvas, group, images))),
+                usvg::Node::Path(path) if path.is_visible() => children.push(convert_path(path)),
+                usvg::Node::Path(_) => {}
+                usvg::Node::Image(image) if image.is_visible() => {
+                    if let Some(node) = convert_image(canvas, image, images) {
+                        children.push(node);
+                    }
+                }
+                usvg::Node::Image(_) => {}
+                usvg::Node::Text(text) => children.push(Node::Group(Self::new(canvas, text.flattened(), images))),
+            }
+        }
+
+        Self {
+            transform: convert_transform(group.transform()),
+            opacity: group.opacity().get(),
+            clip,
+            children,
+        }
+    }
+
+    fn draw<T: Renderer>(&self, canvas: &mut Canvas<T>) {
+        canvas.save();
+        canvas.set_transform(&self.transform);
+
+        if let Some((clip_transform, rect)) = &self.clip {
+            let transform = canvas.transform();
+
+            canvas.set_transform(clip_transform);
+            canvas.intersect_scissor(rect.x, rect.y, rect.w, rect.h);
+            canvas.reset_transform();
+            canvas.set_transform(&transform);
+        }
+
+        let alpha = canvas.state().alpha;
+        canvas.set_global_alpha(alpha * self.opacity);
+
+        for child in &self.children {
+            match child {
+                Node::Group(group) => group.draw(canvas),
+                Node::Path(node) if node.stroke_first => {
+                    canvas.draw_path(&node.path, None, node.stroke.as_ref());
+                    canvas.draw_path(&node.path, node.fill.as_ref(), None);
+                }
+                Node::Path(node) => canvas.draw_path(&node.path, node.fill.as_ref(), node.stroke.as_ref()),
+            }
+        }
+
+        canvas.restore();
+    }
+}
+
+fn convert_path(svg_path: &usvg::Path) -> Node {
+    let mut path = Path::new();
+
+    for segment in svg_path.data().segments() {
+        match segment {
+            PathSegment::MoveTo(p) => path.move_to(p.x, p.y),
+            PathSegment::LineTo(p) => path.line_to(p.x, p.y),
+            PathSegment::QuadTo(c, p) => path.quad_to(c.x, c.y, p.x, p.y),
+            PathSegment::CubicTo(c1, c2, p) => path.bezier_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y),
+            PathSegment::Close => path.close(),
+        }
+    }
+
+    let anti_alias = svg_path.rendering_mode().use_shape_antialiasing();
+
+    let fill = svg_path.fill().and_then(|fill| {
+        let fill_rule = match fill.rule() {
+            usvg::FillRule::NonZero => FillRule::NonZero,
+            usvg::FillRule::EvenOdd => FillRule::EvenOdd,
+        };
+
+        convert_paint(fill.paint(), fill.opacity().get())
+            .map(|paint| paint.with_fill_rule(fill_rule).with_anti_alias(anti_alias))
+    });
+
+    let stroke = svg_path.stroke().and_then(|stroke| {
+        let line_cap = match stroke.linecap() {
+            usvg::LineCap::Butt => LineCap::Butt,
+            usvg::LineCap::Round => LineCap::Round,
+            usvg::LineCap::Square => LineCap::Square,
+        };
+
+        let line_join = match stroke.linejoin() {
+            usvg::LineJoin::Miter | usvg::LineJoin::MiterClip => LineJoin::Miter,
+            usvg::LineJoin::Round => LineJoin::Round,
+            usvg::LineJoin::Bevel => LineJoin::Bevel,
+        };
+
+        convert_paint(stroke.paint(), stroke.opacity().get()).map(|paint| {
+            paint
+                .with_line_width(stroke.width().get())
+                .with_line_cap(line_cap)
+                .with_line_join(line_join)
+                .with_miter_limit(stroke.miterlimit().get())
+                .with_anti_alias(anti_alias)
+        })
+    });
+
+    Node::Path(Box::new(PathNode {
+        path,
+        fill,
+        stroke,
+        stroke_first: svg_path.paint_order() == usvg::PaintOrder::StrokeAndFill,
+    }))
+}
+
+fn convert_paint(paint: &usvg::Paint, opacity: f32) -> Option<Paint> {
+    let stops = |stops: &[usvg::Stop]| {
+        stops
+            .iter()
+            .map(|stop| {
+                (
+                    stop.offset().get(),
+                    convert_color(stop.color(), stop.opacity().get() * opacity),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    match paint {
+        usvg::Paint::Color(color) => Some(Paint::color(convert_color(*color, opacity))),
+        usvg::Paint::LinearGradient(gradient) => {
+            let transform = convert_transform(gradient.transform());
+            let (start_x, start_y) = transform.transform_point(gradient.x1(), gradient.y1());
+            let (end_x, end_y) = transform.transform_point(gradient.x2(), gradient.y2());
+
+            Some(
+                Paint::linear_gradient_stops(start_x, start_y, end_x, end_y, stops(gradient.stops()))
+                    .with_gradient_spread(convert_spread(gradient.spread_method())),
+            )
+        }
+        usvg::Paint::RadialGradient(gradient) => {
+            let transform = convert_transform(gradient.transform());
+            let (cx, cy) = transform.transform_point(gradient.cx(), gradient.cy());
+            let (fx, fy) = transform.transform_point(gradient.fx(), gradient.fy());
+            let radius = gradient.r().get() * transform.average_scale();
+
+            let paint = if (fx, fy) == (cx, cy) {
+                Paint::radial_gradient_stops(cx, cy, 0.0, radius, stops(gradient.stops()))
+            } else {
+                Paint::focal_radial_gradient_stops(fx, fy, 0.0, cx, cy, radius, stops(gradient.stops()))
+            };
+
+            Some(paint.with_gradient_spread(convert_spread(gradient.spread_method())))
+        }
+        usvg::Paint::Pattern(_) => None,
+    }
+}
+
+fn convert_image<T: Renderer>(canvas: &mut Canvas<T>, image: &usvg::Image, images: &mut Vec<ImageId>) -> Option<Node> {
+    let size = image.size();
+
+    match image.kind() {
+        usvg::ImageKind::SVG(tree) => {
+            let mut group = Group::new(canvas, tree.root(), images);
+
+            // Nested documents are scaled to the size of the image element
+            group.transform *=
+                Transform2D::scaling(size.width() / tree.size().width(), size.height() / tree.size().height());
+
+            Some(Node::Group(group))
+        }
+        #[cfg(feature = "image-loading")]
+        usvg::ImageKind::JPEG(data)
+        | usvg::ImageKind::PNG(data)
+        | usvg::ImageKind::GIF(data)
+        | usvg::ImageKind::WEBP(data) => {
+            let flags = match image.rendering_mode() {
+                usvg::ImageRendering::OptimizeSpeed
+                | usvg::ImageRendering::CrispEdges
+                | usvg::ImageRendering::Pixelated => crate::ImageFlags::NEAREST,
+                _ => crate::ImageFlags::empty(),
+            };
+
+            let id = canvas.load_image_mem(data, flags).ok()?;
+            images.push(id);
+
+            let mut path = Path::new();
+            path.rect(0.0, 0.0, size.width(), size.height());
+
+            Some(Node::Path(Box::new(PathNode {
+                path,
+                fill: Some(Paint::image(id, 0.0, 0.0, size.width(), size.height(), 0.0, 1.0)),
+                stroke: None,
+                stroke_first: false,
+            })))
+        }
+        #[cfg(not(feature = "image-loading"))]
+        _ => None,
+    }
+}
+
+fn convert_color(color: usvg::Color, opacity: f32) -> Color {
+    let mut converted = Color::rgb(color.red, color.green, color.blue);
+    converted.set_alphaf(opacity);
+    converted
+}
+
+fn convert_spread(spread: usvg::SpreadMethod) -> Spread {
+    match spread {
+        usvg::SpreadMethod::Pad => Spread::Pad,
+        usvg::SpreadMethod::Reflect => Spread::Reflect,
+        usvg::SpreadMethod::Repeat => Spread::Repeat,
+    }
+}
+
+fn convert_transform(transform: usvg::Transform) -> Transform2D {
+    Transform2D::new(
+        transform.sx,
+        transform.ky,
+        transform.kx,
+        transform.sy,
+        transform.tx,
+        transform.ty,
+    )
+}
@@ -8,11 +8,14 @@ use std::{
     ops::Range,
     path::Path as FilePath,
     rc::Rc,
+    sync::Arc,
 };
 
+use bitflags::bitflags;
 use fnv::{FnvBuildHasher, FnvHashMap, FnvHasher};
 use lru::LruCache;
 use rustybuzz::ttf_parser;
+pub use rustybuzz::{script, Script};
 use slotmap::{DefaultKey, SlotMap};
 
 use unicode_bidi::BidiInfo;
@@ -20,16 +23,20 @@ use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
     paint::{PaintFlavor, StrokeSettings, TextSettings},
-    Canvas, Color, ErrorKind, FillRule, ImageFlags, ImageId, ImageInfo, Paint, PixelFormat, RenderTarget, Renderer,
+    Atlas, Canvas, Color, DrawCommand, ErrorKind, FillRule, GlyphDrawCommands, ImageFlags, ImageId, ImageInfo, Paint,
+    PixelFormat, Quad, RenderTarget, Renderer, Transform2D,
 };
 
-mod atlas;
-pub use atlas::Atlas;
-
 mod font;
 pub use font::FontMetrics;
 use font::{Font, GlyphRendering};
 
+mod paragraph;
+pub use paragraph::{ParagraphAlign, TextLayout, TextLine, TextRun};
+
+mod shaper;
+pub use shaper::{RustybuzzShaper, ShapedGlyphInfo, ShapingRun, TextDirection, TextShaper};
+
 // This padding is an empty border around the glyph’s pixels but inside the
 // sampled area (texture coordinates) for the quad in render_atlas().
 const GLYPH_PADDING: u32 = 1;
@@ -40,12 +47,65 @@ const GLYPH_PADDING: u32 = 1;
 const GLYPH_MARGIN: u32 = 1;
 
 const TEXTURE_SIZE: usize = 512;
+// Layers of glyph atlas texture arrays, each layer is an atlas of TEXTURE_SIZE. Text using several layers keeps
+// sampling the same texture, only the layer changes between its draw calls.
+const TEXTURE_ARRAY_LAYERS: usize = 4;
 const DEFAULT_LRU_CACHE_CAPACITY: usize = 1000;
+// System fonts tried for emoji and symbols missing from the registered fonts, in order of preference
+#[cfg(feature = "fontdb")]
+const SYSTEM_FALLBACK_FAMILIES: [&str; 6] = [
+    "Apple Color Emoji",
+    "Segoe UI Emoji",
+    "Noto Color Emoji",
+    "Apple Symbols",
+    "Segoe UI Symbol",
+    "Noto Sans Symbols2",
+];
+
+// Whether the character is an emoji or a symbol, which are looked up in the fallback fonts of the system
+#[cfg(feature = "fontdb")]
+fn is_emoji_or_symbol(c: char) -> bool {
+    matches!(
+        c as u32,
+        // Arrows, Mathematical Operators, Miscellaneous Technical
+        0x2190..=0x23FF
+        // Enclosed Alphanumerics, Box Drawing, Block Elements, Geometric Shapes, Miscellaneous Symbols, Dingbats
+        | 0x2460..=0x27BF
+        // Supplemental Arrows, Miscellaneous Symbols and Arrows
+        | 0x2900..=0x297F | 0x2B00..=0x2BFF
+        // Mahjong Tiles to Symbols and Pictographs Extended-A
+        | 0x1F000..=0x1FAFF
+    )
+}
+
+// Font data of a system font, shared with the font database
+#[cfg(feature = "fontdb")]
+struct SystemFontData(Arc<dyn AsRef<[u8]> + Send + Sync>);
+
+#[cfg(feature = "fontdb")]
+impl AsRef<[u8]> for SystemFontData {
+    fn as_ref(&self) -> &[u8] {
+        (*self.0).as_ref()
+    }
+}
 
 /// A font handle.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct FontId(DefaultKey);
 
+/// The style of a system font looked up with [`TextContext::find_font()`].
+#[cfg(feature = "fontdb")]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Default)]
+pub enum FontStyle {
+    /// An upright face.
+    #[default]
+    Normal,
+    /// A cursive face designed to be slanted.
+    Italic,
+    /// A slanted version of an upright face.
+    Oblique,
+}
+
 /// Represents the vertical alignment of a text baseline.
 ///
 /// The default value is `Alphabetic`.
@@ -78,6 +138,38 @@ pub enum Align {
     Right,
 }
 
+/// How glyph outlines are fitted to the pixel grid when they're rasterized.
+///
+/// The default value is `None`.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TextHinting {
+    /// Glyphs keep their exact shapes and sub-pixel positions.
+    #[default]
+    None,
+    /// Glyphs are scaled vertically so that the x-height of the font lands on a whole pixel, which keeps the
+    /// horizontal edges of lowercase letters sharp.
+    Slight,
+    /// Like `Slight`, and glyphs are also placed on whole pixels horizontally.
+    Full,
+}
+
+bitflags! {
+    /// Lines drawn along with text, placed and sized by the metrics of the first font of the paint.
+    ///
+    /// The default value is empty.
+    #[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct TextDecoration: u8 {
+        /// A line below the baseline.
+        const UNDERLINE = 1;
+        /// A line through the middle of lowercase letters.
+        const STRIKETHROUGH = 1 << 1;
+        /// A line at the ascender of the font.
+        const OVERLINE = 1 << 2;
+    }
+}
+
 /// Represents the rendering mode for a path.
 ///
 /// The default value is `Fill`.
@@ -98,6 +190,10 @@ pub struct RenderedGlyphId {
     line_width: u32,
     render_mode: RenderMode,
     subpixel_location: u8,
+    transform: Option<[i32; 4]>,
+    subpixel_location_y: u8,
+    hinting: TextHinting,
+    stem_darkening: u32,
 }
 
 impl RenderedGlyphId {
@@ -116,8 +212,40 @@ impl RenderedGlyphId {
             line_width: (line_width * 10.0).trunc() as u32,
             render_mode: mode,
             subpixel_location,
+            transform: None,
+            subpixel_location_y: 0,
+            hinting: TextHinting::None,
+            stem_darkening: 0,
         }
     }
+
+    fn with_rasterization(mut self, hinting: TextHinting, stem_darkening: f32) -> Self {
+        self.hinting = hinting;
+        self.stem_darkening = (stem_darkening * 100.0).trunc() as u32;
+        self
+    }
+
+    fn with_transform(mut self, transform_key: [i32; 4], subpixel_location_y: u8) -> Self {
+        self.transform = Some(transform_key);
+        self.subpixel_location_y = subpixel_location_y;
+        self
+    }
+}
+
+// Steps used for quantizing the linear part of a glyph transform, rotated glyphs are
+// re-rasterized roughly every degree.
+const GLYPH_TRANSFORM_STEPS: f32 = 64.0;
+// Sub-pixel position step for glyphs rasterized with a transform. It's coarser than for
+// axis-aligned glyphs since positions vary in both directions.
+const GLYPH_TRANSFORM_SUBPIXEL_STEP: f32 = 0.25;
+
+/// Returns the quantized linear part of `transform` as used in glyph cache keys, or `None`
+/// if it's close enough to identity to use the regular, axis-aligned glyphs.
+pub(crate) fn glyph_transform_key(transform: &Transform2D) -> Option<[i32; 4]> {
+    let key = [0, 1, 2, 3].map(|i| (transform[i] * GLYPH_TRANSFORM_STEPS).round() as i32);
+    let identity = GLYPH_TRANSFORM_STEPS as i32;
+
+    (key != [identity, 0, 0, identity]).then_some(key)
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -125,6 +253,7 @@ pub struct RenderedGlyph {
     texture_index: usize,
     width: u32,
     height: u32,
+    bearing_x: i32,
     bearing_y: i32,
     atlas_x: u32,
     atlas_y: u32,
@@ -148,6 +277,7 @@ pub struct ShapedGlyph {
     pub bearing_x: f32,
     pub bearing_y: f32,
     pub bitmap_glyph: bool,
+    pub script: Script,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -161,6 +291,7 @@ struct ShapingId {
     size: u32,
     word_hash: u64,
     font_ids: [Option<FontId>; 8],
+    script: Script,
 }
 
 impl ShapingId {
@@ -175,8 +306,25 @@ impl ShapingId {
             size: (font_size * 10.0).trunc() as u32,
             word_hash: hasher.finish(),
             font_ids,
+            script: script::UNKNOWN,
         }
     }
+
+    fn with_script(mut self, script: Script) -> Self {
+        self.script = script;
+        self
+    }
+
+    fn with_script_fonts(mut self, script_fonts: &[(Script, [Option<FontId>; 8])]) -> Self {
+        if !script_fonts.is_empty() {
+            let mut hasher = FnvHasher::default();
+            self.word_hash.hash(&mut hasher);
+            script_fonts.hash(&mut hasher);
+            self.word_hash = hasher.finish();
+        }
+
+        self
+    }
 }
 
 type ShapedWordsCache<H> = LruCache<ShapingId, Result<ShapedWord, ErrorKind>, H>;
@@ -185,6 +333,8 @@ type ShapingRunCache<H> = LruCache<ShapingId, TextMetrics, H>;
 pub struct FontTexture {
     pub atlas: Atlas,
     pub(crate) image_id: ImageId,
+    // Layer of the image if it's a texture array
+    pub(crate) layer: usize,
 }
 
 /// `TextContext` provides functionality for text processing in femtovg. You can
@@ -206,6 +356,18 @@ pub struct FontTexture {
 pub struct TextContext(pub(crate) Rc<RefCell<TextContextImpl>>);
 
 impl TextContext {
+    /// Creates a text context that contains the fonts of the provided set, with the font ids returned
+    /// by the set.
+    pub fn from_font_set(fonts: &FontSet) -> Result<Self, ErrorKind> {
+        let context = Self::default();
+
+        for (data, face_index) in &fonts.fonts {
+            context.add_shared_font_with_index(data.clone(), *face_index)?;
+        }
+
+        Ok(context)
+    }
+
     /// Registers all .ttf files from a directory with this text context. If successful, the
     /// font ids of all registered fonts are returned.
     pub fn add_font_dir<T: AsRef<FilePath>>(&self, path: T) -> Result<Vec<FontId>, ErrorKind> {
@@ -264,6 +426,17 @@ impl TextContext {
         self.0.borrow_mut().break_text_vec(max_width, text, &paint.text)
     }
 
+    /// Breaks `text` into lines no wider than `max_width` and arranges them for display, see [`TextLayout`].
+    pub fn layout_text<S: AsRef<str>>(
+        &self,
+        text: S,
+        max_width: f32,
+        align: ParagraphAlign,
+        paint: &Paint,
+    ) -> Result<TextLayout, ErrorKind> {
+        self.0.borrow_mut().layout_text(text, max_width, align, &paint.text)
+    }
+
     /// Returns font metrics for a particular Paint.
     pub fn measure_font(&self, paint: &Paint) -> Result<FontMetrics, ErrorKind> {
         self.0
@@ -271,6 +444,33 @@ impl TextContext {
             .measure_font(paint.text.font_size, &paint.text.font_ids)
     }
 
+    /// Makes the fonts installed on the system available to [`Self::find_font()`]. Font files are only read
+    /// once a font is found.
+    ///
+    /// Emoji and symbols that none of the registered fonts have glyphs for are then drawn with the emoji font of
+    /// the platform, such as Apple Color Emoji, Segoe UI Emoji or Noto Color Emoji.
+    #[cfg(feature = "fontdb")]
+    pub fn add_system_fonts(&self) {
+        self.0.borrow_mut().add_system_fonts()
+    }
+
+    /// Returns the system font of `family` that matches `weight` and `style` best, registering it with this text
+    /// context the first time it's found.
+    ///
+    /// The `family` is a family name like "Noto Sans" or one of the generic families "serif", "sans-serif",
+    /// "monospace", "cursive" and "fantasy". The `weight` ranges from 100 to 900, 400 being regular and 700 bold.
+    /// Requires a call to [`Self::add_system_fonts()`] first.
+    #[cfg(feature = "fontdb")]
+    pub fn find_font(&self, family: &str, weight: u16, style: FontStyle) -> Result<FontId, ErrorKind> {
+        self.0.borrow_mut().find_system_font(family, weight, style)
+    }
+
+    /// Replaces the shaper that converts text into glyphs, [`RustybuzzShaper`] by default. Text that was already
+    /// shaped is discarded from the caches.
+    pub fn set_text_shaper(&self, shaper: impl TextShaper + 'static) {
+        self.0.borrow_mut().set_text_shaper(Rc::new(shaper))
+    }
+
     /// Adjusts the capacity of the shaping run cache. This is a cache for measurements of whole
     /// strings.
     pub fn resize_shaping_run_cache(&self, capacity: std::num::NonZeroUsize) {
@@ -285,10 +485,62 @@ impl TextContext {
     }
 }
 
+/// A set of fonts that can be shared between threads.
+///
+/// Every [`TextContext`] created with [`TextContext::from_font_set()`] contains the fonts of the set
+/// under the same [`FontId`]s, so the same paints can be used with canvases on different threads,
+/// for example with [`crate::renderer::Recorder`] canvases building parts of a scene.
+#[derive(Clone, Default)]
+pub struct FontSet {
+    fonts: Vec<(Arc<[u8]>, u32)>,
+    ids: SlotMap<DefaultKey, ()>,
+}
+
+impl FontSet {
+    /// Creates an empty font set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the .ttf file from the specified path to this set. If successful, the font id is returned.
+    pub fn add_font_file<T: AsRef<FilePath>>(&mut self, path: T) -> Result<FontId, ErrorKind> {
+        let data = std::fs::read(path)?;
+
+        self.add_shared_font_with_index(data.into(), 0)
+    }
+
+    /// Adds the in-memory representation of a TrueType font to this set. If successful, the font
+    /// id is returned.
+    pub fn add_font_mem(&mut self, data: &[u8]) -> Result<FontId, ErrorKind> {
+        self.add_shared_font_with_index(data.into(), 0)
+    }
+
+    /// Adds the shared in-memory representation of a TrueType font to this set. The `face_index`
+    /// specifies the face index if the font data is a true type font collection. For plain true
+    /// type fonts, use 0 as index.
+    pub fn add_shared_font_with_index(&mut self, data: Arc<[u8]>, face_index: u32) -> Result<FontId, ErrorKind> {
+        ttf_parser::Face::parse(&data, face_index).map_err(|_| ErrorKind::FontParseError)?;
+
+        self.fonts.push((data, face_index));
+        Ok(FontId(self.ids.insert(())))
+    }
+}
+
 pub struct TextContextImpl {
     fonts: SlotMap<DefaultKey, Font>,
     shaping_run_cache: ShapingRunCache<FnvBuildHasher>,
     shaped_words_cache: ShapedWordsCache<FnvBuildHasher>,
+    shaper: Rc<dyn TextShaper>,
+    // Reused to detect the script of words
+    script_buffer: rustybuzz::UnicodeBuffer,
+    #[cfg(feature = "fontdb")]
+    system_fonts: fontdb::Database,
+    // Registered system fonts by their id in `system_fonts`
+    #[cfg(feature = "fontdb")]
+    system_font_ids: FnvHashMap<fontdb::ID, FontId>,
+    // Fonts of the fallback families that were looked up, `None` for families that aren't installed
+    #[cfg(feature = "fontdb")]
+    system_fallback_fonts: FnvHashMap<&'static str, Option<FontId>>,
 }
 
 impl Default for TextContextImpl {
@@ -306,11 +558,31 @@ impl Default for TextContextImpl {
                 std::num::NonZeroUsize::new(DEFAULT_LRU_CACHE_CAPACITY).unwrap(),
                 fnv_words,
             ),
+            shaper: Rc::new(RustybuzzShaper),
+            script_buffer: Default::default(),
+            #[cfg(feature = "fontdb")]
+            system_fonts: fontdb::Database::new(),
+            #[cfg(feature = "fontdb")]
+            system_font_ids: FnvHashMap::default(),
+            #[cfg(feature = "fontdb")]
+            system_fallback_fonts: FnvHashMap::default(),
         }
     }
 }
 
 impl TextContextImpl {
+    // Returns the first script in `word` that isn't shared between scripts, `None` if the
+    // word only consists of whitespace, digits, punctuation and similar.
+    fn detect_script(&mut self, word: &str) -> Option<Script> {
+        let buffer = &mut self.script_buffer;
+        buffer.clear();
+        buffer.push_str(word);
+        buffer.guess_segment_properties();
+
+        let script = buffer.script();
+        (script != script::UNKNOWN).then_some(script)
+    }
+
     pub fn resize_shaping_run_cache(&mut self, capacity: std::num::NonZeroUsize) {
         self.shaping_run_cache.resize(capacity);
     }
@@ -319,6 +591,12 @@ impl TextContextImpl {
         self.shaped_words_cache.resize(capacity);
     }
 
+    pub fn set_text_shaper(&mut self, shaper: Rc<dyn TextShaper>) {
+        self.shaper = shaper;
+        self.shaping_run_cache.clear();
+        self.shaped_words_cache.clear();
+    }
+
     pub fn add_font_dir<T: AsRef<FilePath>>(&mut self, path: T) -> Result<Vec<FontId>, ErrorKind> {
         let path = path.as_ref();
         let mut fonts = Vec::new();
@@ -380,6 +658,64 @@ impl TextContextImpl {
         Ok(FontId(self.fonts.insert(font)))
     }
 
+    #[cfg(feature = "fontdb")]
+    pub fn add_system_fonts(&mut self) {
+        self.system_fonts.load_system_fonts();
+
+        // Fallback families that were missing may be installed now
+        self.system_fallback_fonts.clear();
+        self.clear_caches();
+    }
+
+    #[cfg(feature = "fontdb")]
+    pub fn find_system_font(&mut self, family: &str, weight: u16, style: FontStyle) -> Result<FontId, ErrorKind> {
+        let family = match family {
+            "serif" => fontdb::Family::Serif,
+            "sans-serif" => fontdb::Family::SansSerif,
+            "monospace" => fontdb::Family::Monospace,
+            "cursive" => fontdb::Family::Cursive,
+            "fantasy" => fontdb::Family::Fantasy,
+            name => fontdb::Family::Name(name),
+        };
+
+        let query = fontdb::Query {
+            families: &[family],
+            weight: fontdb::Weight(weight),
+            stretch: fontdb::Stretch::Normal,
+            style: match style {
+                FontStyle::Normal => fontdb::Style::Normal,
+                FontStyle::Italic => fontdb::Style::Italic,
+                FontStyle::Oblique => fontdb::Style::Oblique,
+            },
+        };
+
+        let id = self.system_fonts.query(&query).ok_or(ErrorKind::NoFontFound)?;
+
+        self.add_system_font(id)
+    }
+
+    // Registers the system font with the id, unless it was registered before.
+    #[cfg(feature = "fontdb")]
+    fn add_system_font(&mut self, id: fontdb::ID) -> Result<FontId, ErrorKind> {
+        if let Some(&font_id) = self.system_font_ids.get(&id) {
+            return Ok(font_id);
+        }
+
+        let face = self.system_fonts.face(id).ok_or(ErrorKind::NoFontFound)?;
+        let (source, face_index) = (face.source.clone(), face.index);
+
+        // Font data already in memory is shared with the database, files are read once
+        let font_id = match source {
+            fontdb::Source::Binary(data) | fontdb::Source::SharedFile(_, data) => {
+                self.add_shared_font_with_index(SystemFontData(data), face_index)?
+            }
+            fontdb::Source::File(path) => self.add_shared_font_with_index(std::fs::read(path)?, face_index)?,
+        };
+        self.system_font_ids.insert(id, font_id);
+
+        Ok(font_id)
+    }
+
     pub fn font(&self, id: FontId) -> Option<&Font> {
         self.fonts.get(id.0)
     }
@@ -388,7 +724,15 @@ impl TextContextImpl {
         self.fonts.get_mut(id.0)
     }
 
-    pub fn find_font<F, T>(&mut self, font_ids: &[Option<FontId>; 8], mut callback: F) -> Result<T, ErrorKind>
+    // Calls `callback` with the fonts of `font_ids`, the other registered fonts and, for emoji and symbols in
+    // `text`, the fallback fonts of the system, until one has no missing glyphs.
+    #[cfg_attr(not(feature = "fontdb"), allow(unused_variables))]
+    pub fn find_font<F, T>(
+        &mut self,
+        font_ids: &[Option<FontId>; 8],
+        text: &str,
+        mut callback: F,
+    ) -> Result<T, ErrorKind>
     where
         F: FnMut((FontId, &mut Font)) -> (bool, T),
     {
@@ -417,6 +761,35 @@ impl TextContextImpl {
             }
         }
 
+        // Try the emoji and symbol fonts of the system, registering them as they're needed
+        #[cfg(feature = "fontdb")]
+        for family in SYSTEM_FALLBACK_FAMILIES {
+            if !text.chars().any(is_emoji_or_symbol) {
+                break;
+            }
+
+            let font_id = match self.system_fallback_fonts.get(family) {
+                Some(&font_id) => font_id,
+                None => {
+                    let font_id = self.find_system_font(family, 400, FontStyle::Normal).ok();
+                    self.system_fallback_fonts.insert(family, font_id);
+                    font_id
+                }
+            };
+
+            let Some(font_id) = font_id else {
+                continue;
+            };
+
+            if let Some(font) = self.fonts.get_mut(font_id.0) {
+                let (has_missing, result) = callback((font_id, font));
+
+                if !has_missing {
+                    return Ok(result);
+                }
+            }
+        }
+
         // Just return the first font at this point and let it render .nodef glyphs
         if let Some((id, font)) = self.fonts.iter_mut().next() {
             return Ok(callback((FontId(id), font)).1);
@@ -478,6 +851,16 @@ impl TextContextImpl {
         Ok(res)
     }
 
+    pub fn layout_text<S: AsRef<str>>(
+        &mut self,
+        text: S,
+        max_width: f32,
+        align: ParagraphAlign,
+        text_settings: &TextSettings,
+    ) -> Result<TextLayout, ErrorKind> {
+        paragraph::layout_paragraphs(self, text_settings, text.as_ref(), max_width, align)
+    }
+
     pub fn measure_font(&mut self, font_size: f32, font_ids: &[Option<FontId>; 8]) -> Result<FontMetrics, ErrorKind> {
         if let Some(Some(id)) = font_ids.first() {
             if let Some(font) = self.font(*id) {
@@ -501,6 +884,7 @@ pub struct TextMetrics {
     /// Vector of shaped glyphs resulting from the text shaping run.
     pub glyphs: Vec<ShapedGlyph>,
     pub(crate) final_byte_index: usize,
+    pub(crate) baseline: f32,
 }
 
 impl TextMetrics {
@@ -509,6 +893,7 @@ impl TextMetrics {
         self.y *= scale;
         self.width *= scale;
         self.height *= scale;
+        self.baseline *= scale;
 
         for glyph in &mut self.glyphs {
             glyph.x *= scale;
@@ -518,6 +903,17 @@ impl TextMetrics {
         }
     }
 
+    pub(crate) fn translate(&mut self, x: f32, y: f32) {
+        self.x += x;
+        self.y += y;
+        self.baseline += y;
+
+        for glyph in &mut self.glyphs {
+            glyph.x += x;
+            glyph.y += y;
+        }
+    }
+
     /// width of the glyphs as drawn
     pub fn width(&self) -> f32 {
         self.width
@@ -543,17 +939,11 @@ pub fn shape(
     text: &str,
     max_width: Option<f32>,
 ) -> Result<TextMetrics, ErrorKind> {
-    let id = ShapingId::new(text_settings.font_size, text_settings.font_ids, text, max_width);
+    let id = ShapingId::new(text_settings.font_size, text_settings.font_ids, text, max_width)
+        .with_script_fonts(&text_settings.script_fonts);
 
     if !context.shaping_run_cache.contains(&id) {
-        let metrics = shape_run(
-            context,
-            text_settings.font_size,
-            text_settings.font_ids,
-            text_settings.letter_spacing,
-            text,
-            max_width,
-        )?;
+        let metrics = shape_run(context, text_settings, text, max_width)?;
         context.shaping_run_cache.put(id, metrics);
     }
 
@@ -568,12 +958,12 @@ pub fn shape(
 
 fn shape_run(
     context: &mut TextContextImpl,
-    font_size: f32,
-    font_ids: [Option<FontId>; 8],
-    letter_spacing: f32,
+    text_settings: &TextSettings,
     text: &str,
     max_width: Option<f32>,
 ) -> Result<TextMetrics, ErrorKind> {
+    let letter_spacing = text_settings.letter_spacing;
+
     let mut result = TextMetrics {
         x: 0.0,
         y: 0.0,
@@ -581,6 +971,7 @@ fn shape_run(
         height: 0.0,
         glyphs: Vec::with_capacity(text.len()),
         final_byte_index: 0,
+        baseline: 0.0,
     };
 
     let bidi_info = BidiInfo::new(text, Some(unicode_bidi::Level::ltr()));
@@ -588,6 +979,10 @@ fn shape_run(
     // this controls whether we should break within words
     let mut first_word_in_paragraph = true;
 
+    // words made only of characters shared between scripts (spaces, digits, punctuation)
+    // continue the script of the preceding word
+    let mut script = script::UNKNOWN;
+
     if let Some(paragraph) = bidi_info.paragraphs.first() {
         let line = paragraph.range.clone();
 
@@ -600,10 +995,10 @@ fn shape_run(
                 continue;
             }
 
-            let hb_direction = if levels[run.start].is_rtl() {
-                rustybuzz::Direction::RightToLeft
+            let direction = if levels[run.start].is_rtl() {
+                TextDirection::RightToLeft
             } else {
-                rustybuzz::Direction::LeftToRight
+                TextDirection::LeftToRight
             };
 
             let mut words = Vec::new();
@@ -611,16 +1006,11 @@ fn shape_run(
             let mut byte_index = run.start;
 
             for mut word_txt in sub_text.split_word_bounds() {
-                let id = ShapingId::new(font_size, font_ids, word_txt, max_width);
-
-                if !context.shaped_words_cache.contains(&id) {
-                    let word = shape_word(word_txt, hb_direction, context, font_size, &font_ids, letter_spacing);
-                    context.shaped_words_cache.put(id, word);
-                }
-
-                if let Some(Ok(word)) = context.shaped_words_cache.get(&id) {
-                    let mut word = word.clone();
+                script = word_script(context, text_settings, word_txt, script);
 
+                if let Some(mut word) =
+                    shape_cached_word(context, text_settings, word_txt, direction, script, max_width)
+                {
                     if let Some(max_width) = max_width {
                         if result.width + word.width >= max_width {
                             word_break_reached = true;
@@ -649,22 +1039,16 @@ fn shape_run(
                                 }
 
                                 let subword_txt = &word_txt[..bytes_included];
-                                let id = ShapingId::new(font_size, font_ids, subword_txt, Some(max_width));
-                                if !context.shaped_words_cache.contains(&id) {
-                                    let subword = shape_word(
-                                        subword_txt,
-                                        hb_direction,
-                                        context,
-                                        font_size,
-                                        &font_ids,
-                                        letter_spacing,
-                                    );
-                                    context.shaped_words_cache.put(id, subword);
-                                }
-
-                                if let Some(Ok(subword)) = context.shaped_words_cache.get(&id) {
+                                if let Some(subword) = shape_cached_word(
+                                    context,
+                                    text_settings,
+                                    subword_txt,
+                                    direction,
+                                    script,
+                                    Some(max_width),
+                                ) {
                                     // replace the outer variables so we can continue normally
-                                    word = subword.clone();
+                                    word = subword;
                                     word_txt = subword_txt;
                                 } else {
                                     break;
@@ -718,38 +1102,92 @@ fn shape_run(
     Ok(result)
 }
 
+// Shapes `word` with the fonts of the paint for `script`, or returns it from the cache of shaped words.
+fn shape_cached_word(
+    context: &mut TextContextImpl,
+    text_settings: &TextSettings,
+    word: &str,
+    direction: TextDirection,
+    script: Script,
+    max_width: Option<f32>,
+) -> Option<ShapedWord> {
+    let font_ids = text_settings.font_ids_for_script(script);
+    let id = ShapingId::new(text_settings.font_size, font_ids, word, max_width).with_script(script);
+
+    if !context.shaped_words_cache.contains(&id) {
+        // Words shaped without a script still report the one they're written in
+        let script = match script {
+            script::UNKNOWN => context.detect_script(word).unwrap_or(script),
+            script => script,
+        };
+
+        let shaped_word = shape_word(
+            word,
+            direction,
+            script,
+            context,
+            text_settings.font_size,
+            &font_ids,
+            text_settings.letter_spacing,
+        );
+        context.shaped_words_cache.put(id, shaped_word);
+    }
+
+    match context.shaped_words_cache.get(&id) {
+        Some(Ok(shaped_word)) => Some(shaped_word.clone()),
+        _ => None,
+    }
+}
+
+// Returns the script to pick the fonts of `word` with, which continues `script` for words made only of characters
+// shared between scripts. Without per-script fonts the script doesn't matter until the word is shaped, so it isn't
+// detected for every word.
+fn word_script(context: &mut TextContextImpl, text_settings: &TextSettings, word: &str, script: Script) -> Script {
+    if text_settings.script_fonts.is_empty() {
+        return script::UNKNOWN;
+    }
+
+    context.detect_script(word).unwrap_or(script)
+}
+
 fn shape_word(
     word: &str,
-    hb_direction: rustybuzz::Direction,
+    direction: TextDirection,
+    script: Script,
     context: &mut TextContextImpl,
     font_size: f32,
     font_ids: &[Option<FontId>; 8],
     letter_spacing: f32,
 ) -> Result<ShapedWord, ErrorKind> {
+    let shaper = context.shaper.clone();
+    let mut infos = Vec::new();
+
     // find_font will call the closure with each font matching the provided style
     // until a font capable of shaping the word is found
-    context.find_font(font_ids, |(font_id, font)| {
-        let face = font.face_ref();
-        // Call harfbuzz
-        let output = {
-            let mut buffer = rustybuzz::UnicodeBuffer::new();
-            buffer.push_str(word);
-            buffer.set_direction(hb_direction);
-
-            rustybuzz::shape(&face, &[], buffer)
-        };
+    context.find_font(font_ids, word, |(font_id, font)| {
+        infos.clear();
+        shaper.shape(
+            &ShapingRun {
+                text: word,
+                font_id,
+                font_data: font.data(),
+                face_index: font.face_index(),
+                direction,
+                script,
+            },
+            &mut infos,
+        );
 
-        let positions = output.glyph_positions();
-        let infos = output.glyph_infos();
+        let face = font.face_ref();
 
         let mut shaped_word = ShapedWord {
-            glyphs: Vec::with_capacity(positions.len()),
+            glyphs: Vec::with_capacity(infos.len()),
             width: 0.0,
         };
 
         let mut has_missing = false;
 
-        for (position, (info, c)) in positions.iter().zip(infos.iter().zip(word.chars())) {
+        for (info, c) in infos.iter().zip(word.chars()) {
             if info.glyph_id == 0 {
                 has_missing = true;
             }
@@ -765,13 +1203,14 @@ fn shape_word(
                 codepoint: info.glyph_id,
                 width: 0.0,
                 height: 0.0,
-                advance_x: position.x_advance as f32 * scale,
-                advance_y: position.y_advance as f32 * scale,
-                offset_x: position.x_offset as f32 * scale,
-                offset_y: position.y_offset as f32 * scale,
+                advance_x: info.x_advance as f32 * scale,
+                advance_y: info.y_advance as f32 * scale,
+                offset_x: info.x_offset as f32 * scale,
+                offset_y: info.y_offset as f32 * scale,
                 bearing_x: 0.0,
                 bearing_y: 0.0,
                 bitmap_glyph: false,
+                script,
             };
 
             if let Some(glyph) = font.glyph(&face, info.glyph_id as u16) {
@@ -823,7 +1262,7 @@ fn layout(
         descender = descender.min(metrics.descender());
     }
 
-    let primary_metrics = context.find_font(&text_settings.font_ids, |(_, font)| {
+    let primary_metrics = context.find_font(&text_settings.font_ids, "", |(_, font)| {
         (false, font.metrics(text_settings.font_size))
     })?;
     if ascender.abs() < f32::EPSILON {
@@ -841,6 +1280,8 @@ fn layout(
         Baseline::Bottom => descender,
     };
 
+    res.baseline = (cursor_y + alignment_offset_y).round();
+
     for glyph in &mut res.glyphs {
         glyph.x = cursor_x + glyph.offset_x + glyph.bearing_x;
         glyph.y = (cursor_y + alignment_offset_y).round() + glyph.offset_y - glyph.bearing_y;
@@ -860,44 +1301,6 @@ fn layout(
 
 // Renderer
 
-/// Represents a command to draw an image with a set of quads.
-#[derive(Clone, Debug)]
-pub struct DrawCommand {
-    /// The ID of the image to draw.
-    pub image_id: ImageId,
-    /// The quads defining the positions and texture coordinates for drawing the image.
-    pub quads: Vec<Quad>,
-}
-
-/// Represents a quad with position and texture coordinates.
-#[derive(Copy, Clone, Default, Debug)]
-pub struct Quad {
-    /// X-coordinate of the top-left corner of the quad.
-    pub x0: f32,
-    /// Y-coordinate of the top-left corner of the quad.
-    pub y0: f32,
-    /// U-coordinate (horizontal texture coordinate) of the top-left corner of the quad.
-    pub s0: f32,
-    /// V-coordinate (vertical texture coordinate) of the top-left corner of the quad.
-    pub t0: f32,
-    /// X-coordinate of the bottom-right corner of the quad.
-    pub x1: f32,
-    /// Y-coordinate of the bottom-right corner of the quad.
-    pub y1: f32,
-    /// U-coordinate (horizontal texture coordinate) of the bottom-right corner of the quad.
-    pub s1: f32,
-    /// V-coordinate (vertical texture coordinate) of the bottom-right corner of the quad.
-    pub t1: f32,
-}
-
-/// Represents the drawing commands for glyphs, separated into alpha and color glyphs.
-pub struct GlyphDrawCommands {
-    /// Drawing commands for alpha (opacity) glyphs.
-    pub alpha_glyphs: Vec<DrawCommand>,
-    /// Drawing commands for color glyphs.
-    pub color_glyphs: Vec<DrawCommand>,
-}
-
 #[derive(Default)]
 pub struct GlyphAtlas {
     pub rendered_glyphs: RefCell<FnvHashMap<RenderedGlyphId, RenderedGlyph>>,
@@ -909,35 +1312,52 @@ impl GlyphAtlas {
         &self,
         canvas: &mut Canvas<T>,
         text_layout: &TextMetrics,
-        font_size: f32,
+        text_settings: &TextSettings,
         line_width: f32,
         mode: RenderMode,
     ) -> Result<GlyphDrawCommands, ErrorKind> {
         let mut alpha_cmd_map = FnvHashMap::default();
         let mut color_cmd_map = FnvHashMap::default();
 
+        let stem_darkening = glyph_stem_darkening(text_settings, mode);
         let line_width_offset = if mode == RenderMode::Stroke {
             (line_width / 2.0).ceil()
         } else {
-            0.0
+            (stem_darkening / 2.0).ceil()
         };
 
         let initial_render_target = canvas.current_render_target;
 
         for glyph in &text_layout.glyphs {
-            let subpixel_location = crate::geometry::quantize(glyph.x.fract(), 0.1) * 10.0;
+            let subpixel_location = if text_settings.hinting == TextHinting::Full {
+                0.0
+            } else {
+                crate::geometry::quantize(glyph.x.fract(), 0.1) * 10.0
+            };
 
             let id = RenderedGlyphId::new(
                 glyph.codepoint,
                 glyph.font_id,
-                font_size,
+                text_settings.font_size,
                 line_width,
                 mode,
                 subpixel_location as u8,
-            );
+            )
+            .with_rasterization(text_settings.hinting, stem_darkening);
 
             if !self.rendered_glyphs.borrow().contains_key(&id) {
-                let glyph = self.render_glyph(canvas, font_size, line_width, mode, glyph)?;
+                let glyph = match self.render_glyph(canvas, text_settings, line_width, mode, glyph) {
+                    Ok(glyph) => glyph,
+                    // Glyphs too large for an atlas texture are left out, the rest of the text is still drawn
+                    Err(ErrorKind::FontSizeTooLargeForAtlas) => {
+                        canvas.report_error(ErrorKind::FontSizeTooLargeForAtlas);
+                        continue;
+                    }
+                    Err(error) => {
+                        canvas.set_render_target(initial_render_target);
+                        return Err(error);
+                    }
+                };
 
                 self.rendered_glyphs.borrow_mut().insert(id, glyph);
             }
@@ -948,6 +1368,7 @@ impl GlyphAtlas {
             if let Some(texture) = self.glyph_textures.borrow().get(rendered.texture_index) {
                 let image_id = texture.image_id;
                 let size = texture.atlas.size();
+                let layer = texture.layer;
                 let itw = 1.0 / size.0 as f32;
                 let ith = 1.0 / size.1 as f32;
 
@@ -957,8 +1378,9 @@ impl GlyphAtlas {
                     &mut alpha_cmd_map
                 };
 
-                let cmd = cmd_map.entry(rendered.texture_index).or_insert_with(|| DrawCommand {
+                let cmd = cmd_map.entry((image_id, layer)).or_insert_with(|| DrawCommand {
                     image_id,
+                    layer,
                     quads: Vec::new(),
                 });
 
@@ -994,24 +1416,29 @@ impl GlyphAtlas {
     fn render_glyph<T: Renderer>(
         &self,
         canvas: &mut Canvas<T>,
-        font_size: f32,
+        text_settings: &TextSettings,
         line_width: f32,
         mode: RenderMode,
         glyph: &ShapedGlyph,
     ) -> Result<RenderedGlyph, ErrorKind> {
         let padding = GLYPH_PADDING + GLYPH_MARGIN;
+        let font_size = text_settings.font_size;
 
         let text_context = canvas.text_context.clone();
         let mut text_context = text_context.borrow_mut();
 
-        let (mut maybe_glyph_representation, scale) = {
+        let (mut maybe_glyph_representation, scale, scale_y) = {
             let font = text_context.font_mut(glyph.font_id).ok_or(ErrorKind::NoFontFound)?;
             let face = font.face_ref();
             let scale = font.scale(font_size);
+            let scale_y = match text_settings.hinting {
+                TextHinting::None => scale,
+                TextHinting::Slight | TextHinting::Full => font.hinted_scale_y(font_size),
+            };
 
             let maybe_glyph_representation =
                 font.glyph_rendering_representation(&face, glyph.codepoint as u16, font_size as u16);
-            (maybe_glyph_representation, scale)
+            (maybe_glyph_representation, scale, scale_y)
         };
 
         #[cfg(feature = "image-loading")]
@@ -1024,27 +1451,43 @@ impl GlyphAtlas {
         } else {
             line_width
         };
+        let stem_darkening = if color_glyph {
+            0.0
+        } else {
+            glyph_stem_darkening(text_settings, mode)
+        };
 
-        let line_width_offset = (line_width / 2.0).ceil();
+        let line_width_offset = (line_width.max(stem_darkening) / 2.0).ceil();
+
+        // Hinting stretches glyphs vertically
+        let hinting_factor_y = scale_y / scale;
+        let glyph_height = glyph.height * hinting_factor_y;
+        let glyph_bearing_y = glyph.bearing_y * hinting_factor_y;
 
         let width = glyph.width.ceil() as u32 + (line_width_offset * 2.0) as u32 + padding * 2;
-        let height = glyph.height.ceil() as u32 + (line_width_offset * 2.0) as u32 + padding * 2;
+        let height = glyph_height.ceil() as u32 + (line_width_offset * 2.0) as u32 + padding * 2;
 
-        let (dst_index, dst_image_id, (dst_x, dst_y)) =
+        let (dst_index, dst_image_id, dst_layer, (dst_x, dst_y)) =
             self.find_texture_or_alloc(canvas, width as usize, height as usize)?;
+        let dst_target = RenderTarget::Image(dst_image_id);
 
         // render glyph to image
         canvas.save();
         canvas.reset();
 
-        let rendered_bearing_y = glyph.bearing_y.round();
-        let x_quant = crate::geometry::quantize(glyph.x.fract(), 0.1);
+        let rendered_bearing_y = glyph_bearing_y.round();
+        let x_quant = if text_settings.hinting == TextHinting::Full {
+            0.0
+        } else {
+            crate::geometry::quantize(glyph.x.fract(), 0.1)
+        };
         let x = dst_x as f32 - glyph.bearing_x + line_width_offset + padding as f32 + x_quant;
         let y = TEXTURE_SIZE as f32 - dst_y as f32 - rendered_bearing_y - line_width_offset - padding as f32;
 
         let rendered_glyph = RenderedGlyph {
             width: width - 2 * GLYPH_MARGIN,
             height: height - 2 * GLYPH_MARGIN,
+            bearing_x: 0,
             bearing_y: rendered_bearing_y as i32,
             atlas_x: dst_x as u32 + GLYPH_MARGIN,
             atlas_y: dst_y as u32 + GLYPH_MARGIN,
@@ -1056,7 +1499,7 @@ impl GlyphAtlas {
             Some(GlyphRendering::RenderAsPath(ref mut path)) => {
                 canvas.translate(x, y);
 
-                canvas.set_render_target(RenderTarget::Image(dst_image_id));
+                canvas.set_render_target_layer(dst_target, dst_layer);
                 canvas.clear_rect(
                     dst_x as u32,
                     TEXTURE_SIZE as u32 - dst_y as u32 - height,
@@ -1064,9 +1507,6 @@ impl GlyphAtlas {
                     height,
                     Color::black(),
                 );
-                let factor = 1.0 / 8.0;
-
-                let mask_color = Color::rgbf(factor, factor, factor);
 
                 let mut line_width = line_width;
 
@@ -1074,50 +1514,14 @@ impl GlyphAtlas {
                     line_width /= scale;
                 }
 
-                canvas.global_composite_blend_func(crate::BlendFactor::SrcAlpha, crate::BlendFactor::One);
-
-                // 4x
-                // let points = [
-                //     (-3.0/8.0, 1.0/8.0),
-                //     (1.0/8.0, 3.0/8.0),
-                //     (3.0/8.0, -1.0/8.0),
-                //     (-1.0/8.0, -3.0/8.0),
-                // ];
-
-                // 8x
-                let points = [
-                    (-7.0 / 16.0, -1.0 / 16.0),
-                    (-1.0 / 16.0, -5.0 / 16.0),
-                    (3.0 / 16.0, -7.0 / 16.0),
-                    (5.0 / 16.0, -3.0 / 16.0),
-                    (7.0 / 16.0, 1.0 / 16.0),
-                    (1.0 / 16.0, 5.0 / 16.0),
-                    (-3.0 / 16.0, 7.0 / 16.0),
-                    (-5.0 / 16.0, 3.0 / 16.0),
-                ];
-
-                for point in &points {
-                    canvas.save();
-                    canvas.translate(point.0, point.1);
-
-                    canvas.scale(scale, scale);
-
-                    if mode == RenderMode::Stroke {
-                        canvas.stroke_path_internal(
-                            path,
-                            &PaintFlavor::Color(mask_color),
-                            false,
-                            &StrokeSettings {
-                                line_width,
-                                ..Default::default()
-                            },
-                        );
-                    } else {
-                        canvas.fill_path_internal(path, &PaintFlavor::Color(mask_color), false, FillRule::NonZero);
-                    }
-
-                    canvas.restore();
-                }
+                rasterize_glyph_path(
+                    canvas,
+                    path,
+                    &Transform2D::scaling(scale, scale_y),
+                    mode,
+                    line_width,
+                    stem_darkening / scale,
+                );
             }
             #[cfg(feature = "image-loading")]
             Some(GlyphRendering::RenderAsImage(image_buffer)) => {
@@ -1128,9 +1532,14 @@ impl GlyphAtlas {
 
                 let image_buffer =
                     image_buffer.resize(target_width, target_height, image::imageops::FilterType::Nearest);
-                if let Ok(image) = crate::image::ImageSource::try_from(&image_buffer) {
-                    canvas.update_image(dst_image_id, image, target_x, target_y).unwrap();
-                }
+                canvas.images.update_layer(
+                    &mut canvas.renderer,
+                    dst_image_id,
+                    crate::image::ImageSource::from_dynamic_image(&image_buffer),
+                    target_x,
+                    target_y,
+                    dst_layer,
+                )?;
             }
             _ => {}
         }
@@ -1140,13 +1549,223 @@ impl GlyphAtlas {
         Ok(rendered_glyph)
     }
 
-    // Returns (texture index, image id, glyph padding box)
+    /// Like `render_atlas()`, but rasterizes the glyphs with the linear part of `transform` baked in
+    /// instead of sampling axis-aligned bitmaps. `transform` maps from layout to device coordinates
+    /// and the returned quads are in device coordinates.
+    pub(crate) fn render_atlas_transformed<T: Renderer>(
+        &self,
+        canvas: &mut Canvas<T>,
+        text_layout: &TextMetrics,
+        font_size: f32,
+        line_width: f32,
+        mode: RenderMode,
+        transform: &Transform2D,
+    ) -> Result<GlyphDrawCommands, ErrorKind> {
+        let Some(transform_key) = glyph_transform_key(transform) else {
+            return Err(ErrorKind::UnsupportedOperation);
+        };
+
+        let glyph_transform = Transform2D::new(
+            transform_key[0] as f32 / GLYPH_TRANSFORM_STEPS,
+            transform_key[1] as f32 / GLYPH_TRANSFORM_STEPS,
+            transform_key[2] as f32 / GLYPH_TRANSFORM_STEPS,
+            transform_key[3] as f32 / GLYPH_TRANSFORM_STEPS,
+            0.0,
+            0.0,
+        );
+
+        let mut alpha_cmd_map = FnvHashMap::default();
+
+        let initial_render_target = canvas.current_render_target;
+
+        for glyph in &text_layout.glyphs {
+            // Pen position on the baseline, in device coordinates
+            let (origin_x, origin_y) = transform.transform_point(glyph.x - glyph.bearing_x, glyph.y + glyph.bearing_y);
+
+            let subpixel_x = crate::geometry::quantize(origin_x - origin_x.floor(), GLYPH_TRANSFORM_SUBPIXEL_STEP);
+            let subpixel_y = crate::geometry::quantize(origin_y - origin_y.floor(), GLYPH_TRANSFORM_SUBPIXEL_STEP);
+
+            let id = RenderedGlyphId::new(
+                glyph.codepoint,
+                glyph.font_id,
+                font_size,
+                line_width,
+                mode,
+                (subpixel_x / GLYPH_TRANSFORM_SUBPIXEL_STEP) as u8,
+            )
+            .with_transform(transform_key, (subpixel_y / GLYPH_TRANSFORM_SUBPIXEL_STEP) as u8);
+
+            if !self.rendered_glyphs.borrow().contains_key(&id) {
+                let glyph = match self.render_transformed_glyph(
+                    canvas,
+                    font_size,
+                    line_width,
+                    mode,
+                    glyph,
+                    &glyph_transform,
+                    (subpixel_x, subpixel_y),
+                ) {
+                    Ok(glyph) => glyph,
+                    // Glyphs too large for an atlas texture are left out, the rest of the text is still drawn
+                    Err(ErrorKind::FontSizeTooLargeForAtlas) => {
+                        canvas.report_error(ErrorKind::FontSizeTooLargeForAtlas);
+                        continue;
+                    }
+                    Err(error) => {
+                        canvas.set_render_target(initial_render_target);
+                        return Err(error);
+                    }
+                };
+
+                self.rendered_glyphs.borrow_mut().insert(id, glyph);
+            }
+
+            let rendered_glyphs = self.rendered_glyphs.borrow();
+            let rendered = rendered_glyphs.get(&id).unwrap();
+
+            if let Some(texture) = self.glyph_textures.borrow().get(rendered.texture_index) {
+                let image_id = texture.image_id;
+                let size = texture.atlas.size();
+                let layer = texture.layer;
+                let itw = 1.0 / size.0 as f32;
+                let ith = 1.0 / size.1 as f32;
+
+                let cmd = alpha_cmd_map.entry((image_id, layer)).or_insert_with(|| DrawCommand {
+                    image_id,
+                    layer,
+                    quads: Vec::new(),
+                });
+
+                let mut q = Quad::default();
+
+                q.x0 = origin_x.floor() + rendered.bearing_x as f32 - GLYPH_PADDING as f32;
+                q.y0 = origin_y.floor() - rendered.bearing_y as f32 - GLYPH_PADDING as f32;
+                q.x1 = q.x0 + rendered.width as f32;
+                q.y1 = q.y0 + rendered.height as f32;
+
+                q.s0 = rendered.atlas_x as f32 * itw;
+                q.t0 = rendered.atlas_y as f32 * ith;
+                q.s1 = (rendered.atlas_x + rendered.width) as f32 * itw;
+                q.t1 = (rendered.atlas_y + rendered.height) as f32 * ith;
+
+                cmd.quads.push(q);
+            }
+        }
+
+        canvas.set_render_target(initial_render_target);
+
+        Ok(GlyphDrawCommands {
+            alpha_glyphs: alpha_cmd_map.drain().map(|(_, cmd)| cmd).collect(),
+            color_glyphs: Vec::new(),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_transformed_glyph<T: Renderer>(
+        &self,
+        canvas: &mut Canvas<T>,
+        font_size: f32,
+        line_width: f32,
+        mode: RenderMode,
+        glyph: &ShapedGlyph,
+        glyph_transform: &Transform2D,
+        subpixel_offset: (f32, f32),
+    ) -> Result<RenderedGlyph, ErrorKind> {
+        let padding = GLYPH_PADDING + GLYPH_MARGIN;
+
+        let text_context = canvas.text_context.clone();
+        let mut text_context = text_context.borrow_mut();
+
+        let (maybe_glyph_representation, scale) = {
+            let font = text_context.font_mut(glyph.font_id).ok_or(ErrorKind::NoFontFound)?;
+            let face = font.face_ref();
+            let scale = font.scale(font_size);
+
+            let maybe_glyph_representation =
+                font.glyph_rendering_representation(&face, glyph.codepoint as u16, font_size as u16);
+            (maybe_glyph_representation, scale)
+        };
+
+        let line_width = if mode == RenderMode::Stroke { line_width } else { 0.0 };
+        let line_width_offset = (line_width / 2.0).ceil();
+
+        // Bounding box of the transformed glyph relative to the pen position
+        let (min_x, min_y, max_x, max_y) = {
+            let left = glyph.bearing_x - line_width_offset;
+            let top = -glyph.bearing_y - line_width_offset;
+            let right = glyph.bearing_x + glyph.width + line_width_offset;
+            let bottom = -glyph.bearing_y + glyph.height + line_width_offset;
+
+            [(left, top), (right, top), (right, bottom), (left, bottom)]
+                .iter()
+                .map(|&(x, y)| glyph_transform.transform_point(x, y))
+                .fold(
+                    (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+                    |(min_x, min_y, max_x, max_y), (x, y)| (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)),
+                )
+        };
+
+        let (subpixel_x, subpixel_y) = subpixel_offset;
+        let left = (min_x + subpixel_x).floor();
+        let top = (min_y + subpixel_y).floor();
+        let right = (max_x + subpixel_x).ceil();
+        let bottom = (max_y + subpixel_y).ceil();
+
+        let width = (right - left) as u32 + padding * 2;
+        let height = (bottom - top) as u32 + padding * 2;
+
+        let (dst_index, dst_image_id, dst_layer, (dst_x, dst_y)) =
+            self.find_texture_or_alloc(canvas, width as usize, height as usize)?;
+        let dst_target = RenderTarget::Image(dst_image_id);
+
+        let rendered_glyph = RenderedGlyph {
+            width: width - 2 * GLYPH_MARGIN,
+            height: height - 2 * GLYPH_MARGIN,
+            bearing_x: left as i32,
+            bearing_y: -top as i32,
+            atlas_x: dst_x as u32 + GLYPH_MARGIN,
+            atlas_y: dst_y as u32 + GLYPH_MARGIN,
+            texture_index: dst_index,
+            color_glyph: false,
+        };
+
+        if let Some(GlyphRendering::RenderAsPath(path)) = &maybe_glyph_representation {
+            canvas.save();
+            canvas.reset();
+
+            // Render targets are flipped vertically, as are glyph outlines in font units
+            let x = dst_x as f32 + padding as f32 - left + subpixel_x;
+            let y = TEXTURE_SIZE as f32 - (dst_y as f32 + padding as f32 - top + subpixel_y);
+
+            canvas.translate(x, y);
+
+            canvas.set_render_target_layer(dst_target, dst_layer);
+            canvas.clear_rect(
+                dst_x as u32,
+                TEXTURE_SIZE as u32 - dst_y as u32 - height,
+                width,
+                height,
+                Color::black(),
+            );
+
+            let [a, b, c, d, _, _] = glyph_transform.0;
+            let transform = Transform2D::new(a * scale, -b * scale, -c * scale, d * scale, 0.0, 0.0);
+
+            rasterize_glyph_path(canvas, path, &transform, mode, line_width / scale, 0.0);
+
+            canvas.restore();
+        }
+
+        Ok(rendered_glyph)
+    }
+
+    // Returns (texture index, image id, image layer, glyph padding box)
     fn find_texture_or_alloc<T: Renderer>(
         &self,
         canvas: &mut Canvas<T>,
         width: usize,
         height: usize,
-    ) -> Result<(usize, ImageId, (usize, usize)), ErrorKind> {
+    ) -> Result<(usize, ImageId, usize, (usize, usize)), ErrorKind> {
         // Find a free location in one of the atlases
         let mut texture_search_result = {
             let mut glyph_textures = self.glyph_textures.borrow_mut();
@@ -1155,7 +1774,7 @@ impl GlyphAtlas {
                 texture
                     .atlas
                     .add_rect(width, height)
-                    .map(|loc| (index, texture.image_id, loc))
+                    .map(|loc| (index, texture.image_id, texture.layer, loc))
             })
         };
 
@@ -1167,12 +1786,37 @@ impl GlyphAtlas {
                 .add_rect(width, height)
                 .ok_or(ErrorKind::FontSizeTooLargeForAtlas)?;
 
+            // Use the next free layer of the last texture array before allocating a new image
+            let free_layer = self.glyph_textures.borrow().last().and_then(|texture| {
+                let layers = canvas.images.info(texture.image_id)?.layers();
+                (texture.layer + 1 < layers).then_some((texture.image_id, texture.layer + 1))
+            });
+
+            if let Some((image_id, layer)) = free_layer {
+                self.glyph_textures
+                    .borrow_mut()
+                    .push(FontTexture { atlas, image_id, layer });
+
+                let index = self.glyph_textures.borrow().len() - 1;
+                return Ok((index, image_id, layer, loc));
+            }
+
             // Using PixelFormat::Gray8 works perfectly and takes less VRAM.
             // We keep Rgba8 for now because it might be useful for sub-pixel
             // anti-aliasing (ClearType®), and the atlas debug display is much
             // clearer with different colors. Also, Rgba8 is required for color
             // fonts (typically used for emojis).
-            let info = ImageInfo::new(ImageFlags::NEAREST, atlas.size().0, atlas.size().1, PixelFormat::Rgba8);
+            let mut info = ImageInfo::new(ImageFlags::NEAREST, atlas.size().0, atlas.size().1, PixelFormat::Rgba8);
+
+            let layers = canvas
+                .renderer
+                .capabilities()
+                .max_texture_array_layers
+                .min(TEXTURE_ARRAY_LAYERS);
+            if layers > 1 {
+                info = info.with_layers(layers);
+            }
+
             let image_id = canvas.images.alloc(&mut canvas.renderer, info)?;
 
             #[cfg(feature = "debug_inspector")]
@@ -1191,18 +1835,16 @@ impl GlyphAtlas {
                             size.1 as u32,
                             image::Rgba::<u8>([255, 0, 0, 0]),
                         );
-                        canvas
-                            .update_image(
-                                image_id,
-                                crate::image::ImageSource::from(imgref::Img::new(
-                                    clear_image.as_rgba(),
-                                    clear_image.width() as usize,
-                                    clear_image.height() as usize,
-                                )),
-                                0,
-                                0,
-                            )
-                            .unwrap();
+                        canvas.update_image(
+                            image_id,
+                            crate::image::ImageSource::from(imgref::Img::new(
+                                clear_image.as_rgba(),
+                                clear_image.width() as usize,
+                                clear_image.height() as usize,
+                            )),
+                            0,
+                            0,
+                        )?;
                     }
                     #[cfg(not(feature = "image-loading"))]
                     {
@@ -1221,18 +1863,24 @@ impl GlyphAtlas {
                 }
             }
 
-            self.glyph_textures.borrow_mut().push(FontTexture { atlas, image_id });
+            self.glyph_textures.borrow_mut().push(FontTexture {
+                atlas,
+                image_id,
+                layer: 0,
+            });
 
             let index = self.glyph_textures.borrow().len() - 1;
-            texture_search_result = Some((index, image_id, loc));
+            texture_search_result = Some((index, image_id, 0, loc));
         }
 
         texture_search_result.ok_or(ErrorKind::UnknownError)
     }
 
     pub(crate) fn clear<T: Renderer>(&self, canvas: &mut Canvas<T>) {
+        // Layers of a texture array share their image, which is deleted along with the first layer
         let image_ids = std::mem::take(&mut *self.glyph_textures.borrow_mut())
             .into_iter()
+            .filter(|font_texture| font_texture.layer == 0)
             .map(|font_texture| font_texture.image_id);
         image_ids.for_each(|id| canvas.delete_image(id));
 
@@ -1240,16 +1888,115 @@ impl GlyphAtlas {
     }
 }
 
+// Returns how many pixels the stems of glyphs are thickened by, which only applies to filled text.
+fn glyph_stem_darkening(text_settings: &TextSettings, mode: RenderMode) -> f32 {
+    if mode == RenderMode::Fill {
+        text_settings.stem_darkening
+    } else {
+        0.0
+    }
+}
+
+// Renders a glyph outline supersampled 8 times into the current render target, each sample
+// adding an 1/8th of the coverage. `transform` maps from font units to the render target.
+// Filled outlines are also stroked with `stem_darkening` in font units to thicken them.
+fn rasterize_glyph_path<T: Renderer>(
+    canvas: &mut Canvas<T>,
+    path: &crate::Path,
+    transform: &Transform2D,
+    mode: RenderMode,
+    line_width: f32,
+    stem_darkening: f32,
+) {
+    let factor = 1.0 / 8.0;
+
+    let mask_color = Color::rgbf(factor, factor, factor);
+
+    canvas.global_composite_blend_func(crate::BlendFactor::SrcAlpha, crate::BlendFactor::One);
+
+    // 4x
+    // let points = [
+    //     (-3.0/8.0, 1.0/8.0),
+    //     (1.0/8.0, 3.0/8.0),
+    //     (3.0/8.0, -1.0/8.0),
+    //     (-1.0/8.0, -3.0/8.0),
+    // ];
+
+    // 8x
+    let points = [
+        (-7.0 / 16.0, -1.0 / 16.0),
+        (-1.0 / 16.0, -5.0 / 16.0),
+        (3.0 / 16.0, -7.0 / 16.0),
+        (5.0 / 16.0, -3.0 / 16.0),
+        (7.0 / 16.0, 1.0 / 16.0),
+        (1.0 / 16.0, 5.0 / 16.0),
+        (-3.0 / 16.0, 7.0 / 16.0),
+        (-5.0 / 16.0, 3.0 / 16.0),
+    ];
+
+    for point in &points {
+        canvas.save();
+        canvas.translate(point.0, point.1);
+
+        canvas.set_transform(transform);
+
+        if mode == RenderMode::Stroke {
+            canvas.stroke_path_internal(
+                path,
+                &PaintFlavor::Color(mask_color),
+                None,
+                &StrokeSettings {
+                    line_width,
+                    ..Default::default()
+                },
+            );
+        } else {
+            canvas.fill_path_internal(path, &PaintFlavor::Color(mask_color), None, FillRule::NonZero);
+
+            if stem_darkening > 0.0 {
+                canvas.stroke_path_internal(
+                    path,
+                    &PaintFlavor::Color(mask_color),
+                    None,
+                    &StrokeSettings {
+                        line_width: stem_darkening,
+                        line_join: crate::LineJoin::Round,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        canvas.restore();
+    }
+}
+
+// Paint and size of the glyphs drawn as paths by `render_direct()`.
+pub struct DirectRenderParams<'a> {
+    pub paint_flavor: &'a PaintFlavor,
+    // Width of the anti-aliasing fringe, `None` if the glyphs aren't anti-aliased
+    pub fringe_width: Option<f32>,
+    pub stroke: &'a StrokeSettings,
+    pub font_size: f32,
+    pub mode: RenderMode,
+    // Scale from the device pixels of the layout back to canvas units
+    pub invscale: f32,
+}
+
 pub fn render_direct<T: Renderer>(
     canvas: &mut Canvas<T>,
     text_layout: &TextMetrics,
-    paint_flavor: &PaintFlavor,
-    anti_alias: bool,
-    stroke: &StrokeSettings,
-    font_size: f32,
-    mode: RenderMode,
-    invscale: f32,
+    params: &DirectRenderParams,
 ) -> Result<(), ErrorKind> {
+    let &DirectRenderParams {
+        paint_flavor,
+        fringe_width,
+        stroke,
+        font_size,
+        mode,
+        invscale,
+    } = params;
+
     let text_context = canvas.text_context.clone();
     let text_context = text_context.borrow_mut();
 
@@ -1290,14 +2037,14 @@ pub fn render_direct<T: Renderer>(
                     canvas.stroke_path_internal(
                         path.borrow(),
                         paint_flavor,
-                        anti_alias,
+                        fringe_width,
                         &StrokeSettings {
                             line_width,
                             ..stroke.clone()
                         },
                     );
                 } else {
-                    canvas.fill_path_internal(path.borrow(), paint_flavor, anti_alias, FillRule::NonZero);
+                    canvas.fill_path_internal(path.borrow(), paint_flavor, fringe_width, FillRule::NonZero);
                 }
             }
             #[cfg(feature = "image-loading")]
@@ -1309,3 +2056,84 @@ pub fn render_direct<T: Renderer>(
 
     Ok(())
 }
+
+#[cfg(all(test, feature = "fontdb"))]
+mod tests {
+    use crate::Paint;
+
+    #[test]
+    fn find_system_font() {
+        use super::{FontStyle, TextContextImpl};
+
+        let mut context = TextContextImpl::default();
+        assert!(context.find_system_font("Roboto", 400, FontStyle::Normal).is_err());
+
+        // Bundled fonts stand in for the fonts installed on the system
+        for file in ["Roboto-Regular.ttf", "Roboto-Bold.ttf"] {
+            context
+                .system_fonts
+                .load_font_file(format!("examples/assets/{file}"))
+                .unwrap();
+        }
+
+        let regular = context.find_system_font("Roboto", 400, FontStyle::Normal).unwrap();
+        let bold = context.find_system_font("Roboto", 700, FontStyle::Normal).unwrap();
+        assert_ne!(regular, bold);
+        assert!(context.font(bold).unwrap().face_ref().is_bold());
+
+        // Fonts are registered once
+        assert_eq!(
+            context.find_system_font("Roboto", 700, FontStyle::Normal).unwrap(),
+            bold
+        );
+        assert_eq!(
+            context.find_system_font("Roboto", 600, FontStyle::Normal).unwrap(),
+            bold
+        );
+        assert_eq!(context.fonts.len(), 2);
+    }
+
+    #[test]
+    fn system_fallback_fonts() {
+        use super::TextContext;
+
+        let text_context = TextContext::default();
+        let roboto = text_context
+            .add_font_file("examples/assets/Roboto-Regular.ttf")
+            .unwrap();
+        let paint = Paint::default().with_font(&[roboto]);
+
+        // Entypo has a thumbs up icon and stands in for the emoji font of the system
+        text_context
+            .0
+            .borrow_mut()
+            .system_fonts
+            .push_face_info(fontdb::FaceInfo {
+                id: fontdb::ID::dummy(),
+                source: fontdb::Source::File("examples/assets/entypo.ttf".into()),
+                index: 0,
+                families: vec![("Noto Color Emoji".into(), fontdb::Language::English_UnitedStates)],
+                post_script_name: "Entypo".into(),
+                style: fontdb::Style::Normal,
+                weight: fontdb::Weight::NORMAL,
+                stretch: fontdb::Stretch::Normal,
+                monospaced: false,
+            });
+
+        // Letters missing from the fonts don't look for fallback fonts
+        text_context.measure_text(0., 0., "سلام", &paint).unwrap();
+        assert!(text_context.0.borrow().system_fallback_fonts.is_empty());
+
+        let metrics = text_context.measure_text(0., 0., "ok 👍", &paint).unwrap();
+        let font_ids: Vec<_> = metrics.glyphs.iter().map(|glyph| glyph.font_id).collect();
+        let emoji_font = *font_ids.last().unwrap();
+        assert_ne!(emoji_font, roboto);
+        assert_eq!(font_ids[..2], [roboto, roboto]);
+
+        // Families that aren't installed are only looked up once
+        let context = text_context.0.borrow();
+        assert_eq!(context.system_fallback_fonts["Noto Color Emoji"], Some(emoji_font));
+        assert_eq!(context.system_fallback_fonts["Apple Color Emoji"], None);
+        assert!(!context.system_fallback_fonts.contains_key("Noto Sans Symbols2"));
+    }
+}
@@ -25,12 +25,16 @@ pub enum GlyphRendering<'a> {
 }
 
 /// Information about a font.
-// TODO: underline, strikeout, subscript, superscript metrics
+// TODO: subscript, superscript metrics
 #[derive(Copy, Clone, Default, Debug)]
 pub struct FontMetrics {
     ascender: f32,
     descender: f32,
     height: f32,
+    underline_position: f32,
+    underline_thickness: f32,
+    strikeout_position: f32,
+    strikeout_thickness: f32,
     regular: bool,
     italic: bool,
     bold: bool,
@@ -45,6 +49,10 @@ impl FontMetrics {
         self.ascender *= scale;
         self.descender *= scale;
         self.height *= scale;
+        self.underline_position *= scale;
+        self.underline_thickness *= scale;
+        self.strikeout_position *= scale;
+        self.strikeout_thickness *= scale;
     }
 
     /// Returns the distance from the baseline to the top of the highest glyph.
@@ -62,6 +70,26 @@ impl FontMetrics {
         self.height.round()
     }
 
+    /// Returns the distance from the baseline to the top of the underline, negative below the baseline.
+    pub fn underline_position(&self) -> f32 {
+        self.underline_position
+    }
+
+    /// Returns the thickness of the underline.
+    pub fn underline_thickness(&self) -> f32 {
+        self.underline_thickness
+    }
+
+    /// Returns the distance from the baseline to the top of the strikeout line.
+    pub fn strikeout_position(&self) -> f32 {
+        self.strikeout_position
+    }
+
+    /// Returns the thickness of the strikeout line.
+    pub fn strikeout_thickness(&self) -> f32 {
+        self.strikeout_thickness
+    }
+
     /// Returns if the font is regular.
     pub fn regular(&self) -> bool {
         self.regular
@@ -103,6 +131,7 @@ pub struct Font {
     face_index: u32,
     units_per_em: u16,
     metrics: FontMetrics,
+    x_height: Option<i16>,
     glyphs: RefCell<FnvHashMap<u16, Glyph>>,
 }
 
@@ -111,11 +140,33 @@ impl Font {
         let ttf_font = TtfFont::parse(data.as_ref(), face_index).map_err(|_| ErrorKind::FontParseError)?;
 
         let units_per_em = ttf_font.units_per_em();
+        let x_height = ttf_font.x_height();
+
+        // Fonts without (usable) post or OS/2 tables get lines placed like in common Latin fonts
+        let fallback_thickness = (units_per_em / 20) as i16;
+        let underline = ttf_font
+            .underline_metrics()
+            .filter(|metrics| metrics.thickness > 0)
+            .unwrap_or(ttf_parser::LineMetrics {
+                position: -((units_per_em / 10) as i16),
+                thickness: fallback_thickness,
+            });
+        let strikeout = ttf_font
+            .strikeout_metrics()
+            .filter(|metrics| metrics.thickness > 0)
+            .unwrap_or(ttf_parser::LineMetrics {
+                position: x_height.map_or((units_per_em / 4) as i16, |x_height| x_height / 2) + fallback_thickness / 2,
+                thickness: fallback_thickness,
+            });
 
         let metrics = FontMetrics {
             ascender: ttf_font.ascender() as f32,
             descender: ttf_font.descender() as f32,
             height: ttf_font.height() as f32,
+            underline_position: underline.position as f32,
+            underline_thickness: underline.thickness as f32,
+            strikeout_position: strikeout.position as f32,
+            strikeout_thickness: strikeout.thickness as f32,
             regular: ttf_font.is_regular(),
             italic: ttf_font.is_italic(),
             bold: ttf_font.is_bold(),
@@ -130,12 +181,21 @@ impl Font {
             face_index,
             units_per_em,
             metrics,
+            x_height,
             glyphs: Default::default(),
         })
     }
 
     pub fn face_ref(&self) -> rustybuzz::Face<'_> {
-        rustybuzz::Face::from_slice(self.data.as_ref().as_ref(), self.face_index).unwrap()
+        rustybuzz::Face::from_slice(self.data(), self.face_index).unwrap()
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.data.as_ref().as_ref()
+    }
+
+    pub fn face_index(&self) -> u32 {
+        self.face_index
     }
 
     pub fn metrics(&self, size: f32) -> FontMetrics {
@@ -150,6 +210,19 @@ impl Font {
         size / self.units_per_em as f32
     }
 
+    // The vertical scale for hinted glyphs, which lands the x-height of the font on a whole pixel.
+    pub fn hinted_scale_y(&self, size: f32) -> f32 {
+        let scale = self.scale(size);
+
+        match self.x_height {
+            Some(x_height) if x_height as f32 * scale >= 1.0 => {
+                let x_height = x_height as f32 * scale;
+                scale * x_height.round() / x_height
+            }
+            _ => scale,
+        }
+    }
+
     pub fn glyph(&self, face: &rustybuzz::Face<'_>, codepoint: u16) -> Option<Ref<'_, Glyph>> {
         if let Entry::Vacant(entry) = self.glyphs.borrow_mut().entry(codepoint) {
             let mut path = Path::new();
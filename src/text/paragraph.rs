@@ -0,0 +1,360 @@
+use std::ops::Range;
+
+use unicode_bidi::BidiInfo;
+use unicode_linebreak::BreakOpportunity;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{
+    layout, script, shape, shape_cached_word, word_script, Align, Baseline, Script, TextContextImpl, TextDirection,
+    TextMetrics,
+};
+use crate::{paint::TextSettings, ErrorKind};
+
+/// How the lines of a [`TextLayout`] are aligned within its maximum width.
+///
+/// The default value is `Start`.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ParagraphAlign {
+    /// Lines start at the left edge in left-to-right paragraphs and at the right edge in right-to-left ones.
+    #[default]
+    Start,
+    /// Lines are centered.
+    Center,
+    /// Lines end at the right edge in left-to-right paragraphs and at the left edge in right-to-left ones.
+    End,
+    /// The spaces of each line are stretched so that the line fills the whole width. The last line of a paragraph
+    /// is aligned like `Start`.
+    Justify,
+}
+
+/// A run of glyphs of a [`TextLine`] that share the same direction.
+#[derive(Clone, Debug)]
+pub struct TextRun {
+    /// The byte range of the text the run was shaped from.
+    pub range: Range<usize>,
+    /// The direction of the run.
+    pub direction: TextDirection,
+    /// The range of the glyphs of the run within the glyphs of [`TextLine::metrics()`].
+    pub glyphs: Range<usize>,
+    /// The horizontal position of the run, relative to the origin of the layout.
+    pub x: f32,
+    /// The advance of the run.
+    pub width: f32,
+}
+
+/// A line of a [`TextLayout`].
+#[derive(Clone, Debug)]
+pub struct TextLine {
+    range: Range<usize>,
+    direction: TextDirection,
+    runs: Vec<TextRun>,
+    metrics: TextMetrics,
+}
+
+impl TextLine {
+    /// Returns the byte range of the text on the line, including trailing whitespace and line breaks.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns the direction of the paragraph the line belongs to.
+    pub fn direction(&self) -> TextDirection {
+        self.direction
+    }
+
+    /// Returns the runs of the line in visual order, from left to right.
+    pub fn runs(&self) -> &[TextRun] {
+        &self.runs
+    }
+
+    /// Returns the positioned glyphs of the line in visual order, relative to the origin of the layout.
+    pub fn metrics(&self) -> &TextMetrics {
+        &self.metrics
+    }
+
+    /// Returns the vertical position of the baseline of the line, relative to the origin of the layout.
+    pub fn baseline(&self) -> f32 {
+        self.metrics.baseline
+    }
+}
+
+/// Text broken into lines that fit a maximum width, with each line reordered for display according to the
+/// [Unicode bidirectional algorithm](https://unicode.org/reports/tr9/) and aligned.
+///
+/// Lines are broken at the opportunities defined by [UAX #14](https://unicode.org/reports/tr14/) and at every
+/// line break in the text. The origin of the layout is the top-left corner of its first line. Draw it with
+/// [`Canvas::fill_text_layout()`](crate::Canvas::fill_text_layout).
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+    lines: Vec<TextLine>,
+    width: f32,
+    height: f32,
+}
+
+impl TextLayout {
+    /// Returns the lines of the layout from top to bottom.
+    pub fn lines(&self) -> &[TextLine] {
+        &self.lines
+    }
+
+    /// Returns the advance of the widest line.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Returns the height of all lines together.
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    pub(crate) fn scale(&mut self, scale: f32) {
+        self.width *= scale;
+        self.height *= scale;
+
+        for line in &mut self.lines {
+            line.metrics.scale(scale);
+
+            for run in &mut line.runs {
+                run.x *= scale;
+                run.width *= scale;
+            }
+        }
+    }
+}
+
+pub(crate) fn layout_paragraphs(
+    context: &mut TextContextImpl,
+    text_settings: &TextSettings,
+    text: &str,
+    max_width: f32,
+    align: ParagraphAlign,
+) -> Result<TextLayout, ErrorKind> {
+    let font_metrics = context.measure_font(text_settings.font_size, &text_settings.font_ids)?;
+
+    // lines are positioned on their baseline and aligned here, not by the paint
+    let mut text_settings = text_settings.clone();
+    text_settings.text_align = Align::Left;
+    text_settings.text_baseline = Baseline::Alphabetic;
+
+    let mut builder = LayoutBuilder {
+        context,
+        text_settings,
+        text,
+        bidi_info: BidiInfo::new(text, None),
+        max_width,
+        align,
+        ascender: font_metrics.ascender(),
+        line_height: font_metrics.height(),
+        script: script::UNKNOWN,
+        layout: TextLayout::default(),
+    };
+
+    // greedily fill each line up to the last break opportunity that still fits
+    let mut line_start = 0;
+    let mut last_opportunity = None;
+    // Advance of the line up to the last break opportunity, words are shaped separately so the advances of the
+    // text between opportunities add up
+    let mut line_width = 0.0;
+    let mut segment_start = 0;
+
+    for (index, opportunity) in unicode_linebreak::linebreaks(text) {
+        let (segment_width, trimmed_width) = builder.segment_widths(segment_start..index)?;
+        segment_start = index;
+
+        if let Some(end) = last_opportunity {
+            if line_width + trimmed_width > max_width {
+                builder.add_line(line_start..end, false)?;
+                line_start = end;
+                line_width = 0.0;
+            }
+        }
+
+        line_width += segment_width;
+
+        if opportunity == BreakOpportunity::Mandatory {
+            builder.add_line(line_start..index, true)?;
+            line_start = index;
+            last_opportunity = None;
+            line_width = 0.0;
+        } else {
+            last_opportunity = Some(index);
+        }
+    }
+
+    Ok(builder.layout)
+}
+
+struct LayoutBuilder<'a> {
+    context: &'a mut TextContextImpl,
+    text_settings: TextSettings,
+    text: &'a str,
+    bidi_info: BidiInfo<'a>,
+    max_width: f32,
+    align: ParagraphAlign,
+    ascender: f32,
+    line_height: f32,
+    script: Script,
+    layout: TextLayout,
+}
+
+impl LayoutBuilder<'_> {
+    // Returns the range without trailing whitespace and line breaks, which don't take space at the end of a line.
+    fn trim_end(&self, range: Range<usize>) -> Range<usize> {
+        range.start..range.start + self.text[range].trim_end().len()
+    }
+
+    // Returns the advance of the text in the range, with and without its trailing whitespace and line breaks.
+    fn segment_widths(&mut self, range: Range<usize>) -> Result<(f32, f32), ErrorKind> {
+        let trimmed = self.trim_end(range.clone());
+        let trimmed_width = self.width(trimmed.clone())?;
+        let trailing_width = self.width(trimmed.end..range.end)?;
+
+        Ok((trimmed_width + trailing_width, trimmed_width))
+    }
+
+    fn width(&mut self, range: Range<usize>) -> Result<f32, ErrorKind> {
+        if range.is_empty() {
+            return Ok(0.0);
+        }
+
+        let metrics = shape(0.0, 0.0, self.context, &self.text_settings, &self.text[range], None)?;
+
+        Ok(metrics.width())
+    }
+
+    fn add_line(&mut self, range: Range<usize>, last_in_paragraph: bool) -> Result<(), ErrorKind> {
+        let content = self.trim_end(range.clone());
+        let paragraph = self
+            .bidi_info
+            .paragraphs
+            .iter()
+            .find(|paragraph| paragraph.range.contains(&range.start))
+            .or(self.bidi_info.paragraphs.last());
+
+        let direction = match paragraph {
+            Some(paragraph) if paragraph.level.is_rtl() => TextDirection::RightToLeft,
+            _ => TextDirection::LeftToRight,
+        };
+
+        let mut metrics = TextMetrics {
+            final_byte_index: range.end,
+            ..Default::default()
+        };
+        let mut runs = Vec::new();
+
+        if let Some(paragraph) = paragraph.filter(|_| !content.is_empty()) {
+            let (levels, visual_runs) = self.bidi_info.visual_runs(paragraph, content);
+
+            for run in visual_runs {
+                let run_direction = if levels[run.start].is_rtl() {
+                    TextDirection::RightToLeft
+                } else {
+                    TextDirection::LeftToRight
+                };
+
+                let mut words = Vec::new();
+                let mut byte_index = run.start;
+
+                for word_txt in self.text[run.clone()].split_word_bounds() {
+                    self.script = word_script(self.context, &self.text_settings, word_txt, self.script);
+
+                    if let Some(mut word) = shape_cached_word(
+                        self.context,
+                        &self.text_settings,
+                        word_txt,
+                        run_direction,
+                        self.script,
+                        None,
+                    ) {
+                        for glyph in &mut word.glyphs {
+                            glyph.byte_index += byte_index;
+                        }
+                        words.push(word);
+                    }
+
+                    byte_index += word_txt.len();
+                }
+
+                if run_direction == TextDirection::RightToLeft {
+                    words.reverse();
+                }
+
+                let first_glyph = metrics.glyphs.len();
+                let mut width = 0.0;
+                for word in words {
+                    width += word.width;
+                    metrics.glyphs.extend(word.glyphs);
+                }
+                metrics.width += width;
+
+                runs.push(TextRun {
+                    range: run,
+                    direction: run_direction,
+                    glyphs: first_glyph..metrics.glyphs.len(),
+                    x: 0.0,
+                    width,
+                });
+            }
+        }
+
+        let align = match self.align {
+            ParagraphAlign::Justify if last_in_paragraph => ParagraphAlign::Start,
+            align => align,
+        };
+
+        if align == ParagraphAlign::Justify {
+            self.justify(&mut metrics, &mut runs);
+        }
+
+        let free_width = self.max_width - metrics.width;
+        let x = match (align, direction) {
+            (ParagraphAlign::Center, _) => free_width / 2.0,
+            (ParagraphAlign::Start | ParagraphAlign::Justify, TextDirection::RightToLeft)
+            | (ParagraphAlign::End, TextDirection::LeftToRight) => free_width,
+            _ => 0.0,
+        };
+
+        let baseline = self.ascender + self.layout.lines.len() as f32 * self.line_height;
+        layout(x, baseline, self.context, &mut metrics, &self.text_settings)?;
+
+        let mut run_x = metrics.x;
+        for run in &mut runs {
+            run.x = run_x;
+            run_x += run.width;
+        }
+
+        self.layout.width = self.layout.width.max(metrics.width);
+        self.layout.lines.push(TextLine {
+            range,
+            direction,
+            runs,
+            metrics,
+        });
+        self.layout.height = self.layout.lines.len() as f32 * self.line_height;
+
+        Ok(())
+    }
+
+    // Distributes the width left on the line evenly between its spaces.
+    fn justify(&self, metrics: &mut TextMetrics, runs: &mut [TextRun]) {
+        let is_space = |byte_index: usize| self.text[byte_index..].starts_with(char::is_whitespace);
+
+        let spaces = metrics.glyphs.iter().filter(|glyph| is_space(glyph.byte_index)).count();
+        let free_width = self.max_width - metrics.width;
+        if spaces == 0 || free_width <= 0.0 {
+            return;
+        }
+
+        let extra_width = free_width / spaces as f32;
+        for run in runs {
+            for glyph in &mut metrics.glyphs[run.glyphs.clone()] {
+                if is_space(glyph.byte_index) {
+                    glyph.advance_x += extra_width;
+                    run.width += extra_width;
+                }
+            }
+        }
+        metrics.width = self.max_width;
+    }
+}
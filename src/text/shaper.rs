@@ -0,0 +1,96 @@
+use super::{FontId, Script};
+
+/// The direction of a run of text passed to a [`TextShaper`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TextDirection {
+    /// Text flowing from left to right, like Latin.
+    LeftToRight,
+    /// Text flowing from right to left, like Arabic or Hebrew.
+    RightToLeft,
+}
+
+/// A run of text to be shaped with a single font by a [`TextShaper`].
+#[derive(Copy, Clone, Debug)]
+pub struct ShapingRun<'a> {
+    /// The text of the run, a single word as split by
+    /// [UAX#29 word boundaries](http://www.unicode.org/reports/tr29/#Word_Boundaries).
+    pub text: &'a str,
+    /// The id of the font the run is shaped with.
+    pub font_id: FontId,
+    /// The data of the font file, as it was registered with the text context.
+    pub font_data: &'a [u8],
+    /// The index of the face within the font data, 0 unless the data is a font collection.
+    pub face_index: u32,
+    /// The direction of the run.
+    pub direction: TextDirection,
+    /// The script of the run, [`crate::script::UNKNOWN`] if it only consists of characters shared between scripts.
+    pub script: Script,
+}
+
+/// A glyph produced by a [`TextShaper`]. Advances and offsets are in font units.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ShapedGlyphInfo {
+    /// The id of the glyph in the font, 0 if the font has no glyph for the character.
+    pub glyph_id: u32,
+    /// The byte index in the text of the run of the first character the glyph was shaped from.
+    pub cluster: u32,
+    /// How far the pen moves horizontally after drawing the glyph.
+    pub x_advance: i32,
+    /// How far the pen moves vertically after drawing the glyph.
+    pub y_advance: i32,
+    /// Horizontal offset of the glyph from the pen position.
+    pub x_offset: i32,
+    /// Vertical offset of the glyph from the pen position.
+    pub y_offset: i32,
+}
+
+/// Converts text into positioned glyphs of a font.
+///
+/// The text context splits text into runs of a single direction, script and font and passes each of them to its
+/// shaper, falling back to the next font of the paint if the shaped glyphs include missing ones. Implement this to
+/// shape text with harfbuzz, a platform shaper like CoreText or DirectWrite, or to replay shaping results computed
+/// elsewhere, and install it with [`TextContext::set_text_shaper()`](crate::TextContext::set_text_shaper).
+pub trait TextShaper {
+    /// Shapes `run` and appends the resulting glyphs to `glyphs` in visual order, so right-to-left runs start with
+    /// the glyph of their last character.
+    fn shape(&self, run: &ShapingRun<'_>, glyphs: &mut Vec<ShapedGlyphInfo>);
+}
+
+/// The default [`TextShaper`], backed by [rustybuzz](https://docs.rs/rustybuzz).
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RustybuzzShaper;
+
+impl TextShaper for RustybuzzShaper {
+    fn shape(&self, run: &ShapingRun<'_>, glyphs: &mut Vec<ShapedGlyphInfo>) {
+        let Some(face) = rustybuzz::Face::from_slice(run.font_data, run.face_index) else {
+            return;
+        };
+
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(run.text);
+        buffer.set_direction(match run.direction {
+            TextDirection::LeftToRight => rustybuzz::Direction::LeftToRight,
+            TextDirection::RightToLeft => rustybuzz::Direction::RightToLeft,
+        });
+        if run.script != rustybuzz::script::UNKNOWN {
+            buffer.set_script(run.script);
+        }
+
+        let output = rustybuzz::shape(&face, &[], buffer);
+
+        glyphs.extend(
+            output
+                .glyph_infos()
+                .iter()
+                .zip(output.glyph_positions())
+                .map(|(info, position)| ShapedGlyphInfo {
+                    glyph_id: info.glyph_id,
+                    cluster: info.cluster,
+                    x_advance: position.x_advance,
+                    y_advance: position.y_advance,
+                    x_offset: position.x_offset,
+                    y_offset: position.y_offset,
+                }),
+        );
+    }
+}
@@ -1,4 +1,14 @@
-use femtovg::{renderer::Void, Baseline, Canvas, Color, FillRule, Paint, Path, Solidity};
+use std::collections::HashMap;
+
+use femtovg::{
+    renderer::{SvgRenderer, Void},
+    Canvas, Color, FillRule, Paint, Path, Rect, Solidity, Transform2D,
+};
+use svg::{
+    node::element::path::{Command, Data},
+    node::element::tag::Type,
+    parser::Event,
+};
 
 #[test]
 fn path_with_single_move_to() {
@@ -85,6 +95,31 @@ fn path_with_points_around_zero() {
     canvas.stroke_path(&path, &Paint::color(Color::rgb(100, 100, 100)));
 }
 
+#[test]
+fn path_with_non_finite_points() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    let mut path = Path::new();
+    path.move_to(10.0, 10.0);
+    path.line_to(f32::NAN, 20.0);
+    path.line_to(20.0, 20.0);
+    path.bezier_to(f32::INFINITY, 10.0, 30.0, 30.0, 40.0, 40.0);
+    path.quad_to(10.0, f32::NEG_INFINITY, 40.0, 40.0);
+    path.line_to(10.0, 20.0);
+    path.close();
+
+    // Only the segments with finite points are kept
+    assert_eq!(path.verbs().count(), 4);
+
+    let mut transformed = path.clone();
+    transformed.transform(&Transform2D::scaling(f32::NAN, 1.0));
+
+    canvas.fill_path(&path, &Paint::color(Color::rgb(100, 100, 100)));
+    canvas.stroke_path(&path, &Paint::color(Color::rgb(100, 100, 100)));
+    canvas.fill_path(&transformed, &Paint::color(Color::rgb(100, 100, 100)));
+    canvas.stroke_path(&transformed, &Paint::color(Color::rgb(100, 100, 100)));
+}
+
 #[test]
 fn degenerate_stroke() {
     let mut canvas = Canvas::new(Void).unwrap();
@@ -144,8 +179,114 @@ fn path_contains_point() {
     assert!(canvas.contains_point(&path, 50.0, 5.0, FillRule::NonZero));
 }
 
+#[test]
+fn path_append_transformed() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let mut square = Path::new();
+    square.rect(0.0, 0.0, 10.0, 10.0);
+
+    let mut path = Path::new();
+    path.append(&square);
+    path.append_transformed(&square, &Transform2D::translation(50.0, 50.0));
+
+    assert_eq!(path.verbs().count(), 2 * square.verbs().count());
+    assert!(canvas.contains_point(&path, 5.0, 5.0, FillRule::NonZero));
+    assert!(canvas.contains_point(&path, 55.0, 55.0, FillRule::NonZero));
+    assert!(!canvas.contains_point(&path, 30.0, 30.0, FillRule::NonZero));
+
+    // transforming in place must not reuse the previously flattened geometry
+    path.transform(&Transform2D::translation(20.0, 20.0));
+    assert!(!canvas.contains_point(&path, 5.0, 5.0, FillRule::NonZero));
+    assert!(canvas.contains_point(&path, 25.0, 25.0, FillRule::NonZero));
+}
+
+#[test]
+fn path_winding_utilities() {
+    // A square with a hole of the same winding, as often found in imported geometry
+    let mut path = Path::new();
+    path.rect(0.0, 0.0, 100.0, 100.0);
+    path.rect(25.0, 25.0, 50.0, 50.0);
+    assert_eq!(path.subpath_areas(), [10000.0, 2500.0]);
+
+    path.reverse_subpaths();
+    assert_eq!(path.subpath_areas(), [-10000.0, -2500.0]);
+
+    path.normalize_windings(FillRule::EvenOdd);
+    assert_eq!(path.subpath_areas(), [10000.0, -2500.0]);
+
+    // Already consistent windings are kept
+    path.normalize_windings(FillRule::NonZero);
+    assert_eq!(path.subpath_areas(), [10000.0, -2500.0]);
+
+    // Areas follow the curvature of bezier segments
+    let mut circle = Path::new();
+    circle.circle(50.0, 50.0, 10.0);
+    let area = circle.subpath_areas()[0].abs();
+    assert!((area - std::f32::consts::PI * 100.0).abs() < 0.1, "{area}");
+}
+
+#[test]
+fn path_bounds_without_canvas() {
+    assert_eq!(Path::new().bounds(), Rect::default());
+
+    let mut circle = Path::new();
+    circle.circle(50.0, 50.0, 10.0);
+    let bounds = circle.bounds();
+    assert!(
+        (bounds.x - 40.0).abs() < 0.01 && (bounds.w - 20.0).abs() < 0.01,
+        "{bounds:?}"
+    );
+    assert!(
+        (bounds.y - 40.0).abs() < 0.01 && (bounds.h - 20.0).abs() < 0.01,
+        "{bounds:?}"
+    );
+
+    // Control points outside of the curve are not part of the bounds
+    let mut curve = Path::new();
+    curve.move_to(0.0, 0.0);
+    curve.bezier_to(0.0, 40.0, 100.0, 40.0, 100.0, 0.0);
+    assert_eq!(curve.bounds(), Rect::new(0.0, 0.0, 100.0, 30.0));
+
+    let mut line = Path::new();
+    line.move_to(0.0, 0.0);
+    line.line_to(100.0, 0.0);
+
+    let paint = Paint::color(Color::black()).with_line_width(10.0);
+    assert_eq!(line.stroke_bounds(&paint), Rect::new(-5.0, -5.0, 110.0, 10.0));
+
+    // Square caps of diagonal lines reach beyond half the line width
+    let mut diagonal = Path::new();
+    diagonal.move_to(0.0, 0.0);
+    diagonal.line_to(100.0, 100.0);
+    assert_eq!(diagonal.stroke_bounds(&paint), Rect::new(-5.0, -5.0, 110.0, 110.0));
+
+    let square = paint.clone().with_line_cap(femtovg::LineCap::Square);
+    let bounds = diagonal.stroke_bounds(&square);
+    assert!(
+        (bounds.x + 7.07).abs() < 0.01 && (bounds.w - 114.14).abs() < 0.01,
+        "{bounds:?}"
+    );
+
+    // Sharp miter joins reach beyond half the line width
+    let mut corner = Path::new();
+    corner.move_to(0.0, 0.0);
+    corner.line_to(100.0, 10.0);
+    corner.line_to(0.0, 20.0);
+
+    let miter = paint.clone().with_miter_limit(100.0);
+    assert!(corner.stroke_bounds(&miter).w > 150.0);
+
+    let bevel = miter.with_line_join(femtovg::LineJoin::Bevel);
+    assert_eq!(corner.stroke_bounds(&bevel), Rect::new(-5.0, -5.0, 110.0, 30.0));
+}
+
+#[cfg(feature = "text")]
 #[test]
 fn text_location_respects_scale() {
+    use femtovg::Baseline;
+
     let mut canvas = Canvas::new(Void).unwrap();
 
     canvas
@@ -161,6 +302,7 @@ fn text_location_respects_scale() {
     assert_eq!(res.y, 100.0);
 }
 
+#[cfg(feature = "text")]
 #[test]
 fn text_measure_without_canvas() {
     let text_context = femtovg::TextContext::default();
@@ -179,6 +321,69 @@ fn text_measure_without_canvas() {
     assert_eq!(metrics.height().ceil(), 13.);
 }
 
+#[cfg(feature = "text")]
+#[test]
+fn custom_text_shaper() {
+    struct MonospaceShaper;
+
+    impl femtovg::TextShaper for MonospaceShaper {
+        fn shape(&self, run: &femtovg::ShapingRun<'_>, glyphs: &mut Vec<femtovg::ShapedGlyphInfo>) {
+            let start = glyphs.len();
+            femtovg::RustybuzzShaper.shape(run, glyphs);
+
+            for glyph in &mut glyphs[start..] {
+                glyph.x_advance = 1000;
+            }
+        }
+    }
+
+    let text_context = femtovg::TextContext::default();
+
+    let font_id = text_context
+        .add_font_file("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let test_paint = femtovg::Paint::default().with_font(&[font_id]).with_font_size(16.);
+
+    let default_width = text_context.measure_text(0., 0., "Hi!", &test_paint).unwrap().width();
+
+    // Cached measurements are discarded
+    text_context.set_text_shaper(MonospaceShaper);
+    let metrics = text_context.measure_text(0., 0., "Hi!", &test_paint).unwrap();
+
+    assert_ne!(metrics.width(), default_width);
+    assert!(metrics
+        .glyphs
+        .iter()
+        .all(|glyph| glyph.advance_x == 16.0 * 1000.0 / 2048.0));
+}
+
+#[cfg(feature = "fontdb")]
+#[test]
+fn find_system_fonts() {
+    use femtovg::FontStyle;
+
+    let text_context = femtovg::TextContext::default();
+
+    // Nothing is found before the system fonts are added
+    assert!(text_context.find_font("sans-serif", 400, FontStyle::Normal).is_err());
+
+    text_context.add_system_fonts();
+    assert!(text_context
+        .find_font("No Such Font Family", 400, FontStyle::Normal)
+        .is_err());
+
+    // Characters missing from the fonts of the paint fall back to the fonts of the system
+    let font_id = text_context
+        .add_font_file("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+    let test_paint = femtovg::Paint::default().with_font(&[font_id]).with_font_size(16.);
+
+    let metrics = text_context.measure_text(0., 0., "ok 👍", &test_paint).unwrap();
+    assert_eq!(metrics.glyphs.first().map(|glyph| glyph.font_id), Some(font_id));
+}
+
+#[cfg(feature = "text")]
 #[test]
 fn font_measure_without_canvas() {
     let text_context = femtovg::TextContext::default();
@@ -196,6 +401,7 @@ fn font_measure_without_canvas() {
     assert_eq!(metrics.ascender().ceil(), 17.);
 }
 
+#[cfg(feature = "text")]
 #[test]
 fn break_text_without_canvas() {
     let text_context = femtovg::TextContext::default();
@@ -220,3 +426,433 @@ fn break_text_without_canvas() {
         vec!["Multiple ", "Lines ", "Broken"]
     );
 }
+
+#[cfg(feature = "text")]
+#[test]
+fn layout_text_without_canvas() {
+    use femtovg::{ParagraphAlign, TextDirection};
+
+    let text_context = femtovg::TextContext::default();
+
+    let latin_font = text_context
+        .add_font_file("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+    let arabic_font = text_context
+        .add_font_file("examples/assets/amiri-regular.ttf")
+        .expect("Font not found");
+
+    let test_paint = femtovg::Paint::default()
+        .with_font(&[latin_font, arabic_font])
+        .with_font_size(16.);
+
+    let text = "Multiple Lines Broken\nسلام abc";
+    let layout = text_context
+        .layout_text(text, 60., ParagraphAlign::Start, &test_paint)
+        .expect("text layout failed unexpectedly");
+
+    let lines = layout.lines();
+    assert_eq!(
+        lines.iter().map(|line| &text[line.range()]).collect::<Vec<_>>(),
+        vec!["Multiple ", "Lines ", "Broken\n", "سلام abc"]
+    );
+    assert!(lines.windows(2).all(|pair| pair[0].baseline() < pair[1].baseline()));
+    assert_eq!(lines[0].metrics().x, 0.);
+
+    // the right-to-left paragraph starts at the right edge, with the embedded latin word on the left
+    let rtl_line = &lines[3];
+    assert_eq!(rtl_line.direction(), TextDirection::RightToLeft);
+    assert!((rtl_line.metrics().x + rtl_line.metrics().width() - 60.).abs() < 0.01);
+    let runs = rtl_line.runs();
+    assert_eq!(runs.len(), 2);
+    assert_eq!(
+        (&text[runs[0].range.clone()], runs[0].direction),
+        ("abc", TextDirection::LeftToRight)
+    );
+    assert_eq!(runs[1].direction, TextDirection::RightToLeft);
+    assert!(runs[0].x < runs[1].x);
+
+    // all lines but the last one of a paragraph are stretched to the full width
+    let text = "one two three four five six";
+    let layout = text_context
+        .layout_text(text, 80., ParagraphAlign::Justify, &test_paint)
+        .expect("text layout failed unexpectedly");
+    let (last, lines) = layout.lines().split_last().unwrap();
+    assert!(!lines.is_empty());
+    for line in lines {
+        assert!((line.metrics().width() - 80.).abs() < 0.01);
+    }
+    assert!(last.metrics().width() < 80.);
+
+    let mut canvas = Canvas::new_with_text_context(Void, text_context).unwrap();
+    canvas.set_size(100, 100, 1.);
+    canvas.fill_text_layout(10., 10., &layout, &test_paint).unwrap();
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn layout_long_paragraph_without_canvas() {
+    use femtovg::ParagraphAlign;
+
+    let text_context = femtovg::TextContext::default();
+
+    let font_id = text_context
+        .add_font_file("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let test_paint = femtovg::Paint::default().with_font(&[font_id]).with_font_size(16.);
+
+    let words = [
+        "lorem",
+        "ipsum",
+        "dolor",
+        "sit",
+        "amet",
+        "consectetur",
+        "adipiscing",
+        "elit",
+    ];
+    let text = words.iter().cycle().take(400).copied().collect::<Vec<_>>().join(" ");
+    let layout = text_context
+        .layout_text(&text, 200., ParagraphAlign::Start, &test_paint)
+        .expect("text layout failed unexpectedly");
+
+    // every line fits, and would not fit anymore with the first word of the next line
+    let lines = layout.lines();
+    assert!(lines.len() > 10);
+    for pair in lines.windows(2) {
+        let line = text[pair[0].range()].trim_end();
+        let next_word = text[pair[1].range()].split(' ').next().unwrap();
+        let width = |text: &str| text_context.measure_text(0., 0., text, &test_paint).unwrap().width();
+
+        assert!(width(line) <= 200.);
+        assert!(width(&format!("{line} {next_word}")) > 200.);
+        assert!((pair[0].metrics().width() - width(line)).abs() < 0.01);
+    }
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn per_script_font_override() {
+    let text_context = femtovg::TextContext::default();
+
+    let latin_font = text_context
+        .add_font_file("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+    let arabic_font = text_context
+        .add_font_file("examples/assets/amiri-regular.ttf")
+        .expect("Font not found");
+
+    let test_paint = femtovg::Paint::default()
+        .with_font(&[latin_font])
+        .with_font_for_script(femtovg::script::ARABIC, &[arabic_font])
+        .with_font_size(16.);
+
+    let metrics = text_context
+        .measure_text(0., 0., "abc سلام", &test_paint)
+        .expect("text shaping failed unexpectedly");
+
+    for glyph in &metrics.glyphs {
+        if glyph.c.is_ascii_alphabetic() {
+            assert_eq!(glyph.script, femtovg::script::LATIN);
+            assert_eq!(glyph.font_id, latin_font);
+        } else if !glyph.c.is_whitespace() {
+            assert_eq!(glyph.script, femtovg::script::ARABIC);
+            assert_eq!(glyph.font_id, arabic_font);
+        }
+    }
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn glyph_scripts_without_script_fonts() {
+    let text_context = femtovg::TextContext::default();
+
+    let font = text_context
+        .add_font_file("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+    let test_paint = femtovg::Paint::default().with_font(&[font]).with_font_size(16.);
+
+    // Scripts are detected when words are shaped, also for words taken from the cache
+    for _ in 0..2 {
+        let metrics = text_context
+            .measure_text(0., 0., "abc def", &test_paint)
+            .expect("text shaping failed unexpectedly");
+
+        for glyph in metrics.glyphs.iter().filter(|glyph| glyph.c.is_ascii_alphabetic()) {
+            assert_eq!(glyph.script, femtovg::script::LATIN);
+        }
+    }
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn fill_rotated_text_with_transformed_glyphs() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(200, 200, 1.0);
+
+    canvas
+        .add_font("examples/assets/Roboto-Regular.ttf")
+        .expect("Font not found");
+
+    let paint = Paint::color(Color::black()).with_rasterize_transformed_text(true);
+
+    canvas.translate(100.0, 100.0);
+    canvas.rotate(0.5);
+
+    let rotated = canvas.fill_text(0.0, 0.0, "Hello", &paint).unwrap();
+
+    canvas.reset_transform();
+    let upright = canvas.fill_text(0.0, 0.0, "Hello", &paint).unwrap();
+
+    assert_eq!(rotated.width(), upright.width());
+}
+
+#[test]
+fn restore_state_snapshot() {
+    let mut canvas = Canvas::new(Void).unwrap();
+
+    canvas.translate(10.0, 10.0);
+    let snapshot = canvas.state_snapshot();
+
+    // unbalanced saves are discarded
+    canvas.save();
+    canvas.save();
+    canvas.scale(2.0, 2.0);
+    canvas.set_global_alpha(0.5);
+    canvas.restore_snapshot(&snapshot);
+    assert_eq!(canvas.transform(), Transform2D::translation(10.0, 10.0));
+
+    canvas.restore();
+    assert_eq!(canvas.transform(), Transform2D::identity());
+
+    // restoring past the snapshot is undone as well, including the saved states
+    canvas.save();
+    canvas.translate(5.0, 5.0);
+    let snapshot = canvas.state_snapshot();
+    canvas.restore();
+    canvas.scale(3.0, 3.0);
+    canvas.save();
+    canvas.restore();
+    canvas.restore();
+    canvas.restore_snapshot(&snapshot);
+    assert_eq!(canvas.transform(), Transform2D::translation(5.0, 5.0));
+
+    canvas.restore();
+    assert_eq!(canvas.transform(), Transform2D::identity());
+}
+
+#[test]
+fn save_guards_restore_on_early_return() {
+    fn draw_scaled(canvas: &mut Canvas<Void>, fail: bool) -> Result<(), ()> {
+        let mut canvas = canvas.save_guard();
+        canvas.scale(2.0, 2.0);
+        if fail {
+            return Err(());
+        }
+        canvas.translate(1.0, 1.0);
+        Ok(())
+    }
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.translate(10.0, 10.0);
+
+    assert!(draw_scaled(&mut canvas, true).is_err());
+    assert_eq!(canvas.transform(), Transform2D::translation(10.0, 10.0));
+    assert!(draw_scaled(&mut canvas, false).is_ok());
+    assert_eq!(canvas.transform(), Transform2D::translation(10.0, 10.0));
+
+    // a checkpoint also discards unbalanced saves
+    {
+        let mut checkpoint = canvas.checkpoint();
+        checkpoint.save();
+        checkpoint.save();
+        checkpoint.rotate(1.0);
+    }
+    assert_eq!(canvas.transform(), Transform2D::translation(10.0, 10.0));
+    canvas.restore();
+    assert_eq!(canvas.transform(), Transform2D::identity());
+}
+
+// Returns the name and attributes of the elements of an SVG document, in document order.
+fn svg_elements(document: &str) -> Vec<(String, HashMap<String, String>)> {
+    svg::read(document)
+        .unwrap()
+        .filter_map(|event| match event {
+            Event::Tag(name, Type::Start | Type::Empty, attributes) => Some((
+                name.to_string(),
+                attributes
+                    .into_iter()
+                    .map(|(key, value)| (key, value.to_string()))
+                    .collect(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn svg_paths(elements: &[(String, HashMap<String, String>)]) -> Vec<&HashMap<String, String>> {
+    elements
+        .iter()
+        .filter(|(name, _)| name == "path")
+        .map(|(_, attributes)| attributes)
+        .collect()
+}
+
+#[test]
+fn export_svg() {
+    let mut canvas = Canvas::new(SvgRenderer::new()).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let mut rect = Path::new();
+    rect.rect(10.0, 10.0, 50.0, 50.0);
+    let mut circle = Path::new();
+    circle.circle(50.0, 50.0, 20.0);
+
+    canvas.fill_path(&rect, &Paint::color(Color::rgb(255, 0, 0)));
+    canvas.fill_path(
+        &circle,
+        &Paint::linear_gradient(30.0, 50.0, 70.0, 50.0, Color::white(), Color::black()),
+    );
+    canvas.scissor(0.0, 0.0, 30.0, 30.0);
+    canvas.translate(5.0, 5.0);
+    canvas.stroke_path(&rect, &Paint::color(Color::black()).with_line_width(2.0));
+
+    let document = canvas.export_svg();
+    let elements = svg_elements(&document);
+    assert_eq!(elements[0].0, "svg");
+
+    let paths = svg_paths(&elements);
+    assert_eq!(paths.len(), 3);
+
+    // Paths keep their segments instead of being flattened
+    let rect_data = Data::parse(&paths[0]["d"]).unwrap();
+    assert_eq!(rect_data.len(), 5);
+    assert_eq!(paths[0]["fill"], "#ff0000");
+
+    let circle_data = Data::parse(&paths[1]["d"]).unwrap();
+    assert!(circle_data
+        .iter()
+        .any(|command| matches!(command, Command::CubicCurve(..))));
+
+    // The gradient fill refers to a gradient defined in the document
+    let gradient_id = paths[1]["fill"]
+        .strip_prefix("url(#")
+        .and_then(|url| url.strip_suffix(')'))
+        .unwrap();
+    assert!(elements
+        .iter()
+        .any(|(name, attributes)| name == "linearGradient" && attributes["id"] == gradient_id));
+
+    // Strokes are written with their settings and transform, clipped by the scissor
+    let stroke = paths[2];
+    assert_eq!(stroke["fill"], "none");
+    assert_eq!(stroke["stroke"], "#000000");
+    assert_eq!(stroke["stroke-width"], "2");
+    assert_eq!(stroke["transform"], "matrix(1 0 0 1 5 5)");
+    assert_eq!(Data::parse(&stroke["d"]).unwrap().len(), 5);
+
+    let (group_index, _) = elements.iter().enumerate().rfind(|(_, (name, _))| name == "g").unwrap();
+    assert!(elements[group_index + 1].1 == *stroke);
+    let clip_id = elements[group_index].1["clip-path"]
+        .strip_prefix("url(#")
+        .and_then(|url| url.strip_suffix(')'))
+        .unwrap();
+    assert!(elements
+        .iter()
+        .any(|(name, attributes)| name == "clipPath" && attributes["id"] == clip_id));
+
+    // Every export starts a new document
+    assert!(svg_paths(&svg_elements(&canvas.export_svg())).is_empty());
+
+    // Clearing the screen discards what was drawn before along with its definitions
+    canvas.reset();
+    canvas.fill_path(
+        &circle,
+        &Paint::linear_gradient(30.0, 50.0, 70.0, 50.0, Color::white(), Color::black()),
+    );
+    canvas.clear_rect(0, 0, 100, 100, Color::white());
+    canvas.fill_path(&rect, &Paint::color(Color::rgb(255, 0, 0)));
+
+    let elements = svg_elements(&canvas.export_svg());
+    assert!(!elements.iter().any(|(name, _)| name == "linearGradient"));
+    assert_eq!(elements.iter().filter(|(name, _)| name == "rect").count(), 1);
+    assert_eq!(svg_paths(&elements).len(), 1);
+}
+
+#[test]
+fn circle_segments_adapt_to_radius() {
+    let mut small = Path::new();
+    small.circle(0.0, 0.0, 10.0);
+
+    let mut large = Path::new();
+    large.circle(0.0, 0.0, 100_000.0);
+
+    let mut precise = Path::new();
+    precise.circle_with_tolerance(0.0, 0.0, 10.0, 0.0001);
+
+    // move_to, four quarter curves and close
+    assert_eq!(small.verbs().count(), 6);
+    assert!(large.verbs().count() > small.verbs().count());
+    assert!(precise.verbs().count() > small.verbs().count());
+
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+    assert!(!canvas.contains_point(&precise, 50.0, 50.0, FillRule::NonZero));
+    assert!(canvas.contains_point(&large, 50.0, 50.0, FillRule::NonZero));
+}
+
+#[cfg(feature = "svg")]
+#[test]
+fn draw_svg() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let data = br##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="30">
+        <defs>
+            <linearGradient id="gradient"><stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/></linearGradient>
+            <clipPath id="clip"><rect width="20" height="20"/></clipPath>
+        </defs>
+        <g opacity="0.5" clip-path="url(#clip)">
+            <rect width="40" height="30" fill="url(#gradient)" stroke="black" stroke-width="2"/>
+        </g>
+        <circle cx="20" cy="15" r="5" fill="#00ff00" paint-order="stroke"/>
+    </svg>"##;
+
+    let svg = femtovg::svg::Svg::from_data(&mut canvas, data).unwrap();
+    assert_eq!((svg.width(), svg.height()), (40.0, 30.0));
+
+    svg.draw(&mut canvas);
+    svg.delete(&mut canvas);
+
+    assert!(femtovg::svg::Svg::from_data(&mut canvas, b"not svg").is_err());
+}
+
+#[test]
+fn draw_image_into_rect() {
+    let mut canvas = Canvas::new(Void).unwrap();
+    canvas.set_size(100, 100, 1.0);
+
+    let image = canvas
+        .create_image_empty(16, 16, femtovg::PixelFormat::Rgba8, femtovg::ImageFlags::empty())
+        .unwrap();
+
+    canvas.draw_image(image, None, Rect::new(10.0, 10.0, 32.0, 32.0), 1.0);
+    canvas.draw_image(
+        image,
+        Some(Rect::new(4.0, 4.0, 8.0, 8.0)),
+        Rect::new(50.0, 50.0, 16.0, 16.0),
+        0.5,
+    );
+    canvas.rotate(0.5);
+    canvas.draw_image(image, None, Rect::new(10.0, 10.0, 32.0, 32.0), 1.0);
+
+    // empty rectangles and deleted images are ignored
+    canvas.draw_image(
+        image,
+        Some(Rect::new(0.0, 0.0, 0.0, 8.0)),
+        Rect::new(0.0, 0.0, 8.0, 8.0),
+        1.0,
+    );
+    canvas.delete_image(image);
+    canvas.draw_image(image, None, Rect::new(10.0, 10.0, 32.0, 32.0), 1.0);
+}
@@ -1,4 +1,10 @@
-use femtovg::Transform2D;
+use femtovg::{Transform2D, TransformComponents};
+
+fn assert_approx_eq(a: Transform2D, b: Transform2D) {
+    for (a, b) in a.0.iter().zip(b.0) {
+        assert!((a - b).abs() < 1e-4, "{a:?} != {b:?}");
+    }
+}
 
 #[test]
 fn test_multiplication() {
@@ -83,3 +89,46 @@ fn test_scaling() {
 
     assert_eq!(scaled, transform * Transform2D::scaling(sx, sy))
 }
+
+#[test]
+fn test_decompose() {
+    let mut transform = Transform2D::scaling(2.0, -3.0);
+    transform.skew_x(0.25);
+    transform.rotate(1.0);
+    transform.translate(5.0, 6.0);
+
+    let components = transform.decompose();
+    assert!((components.translate_x - 5.0).abs() < 1e-4);
+    assert!((components.translate_y - 6.0).abs() < 1e-4);
+    assert!((components.rotation - 1.0).abs() < 1e-4);
+    assert!((components.scale_x - 2.0).abs() < 1e-4);
+    assert!((components.scale_y + 3.0).abs() < 1e-4);
+    assert!((components.skew - 0.25).abs() < 1e-4);
+
+    assert_approx_eq(Transform2D::recompose(components), transform);
+    assert_eq!(
+        Transform2D::recompose(TransformComponents::default()),
+        Transform2D::identity()
+    );
+}
+
+#[test]
+fn test_lerp() {
+    let a = Transform2D::translation(10.0, 0.0);
+    let mut b = Transform2D::rotation(std::f32::consts::FRAC_PI_2);
+    b.translate(20.0, 10.0);
+
+    assert_approx_eq(a.lerp(&b, 0.0), a);
+    assert_approx_eq(a.lerp(&b, 1.0), b);
+
+    // halfway the content is rotated, not squashed as with interpolated matrix elements
+    let mut halfway = Transform2D::rotation(std::f32::consts::FRAC_PI_4);
+    halfway.translate(15.0, 5.0);
+    assert_approx_eq(a.lerp(&b, 0.5), halfway);
+
+    // rotation takes the shorter way across the ±π boundary
+    let from = Transform2D::rotation(3.0);
+    let to = Transform2D::rotation(-3.0);
+    let rotation = from.lerp(&to, 0.5).decompose().rotation;
+    assert!((rotation.abs() - std::f32::consts::PI).abs() < 1e-4);
+}